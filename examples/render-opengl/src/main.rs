@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use std::{error::Error, fs};
 
-use inox2d::formats::inp::parse_inp;
+use inox2d::formats::inp::{parse_inp, parse_inx};
 use inox2d::model::Model;
 use inox2d::render::InoxRendererExt;
 use inox2d_opengl::OpenglRenderer;
@@ -40,8 +40,13 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	tracing::info!("Parsing puppet");
 
-	let data = fs::read(cli.inp_path)?;
-	let mut model = parse_inp(data.as_slice())?;
+	let data = fs::read(&cli.inp_path)?;
+	let mut model = if cli.inp_path.extension().is_some_and(|ext| ext == "inx") {
+		let base_dir = cli.inp_path.parent().map(PathBuf::from).unwrap_or_default();
+		parse_inx(data.as_slice(), &base_dir)?
+	} else {
+		parse_inp(data.as_slice())?
+	};
 	tracing::info!(
 		"Successfully parsed puppet: {}",
 		(model.puppet.meta.name.as_deref()).unwrap_or("<no puppet name specified in file>")
@@ -49,7 +54,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	tracing::info!("Setting up puppet for transforms, params and rendering.");
 	model.puppet.init_transforms();
-	model.puppet.init_rendering();
+	model.puppet.try_init_rendering()?;
 	model.puppet.init_params();
 	model.puppet.init_physics();
 
@@ -91,7 +96,15 @@ impl App for Inox2dOpenglExampleApp {
 			Ok(mut renderer) => {
 				tracing::info!("Initializing Inox2D renderer");
 				renderer.resize(self.width, self.height);
-				renderer.camera.scale = Vec2::splat(0.15);
+
+				// frame the puppet in its rest pose
+				let puppet = &mut self.model.puppet;
+				puppet.begin_frame();
+				puppet.end_frame(0.0);
+				match puppet.bounding_box() {
+					Some((min, max)) => renderer.camera.fit_bounds(min, max, renderer.viewport.as_vec2(), 20.0),
+					None => renderer.camera.scale = Vec2::splat(0.15),
+				}
 				tracing::info!("Inox2D renderer initialized");
 
 				let scene_ctrl = ExampleSceneController::new(&renderer.camera, 0.5);
@@ -91,7 +91,7 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
 	tracing::info!("Setting up puppet for transforms, params and rendering.");
 	model.puppet.init_transforms();
-	model.puppet.init_rendering();
+	model.puppet.try_init_rendering()?;
 	model.puppet.init_params();
 	model.puppet.init_physics();
 
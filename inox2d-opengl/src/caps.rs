@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use glow::HasContext;
+
+const ANISOTROPIC_FILTERING_EXTENSIONS: [&str; 3] = [
+	"GL_EXT_texture_filter_anisotropic",
+	"GL_ARB_texture_filter_anisotropic",
+	"EXT_texture_filter_anisotropic",
+];
+
+/// What an OpenGL context supports, for embedders to pick fallbacks before rendering.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RendererCaps {
+	/// Framebuffers can have float color attachments, as used for emissive composite output.
+	pub float_framebuffers: bool,
+	/// Debug groups show up in tools like `apitrace`. See `GL_KHR_debug`.
+	pub debug_groups: bool,
+	/// Maximum anisotropy of texture filtering, if anisotropic filtering is supported.
+	pub max_anisotropy: Option<f32>,
+	/// Maximum width and height of a texture.
+	pub max_texture_size: u32,
+	/// All extensions advertised by the context.
+	pub extensions: HashSet<String>,
+}
+
+impl RendererCaps {
+	/// Capabilities implied by the version and advertised extensions of a context, along with queried limits.
+	///
+	/// `max_anisotropy` is ignored if no anisotropic filtering extension is advertised.
+	pub fn new(
+		version: &glow::Version,
+		extensions: HashSet<String>,
+		max_texture_size: u32,
+		max_anisotropy: f32,
+	) -> Self {
+		let has = |names: &[&str]| names.iter().any(|name| extensions.contains(*name));
+
+		// float color attachments are core in desktop OpenGL 3.0
+		let float_framebuffers = (!version.is_embedded && version.major >= 3)
+			|| has(&["GL_EXT_color_buffer_float", "EXT_color_buffer_float"]);
+		let anisotropic_filtering = has(&ANISOTROPIC_FILTERING_EXTENSIONS);
+
+		Self {
+			float_framebuffers,
+			debug_groups: has(&["GL_KHR_debug"]),
+			max_anisotropy: anisotropic_filtering.then_some(max_anisotropy),
+			max_texture_size,
+			extensions,
+		}
+	}
+
+	/// Query capabilities of a context.
+	pub fn query(gl: &glow::Context) -> Self {
+		let extensions = gl.supported_extensions().clone();
+		let max_texture_size = unsafe { gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) };
+		// querying the anisotropy limit without the extension is an error
+		let max_anisotropy = if ANISOTROPIC_FILTERING_EXTENSIONS
+			.iter()
+			.any(|name| extensions.contains(*name))
+		{
+			unsafe { gl.get_parameter_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY_EXT) }
+		} else {
+			1.
+		};
+
+		Self::new(gl.version(), extensions, max_texture_size as u32, max_anisotropy)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn version(major: u32, is_embedded: bool) -> glow::Version {
+		glow::Version {
+			major,
+			minor: 0,
+			is_embedded,
+			revision: None,
+			vendor_info: String::new(),
+		}
+	}
+
+	fn extensions(names: &[&str]) -> HashSet<String> {
+		names.iter().map(|name| name.to_string()).collect()
+	}
+
+	#[test]
+	fn caps_from_extensions() {
+		let caps = RendererCaps::new(&version(3, true), extensions(&[]), 2048, 16.);
+		assert!(!caps.float_framebuffers && !caps.debug_groups);
+		assert_eq!(caps.max_anisotropy, None);
+		assert_eq!(caps.max_texture_size, 2048);
+
+		let advertised = extensions(&[
+			"GL_EXT_color_buffer_float",
+			"GL_KHR_debug",
+			"GL_EXT_texture_filter_anisotropic",
+		]);
+		let caps = RendererCaps::new(&version(3, true), advertised.clone(), 4096, 16.);
+		assert!(caps.float_framebuffers && caps.debug_groups);
+		assert_eq!(caps.max_anisotropy, Some(16.));
+		assert_eq!(caps.extensions, advertised);
+
+		// core in desktop OpenGL
+		let caps = RendererCaps::new(&version(3, false), extensions(&[]), 4096, 1.);
+		assert!(caps.float_framebuffers);
+	}
+}
@@ -3,6 +3,65 @@ use glow::HasContext;
 
 use super::OpenglRendererError;
 
+/// How static vertex data, positions and UVs, is laid out in buffers. Deforms change every frame and always get their own buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VertexLayout {
+	/// One buffer for positions, one for UVs.
+	#[default]
+	Separate,
+	/// One buffer alternating the position and UV of each vertex, for locality of vertex fetches on some GPUs.
+	Interleaved,
+}
+
+/// Where a vertex attribute is read from: which of the buffers built for a `VertexLayout`, and its stride and offset in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttribSource {
+	pub buffer: usize,
+	pub stride: usize,
+	pub offset: usize,
+}
+
+impl VertexLayout {
+	/// Static vertex buffers of this layout.
+	pub fn buffers(self, verts: &[Vec2], uvs: &[Vec2]) -> Vec<Vec<Vec2>> {
+		match self {
+			VertexLayout::Separate => vec![verts.to_vec(), uvs.to_vec()],
+			VertexLayout::Interleaved => vec![verts.iter().zip(uvs).flat_map(|(vert, uv)| [*vert, *uv]).collect()],
+		}
+	}
+
+	/// Sources of the position and UV attributes in `.buffers()`.
+	pub fn attrib_sources(self) -> [AttribSource; 2] {
+		let size = std::mem::size_of::<Vec2>();
+		match self {
+			VertexLayout::Separate => [
+				AttribSource {
+					buffer: 0,
+					stride: size,
+					offset: 0,
+				},
+				AttribSource {
+					buffer: 1,
+					stride: size,
+					offset: 0,
+				},
+			],
+			VertexLayout::Interleaved => [
+				AttribSource {
+					buffer: 0,
+					stride: 2 * size,
+					offset: 0,
+				},
+				AttribSource {
+					buffer: 0,
+					stride: 2 * size,
+					offset: size,
+				},
+			],
+		}
+	}
+}
+
 /// Create and BIND an OpenGL buffer and upload data.
 ///
 /// # Errors
@@ -29,16 +88,46 @@ pub unsafe fn upload_array_to_gl<T>(
 	Ok(buffer)
 }
 
-/// Upload full deform buffer content.
+/// Upload deform buffer content, starting at vertex `offset`.
 ///
 /// # Safety
 ///
 /// The vertex array object created in `setup_gl_buffers()` must be bound and no new ARRAY_BUFFER is enabled.
-pub unsafe fn upload_deforms_to_gl(gl: &glow::Context, deforms: &[Vec2], buffer: glow::Buffer) {
+/// `offset + deforms.len()` must not exceed the length of the buffer.
+pub unsafe fn upload_deforms_to_gl(gl: &glow::Context, deforms: &[Vec2], offset: usize, buffer: glow::Buffer) {
 	gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
 
 	// Safety: same as those described in upload_array_to_gl().
 	let bytes: &[u8] = core::slice::from_raw_parts(deforms.as_ptr() as *const u8, std::mem::size_of_val(deforms));
 	// if the above preconditions are met, deform is then the currently bound ARRAY_BUFFER.
-	gl.buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, 0, bytes);
-}
\ No newline at end of file
+	gl.buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, (offset * std::mem::size_of::<Vec2>()) as i32, bytes);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Attribute values of all vertices, read from `buffers` like the GPU does.
+	fn read_attrib(buffers: &[Vec<Vec2>], source: AttribSource) -> Vec<Vec2> {
+		let buffer = &buffers[source.buffer];
+		let size = std::mem::size_of::<Vec2>();
+		(source.offset / size..buffer.len())
+			.step_by(source.stride / size)
+			.map(|i| buffer[i])
+			.collect()
+	}
+
+	#[test]
+	fn interleaved_matches_separate() {
+		let verts = [Vec2::new(-1., -1.), Vec2::new(1., -1.), Vec2::new(0., 1.)];
+		let uvs = [Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(0.5, 1.)];
+
+		let attribs = |layout: VertexLayout| {
+			let buffers = layout.buffers(&verts, &uvs);
+			layout.attrib_sources().map(|source| read_attrib(&buffers, source))
+		};
+		let separate = attribs(VertexLayout::Separate);
+		assert_eq!(separate, [verts.to_vec(), uvs.to_vec()]);
+		assert_eq!(attribs(VertexLayout::Interleaved), separate);
+	}
+}
@@ -0,0 +1,203 @@
+//! Offscreen OpenGL contexts and minimal models for tests that render and read back pixels.
+//!
+//! Contexts are created on the first EGL device, e.g. Mesa's software rasterizer, so no display is needed.
+
+use std::io::Cursor;
+use std::num::NonZeroU32;
+
+use glam::UVec2;
+use glow::HasContext;
+use glutin::api::egl::{context::PossiblyCurrentContext, device::Device, display::Display, surface::Surface};
+use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
+use glutin::prelude::*;
+use glutin::surface::{PbufferSurface, SurfaceAttributesBuilder};
+use image::{ImageBuffer, ImageFormat, Rgba};
+use json::{object, JsonValue};
+
+use inox2d::model::{Model, ModelTexture};
+use inox2d::puppet::Puppet;
+use inox2d::render::InoxRendererExt;
+
+use crate::{OpenglRenderer, RendererOptions};
+
+/// A renderer drawing to an offscreen surface, which is its default framebuffer.
+pub(crate) struct HeadlessRenderer {
+	// dropped before the context it was created with
+	pub renderer: OpenglRenderer,
+	_surface: Surface<PbufferSurface>,
+	_context: PossiblyCurrentContext,
+}
+
+impl HeadlessRenderer {
+	/// Create a renderer for `model` with a viewport of `size`. `None` if there is no EGL device to render with.
+	pub fn new(model: &Model, size: UVec2) -> Option<Self> {
+		Self::new_with_options(model, size, RendererOptions::default())
+	}
+
+	/// Same as `new()`, with the renderer set up with `options`.
+	pub fn new_with_options(model: &Model, size: UVec2, options: RendererOptions) -> Option<Self> {
+		let Some(device) = Device::query_devices().ok().and_then(|mut devices| devices.next()) else {
+			eprintln!("no EGL device, skipping test that renders");
+			return None;
+		};
+
+		unsafe {
+			let display = Display::with_device(&device, None).expect("EGL display");
+			let template = ConfigTemplateBuilder::new()
+				.with_surface_type(ConfigSurfaceTypes::PBUFFER)
+				.with_alpha_size(8)
+				.with_stencil_size(8)
+				.build();
+			let config = display
+				.find_configs(template)
+				.expect("EGL configs")
+				.next()
+				.expect("EGL config");
+			let context_attributes = ContextAttributesBuilder::new()
+				.with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+				.build(None);
+			let context = display
+				.create_context(&config, &context_attributes)
+				.expect("OpenGL 3.3 context");
+			let surface_attributes = SurfaceAttributesBuilder::<PbufferSurface>::new()
+				.build(NonZeroU32::new(size.x).unwrap(), NonZeroU32::new(size.y).unwrap());
+			let surface = display
+				.create_pbuffer_surface(&config, &surface_attributes)
+				.expect("pbuffer surface");
+			let context = context.make_current(&surface).expect("current context");
+
+			let gl = glow::Context::from_loader_function_cstr(|symbol| display.get_proc_address(symbol));
+			let mut renderer = OpenglRenderer::new_with_options(gl, model, options).expect("renderer");
+			renderer.resize(size.x, size.y);
+
+			Some(Self {
+				renderer,
+				_surface: surface,
+				_context: context,
+			})
+		}
+	}
+
+	/// Draw one frame of `puppet` over a transparent background, and read it back. See `OpenglRenderer::read_pixels()`.
+	pub fn render(&self, puppet: &mut Puppet) -> Vec<u8> {
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let renderer = &self.renderer;
+		renderer.clear();
+		renderer.on_begin_draw(puppet);
+		renderer.draw(puppet);
+		renderer.on_end_draw(puppet);
+		renderer.read_pixels().1
+	}
+
+	/// The framebuffer as is, bottom row first, without the unpremultiplying of `OpenglRenderer::read_pixels()`.
+	pub fn read_framebuffer(&self) -> Vec<u8> {
+		let size = self.renderer.viewport;
+		let mut pixels = vec![0; size.x as usize * size.y as usize * 4];
+		unsafe {
+			let gl = &self.renderer.gl;
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+			gl.read_pixels(
+				0,
+				0,
+				size.x as i32,
+				size.y as i32,
+				glow::RGBA,
+				glow::UNSIGNED_BYTE,
+				glow::PixelPackData::Slice(&mut pixels),
+			);
+		}
+		pixels
+	}
+}
+
+/// A Part drawing texture `texture` over the `size` pixel rectangle centered on the origin,
+/// which is the whole viewport of a default camera.
+pub(crate) fn part(uuid: u32, name: &str, texture: u32, size: UVec2) -> JsonValue {
+	let (x, y) = (size.x as f32 / 2.0, size.y as f32 / 2.0);
+	object! {
+		uuid: uuid,
+		name: name,
+		"type": "Part",
+		enabled: true,
+		zsort: 0.0,
+		transform: {
+			trans: [0.0, 0.0, 0.0],
+			rot: [0.0, 0.0, 0.0],
+			scale: [1.0, 1.0],
+		},
+		lockToRoot: false,
+		blend_mode: "Normal",
+		textures: [texture],
+		mesh: {
+			verts: [-x, -y, x, -y, -x, y, x, y],
+			uvs: [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+			indices: [0, 1, 2, 2, 1, 3],
+			origin: [0.0, 0.0],
+		},
+		children: [],
+	}
+}
+
+/// A model with `parts` under the root node, and RGBA8 `textures` of the given width and pixels.
+pub(crate) fn model(parts: Vec<JsonValue>, textures: &[(u32, &[[u8; 4]])]) -> Model {
+	let payload = object! {
+		meta: {
+			name: "Test Puppet",
+			version: "1.0-alpha",
+			rigger: null,
+			artist: null,
+			copyright: null,
+			licenseURL: null,
+			contact: null,
+			reference: null,
+			preservePixels: false,
+		},
+		physics: {
+			pixelsPerMeter: 1000.0,
+			gravity: 9.8,
+		},
+		nodes: {
+			uuid: 0,
+			name: "root",
+			"type": "Node",
+			enabled: true,
+			zsort: 0.0,
+			transform: {
+				trans: [0.0, 0.0, 0.0],
+				rot: [0.0, 0.0, 0.0],
+				scale: [1.0, 1.0],
+			},
+			lockToRoot: false,
+			children: parts,
+		},
+		param: [],
+	};
+
+	let mut puppet = Puppet::new_from_json(&payload).expect("Test payload must be valid.");
+	puppet.init_transforms();
+	puppet.init_rendering();
+	puppet.init_params();
+	puppet.init_physics();
+
+	let textures = textures.iter().map(|(width, pixels)| png(*width, pixels)).collect();
+	Model {
+		puppet,
+		textures,
+		vendors: Vec::new(),
+	}
+}
+
+fn png(width: u32, pixels: &[[u8; 4]]) -> ModelTexture {
+	let height = pixels.len() as u32 / width;
+	let mut data = Vec::new();
+	ImageBuffer::<Rgba<u8>, _>::from_fn(width, height, |x, y| Rgba(pixels[(y * width + x) as usize]))
+		.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+		.unwrap();
+	ModelTexture {
+		format: ImageFormat::Png,
+		data: data.into(),
+	}
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use inox2d::node::InoxNodeUuid;
+
+/// Callback run right after a node is drawn, given the context it is drawn with.
+pub type NodeDrawnHook<C> = Box<dyn Fn(&C, InoxNodeUuid)>;
+
+/// Callbacks registered for specific nodes.
+pub(crate) struct DrawHooks<C> {
+	hooks: HashMap<InoxNodeUuid, Vec<NodeDrawnHook<C>>>,
+}
+
+impl<C> Default for DrawHooks<C> {
+	fn default() -> Self {
+		Self { hooks: HashMap::new() }
+	}
+}
+
+impl<C> DrawHooks<C> {
+	pub fn add(&mut self, id: InoxNodeUuid, hook: NodeDrawnHook<C>) {
+		self.hooks.entry(id).or_default().push(hook);
+	}
+
+	pub fn remove(&mut self, id: InoxNodeUuid) {
+		self.hooks.remove(&id);
+	}
+
+	/// Run hooks of node `id`, in order of registration. `false` if there are none.
+	pub fn node_drawn(&self, ctx: &C, id: InoxNodeUuid) -> bool {
+		let Some(hooks) = self.hooks.get(&id) else {
+			return false;
+		};
+		hooks.iter().for_each(|hook| hook(ctx, id));
+		true
+	}
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+	use std::cell::RefCell;
+	use std::rc::Rc;
+
+	use glam::uvec2;
+
+	use crate::headless;
+
+	use super::*;
+
+	#[test]
+	fn hooks_fire_once_per_frame_in_draw_order() {
+		let size = uvec2(4, 4);
+		let white = [[255; 4]; 16];
+		let part = |uuid, texture, zsort: f32| {
+			let mut part = headless::part(uuid, "part", texture, size);
+			part["zsort"] = zsort.into();
+			part
+		};
+		// drawn in order 4, 1, 2 and 3 batched together, then 5 masked by 4
+		let mut masked = part(5, 0, 0.0);
+		masked["masks"] = json::array![json::object! { source: 4, mode: "Mask" }];
+		let mut model = headless::model(
+			vec![
+				part(1, 1, 2.0),
+				part(2, 0, 1.0),
+				part(3, 0, 1.0),
+				part(4, 0, 3.0),
+				masked,
+			],
+			&[(size.x, &white), (size.x, &white)],
+		);
+		let Some(mut headless) = headless::HeadlessRenderer::new(&model, size) else {
+			return;
+		};
+
+		let fired = Rc::new(RefCell::new(Vec::new()));
+		for id in 1..=5 {
+			let fired = fired.clone();
+			headless.renderer.on_node_drawn(
+				InoxNodeUuid::new(id),
+				Box::new(move |_, id| fired.borrow_mut().push(id.raw())),
+			);
+		}
+
+		// not run again for the mask source drawn as a mask
+		headless.render(&mut model.puppet);
+		assert_eq!(fired.take(), [4, 1, 2, 3, 5]);
+
+		// run for every part of a batch
+		headless.renderer.batch_parts = true;
+		headless.render(&mut model.puppet);
+		assert_eq!(fired.take(), [4, 1, 2, 3, 5]);
+
+		headless.renderer.remove_node_hooks(InoxNodeUuid::new(2));
+		headless.render(&mut model.puppet);
+		assert_eq!(fired.take(), [4, 1, 3, 5]);
+	}
+}
@@ -1,32 +1,61 @@
+mod caps;
 mod gl_buffer;
+#[cfg(all(test, target_os = "linux"))]
+mod headless;
+mod hooks;
 mod shader;
 mod shaders;
 pub mod texture;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::mem;
 use std::ops::Deref;
 
-use glam::{uvec2, UVec2, Vec3};
+use glam::{uvec2, Mat4, UVec2, Vec3, Vec4};
 use glow::HasContext;
 
 use inox2d::math::camera::Camera;
 use inox2d::model::Model;
 use inox2d::node::{
 	components::{BlendMode, Mask, MaskMode, Masks, TexturedMesh},
-	drawables::{CompositeComponents, TexturedMeshComponents},
+	drawables::{CompositeComponents, PlainMeshComponents, TexturedMeshComponents},
 	InoxNodeUuid,
 };
 use inox2d::puppet::Puppet;
 use inox2d::render::{CompositeRenderCtx, InoxRenderer, TexturedMeshRenderCtx};
-use inox2d::texture::{decode_model_textures, TextureId};
+use inox2d::texture::{decode_model_textures_with_options, DecodeOptions, ShallowTexture, TextureId, TextureWrapping};
 
+pub use self::caps::RendererCaps;
+use self::hooks::DrawHooks;
+pub use self::hooks::NodeDrawnHook;
 use self::shader::ShaderCompileError;
-use self::shaders::{CompositeMaskShader, CompositeShader, PartMaskShader, PartShader};
-use self::texture::Texture;
+use self::shaders::{CompositeMaskShader, CompositeShader, PartMaskShader, PartShader, PlainMaskShader};
+use self::texture::{Texture, TextureSlots, TextureUpload};
 
+pub use gl_buffer::VertexLayout;
 use gl_buffer::{upload_array_to_gl, upload_deforms_to_gl};
 
+/// Texture slot of the coverage of alpha coverage masks, after the three part textures.
+const COVERAGE_MASK_SLOT: u32 = 3;
+/// Texture slot of the copy of what is below, for blend modes that `BlendMode::reads_backdrop()`.
+const BACKDROP_SLOT: u32 = 4;
+/// Texture slot of the copy of what is below a composite, for lower layer blend modes in it, see `shader_blend_mode()`.
+const COMPOSITE_LOWER_SLOT: u32 = 5;
+
+/// Bytes per viewport pixel of framebuffer textures: RGBA8 composite albedo, RGBA32F composite emissive,
+/// RGBA8 composite bumpmap, DEPTH24_STENCIL8 composite stencil, RGBA8 mask coverage, RGBA8 backdrop,
+/// RGBA8 composite lower layer.
+const FRAMEBUFFER_BYTES_PER_PIXEL: usize = 4 + 16 + 4 + 4 + 4 + 4 + 4;
+
+/// GPU memory taken by textures: model textures of `model_texture_bytes` each, framebuffer textures for `viewport`,
+/// and the two 1x1 flat textures.
+fn texture_memory(model_texture_bytes: impl Iterator<Item = usize>, viewport: UVec2) -> usize {
+	let framebuffer_bytes = viewport.x as usize * viewport.y as usize * FRAMEBUFFER_BYTES_PER_PIXEL;
+	let flat_texture_bytes = 2 * texture::texture_bytes(1, 1, 32);
+	model_texture_bytes.sum::<usize>() + framebuffer_bytes + flat_texture_bytes
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("Could not initialize OpenGL renderer: {0}")]
 pub enum OpenglRendererError {
@@ -38,6 +67,8 @@ pub enum OpenglRendererError {
 struct GlCache {
 	pub camera: Option<Camera>,
 	pub viewport: Option<UVec2>,
+	/// Matrix of `camera` into `viewport`, see `.update_camera_matrix()`.
+	pub camera_matrix: Mat4,
 	pub blend_mode: Option<BlendMode>,
 	pub program: Option<glow::Program>,
 	pub albedo: Option<TextureId>,
@@ -64,6 +95,10 @@ impl GlCache {
 				prev_camera.scale = camera.scale;
 				changed = true;
 			}
+			if (prev_camera.flip_x, prev_camera.flip_y) != (camera.flip_x, camera.flip_y) {
+				(prev_camera.flip_x, prev_camera.flip_y) = (camera.flip_x, camera.flip_y);
+				changed = true;
+			}
 
 			changed
 		} else {
@@ -72,6 +107,18 @@ impl GlCache {
 		}
 	}
 
+	/// Updates the camera and viewport, and recomputes `camera_matrix` if either changed. Returns whether they did.
+	pub fn update_camera_matrix(&mut self, camera: &Camera, viewport: UVec2) -> bool {
+		let camera_changed = self.update_camera(camera);
+		let viewport_changed = self.update_viewport(viewport);
+		if !camera_changed && !viewport_changed {
+			return false;
+		}
+
+		self.camera_matrix = camera.matrix(viewport.as_vec2());
+		true
+	}
+
 	pub fn update_viewport(&mut self, viewport: UVec2) -> bool {
 		if let Some(prev_viewport) = self.viewport.replace(viewport) {
 			prev_viewport != viewport
@@ -105,15 +152,351 @@ impl GlCache {
 	}
 }
 
+/// Color adjustments applied to the whole puppet when it is drawn to the output, on top of per-part tints.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlobalAdjustments {
+	/// Multiplied with the output color.
+	pub tint: Vec3,
+	/// Output color is raised to the power of `1 / gamma`, so values above 1 brighten.
+	pub gamma: f32,
+}
+
+impl Default for GlobalAdjustments {
+	fn default() -> Self {
+		Self {
+			tint: Vec3::ONE,
+			gamma: 1.0,
+		}
+	}
+}
+
+/// How masks cut their masked content.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaskingMode {
+	/// Content is kept where mask sources are more opaque than the threshold, with hard edges.
+	#[default]
+	Stencil,
+	/// Content is multiplied by the mask sources' alpha, smoothed around the threshold, for soft edges.
+	///
+	/// Mask sources cannot be masked themselves, drawables masked this way are skipped with an error.
+	AlphaCoverage,
+}
+
+/// Stencil value the masked content is drawn over in `MaskingMode::Stencil`.
+const STENCIL_SHOWN: i32 = 1;
+
+/// Stencil value written where a mask source of `mode` is drawn: `Mask` shows content, `Dodge` hides it.
+fn mask_stencil_value(mode: MaskMode) -> i32 {
+	match mode {
+		MaskMode::Mask => STENCIL_SHOWN,
+		MaskMode::Dodge => 1 - STENCIL_SHOWN,
+	}
+}
+
+/// Arguments of `glStencilFunc()` and `glStencilMask()`, and whether color is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct StencilOps {
+	func: u32,
+	reference: i32,
+	func_mask: u32,
+	write_mask: u32,
+	color: bool,
+}
+
+/// Masks of one drawable, while its mask sources or its content are drawn.
+struct MaskLevel {
+	threshold: f32,
+	/// Mode of the mask source being drawn.
+	mode: MaskMode,
+	/// Whether the masked content is being drawn, after the mask sources.
+	content: bool,
+}
+
+/// Masks being drawn, one level per drawable, the innermost last.
+///
+/// Content masked by a level other than the outermost is a mask source of the level below.
+/// In `MaskingMode::Stencil`, each level has its own stencil bit, the outermost the lowest,
+/// so that such content is drawn into the bit of the level below instead of the color buffer.
+#[derive(Default)]
+struct MaskLevels(Vec<MaskLevel>);
+
+impl MaskLevels {
+	/// One level per bit of the stencil buffer.
+	const MAX_STENCIL_LEVELS: usize = 8;
+
+	/// Opens a level, returning the stencil value and write mask to clear the stencil with, as in the reference implementation:
+	/// content is hidden until a `Mask` source shows it, or shown until a `Dodge` source hides it if there are only dodges.
+	fn begin(&mut self, masks: &Masks) -> (i32, u32) {
+		let level = self.0.len();
+		self.0.push(MaskLevel {
+			threshold: masks.threshold.clamp(0.0, 1.0),
+			mode: MaskMode::Mask,
+			content: false,
+		});
+		let clear = if masks.has_masks() {
+			1 - STENCIL_SHOWN
+		} else {
+			STENCIL_SHOWN
+		};
+		(clear << level, 1 << level)
+	}
+
+	fn innermost(&mut self) -> &mut MaskLevel {
+		self.0
+			.last_mut()
+			.expect("Masks must be begun before their sources and content.")
+	}
+
+	fn begin_mask(&mut self, mode: MaskMode) {
+		self.innermost().mode = mode;
+	}
+
+	fn begin_content(&mut self) {
+		self.innermost().content = true;
+	}
+
+	fn end(&mut self) {
+		self.0.pop();
+	}
+
+	/// Mode and threshold mask sources are drawn with, that is by the innermost level,
+	/// or by the level below when drawing masked content that is itself a mask source.
+	fn source(&self) -> Option<(MaskMode, f32)> {
+		let mut levels = self.0.iter().rev();
+		let innermost = levels.next()?;
+		let level = if innermost.content { levels.next()? } else { innermost };
+		Some((level.mode, level.threshold))
+	}
+
+	/// Stencil settings of what is drawn in the innermost level, `None` outside of masks.
+	fn stencil(&self) -> Option<StencilOps> {
+		let level = self.0.len().checked_sub(1)?;
+		let innermost = &self.0[level];
+		if !innermost.content {
+			return Some(StencilOps {
+				func: glow::ALWAYS,
+				reference: mask_stencil_value(innermost.mode) << level,
+				func_mask: 0xff,
+				write_mask: 1 << level,
+				color: false,
+			});
+		}
+
+		let content = StencilOps {
+			func: glow::EQUAL,
+			reference: STENCIL_SHOWN << level,
+			func_mask: 1 << level,
+			write_mask: 0x00,
+			color: true,
+		};
+		Some(match level.checked_sub(1) {
+			Some(outer) => StencilOps {
+				reference: content.reference | mask_stencil_value(self.0[outer].mode) << outer,
+				write_mask: 1 << outer,
+				color: false,
+				..content
+			},
+			None => content,
+		})
+	}
+}
+
+/// Color a composite framebuffer is cleared to before drawing children of a composite with `blend_mode`.
+///
+/// This is the backdrop that leaves the output unchanged when the composite is blended onto it where no child is drawn:
+/// opaque white for `Multiply`, transparent black otherwise.
+pub fn composite_clear_color(blend_mode: BlendMode) -> Vec4 {
+	match blend_mode {
+		BlendMode::Multiply => Vec4::ONE,
+		_ => Vec4::ZERO,
+	}
+}
+
+/// Whether the composite framebuffer is cleared before drawing children of a composite,
+/// consuming a clear requested with `OpenglRenderer::clear_composites()`.
+fn take_composite_clear(accumulate: bool, clear_requested: &Cell<bool>) -> bool {
+	clear_requested.replace(false) || !accumulate
+}
+
+/// Reverse the order of rows of `width` RGBA8 pixels, converting between bottom-left and top-left origins.
+fn flip_rows(pixels: &mut [u8], width: usize) {
+	let row_bytes = width * 4;
+	if row_bytes == 0 {
+		return;
+	}
+	let rows = pixels.len() / row_bytes;
+	for i in 0..rows / 2 {
+		let (top, bottom) = pixels.split_at_mut((rows - 1 - i) * row_bytes);
+		top[i * row_bytes..(i + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+	}
+}
+
+/// Divide colors of RGBA8 pixels by their alpha, turning premultiplied pixels into straight alpha ones.
+/// Fully transparent pixels become transparent black.
+fn unpremultiply(pixels: &mut [u8]) {
+	for pixel in pixels.chunks_exact_mut(4) {
+		let alpha = pixel[3] as u32;
+		for c in &mut pixel[..3] {
+			*c = match alpha {
+				0 => 0,
+				_ => ((*c as u32 * 255 + alpha / 2) / alpha).min(255) as u8,
+			};
+		}
+	}
+}
+
+/// Texture bound on a slot for drawing a part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PartTexture {
+	Model(TextureId),
+	/// Flat opaque white.
+	White,
+	/// Flat transparent black.
+	Black,
+}
+
+/// Textures bound on slots 0 (albedo), 1 (bumpmap) and 2 (emissive) for drawing `part`.
+fn part_textures(part: &TexturedMesh, geometry_only: bool) -> [PartTexture; 3] {
+	if geometry_only {
+		[PartTexture::White, PartTexture::Black, PartTexture::Black]
+	} else {
+		[
+			PartTexture::Model(part.tex_albedo),
+			PartTexture::Model(part.tex_bumpmap),
+			PartTexture::Model(part.tex_emissive),
+		]
+	}
+}
+
+/// Where part textures are bound: the OpenGL context of the renderer, or a simulated one in tests.
+trait PartTextureBinder {
+	/// Upload model texture `id` if it is pending, which leaves no texture bound on the active slot.
+	fn upload(&self, id: TextureId);
+	fn bind(&self, texture: PartTexture, slot: u32);
+}
+
+/// Bind the textures of `part` on slots 0 to 2, unless `cache` knows its albedo is bound already.
+fn bind_part_textures(binder: &impl PartTextureBinder, cache: &mut GlCache, part: &TexturedMesh, geometry_only: bool) {
+	if geometry_only {
+		// placeholders are cheap to bind, and model textures must be rebound once geometry-only mode is left
+		cache.albedo = None;
+	} else if !cache.update_albedo(part.tex_albedo) {
+		return;
+	}
+
+	let textures = part_textures(part, geometry_only);
+	// uploads must not unbind textures bound on previous slots
+	for texture in textures {
+		if let PartTexture::Model(id) = texture {
+			binder.upload(id);
+		}
+	}
+	for (slot, texture) in textures.into_iter().enumerate() {
+		binder.bind(texture, slot as u32);
+	}
+}
+
+/// Blend equation, source and destination factors realizing `blend_mode`.
+///
+/// Modes that `BlendMode::reads_backdrop()` are blended in the shader, see `shader_blend_mode()`,
+/// and drawn over what is below as in `Normal`.
+fn blend_func(blend_mode: BlendMode) -> (u32, u32, u32) {
+	match blend_mode {
+		BlendMode::Normal | BlendMode::Overlay | BlendMode::HardLight | BlendMode::Darken | BlendMode::Lighten => {
+			(glow::FUNC_ADD, glow::ONE, glow::ONE_MINUS_SRC_ALPHA)
+		}
+		BlendMode::Multiply => (glow::FUNC_ADD, glow::DST_COLOR, glow::ONE_MINUS_SRC_ALPHA),
+		BlendMode::ColorDodge => (glow::FUNC_ADD, glow::DST_COLOR, glow::ONE),
+		BlendMode::LinearDodge => (glow::FUNC_ADD, glow::ONE, glow::ONE),
+		BlendMode::Screen => (glow::FUNC_ADD, glow::ONE, glow::ONE_MINUS_SRC_COLOR),
+		BlendMode::ClipToLower => (glow::FUNC_ADD, glow::DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA),
+		BlendMode::SliceFromLower => (
+			glow::FUNC_SUBTRACT,
+			glow::ONE_MINUS_DST_ALPHA,
+			glow::ONE_MINUS_SRC_ALPHA,
+		),
+	}
+}
+
+/// Blend equation, source and destination factors of lower layer modes in composites, see `shader_blend_mode()`.
+///
+/// They are blended in the shader with the composite content drawn so far, which the result replaces.
+const LOWER_LAYER_BLEND_FUNC: (u32, u32, u32) = (glow::FUNC_ADD, glow::ONE, glow::ZERO);
+
+/// Values of the `blendMode` uniform of part and composite shaders, which declare the same constants.
+const SHADER_BLEND_FIXED_FUNCTION: i32 = 0;
+const SHADER_BLEND_OVERLAY: i32 = 1;
+const SHADER_BLEND_HARD_LIGHT: i32 = 2;
+/// `ClipToLower` in composites, see `shader_blend_mode()`.
+const SHADER_BLEND_CLIP_TO_LOWER: i32 = 3;
+/// `SliceFromLower` in composites, see `shader_blend_mode()`.
+const SHADER_BLEND_SLICE_FROM_LOWER: i32 = 4;
+const SHADER_BLEND_DARKEN: i32 = 5;
+const SHADER_BLEND_LIGHTEN: i32 = 6;
+
+/// Value of the `blendMode` uniform of part and composite shaders for `blend_mode`, of content drawn `in_composite`.
+///
+/// `ClipToLower` and `SliceFromLower` blend with the alpha of what is below. In a composite, that is the composite
+/// content drawn so far over what is below the composite, which starts out cleared in the composite framebuffer,
+/// so parts in composites are blended in the shader with that combined alpha, see `LOWER_LAYER_BLEND_FUNC`.
+fn shader_blend_mode(blend_mode: BlendMode, in_composite: bool) -> i32 {
+	match blend_mode {
+		BlendMode::Overlay => SHADER_BLEND_OVERLAY,
+		BlendMode::HardLight => SHADER_BLEND_HARD_LIGHT,
+		BlendMode::ClipToLower if in_composite => SHADER_BLEND_CLIP_TO_LOWER,
+		BlendMode::SliceFromLower if in_composite => SHADER_BLEND_SLICE_FROM_LOWER,
+		BlendMode::Darken => SHADER_BLEND_DARKEN,
+		BlendMode::Lighten => SHADER_BLEND_LIGHTEN,
+		_ => SHADER_BLEND_FIXED_FUNCTION,
+	}
+}
+
+/// How an `OpenglRenderer` is set up, see `OpenglRenderer::new_with_options()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RendererOptions {
+	pub texture_upload: TextureUpload,
+	pub vertex_layout: VertexLayout,
+	/// How model textures are decoded, premultiplied by default.
+	pub decode: DecodeOptions,
+}
+
 pub struct OpenglRenderer {
 	gl: glow::Context,
 	support_debug_extension: bool,
 	pub camera: Camera,
 	pub viewport: UVec2,
+	/// Multiply part colors by their texture alpha, so that the rendered output is premultiplied.
+	/// Only has an effect with textures decoded straight alpha, see `RendererOptions::decode`,
+	/// premultiplied textures always render premultiplied output.
+	pub output_premultiplied: bool,
+	pub masking_mode: MaskingMode,
+	/// Draw all parts flat white instead of with their textures, to look at deformations without texture noise.
+	pub geometry_only: bool,
+	/// Draw consecutive parts sharing all draw state with one draw call, see `InoxRenderer::batch_parts()`.
+	pub batch_parts: bool,
+	/// Keep the content of the composite framebuffer instead of clearing it before drawing each composite,
+	/// so that composites accumulate over frames, e.g. for motion trails. Clear on demand with `.clear_composites()`.
+	///
+	/// All composites share one framebuffer: with several composites, each one accumulates the others as well.
+	/// Its content is lost on `.resize()`. Composite clear colors are only applied on clears.
+	pub accumulate_composites: bool,
+	composite_clear_requested: Cell<bool>,
+	global_adjustments: GlobalAdjustments,
+	/// Overrides of `composite_clear_color()` for specific composites.
+	composite_clear_colors: HashMap<InoxNodeUuid, Vec4>,
+	draw_hooks: DrawHooks<glow::Context>,
 	cache: RefCell<GlCache>,
+	/// Whether content is being drawn into the composite framebuffer rather than the output.
+	in_composite: Cell<bool>,
+	/// Whether masked content is being drawn in `MaskingMode::AlphaCoverage`.
+	coverage_masking: Cell<bool>,
+	/// Masks being drawn, to restore mask state after a composite mask source or the masks of a mask source.
+	mask_levels: RefCell<MaskLevels>,
 
 	vao: glow::VertexArray,
 	deform_buffer: glow::Buffer,
+	/// Deform generation of the puppet last uploaded to `deform_buffer`, see `RenderCtx::dirty_deform_range_since()`.
+	uploaded_deform_generation: Cell<u64>,
 
 	composite_framebuffer: glow::Framebuffer,
 	cf_albedo: glow::Texture,
@@ -121,12 +504,27 @@ pub struct OpenglRenderer {
 	cf_bump: glow::Texture,
 	cf_stencil: glow::Texture,
 
+	mask_framebuffer: glow::Framebuffer,
+	mf_coverage: glow::Texture,
+
+	/// Copy of the content framebuffer, for blend modes that `BlendMode::reads_backdrop()`.
+	backdrop: glow::Texture,
+	/// Copy of what is below the composite being drawn, see `shader_blend_mode()`.
+	composite_lower: glow::Texture,
+	/// Whether `composite_lower` holds what is below the composite being drawn.
+	composite_lower_copied: Cell<bool>,
+
 	part_shader: PartShader,
 	part_mask_shader: PartMaskShader,
+	plain_mask_shader: PlainMaskShader,
 	composite_shader: CompositeShader,
 	composite_mask_shader: CompositeMaskShader,
 
-	textures: Vec<Texture>,
+	textures: RefCell<TextureSlots<ShallowTexture, Texture>>,
+	/// Whether model textures were premultiplied when decoded.
+	textures_premultiplied: bool,
+	/// 1x1 white and transparent black textures, bound instead of model textures in geometry-only mode.
+	flat_textures: [Texture; 2],
 }
 
 impl OpenglRenderer {
@@ -135,6 +533,28 @@ impl OpenglRenderer {
 	/// - Decode textures.
 	/// - Upload static buffer data and textures.
 	pub fn new(gl: glow::Context, model: &Model) -> Result<Self, OpenglRendererError> {
+		Self::new_with_texture_upload(gl, model, TextureUpload::default())
+	}
+
+	/// Create an OpenglRenderer like `new()`, uploading textures of the model as specified by `texture_upload`.
+	pub fn new_with_texture_upload(
+		gl: glow::Context,
+		model: &Model,
+		texture_upload: TextureUpload,
+	) -> Result<Self, OpenglRendererError> {
+		let options = RendererOptions {
+			texture_upload,
+			..Default::default()
+		};
+		Self::new_with_options(gl, model, options)
+	}
+
+	/// Create an OpenglRenderer like `new()`, with everything set up as specified by `options`.
+	pub fn new_with_options(
+		gl: glow::Context,
+		model: &Model,
+		options: RendererOptions,
+	) -> Result<Self, OpenglRendererError> {
 		unsafe {
 			// Initialize framebuffers
 			let cf_albedo = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
@@ -144,9 +564,16 @@ impl OpenglRenderer {
 
 			let composite_framebuffer = gl.create_framebuffer().map_err(OpenglRendererError::Opengl)?;
 
+			let mf_coverage = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
+			let mask_framebuffer = gl.create_framebuffer().map_err(OpenglRendererError::Opengl)?;
+
+			let backdrop = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
+			let composite_lower = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
+
 			// Shaders
 			let part_shader = PartShader::new(&gl)?;
 			let part_mask_shader = PartMaskShader::new(&gl)?;
+			let plain_mask_shader = PlainMaskShader::new(&gl)?;
 			let composite_shader = CompositeShader::new(&gl)?;
 			let composite_mask_shader = CompositeMaskShader::new(&gl)?;
 
@@ -159,15 +586,20 @@ impl OpenglRenderer {
 			let vao = gl.create_vertex_array().map_err(OpenglRendererError::Opengl)?;
 			gl.bind_vertex_array(Some(vao));
 
-			let verts = inox_buffers.vertex_buffers.verts.as_slice();
-			upload_array_to_gl(&gl, verts, glow::ARRAY_BUFFER, glow::STATIC_DRAW)?;
-			gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
-			gl.enable_vertex_attrib_array(0);
-
-			let uvs = inox_buffers.vertex_buffers.uvs.as_slice();
-			upload_array_to_gl(&gl, uvs, glow::ARRAY_BUFFER, glow::STATIC_DRAW)?;
-			gl.vertex_attrib_pointer_f32(1, 2, glow::FLOAT, false, 0, 0);
-			gl.enable_vertex_attrib_array(1);
+			let vertex_buffers = &inox_buffers.vertex_buffers;
+			let static_buffers = (options
+				.vertex_layout
+				.buffers(&vertex_buffers.verts, &vertex_buffers.uvs)
+				.iter())
+			.map(|buffer| upload_array_to_gl(&gl, buffer, glow::ARRAY_BUFFER, glow::STATIC_DRAW))
+			.collect::<Result<Vec<_>, _>>()?;
+			// attributes 0 and 1 are positions and UVs
+			for (attrib, source) in options.vertex_layout.attrib_sources().into_iter().enumerate() {
+				gl.bind_buffer(glow::ARRAY_BUFFER, Some(static_buffers[source.buffer]));
+				let (stride, offset) = (source.stride as i32, source.offset as i32);
+				gl.vertex_attrib_pointer_f32(attrib as u32, 2, glow::FLOAT, false, stride, offset);
+				gl.enable_vertex_attrib_array(attrib as u32);
+			}
 
 			let deforms = inox_buffers.vertex_buffers.deforms.as_slice();
 			let deform_buffer = upload_array_to_gl(&gl, deforms, glow::ARRAY_BUFFER, glow::DYNAMIC_DRAW)?;
@@ -180,24 +612,43 @@ impl OpenglRenderer {
 			gl.bind_vertex_array(None);
 
 			// decode textures in parallel
-			let shalltexs = decode_model_textures(model.textures.iter());
-			let textures = (shalltexs.iter().enumerate())
-				.map(|e| {
-					tracing::debug!("Uploading shallow texture {:?}", e.0);
-					texture::Texture::from_shallow_texture(&gl, e.1)
+			let shalltexs = decode_model_textures_with_options(model.textures.iter(), options.decode);
+			let mut textures = TextureSlots::new(shalltexs);
+			if options.texture_upload == TextureUpload::Eager {
+				textures.upload_all(|shalltex| {
+					texture::Texture::from_shallow_texture(&gl, shalltex)
 						.map_err(|e| OpenglRendererError::Opengl(e.to_string()))
-				})
-				.collect::<Result<Vec<_>, _>>()?;
+				})?;
+			}
+
+			let flat_texture = |pixel: [u8; 4]| {
+				Texture::from_raw_pixels(&gl, &pixel, 1, 1).map_err(|e| OpenglRendererError::Opengl(e.to_string()))
+			};
+			let flat_textures = [flat_texture([255; 4])?, flat_texture([0; 4])?];
 
 			let renderer = Self {
 				gl,
 				support_debug_extension,
 				camera: Camera::default(),
 				viewport: UVec2::default(),
+				output_premultiplied: false,
+				textures_premultiplied: options.decode.premultiply,
+				masking_mode: MaskingMode::default(),
+				geometry_only: false,
+				batch_parts: false,
+				accumulate_composites: false,
+				composite_clear_requested: Cell::new(false),
+				global_adjustments: GlobalAdjustments::default(),
+				composite_clear_colors: HashMap::new(),
+				draw_hooks: DrawHooks::default(),
 				cache: RefCell::new(GlCache::default()),
+				in_composite: Cell::new(false),
+				coverage_masking: Cell::new(false),
+				mask_levels: RefCell::new(MaskLevels::default()),
 
 				vao,
 				deform_buffer,
+				uploaded_deform_generation: Cell::new(inox_buffers.deform_generation()),
 
 				composite_framebuffer,
 				cf_albedo,
@@ -205,17 +656,37 @@ impl OpenglRenderer {
 				cf_bump,
 				cf_stencil,
 
+				mask_framebuffer,
+				mf_coverage,
+
+				backdrop,
+				composite_lower,
+				composite_lower_copied: Cell::new(false),
+
 				part_shader,
 				part_mask_shader,
+				plain_mask_shader,
 				composite_shader,
 				composite_mask_shader,
 
-				textures,
+				textures: RefCell::new(textures),
+				flat_textures,
 			};
 
-			// Set emission strength once (it doesn't change anywhere else)
+			// Coverage of alpha coverage masks, the backdrop and the composite lower layer are always read from the same slots
 			renderer.bind_shader(&renderer.part_shader);
-			renderer.part_shader.set_emission_strength(&renderer.gl, 1.);
+			renderer
+				.part_shader
+				.set_coverage_mask_slot(&renderer.gl, COVERAGE_MASK_SLOT);
+			renderer.part_shader.set_backdrop_slot(&renderer.gl, BACKDROP_SLOT);
+			renderer
+				.part_shader
+				.set_composite_lower_slot(&renderer.gl, COMPOSITE_LOWER_SLOT);
+			renderer.bind_shader(&renderer.composite_shader);
+			renderer
+				.composite_shader
+				.set_coverage_mask_slot(&renderer.gl, COVERAGE_MASK_SLOT);
+			renderer.composite_shader.set_backdrop_slot(&renderer.gl, BACKDROP_SLOT);
 
 			Ok(renderer)
 		}
@@ -249,14 +720,13 @@ impl OpenglRenderer {
 
 	/// Updates the camera in the GL cache and returns whether it changed.
 	fn update_camera(&self) -> bool {
-		{
+		let matrix = {
 			let mut cache = self.cache.borrow_mut();
-			if !cache.update_camera(&self.camera) && !cache.update_viewport(self.viewport) {
+			if !cache.update_camera_matrix(&self.camera, self.viewport) {
 				return false;
 			}
-		}
-
-		let matrix = self.camera.matrix(self.viewport.as_vec2());
+			cache.camera_matrix
+		};
 
 		self.bind_shader(&self.composite_shader);
 		self.composite_shader.set_mvp(&self.gl, matrix);
@@ -267,44 +737,67 @@ impl OpenglRenderer {
 		true
 	}
 
+	/// Set up drawing in the innermost masks, in the current masking mode.
+	fn set_mask_state(&self) {
+		let gl = &self.gl;
+		let mask_levels = self.mask_levels.borrow();
+		if self.masking_mode == MaskingMode::AlphaCoverage {
+			let Some((mode, _)) = mask_levels.source() else {
+				return;
+			};
+			// Mask adds its coverage, Dodge removes it
+			unsafe {
+				gl.blend_equation(glow::FUNC_ADD);
+				match mode {
+					MaskMode::Mask => gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA),
+					MaskMode::Dodge => gl.blend_func(glow::ZERO, glow::ONE_MINUS_SRC_ALPHA),
+				}
+			}
+			self.cache.borrow_mut().blend_mode = None;
+		} else if let Some(stencil) = mask_levels.stencil() {
+			unsafe {
+				gl.stencil_func(stencil.func, stencil.reference, stencil.func_mask);
+				gl.stencil_mask(stencil.write_mask);
+				gl.color_mask(stencil.color, stencil.color, stencil.color, stencil.color);
+			}
+		}
+	}
+
+	/// Upload the threshold mask sources are drawn with, see `MaskLevels::source()`.
+	fn set_mask_threshold(&self) {
+		if let Some((_, threshold)) = self.mask_levels.borrow().source() {
+			self.bind_shader(&self.part_mask_shader);
+			self.part_mask_shader.set_threshold(&self.gl, threshold);
+		}
+	}
+
+	/// Set blending for content drawn with the `blendMode` uniform `shader_blend_mode`.
+	fn set_shader_blending(&self, blend_mode: BlendMode, shader_blend_mode: i32) {
+		match shader_blend_mode {
+			SHADER_BLEND_FIXED_FUNCTION => self.set_blend_mode(blend_mode),
+			SHADER_BLEND_CLIP_TO_LOWER | SHADER_BLEND_SLICE_FROM_LOWER => {
+				let (equation, src, dst) = LOWER_LAYER_BLEND_FUNC;
+				unsafe {
+					self.gl.blend_equation(equation);
+					self.gl.blend_func(src, dst);
+				}
+				self.cache.borrow_mut().blend_mode = None;
+			}
+			// modes blended in the shader are drawn as `Normal`
+			_ => self.set_blend_mode(BlendMode::Normal),
+		}
+	}
+
 	/// Set blending mode. See `BlendMode` for supported blend modes.
 	fn set_blend_mode(&self, blend_mode: BlendMode) {
 		if !self.cache.borrow_mut().update_blend_mode(blend_mode) {
 			return;
 		}
 
-		let gl = &self.gl;
+		let (equation, src, dst) = blend_func(blend_mode);
 		unsafe {
-			match blend_mode {
-				BlendMode::Normal => {
-					gl.blend_equation(glow::FUNC_ADD);
-					gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
-				}
-				BlendMode::Multiply => {
-					gl.blend_equation(glow::FUNC_ADD);
-					gl.blend_func(glow::DST_COLOR, glow::ONE_MINUS_SRC_ALPHA);
-				}
-				BlendMode::ColorDodge => {
-					gl.blend_equation(glow::FUNC_ADD);
-					gl.blend_func(glow::DST_COLOR, glow::ONE);
-				}
-				BlendMode::LinearDodge => {
-					gl.blend_equation(glow::FUNC_ADD);
-					gl.blend_func(glow::ONE, glow::ONE);
-				}
-				BlendMode::Screen => {
-					gl.blend_equation(glow::FUNC_ADD);
-					gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_COLOR);
-				}
-				BlendMode::ClipToLower => {
-					gl.blend_equation(glow::FUNC_ADD);
-					gl.blend_func(glow::DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
-				}
-				BlendMode::SliceFromLower => {
-					gl.blend_equation(glow::FUNC_SUBTRACT);
-					gl.blend_func(glow::ONE_MINUS_DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
-				}
-			}
+			self.gl.blend_equation(equation);
+			self.gl.blend_func(src, dst);
 		}
 	}
 
@@ -318,14 +811,17 @@ impl OpenglRenderer {
 	}
 
 	fn bind_part_textures(&self, part: &TexturedMesh) {
-		if !self.cache.borrow_mut().update_albedo(part.tex_albedo) {
-			return;
-		}
+		bind_part_textures(self, &mut self.cache.borrow_mut(), part, self.geometry_only);
+	}
 
+	/// Run `f` on texture `id` of the model, uploading it first in lazy mode.
+	fn with_model_texture(&self, id: TextureId, f: impl FnOnce(&Texture)) {
 		let gl = &self.gl;
-		self.textures[part.tex_albedo.raw()].bind_on(gl, 0);
-		self.textures[part.tex_bumpmap.raw()].bind_on(gl, 1);
-		self.textures[part.tex_emissive.raw()].bind_on(gl, 2);
+		let mut textures = self.textures.borrow_mut();
+		if let Some(texture) = textures.get_or_upload(id.raw(), |shalltex| Texture::from_shallow_texture(gl, shalltex))
+		{
+			f(texture);
+		}
 	}
 
 	/// Clear the texture cache
@@ -367,6 +863,15 @@ impl OpenglRenderer {
 			0,
 		);
 
+		gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.mask_framebuffer));
+		gl.framebuffer_texture_2d(
+			glow::FRAMEBUFFER,
+			glow::COLOR_ATTACHMENT0,
+			glow::TEXTURE_2D,
+			Some(self.mf_coverage),
+			0,
+		);
+
 		gl.bind_framebuffer(glow::FRAMEBUFFER, None);
 	}
 
@@ -381,6 +886,9 @@ impl OpenglRenderer {
 			texture::upload_empty(gl, self.cf_albedo, w, h, glow::UNSIGNED_BYTE);
 			texture::upload_empty(gl, self.cf_emissive, w, h, glow::FLOAT);
 			texture::upload_empty(gl, self.cf_bump, w, h, glow::UNSIGNED_BYTE);
+			texture::upload_empty(gl, self.mf_coverage, w, h, glow::UNSIGNED_BYTE);
+			texture::upload_empty(gl, self.backdrop, w, h, glow::UNSIGNED_BYTE);
+			texture::upload_empty(gl, self.composite_lower, w, h, glow::UNSIGNED_BYTE);
 
 			gl.bind_texture(glow::TEXTURE_2D, Some(self.cf_stencil));
 			gl.tex_image_2d(
@@ -400,6 +908,171 @@ impl OpenglRenderer {
 		self.update_camera();
 	}
 
+	/// Matrix of the camera into the viewport, as parts are drawn with, e.g. to map pointer positions onto the puppet.
+	///
+	/// Same as `self.camera.matrix(self.viewport.as_vec2())`, but only recomputed when either changed.
+	pub fn camera_matrix(&self) -> Mat4 {
+		self.update_camera();
+		self.cache.borrow().camera_matrix
+	}
+
+	/// Read back what was rendered to the default framebuffer over the current viewport, e.g. for thumbnails or tests.
+	///
+	/// Returns the viewport size and its RGBA8 pixels, row by row from the top-left corner. Pixels have straight alpha
+	/// like model textures as authored, also with premultiplied output, where the rendered colors are divided back by
+	/// their alpha.
+	pub fn read_pixels(&self) -> (UVec2, Vec<u8>) {
+		let size = self.viewport;
+		let mut pixels = vec![0; size.x as usize * size.y as usize * 4];
+
+		let gl = &self.gl;
+		unsafe {
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+			gl.read_pixels(
+				0,
+				0,
+				size.x as i32,
+				size.y as i32,
+				glow::RGBA,
+				glow::UNSIGNED_BYTE,
+				glow::PixelPackData::Slice(&mut pixels),
+			);
+		}
+
+		flip_rows(&mut pixels, size.x as usize);
+		if self.output_premultiplied || self.textures_premultiplied {
+			unpremultiply(&mut pixels);
+		}
+		(size, pixels)
+	}
+
+	/// Set how a texture of the model is sampled outside of its UV range. Textures clamp to transparent black by default.
+	pub fn set_texture_wrapping(&self, id: TextureId, wrapping: TextureWrapping) {
+		// in lazy mode, this uploads the texture
+		self.with_model_texture(id, |texture| texture.set_wrapping(&self.gl, wrapping));
+		// binding for the wrapping change left no texture bound
+		self.clear_texture_cache();
+	}
+
+	/// GPU memory taken by uploaded model textures and framebuffers of the current viewport size, in bytes.
+	///
+	/// With `TextureUpload::Lazy`, textures not drawn yet are not accounted for.
+	pub fn texture_memory_bytes(&self) -> usize {
+		let textures = self.textures.borrow();
+		texture_memory(textures.uploaded().map(Texture::memory_bytes), self.viewport)
+	}
+
+	/// Whether a texture of the model is on the GPU. Always the case after creation, unless using `TextureUpload::Lazy`.
+	pub fn is_texture_uploaded(&self, id: TextureId) -> bool {
+		self.textures.borrow().is_uploaded(id.raw())
+	}
+
+	/// Tint the whole puppet, e.g. for day/night lighting. Defaults to white, i.e. no tint.
+	pub fn set_global_tint(&mut self, tint: Vec3) {
+		self.global_adjustments.tint = tint;
+	}
+
+	/// Gamma adjust the whole puppet. Defaults to 1, i.e. no adjustment.
+	pub fn set_global_gamma(&mut self, gamma: f32) {
+		self.global_adjustments.gamma = gamma;
+	}
+
+	/// Clear composite `id` to `color` instead of the backdrop derived from its blend mode, see `composite_clear_color()`.
+	/// `None` removes the override.
+	pub fn set_composite_clear_color(&mut self, id: InoxNodeUuid, color: Option<Vec4>) {
+		match color {
+			Some(color) => self.composite_clear_colors.insert(id, color),
+			None => self.composite_clear_colors.remove(&id),
+		};
+	}
+
+	/// Clear the composite framebuffer before the next composite is drawn, when accumulating composites.
+	pub fn clear_composites(&self) {
+		self.composite_clear_requested.set(true);
+	}
+
+	/// Run `hook` every time node `id` is drawn, right after it is drawn, e.g. to draw a particle emitter attached to it.
+	///
+	/// Hooks do not run for nodes drawn as masks. The bound program, vertex array, textures and blend function are restored
+	/// after hooks run, other state such as the bound framebuffer or stencil settings must be left as hooks found it.
+	pub fn on_node_drawn(&mut self, id: InoxNodeUuid, hook: NodeDrawnHook<glow::Context>) {
+		self.draw_hooks.add(id, hook);
+	}
+
+	/// Remove all hooks of node `id` added by `.on_node_drawn()`.
+	pub fn remove_node_hooks(&mut self, id: InoxNodeUuid) {
+		self.draw_hooks.remove(id);
+	}
+
+	/// Run hooks of node `id`, then restore the state they may have changed.
+	fn run_draw_hooks(&self, id: InoxNodeUuid) {
+		if self.draw_hooks.node_drawn(&self.gl, id) {
+			self.cache.borrow_mut().clear();
+			unsafe { self.gl.bind_vertex_array(Some(self.vao)) };
+		}
+	}
+
+	/// Current global tint and gamma.
+	pub fn global_adjustments(&self) -> GlobalAdjustments {
+		self.global_adjustments
+	}
+
+	/// Adjustments for content drawn right now: parts inside composites are adjusted once the composite is drawn.
+	fn current_global_adjustments(&self) -> GlobalAdjustments {
+		if self.in_composite.get() {
+			GlobalAdjustments::default()
+		} else {
+			self.global_adjustments
+		}
+	}
+
+	/// What the OpenGL context of this renderer supports.
+	pub fn capabilities(&self) -> RendererCaps {
+		RendererCaps::query(&self.gl)
+	}
+
+	/// Framebuffer content is drawn to, outside of masks.
+	fn content_framebuffer(&self) -> Option<glow::Framebuffer> {
+		if self.in_composite.get() {
+			Some(self.composite_framebuffer)
+		} else {
+			None
+		}
+	}
+
+	/// Copy what has been drawn to the content framebuffer so far into the backdrop texture,
+	/// for drawing with a blend mode that is blended in the shader, see `shader_blend_mode()`.
+	fn copy_backdrop(&self) {
+		self.copy_framebuffer(self.content_framebuffer(), self.backdrop, BACKDROP_SLOT);
+	}
+
+	/// Copy what is below the composite being drawn into the composite lower layer texture, once per composite,
+	/// for drawing with `ClipToLower` or `SliceFromLower` in it. The output is left alone while drawing a composite.
+	fn copy_composite_lower(&self) {
+		if !self.composite_lower_copied.replace(true) {
+			self.copy_framebuffer(None, self.composite_lower, COMPOSITE_LOWER_SLOT);
+		}
+	}
+
+	/// Copy the albedo of `framebuffer` into `texture`, bound to `slot`.
+	fn copy_framebuffer(&self, framebuffer: Option<glow::Framebuffer>, texture: glow::Texture, slot: u32) {
+		let gl = &self.gl;
+		let (w, h) = (self.viewport.x as i32, self.viewport.y as i32);
+		unsafe {
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, framebuffer);
+			if framebuffer.is_some() {
+				gl.read_buffer(glow::COLOR_ATTACHMENT0);
+			}
+
+			gl.active_texture(glow::TEXTURE0 + slot);
+			gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+			gl.copy_tex_sub_image_2d(glow::TEXTURE_2D, 0, 0, 0, 0, 0, w, h);
+			gl.active_texture(glow::TEXTURE0);
+
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+		}
+	}
+
 	pub fn clear(&self) {
 		self.cache.borrow_mut().clear();
 
@@ -409,47 +1082,80 @@ impl OpenglRenderer {
 	}
 }
 
+impl PartTextureBinder for OpenglRenderer {
+	fn upload(&self, id: TextureId) {
+		self.with_model_texture(id, |_| ());
+	}
+
+	fn bind(&self, texture: PartTexture, slot: u32) {
+		match texture {
+			PartTexture::Model(id) => self.with_model_texture(id, |texture| texture.bind_on(&self.gl, slot)),
+			PartTexture::White => self.flat_textures[0].bind_on(&self.gl, slot),
+			PartTexture::Black => self.flat_textures[1].bind_on(&self.gl, slot),
+		}
+	}
+}
+
 impl InoxRenderer for OpenglRenderer {
 	fn on_begin_masks(&self, masks: &Masks) {
 		self.push_debug_group("inox2d - begin masks");
 
 		let gl = &self.gl;
+		let coverage = self.masking_mode == MaskingMode::AlphaCoverage;
+		let (stencil_clear, stencil_write_mask) = self.mask_levels.borrow_mut().begin(masks);
 
 		unsafe {
-			gl.enable(glow::STENCIL_TEST);
-			gl.clear_stencil(!masks.has_masks() as i32);
-			gl.clear(glow::STENCIL_BUFFER_BIT);
-
-			gl.color_mask(false, false, false, false);
-			gl.stencil_op(glow::KEEP, glow::KEEP, glow::REPLACE);
-			gl.stencil_mask(0xff);
+			if coverage {
+				// mask sources accumulate their coverage in a separate texture
+				gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.mask_framebuffer));
+				gl.clear_buffer_f32_slice(glow::COLOR, 0, &[!masks.has_masks() as u8 as f32; 4]);
+			} else {
+				// only the bit of this level is cleared, those of the levels below still hold their masks
+				gl.enable(glow::STENCIL_TEST);
+				gl.stencil_mask(stencil_write_mask);
+				gl.clear_stencil(stencil_clear);
+				gl.clear(glow::STENCIL_BUFFER_BIT);
+
+				gl.color_mask(false, false, false, false);
+				gl.stencil_op(glow::KEEP, glow::KEEP, glow::REPLACE);
+			}
 		}
 
-		let part_mask_shader = &self.part_mask_shader;
-		self.bind_shader(part_mask_shader);
-		part_mask_shader.set_threshold(gl, masks.threshold.clamp(0.0, 1.0));
+		self.set_mask_threshold();
+		self.part_mask_shader.set_coverage(gl, coverage);
 
 		self.pop_debug_group();
+
+		// mask sources and masked content, until `.on_end_mask()`
+		self.push_debug_group("inox2d - masks");
 	}
 
 	fn on_begin_mask(&self, mask: &Mask) {
 		self.push_debug_group("inox2d - begin mask");
 
-		let gl = &self.gl;
-		unsafe {
-			gl.stencil_func(glow::ALWAYS, (mask.mode == MaskMode::Mask) as i32, 0xff);
-		}
+		self.mask_levels.borrow_mut().begin_mask(mask.mode);
+		self.set_mask_state();
+
+		self.pop_debug_group();
 	}
 
 	fn on_begin_masked_content(&self) {
 		self.push_debug_group("inox2d - begin masked content");
 
 		let gl = &self.gl;
-		unsafe {
-			gl.stencil_func(glow::EQUAL, 1, 0xff);
-			gl.stencil_mask(0x00);
-
-			gl.color_mask(true, true, true, true);
+		self.mask_levels.borrow_mut().begin_content();
+		if self.masking_mode == MaskingMode::AlphaCoverage {
+			unsafe {
+				gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, self.content_framebuffer());
+				gl.active_texture(glow::TEXTURE0 + COVERAGE_MASK_SLOT);
+				gl.bind_texture(glow::TEXTURE_2D, Some(self.mf_coverage));
+				gl.active_texture(glow::TEXTURE0);
+			}
+			self.coverage_masking.set(true);
+		} else {
+			// content that is a mask source itself is drawn with the threshold of the masks it is a source of
+			self.set_mask_state();
+			self.set_mask_threshold();
 		}
 
 		self.pop_debug_group();
@@ -457,12 +1163,34 @@ impl InoxRenderer for OpenglRenderer {
 
 	fn on_end_mask(&self) {
 		let gl = &self.gl;
-		unsafe {
-			gl.stencil_mask(0xff);
-			gl.stencil_func(glow::ALWAYS, 1, 0xff);
-			gl.disable(glow::STENCIL_TEST);
+		self.mask_levels.borrow_mut().end();
+		if self.coverage_masking.replace(false) {
+			// the coverage texture must not stay bound while the next masks are drawn into it
+			unsafe {
+				gl.active_texture(glow::TEXTURE0 + COVERAGE_MASK_SLOT);
+				gl.bind_texture(glow::TEXTURE_2D, None);
+				gl.active_texture(glow::TEXTURE0);
+			}
+		} else if self.mask_levels.borrow().stencil().is_some() {
+			// back to the mask sources of the level below
+			self.set_mask_state();
+			self.set_mask_threshold();
+		} else {
+			unsafe {
+				gl.stencil_mask(0xff);
+				gl.stencil_func(glow::ALWAYS, 1, 0xff);
+				gl.disable(glow::STENCIL_TEST);
+			}
 		}
-		
+
+		self.pop_debug_group();
+	}
+
+	fn on_enter_node(&self, _id: InoxNodeUuid, name: &str) {
+		self.push_debug_group(name);
+	}
+
+	fn on_exit_node(&self, _id: InoxNodeUuid) {
 		self.pop_debug_group();
 	}
 
@@ -471,43 +1199,44 @@ impl InoxRenderer for OpenglRenderer {
 		as_mask: bool,
 		components: &TexturedMeshComponents,
 		render_ctx: &TexturedMeshRenderCtx,
-		_id: InoxNodeUuid,
+		id: InoxNodeUuid,
 	) {
 		self.push_debug_group("inox2d - draw textured content");
 
 		let gl = &self.gl;
-
-		// TODO: plain masks, meshes as masks without textures
-		/*
-		maskShader.use();
-		maskShader.setUniform(offset, data.origin);
-		maskShader.setUniform(mvp, inGetCamera().matrix * transform.matrix());
-
-		// Enable points array
-		glEnableVertexAttribArray(0);
-		glBindBuffer(GL_ARRAY_BUFFER, vbo);
-		glVertexAttribPointer(0, 2, GL_FLOAT, GL_FALSE, 0, null);
-
-		// Bind index buffer
-		this.bindIndex();
-
-		// Disable the vertex attribs after use
-		glDisableVertexAttribArray(0);
-		*/
+		// content of a composite mask source is drawn as-is, the composite is then drawn as the mask
+		let draw_as_mask = as_mask && !self.in_composite.get();
 
 		self.bind_part_textures(components.texture);
-		self.set_blend_mode(components.drawable.blending.mode);
+		let blend_mode = components.drawable.blending.mode;
+		let shader_blend_mode = shader_blend_mode(blend_mode, self.in_composite.get());
+		// mask sources are drawn with the blending set up by `.on_begin_mask()`
+		if !draw_as_mask {
+			self.set_shader_blending(blend_mode, shader_blend_mode);
+		}
 
-		let mvp = self.camera.matrix(self.viewport.as_vec2()) * *components.transform;
+		let mvp = self.camera_matrix() * *components.transform;
 
-		if as_mask {
+		if draw_as_mask {
 			// if as_mask is set, in .on_begin_masks():
-			// - part_mask_shader must have been bound and prepared.
+			// - part_mask_shader must have been prepared.
 			// - mask threshold must have been uploaded.
+			// it is bound again, as a composite mask source may have been drawn since.
+			self.bind_shader(&self.part_mask_shader);
 
 			// vert uniforms
 			self.part_mask_shader.set_mvp(gl, mvp);
 		} else {
+			if shader_blend_mode != SHADER_BLEND_FIXED_FUNCTION {
+				self.copy_backdrop();
+			}
+			if matches!(
+				shader_blend_mode,
+				SHADER_BLEND_CLIP_TO_LOWER | SHADER_BLEND_SLICE_FROM_LOWER
+			) {
+				self.copy_composite_lower();
+			}
+
 			let part_shader = &self.part_shader;
 			self.bind_shader(part_shader);
 
@@ -518,6 +1247,14 @@ impl InoxRenderer for OpenglRenderer {
 			part_shader.set_opacity(gl, components.drawable.blending.opacity);
 			part_shader.set_mult_color(gl, components.drawable.blending.tint);
 			part_shader.set_screen_color(gl, components.drawable.blending.screen_tint);
+			part_shader.set_emission_strength(gl, components.texture.emission_strength);
+			part_shader.set_premultiply(gl, self.output_premultiplied && !self.textures_premultiplied);
+			part_shader.set_mask_coverage(gl, self.coverage_masking.get());
+			part_shader.set_blend_mode(gl, shader_blend_mode);
+
+			let global_adjustments = self.current_global_adjustments();
+			part_shader.set_global_tint(gl, global_adjustments.tint);
+			part_shader.set_global_gamma(gl, global_adjustments.gamma);
 		}
 
 		unsafe {
@@ -530,21 +1267,64 @@ impl InoxRenderer for OpenglRenderer {
 		}
 
 		self.pop_debug_group();
+
+		if !as_mask {
+			self.run_draw_hooks(id);
+		}
+	}
+
+	fn draw_plain_mesh_mask(
+		&self,
+		components: &PlainMeshComponents,
+		render_ctx: &TexturedMeshRenderCtx,
+		_id: InoxNodeUuid,
+	) {
+		self.push_debug_group("inox2d - draw plain mask");
+
+		let gl = &self.gl;
+		// no texture is sampled, the mesh covers its whole shape
+		let plain_mask_shader = &self.plain_mask_shader;
+		self.bind_shader(plain_mask_shader);
+		plain_mask_shader.set_mvp(gl, self.camera_matrix() * *components.transform);
+
+		unsafe {
+			gl.draw_elements(
+				glow::TRIANGLES,
+				render_ctx.index_len as i32,
+				glow::UNSIGNED_SHORT,
+				render_ctx.index_offset as i32 * mem::size_of::<u16>() as i32,
+			);
+		}
+
+		self.pop_debug_group();
 	}
 
 	fn begin_composite_content(
 		&self,
-		_as_mask: bool,
-		_components: &CompositeComponents,
+		as_mask: bool,
+		components: &CompositeComponents,
 		_render_ctx: &CompositeRenderCtx,
-		_id: InoxNodeUuid,
+		id: InoxNodeUuid,
 	) {
 		self.push_debug_group("inox2d - begin composite content");
 
+		let clear_color = self
+			.composite_clear_colors
+			.get(&id)
+			.copied()
+			.unwrap_or_else(|| composite_clear_color(components.drawable.blending.mode));
+
 		self.clear_texture_cache();
+		self.in_composite.set(true);
+		self.composite_lower_copied.set(false);
 
 		let gl = &self.gl;
 		unsafe {
+			if as_mask && self.masking_mode == MaskingMode::Stencil {
+				// content is drawn as-is, leaving the stencil alone until the composite is drawn
+				gl.disable(glow::STENCIL_TEST);
+				gl.color_mask(true, true, true, true);
+			}
 			gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.composite_framebuffer));
 			gl.disable(glow::DEPTH_TEST);
 			gl.draw_buffers(&[
@@ -552,8 +1332,12 @@ impl InoxRenderer for OpenglRenderer {
 				glow::COLOR_ATTACHMENT1,
 				glow::COLOR_ATTACHMENT2,
 			]);
-			gl.clear_color(0.0, 0.0, 0.0, 0.0);
-			gl.clear(glow::COLOR_BUFFER_BIT);
+			if take_composite_clear(self.accumulate_composites, &self.composite_clear_requested) {
+				// only albedo takes the backdrop, emission and bumps start empty
+				gl.clear_buffer_f32_slice(glow::COLOR, 0, &clear_color.to_array());
+				gl.clear_buffer_f32_slice(glow::COLOR, 1, &[0.0; 4]);
+				gl.clear_buffer_f32_slice(glow::COLOR, 2, &[0.0; 4]);
+			}
 
 			// Everything else is the actual texture used by the meshes at id 0
 			gl.active_texture(glow::TEXTURE0);
@@ -570,7 +1354,7 @@ impl InoxRenderer for OpenglRenderer {
 		as_mask: bool,
 		components: &CompositeComponents,
 		_render_ctx: &CompositeRenderCtx,
-		_id: InoxNodeUuid,
+		id: InoxNodeUuid,
 	) {
 		self.pop_debug_group();
 
@@ -579,19 +1363,32 @@ impl InoxRenderer for OpenglRenderer {
 		let gl = &self.gl;
 
 		self.clear_texture_cache();
+		self.in_composite.set(false);
 		unsafe {
 			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
 		}
 
 		let blending = &components.drawable.blending;
 		if as_mask {
-			/*
-			cShaderMask.use();
-			cShaderMask.setUniform(mopacity, opacity);
-			cShaderMask.setUniform(mthreshold, threshold);
-			glBlendFunc(GL_ONE, GL_ONE_MINUS_SRC_ALPHA);
-			*/
-			todo!()
+			// back to the mask state set up by `.on_begin_masks()` and `.on_begin_mask()`
+			unsafe {
+				if self.masking_mode == MaskingMode::AlphaCoverage {
+					gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.mask_framebuffer));
+				} else {
+					gl.enable(glow::STENCIL_TEST);
+				}
+
+				gl.active_texture(glow::TEXTURE0);
+				gl.bind_texture(glow::TEXTURE_2D, Some(self.cf_albedo));
+			}
+			self.set_mask_state();
+
+			let composite_mask_shader = &self.composite_mask_shader;
+			self.bind_shader(composite_mask_shader);
+			composite_mask_shader.set_opacity(gl, blending.opacity.clamp(0.0, 1.0));
+			if let Some((_, threshold)) = self.mask_levels.borrow().source() {
+				composite_mask_shader.set_threshold(gl, threshold);
+			}
 		} else {
 			unsafe {
 				gl.active_texture(glow::TEXTURE0);
@@ -603,6 +1400,9 @@ impl InoxRenderer for OpenglRenderer {
 			}
 
 			self.set_blend_mode(blending.mode);
+			if blending.mode.reads_backdrop() {
+				self.copy_backdrop();
+			}
 
 			let opacity = blending.opacity.clamp(0.0, 1.0);
 			let tint = blending.tint.clamp(Vec3::ZERO, Vec3::ONE);
@@ -613,6 +1413,10 @@ impl InoxRenderer for OpenglRenderer {
 			composite_shader.set_opacity(gl, opacity);
 			composite_shader.set_mult_color(gl, tint);
 			composite_shader.set_screen_color(gl, screen_tint);
+			composite_shader.set_mask_coverage(gl, self.coverage_masking.get());
+			composite_shader.set_blend_mode(gl, shader_blend_mode(blending.mode, false));
+			composite_shader.set_global_tint(gl, self.global_adjustments.tint);
+			composite_shader.set_global_gamma(gl, self.global_adjustments.gamma);
 		}
 
 		unsafe {
@@ -620,6 +1424,38 @@ impl InoxRenderer for OpenglRenderer {
 		}
 
 		self.pop_debug_group();
+
+		if !as_mask {
+			self.run_draw_hooks(id);
+		}
+	}
+
+	fn batch_parts(&self) -> bool {
+		self.batch_parts
+	}
+
+	fn max_nesting_depth(&self) -> usize {
+		match self.masking_mode {
+			// the deepest mask source opens one more level before it is found to be too deep
+			MaskingMode::Stencil => MaskLevels::MAX_STENCIL_LEVELS - 1,
+			// there is a single coverage texture
+			MaskingMode::AlphaCoverage => 1,
+		}
+	}
+
+	fn draw_textured_mesh_batch(
+		&self,
+		components: &TexturedMeshComponents,
+		render_ctx: &TexturedMeshRenderCtx,
+		ids: &[InoxNodeUuid],
+	) {
+		self.draw_textured_mesh_content(false, components, render_ctx, ids[0]);
+		// every part of the batch has been drawn, not only the first one
+		ids[1..].iter().for_each(|id| self.run_draw_hooks(*id));
+	}
+
+	fn view_matrix(&self) -> Option<Mat4> {
+		Some(self.camera_matrix())
 	}
 }
 
@@ -630,25 +1466,27 @@ impl OpenglRenderer {
 
 		let gl = &self.gl;
 
-		// TODO: calculate this matrix only once per draw pass.
-		// let matrix = self.camera.matrix(self.viewport.as_vec2());
+		let render_ctx = puppet
+			.render_ctx
+			.as_ref()
+			.expect("Rendering for a puppet must be initialized by now.");
 
 		unsafe {
 			gl.bind_vertex_array(Some(self.vao));
-			upload_deforms_to_gl(
-				gl,
-				puppet
-					.render_ctx
-					.as_ref()
-					.expect("Rendering for a puppet must be initialized by now.")
-					.vertex_buffers
-					.deforms
-					.as_slice(),
-				self.deform_buffer,
-			);
+			// only deforms changed since the last upload need uploading
+			let generation = self.uploaded_deform_generation.replace(render_ctx.deform_generation());
+			if let Some(range) = render_ctx.dirty_deform_range_since(generation) {
+				upload_deforms_to_gl(
+					gl,
+					&render_ctx.vertex_buffers.deforms[range.clone()],
+					range.start,
+					self.deform_buffer,
+				);
+			}
 			gl.enable(glow::BLEND);
 			gl.disable(glow::DEPTH_TEST);
 		}
+		self.update_camera();
 
 		self.pop_debug_group();
 
@@ -660,12 +1498,518 @@ impl OpenglRenderer {
 		self.pop_debug_group();
 
 		self.push_debug_group("inox2d - end draw");
-		
+
 		let gl = &self.gl;
 		unsafe {
 			gl.bind_vertex_array(None);
 		}
-		
+
 		self.pop_debug_group();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use glam::{vec2, vec4};
+	use inox2d::render::blend_reference;
+
+	use super::*;
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn alpha_coverage_edges() {
+		// a white part masked by a mask source going from transparent to opaque, one texel per pixel
+		let size = uvec2(11, 1);
+		let white = [[255; 4]; 11];
+		let ramp = (0..=10).map(|i| [255, 255, 255, i * 25 + i / 2]).collect::<Vec<_>>();
+		// the mask source itself is not seen
+		let mut mask = headless::part(1, "mask", 1, size);
+		mask["opacity"] = 0.0.into();
+		let mut masked = headless::part(2, "masked", 0, size);
+		masked["masks"] = json::array![json::object! { source: 1, mode: "Mask" }];
+		masked["mask_threshold"] = 0.5.into();
+		let mut model = headless::model(vec![mask, masked], &[(size.x, &white), (size.x, &ramp)]);
+		let Some(mut headless) = headless::HeadlessRenderer::new(&model, size) else {
+			return;
+		};
+		let alphas = |pixels: Vec<u8>| pixels.chunks(4).map(|pixel| pixel[3]).collect::<Vec<_>>();
+
+		let stencil = alphas(headless.render(&mut model.puppet));
+		headless.renderer.masking_mode = MaskingMode::AlphaCoverage;
+		let coverage = alphas(headless.render(&mut model.puppet));
+
+		// both agree away from the edge
+		assert_eq!((stencil[0], stencil[10]), (0, 255));
+		assert_eq!((coverage[0], coverage[10]), (0, 255));
+
+		// stencil jumps in one pixel, coverage ramps smoothly across the edge
+		assert!(stencil.iter().all(|alpha| *alpha == 0 || *alpha == 255), "{stencil:?}");
+		assert_eq!(stencil.windows(2).filter(|w| w[0] != w[1]).count(), 1, "{stencil:?}");
+		assert_eq!(
+			coverage.iter().filter(|alpha| **alpha > 0 && **alpha < 255).count(),
+			9,
+			"{coverage:?}"
+		);
+		assert!(coverage.windows(2).all(|w| w[0] < w[1]), "{coverage:?}");
+		assert!(coverage[5].abs_diff(128) <= 1, "{coverage:?}");
+
+		// the images only differ around the edge
+		let diff = stencil
+			.iter()
+			.zip(&coverage)
+			.map(|(s, c)| s.abs_diff(*c))
+			.collect::<Vec<_>>();
+		assert!(diff[4..=6].iter().all(|d| *d > 0), "{diff:?}");
+		assert!(diff[1] < diff[4] && diff[9] < diff[6], "{diff:?}");
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn global_tint_halves_rendered_rgb() {
+		// one texel per pixel, so that none is sampled at the edge of the texture
+		let size = uvec2(4, 4);
+		let white = [[255; 4]; 16];
+		let mut model = headless::model(vec![headless::part(1, "white", 0, size)], &[(size.x, &white)]);
+		let Some(mut headless) = headless::HeadlessRenderer::new(&model, size) else {
+			return;
+		};
+
+		let untinted = headless.render(&mut model.puppet);
+		assert!(untinted.chunks(4).all(|pixel| pixel == [255; 4]), "{untinted:?}");
+
+		headless.renderer.set_global_tint(Vec3::splat(0.5));
+		let tinted = headless.render(&mut model.puppet);
+		let halved = |pixel: &[u8]| pixel[..3].iter().all(|c| c.abs_diff(128) <= 1) && pixel[3] == 255;
+		assert!(tinted.chunks(4).all(halved), "{tinted:?}");
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn output_premultiplied_with_straight_textures() {
+		// an orange part fading out to the left, one texel per pixel, decoded straight alpha
+		let size = uvec2(11, 1);
+		let ramp = (0..=10).map(|i| [240, 120, 60, i * 25 + i / 2]).collect::<Vec<_>>();
+		let mut model = headless::model(vec![headless::part(1, "edge", 0, size)], &[(size.x, &ramp)]);
+		let options = RendererOptions {
+			decode: DecodeOptions { premultiply: false },
+			..Default::default()
+		};
+		let Some(mut headless) = headless::HeadlessRenderer::new_with_options(&model, size, options) else {
+			return;
+		};
+		let premultiplied = |pixel: &[u8]| pixel[..3].iter().all(|c| *c <= pixel[3]);
+
+		// straight colors end up in the framebuffer as is
+		headless.render(&mut model.puppet);
+		let straight = headless.read_framebuffer();
+		assert!(!straight.chunks(4).all(premultiplied), "{straight:?}");
+
+		headless.renderer.output_premultiplied = true;
+		let read = headless.render(&mut model.puppet);
+		let raw = headless.read_framebuffer();
+		assert!(raw.chunks(4).all(premultiplied), "{raw:?}");
+		assert_eq!(raw[4 * 10..], [240, 120, 60, 255]);
+		// read back straight again
+		let orange = |pixel: &[u8]| pixel[..3].iter().zip([240, 120, 60]).all(|(c, o)| c.abs_diff(o) <= 8);
+		assert!(read.chunks(4).skip(3).all(orange), "{read:?}");
+	}
+
+	#[test]
+	fn blend_funcs() {
+		assert_eq!(
+			blend_func(BlendMode::LinearDodge),
+			(glow::FUNC_ADD, glow::ONE, glow::ONE)
+		);
+		assert_eq!(
+			blend_func(BlendMode::Normal),
+			(glow::FUNC_ADD, glow::ONE, glow::ONE_MINUS_SRC_ALPHA)
+		);
+
+		// blended in shaders, over what is below
+		for mode in BlendMode::VALUES {
+			assert_eq!(
+				mode.reads_backdrop(),
+				shader_blend_mode(mode, false) != SHADER_BLEND_FIXED_FUNCTION
+			);
+			if mode.reads_backdrop() {
+				assert_eq!(blend_func(mode), blend_func(BlendMode::Normal));
+			}
+		}
+	}
+
+	#[test]
+	fn shader_blend_constants_match_shaders() {
+		let backdrop_modes = [
+			("SHADER_BLEND_FIXED_FUNCTION", SHADER_BLEND_FIXED_FUNCTION),
+			("SHADER_BLEND_OVERLAY", SHADER_BLEND_OVERLAY),
+			("SHADER_BLEND_HARD_LIGHT", SHADER_BLEND_HARD_LIGHT),
+			("SHADER_BLEND_DARKEN", SHADER_BLEND_DARKEN),
+			("SHADER_BLEND_LIGHTEN", SHADER_BLEND_LIGHTEN),
+		];
+		let lower_layer_modes = [
+			("SHADER_BLEND_CLIP_TO_LOWER", SHADER_BLEND_CLIP_TO_LOWER),
+			("SHADER_BLEND_SLICE_FROM_LOWER", SHADER_BLEND_SLICE_FROM_LOWER),
+		];
+		// composites themselves are never drawn with lower layer modes in the shader
+		let part_modes = backdrop_modes.iter().chain(&lower_layer_modes);
+		for (source, constants) in [
+			(shaders::PART_FRAG, part_modes.collect::<Vec<_>>()),
+			(shaders::COMP_FRAG, backdrop_modes.iter().collect()),
+		] {
+			for (name, value) in constants {
+				let declaration = format!("const int {name} = {value};");
+				assert!(source.contains(&declaration), "{declaration}");
+			}
+		}
+	}
+
+	#[test]
+	fn lower_layer_modes_in_composites() {
+		// blended in the shader with what is below the composite, outside of composites by fixed function
+		for mode in [BlendMode::ClipToLower, BlendMode::SliceFromLower] {
+			assert_eq!(shader_blend_mode(mode, false), SHADER_BLEND_FIXED_FUNCTION);
+			assert_ne!(shader_blend_mode(mode, true), SHADER_BLEND_FIXED_FUNCTION);
+		}
+		for mode in BlendMode::VALUES {
+			if !matches!(mode, BlendMode::ClipToLower | BlendMode::SliceFromLower) {
+				assert_eq!(shader_blend_mode(mode, true), shader_blend_mode(mode, false));
+			}
+		}
+	}
+
+	/// Premultiplied `out` blended over `dst` in a unorm buffer by fixed function blending with `func`.
+	fn fixed_function((equation, src_factor, dst_factor): (u32, u32, u32), out: Vec4, dst: Vec4) -> Vec4 {
+		let factor = |factor| match factor {
+			glow::ZERO => Vec4::ZERO,
+			glow::ONE => Vec4::ONE,
+			glow::ONE_MINUS_SRC_COLOR => Vec4::ONE - out,
+			glow::ONE_MINUS_SRC_ALPHA => Vec4::splat(1.0 - out.w),
+			glow::DST_COLOR => dst,
+			glow::DST_ALPHA => Vec4::splat(dst.w),
+			glow::ONE_MINUS_DST_ALPHA => Vec4::splat(1.0 - dst.w),
+			_ => unimplemented!("blend factor {factor:#x}"),
+		};
+		let (src_term, dst_term) = (out * factor(src_factor), dst * factor(dst_factor));
+		let blended = match equation {
+			glow::FUNC_ADD => src_term + dst_term,
+			glow::FUNC_SUBTRACT => src_term - dst_term,
+			_ => unimplemented!("blend equation {equation:#x}"),
+		};
+		blended.clamp(Vec4::ZERO, Vec4::ONE)
+	}
+
+	/// Mirror of `blendLower()` of the part shader, with `dst` the composite content drawn so far
+	/// and `below` the alpha of what is below the composite.
+	fn blend_lower(mode: BlendMode, src: Vec4, dst: Vec4, below: f32) -> Vec4 {
+		let lower_alpha = dst.w + below * (1.0 - dst.w);
+		match shader_blend_mode(mode, true) {
+			SHADER_BLEND_CLIP_TO_LOWER => src * lower_alpha + dst * (1.0 - src.w),
+			SHADER_BLEND_SLICE_FROM_LOWER => (src * (1.0 - lower_alpha) - dst * (1.0 - src.w)).max(Vec4::ZERO),
+			_ => unreachable!(),
+		}
+	}
+
+	#[test]
+	fn lower_layer_modes_match_reference() {
+		let clear = Vec4::ZERO;
+		let red = vec4(1.0, 0.0, 0.0, 1.0);
+		let half_blue = vec4(0.0, 0.0, 0.5, 0.5);
+		let quarter_green = vec4(0.0, 0.25, 0.0, 0.25);
+		let colors = [clear, red, half_blue, quarter_green];
+
+		for mode in BlendMode::VALUES.into_iter().filter(|mode| !mode.reads_backdrop()) {
+			for (src, dst) in colors.into_iter().flat_map(|src| colors.map(|dst| (src, dst))) {
+				let reference = blend_reference(mode, src, dst);
+				let fixed = fixed_function(blend_func(mode), src, dst);
+				assert!(fixed.abs_diff_eq(reference, 1e-6), "{mode:?} {src} over {dst}: {fixed}");
+
+				// in a composite with nothing below it, lower layer modes blend as outside of composites
+				if shader_blend_mode(mode, true) != SHADER_BLEND_FIXED_FUNCTION {
+					let shaded = fixed_function(LOWER_LAYER_BLEND_FUNC, blend_lower(mode, src, dst, 0.0), dst);
+					assert!(
+						shaded.abs_diff_eq(reference, 1e-6),
+						"{mode:?} {src} over {dst}: {shaded}"
+					);
+				}
+			}
+		}
+
+		// a first part in a composite is clipped to what is below the composite, or sliced from it
+		let clip = blend_lower(BlendMode::ClipToLower, half_blue, clear, 1.0);
+		assert_eq!(fixed_function(LOWER_LAYER_BLEND_FUNC, clip, clear), half_blue);
+		let slice = blend_lower(BlendMode::SliceFromLower, half_blue, clear, 1.0);
+		assert_eq!(fixed_function(LOWER_LAYER_BLEND_FUNC, slice, clear), clear);
+		let slice = blend_lower(BlendMode::SliceFromLower, half_blue, clear, 0.5);
+		assert_eq!(fixed_function(LOWER_LAYER_BLEND_FUNC, slice, clear), half_blue * 0.5);
+	}
+
+	#[test]
+	fn camera_flip_updates_cache() {
+		let mut cache = GlCache::default();
+		let mut camera = Camera::default();
+		assert!(cache.update_camera(&camera));
+		assert!(!cache.update_camera(&camera));
+
+		camera.flip_x = true;
+		assert!(cache.update_camera(&camera));
+		assert!(!cache.update_camera(&camera));
+		camera.flip_y = true;
+		assert!(cache.update_camera(&camera));
+	}
+
+	#[test]
+	fn camera_matrix_recomputed_on_change_only() {
+		let mut cache = GlCache::default();
+		let mut camera = Camera::default();
+		let mut viewport = uvec2(800, 600);
+		let mut recomputes = 0;
+		let mut update = |cache: &mut GlCache, camera: &Camera, viewport: UVec2| {
+			if cache.update_camera_matrix(camera, viewport) {
+				recomputes += 1;
+			}
+			assert_eq!(cache.camera_matrix, camera.matrix(viewport.as_vec2()));
+		};
+
+		update(&mut cache, &camera, viewport);
+		update(&mut cache, &camera, viewport);
+
+		camera.position = vec2(12.0, -34.0);
+		camera.rotation = 0.5;
+		update(&mut cache, &camera, viewport);
+		update(&mut cache, &camera, viewport);
+
+		viewport = uvec2(1920, 1080);
+		update(&mut cache, &camera, viewport);
+		camera.flip_x = true;
+		update(&mut cache, &camera, viewport);
+		update(&mut cache, &camera, viewport);
+
+		assert_eq!(recomputes, 4);
+	}
+
+	#[test]
+	fn multiply_composite_backdrop() {
+		assert_eq!(composite_clear_color(BlendMode::Multiply), Vec4::ONE);
+		assert_eq!(composite_clear_color(BlendMode::Normal), Vec4::ZERO);
+		assert_eq!(composite_clear_color(BlendMode::Screen), Vec4::ZERO);
+	}
+
+	#[test]
+	fn geometry_only_binds_no_model_texture() {
+		let part = TexturedMesh {
+			tex_albedo: TextureId::new(0),
+			tex_emissive: TextureId::new(1),
+			tex_bumpmap: TextureId::new(2),
+			emission_strength: 1.,
+		};
+
+		assert_eq!(
+			part_textures(&part, false),
+			[0, 2, 1].map(|id| PartTexture::Model(TextureId::new(id)))
+		);
+		let textures = part_textures(&part, true);
+		assert!(!textures.iter().any(|texture| matches!(texture, PartTexture::Model(_))));
+		assert_eq!(textures[0], PartTexture::White);
+	}
+
+	#[test]
+	fn lazy_upload_keeps_shared_albedo_bound() {
+		/// Texture slots as OpenGL keeps them, with model textures uploaded lazily.
+		struct SimulatedGl {
+			pending: RefCell<Vec<TextureId>>,
+			active_slot: Cell<u32>,
+			bound: RefCell<[Option<PartTexture>; 3]>,
+		}
+		impl PartTextureBinder for SimulatedGl {
+			fn upload(&self, id: TextureId) {
+				let mut pending = self.pending.borrow_mut();
+				if let Some(i) = pending.iter().position(|pending| *pending == id) {
+					pending.remove(i);
+					self.bound.borrow_mut()[self.active_slot.get() as usize] = None;
+				}
+			}
+			fn bind(&self, texture: PartTexture, slot: u32) {
+				self.active_slot.set(slot);
+				self.bound.borrow_mut()[slot as usize] = Some(texture);
+			}
+		}
+
+		let gl = SimulatedGl {
+			pending: RefCell::new((0..5).map(TextureId::new).collect()),
+			active_slot: Cell::new(0),
+			bound: RefCell::new([None; 3]),
+		};
+		let part = |albedo, bumpmap, emissive| TexturedMesh {
+			tex_albedo: TextureId::new(albedo),
+			tex_emissive: TextureId::new(emissive),
+			tex_bumpmap: TextureId::new(bumpmap),
+			emission_strength: 1.,
+		};
+		let (first, second) = (part(0, 1, 2), part(0, 3, 4));
+		let mut cache = GlCache::default();
+
+		bind_part_textures(&gl, &mut cache, &first, false);
+		assert_eq!(*gl.bound.borrow(), part_textures(&first, false).map(Some));
+
+		// sharing the albedo, the second part binds nothing: the albedo must still be bound
+		bind_part_textures(&gl, &mut cache, &second, false);
+		assert_eq!(gl.bound.borrow()[0], Some(PartTexture::Model(TextureId::new(0))));
+	}
+
+	#[test]
+	fn texture_memory_of_viewport() {
+		// two 256x256 RGBA8 model textures, 800x600 viewport
+		let model_textures = [texture::texture_bytes(256, 256, 32); 2];
+		assert_eq!(model_textures[0], 256 * 256 * 4);
+		assert_eq!(
+			texture_memory(model_textures.into_iter(), uvec2(800, 600)),
+			2 * 256 * 256 * 4 + 800 * 600 * 40 + 2 * 4
+		);
+	}
+
+	#[test]
+	fn accumulated_composites_cleared_on_demand() {
+		let clear_requested = Cell::new(false);
+		assert!(take_composite_clear(false, &clear_requested));
+
+		// content of a frame is left for the next one
+		assert!(!take_composite_clear(true, &clear_requested));
+		assert!(!take_composite_clear(true, &clear_requested));
+
+		clear_requested.set(true);
+		assert!(take_composite_clear(true, &clear_requested));
+		assert!(!take_composite_clear(true, &clear_requested));
+	}
+
+	#[test]
+	fn read_back_pixels() {
+		// 1x3 image, bottom row first as read from GL
+		let mut pixels = vec![1, 1, 1, 255, 2, 2, 2, 255, 3, 3, 3, 255];
+		flip_rows(&mut pixels, 1);
+		assert_eq!(pixels, [3, 3, 3, 255, 2, 2, 2, 255, 1, 1, 1, 255]);
+
+		let mut pixels = vec![1, 2, 3, 4, 5, 6, 7, 8];
+		flip_rows(&mut pixels, 2);
+		assert_eq!(pixels, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+		let mut pixels = vec![64, 32, 0, 128, 255, 255, 255, 255, 10, 20, 30, 0];
+		unpremultiply(&mut pixels);
+		assert_eq!(pixels, [128, 64, 0, 128, 255, 255, 255, 255, 0, 0, 0, 0]);
+	}
+
+	/// What `glStencilOp(KEEP, KEEP, REPLACE)` does to the stencil of a pixel covered by a fragment drawn with `ops`,
+	/// returning whether the fragment passes the stencil test.
+	fn stencil_fragment(stencil: &mut u8, ops: StencilOps) -> bool {
+		let passes = match ops.func {
+			glow::ALWAYS => true,
+			glow::EQUAL => (ops.reference as u32 & ops.func_mask) == (*stencil as u32 & ops.func_mask),
+			func => unimplemented!("stencil func {func:#x}"),
+		};
+		if passes {
+			*stencil = (ops.reference as u32 & ops.write_mask | *stencil as u32 & !ops.write_mask) as u8;
+		}
+		passes
+	}
+
+	/// A drawable with its masks, and whether it covers the pixel.
+	struct Masked(bool, Vec<(MaskMode, Masked)>);
+
+	impl Masked {
+		/// Whether color is written to the pixel when drawing this as `draw_node()` does, on a stencil holding `stencil`.
+		fn draw(&self, levels: &mut MaskLevels, stencil: &mut u8) -> bool {
+			let Masked(covers, masks) = self;
+			if !masks.is_empty() {
+				let masks_comp = Masks {
+					threshold: 0.5,
+					masks: (masks.iter())
+						.map(|(mode, _)| Mask {
+							source: InoxNodeUuid::new(0),
+							mode: *mode,
+						})
+						.collect(),
+				};
+				// `glClear()` respects the stencil write mask
+				let (clear, write_mask) = levels.begin(&masks_comp);
+				*stencil = (clear as u32 & write_mask | *stencil as u32 & !write_mask) as u8;
+				for (mode, source) in masks {
+					levels.begin_mask(*mode);
+					source.draw(levels, stencil);
+				}
+				levels.begin_content();
+			}
+
+			let ops = levels.stencil().unwrap_or(StencilOps {
+				func: glow::ALWAYS,
+				reference: 0,
+				func_mask: 0xff,
+				write_mask: 0x00,
+				color: true,
+			});
+			let drawn = *covers && stencil_fragment(stencil, ops) && ops.color;
+
+			if !masks.is_empty() {
+				levels.end();
+			}
+			drawn
+		}
+
+		/// Whether this is visible on the pixel, as in the reference implementation:
+		/// hidden until a `Mask` source shows it, or shown until a `Dodge` source hides it if there are only dodges,
+		/// each source drawn where it is visible itself.
+		fn visible(&self) -> bool {
+			let Masked(covers, masks) = self;
+			let start = !masks.iter().any(|(mode, _)| *mode == MaskMode::Mask);
+			let shown = masks
+				.iter()
+				.fold(start, |shown, (mode, source)| match source.visible() {
+					true => *mode == MaskMode::Mask,
+					false => shown,
+				});
+			*covers && shown
+		}
+	}
+
+	#[test]
+	fn dodge_mask_stencil() {
+		use MaskMode::{Dodge, Mask};
+		let shown = |masked: Masked| {
+			let expected = masked.visible();
+			// bits of other levels must not matter
+			for stencil in [0x00, 0xff, 0xaa, 0x55] {
+				let mut levels = MaskLevels::default();
+				assert_eq!(masked.draw(&mut levels, &mut { stencil }), expected);
+				assert!(levels.0.is_empty());
+			}
+			expected
+		};
+		let part = |covers| Masked(covers, Vec::new());
+
+		// dodges alone cut holes
+		assert!(shown(Masked(true, vec![(Dodge, part(false))])));
+		assert!(!shown(Masked(true, vec![(Dodge, part(true))])));
+		// dodges cut holes in what masks before them show
+		assert!(!shown(Masked(true, vec![(Mask, part(false)), (Dodge, part(false))])));
+		assert!(shown(Masked(true, vec![(Mask, part(true)), (Dodge, part(false))])));
+		assert!(!shown(Masked(true, vec![(Mask, part(true)), (Dodge, part(true))])));
+
+		// a dodge source only cuts where its own masks show it
+		let hole = |covers, face| Masked(true, vec![(Dodge, Masked(covers, vec![(Mask, part(face))]))]);
+		assert!(shown(hole(true, false)));
+		assert!(!shown(hole(true, true)));
+		assert!(shown(hole(false, true)));
+
+		// every combination of sources three levels deep, in both modes at every level
+		for bits in 0..1 << 7 {
+			let covers = |bit: u32| bits & 1 << bit != 0;
+			for modes in 0..1 << 3 {
+				let mode = |bit: u32| if modes & 1 << bit != 0 { Dodge } else { Mask };
+				let inner = Masked(covers(0), vec![(mode(0), part(covers(1)))]);
+				let source = Masked(covers(2), vec![(mode(1), inner), (Dodge, part(covers(3)))]);
+				shown(Masked(
+					covers(4),
+					vec![(mode(2), source), (Mask, part(covers(5))), (Dodge, part(covers(6)))],
+				));
+			}
+		}
+	}
+}
@@ -9,7 +9,7 @@ use tracing::debug;
 use super::shader::{self, ShaderCompileError};
 
 const PART_VERT: &str = include_str!("shaders/basic/basic.vert");
-const PART_FRAG: &str = include_str!("shaders/basic/basic.frag");
+pub(crate) const PART_FRAG: &str = include_str!("shaders/basic/basic.frag");
 const PART_MASK_FRAG: &str = include_str!("shaders/basic/basic-mask.frag");
 
 #[derive(Clone)]
@@ -21,6 +21,14 @@ pub struct PartShader {
 	u_mult_color: Option<glow::UniformLocation>,
 	u_screen_color: Option<glow::UniformLocation>,
 	u_emission_strength: Option<glow::UniformLocation>,
+	u_premultiply: Option<glow::UniformLocation>,
+	u_global_tint: Option<glow::UniformLocation>,
+	u_global_gamma: Option<glow::UniformLocation>,
+	u_mask_coverage: Option<glow::UniformLocation>,
+	u_coverage_mask: Option<glow::UniformLocation>,
+	u_blend_mode: Option<glow::UniformLocation>,
+	u_backdrop: Option<glow::UniformLocation>,
+	u_composite_lower: Option<glow::UniformLocation>,
 }
 
 impl Deref for PartShader {
@@ -44,6 +52,14 @@ impl PartShader {
 			u_mult_color: unsafe { gl.get_uniform_location(program, "multColor") },
 			u_screen_color: unsafe { gl.get_uniform_location(program, "screenColor") },
 			u_emission_strength: unsafe { gl.get_uniform_location(program, "emissionStrength") },
+			u_premultiply: unsafe { gl.get_uniform_location(program, "premultiply") },
+			u_global_tint: unsafe { gl.get_uniform_location(program, "globalTint") },
+			u_global_gamma: unsafe { gl.get_uniform_location(program, "globalGamma") },
+			u_mask_coverage: unsafe { gl.get_uniform_location(program, "maskCoverage") },
+			u_coverage_mask: unsafe { gl.get_uniform_location(program, "coverageMask") },
+			u_blend_mode: unsafe { gl.get_uniform_location(program, "blendMode") },
+			u_backdrop: unsafe { gl.get_uniform_location(program, "backdrop") },
+			u_composite_lower: unsafe { gl.get_uniform_location(program, "compositeLower") },
 		})
 	}
 
@@ -82,6 +98,54 @@ impl PartShader {
 	pub fn set_emission_strength(&self, gl: &glow::Context, emission_strength: f32) {
 		unsafe { gl.uniform_1_f32(self.u_emission_strength.as_ref(), emission_strength) };
 	}
+
+	/// Sets the `premultiply` uniform of the shader.
+	#[inline]
+	pub fn set_premultiply(&self, gl: &glow::Context, premultiply: bool) {
+		unsafe { gl.uniform_1_i32(self.u_premultiply.as_ref(), premultiply as i32) };
+	}
+
+	/// Sets the `globalTint` uniform of the shader.
+	#[inline]
+	pub fn set_global_tint(&self, gl: &glow::Context, global_tint: Vec3) {
+		unsafe { gl.uniform_3_f32_slice(self.u_global_tint.as_ref(), global_tint.as_ref()) };
+	}
+
+	/// Sets the `globalGamma` uniform of the shader.
+	#[inline]
+	pub fn set_global_gamma(&self, gl: &glow::Context, global_gamma: f32) {
+		unsafe { gl.uniform_1_f32(self.u_global_gamma.as_ref(), global_gamma) };
+	}
+
+	/// Sets the `maskCoverage` uniform of the shader.
+	#[inline]
+	pub fn set_mask_coverage(&self, gl: &glow::Context, mask_coverage: bool) {
+		unsafe { gl.uniform_1_i32(self.u_mask_coverage.as_ref(), mask_coverage as i32) };
+	}
+
+	/// Sets the `coverageMask` uniform of the shader.
+	#[inline]
+	pub fn set_coverage_mask_slot(&self, gl: &glow::Context, slot: u32) {
+		unsafe { gl.uniform_1_i32(self.u_coverage_mask.as_ref(), slot as i32) };
+	}
+
+	/// Sets the `blendMode` uniform of the shader.
+	#[inline]
+	pub fn set_blend_mode(&self, gl: &glow::Context, blend_mode: i32) {
+		unsafe { gl.uniform_1_i32(self.u_blend_mode.as_ref(), blend_mode) };
+	}
+
+	/// Sets the `backdrop` uniform of the shader.
+	#[inline]
+	pub fn set_backdrop_slot(&self, gl: &glow::Context, slot: u32) {
+		unsafe { gl.uniform_1_i32(self.u_backdrop.as_ref(), slot as i32) };
+	}
+
+	/// Sets the `compositeLower` uniform of the shader.
+	#[inline]
+	pub fn set_composite_lower_slot(&self, gl: &glow::Context, slot: u32) {
+		unsafe { gl.uniform_1_i32(self.u_composite_lower.as_ref(), slot as i32) };
+	}
 }
 
 pub struct PartMaskShader {
@@ -89,6 +153,7 @@ pub struct PartMaskShader {
 	u_mvp: Option<glow::UniformLocation>,
 	u_offset: Option<glow::UniformLocation>,
 	u_threshold: Option<glow::UniformLocation>,
+	u_coverage: Option<glow::UniformLocation>,
 }
 
 impl Deref for PartMaskShader {
@@ -109,6 +174,7 @@ impl PartMaskShader {
 			u_mvp: unsafe { gl.get_uniform_location(program, "mvp") },
 			u_offset: unsafe { gl.get_uniform_location(program, "offset") },
 			u_threshold: unsafe { gl.get_uniform_location(program, "threshold") },
+			u_coverage: unsafe { gl.get_uniform_location(program, "coverage") },
 		})
 	}
 
@@ -129,10 +195,51 @@ impl PartMaskShader {
 	pub fn set_threshold(&self, gl: &glow::Context, threshold: f32) {
 		unsafe { gl.uniform_1_f32(self.u_threshold.as_ref(), threshold) };
 	}
+
+	/// Sets the `coverage` uniform of the shader.
+	#[inline]
+	pub fn set_coverage(&self, gl: &glow::Context, coverage: bool) {
+		unsafe { gl.uniform_1_i32(self.u_coverage.as_ref(), coverage as i32) };
+	}
+}
+
+const PLAIN_MASK_VERT: &str = include_str!("shaders/mask.vert");
+const PLAIN_MASK_FRAG: &str = include_str!("shaders/mask.frag");
+
+/// Position-only shader for meshes without textures drawn as masks.
+pub struct PlainMaskShader {
+	program: glow::Program,
+	u_mvp: Option<glow::UniformLocation>,
+}
+
+impl Deref for PlainMaskShader {
+	type Target = glow::Program;
+
+	fn deref(&self) -> &Self::Target {
+		&self.program
+	}
+}
+
+impl PlainMaskShader {
+	pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+		debug!("Compiling Plain Mask shader");
+		let program = shader::compile(gl, PLAIN_MASK_VERT, PLAIN_MASK_FRAG)?;
+
+		Ok(Self {
+			program,
+			u_mvp: unsafe { gl.get_uniform_location(program, "mvp") },
+		})
+	}
+
+	/// Sets the `mvp` uniform of the shader.
+	#[inline]
+	pub fn set_mvp(&self, gl: &glow::Context, mvp: Mat4) {
+		unsafe { gl.uniform_matrix_4_f32_slice(self.u_mvp.as_ref(), false, mvp.as_ref()) };
+	}
 }
 
 const COMP_VERT: &str = include_str!("shaders/basic/composite.vert");
-const COMP_FRAG: &str = include_str!("shaders/basic/composite.frag");
+pub(crate) const COMP_FRAG: &str = include_str!("shaders/basic/composite.frag");
 const COMP_MASK_FRAG: &str = include_str!("shaders/basic/composite-mask.frag");
 
 pub struct CompositeShader {
@@ -141,6 +248,12 @@ pub struct CompositeShader {
 	u_opacity: Option<glow::UniformLocation>,
 	u_mult_color: Option<glow::UniformLocation>,
 	u_screen_color: Option<glow::UniformLocation>,
+	u_global_tint: Option<glow::UniformLocation>,
+	u_global_gamma: Option<glow::UniformLocation>,
+	u_mask_coverage: Option<glow::UniformLocation>,
+	u_coverage_mask: Option<glow::UniformLocation>,
+	u_blend_mode: Option<glow::UniformLocation>,
+	u_backdrop: Option<glow::UniformLocation>,
 }
 
 impl Deref for CompositeShader {
@@ -162,6 +275,12 @@ impl CompositeShader {
 			u_opacity: unsafe { gl.get_uniform_location(program, "opacity") },
 			u_mult_color: unsafe { gl.get_uniform_location(program, "multColor") },
 			u_screen_color: unsafe { gl.get_uniform_location(program, "screenColor") },
+			u_global_tint: unsafe { gl.get_uniform_location(program, "globalTint") },
+			u_global_gamma: unsafe { gl.get_uniform_location(program, "globalGamma") },
+			u_mask_coverage: unsafe { gl.get_uniform_location(program, "maskCoverage") },
+			u_coverage_mask: unsafe { gl.get_uniform_location(program, "coverageMask") },
+			u_blend_mode: unsafe { gl.get_uniform_location(program, "blendMode") },
+			u_backdrop: unsafe { gl.get_uniform_location(program, "backdrop") },
 		})
 	}
 
@@ -188,6 +307,42 @@ impl CompositeShader {
 	pub fn set_screen_color(&self, gl: &glow::Context, screen_color: Vec3) {
 		unsafe { gl.uniform_3_f32_slice(self.u_screen_color.as_ref(), screen_color.as_ref()) };
 	}
+
+	/// Sets the `globalTint` uniform of the shader.
+	#[inline]
+	pub fn set_global_tint(&self, gl: &glow::Context, global_tint: Vec3) {
+		unsafe { gl.uniform_3_f32_slice(self.u_global_tint.as_ref(), global_tint.as_ref()) };
+	}
+
+	/// Sets the `globalGamma` uniform of the shader.
+	#[inline]
+	pub fn set_global_gamma(&self, gl: &glow::Context, global_gamma: f32) {
+		unsafe { gl.uniform_1_f32(self.u_global_gamma.as_ref(), global_gamma) };
+	}
+
+	/// Sets the `maskCoverage` uniform of the shader.
+	#[inline]
+	pub fn set_mask_coverage(&self, gl: &glow::Context, mask_coverage: bool) {
+		unsafe { gl.uniform_1_i32(self.u_mask_coverage.as_ref(), mask_coverage as i32) };
+	}
+
+	/// Sets the `coverageMask` uniform of the shader.
+	#[inline]
+	pub fn set_coverage_mask_slot(&self, gl: &glow::Context, slot: u32) {
+		unsafe { gl.uniform_1_i32(self.u_coverage_mask.as_ref(), slot as i32) };
+	}
+
+	/// Sets the `blendMode` uniform of the shader.
+	#[inline]
+	pub fn set_blend_mode(&self, gl: &glow::Context, blend_mode: i32) {
+		unsafe { gl.uniform_1_i32(self.u_blend_mode.as_ref(), blend_mode) };
+	}
+
+	/// Sets the `backdrop` uniform of the shader.
+	#[inline]
+	pub fn set_backdrop_slot(&self, gl: &glow::Context, slot: u32) {
+		unsafe { gl.uniform_1_i32(self.u_backdrop.as_ref(), slot as i32) };
+	}
 }
 
 pub struct CompositeMaskShader {
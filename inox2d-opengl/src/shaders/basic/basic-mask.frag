@@ -10,9 +10,20 @@ out vec4 outColor;
 
 uniform sampler2D tex;
 uniform float threshold;
+uniform bool coverage;
 
 void main() {
   vec4 color = texture(tex, texUVs);
+
+  // Alpha coverage: a soft edge centered on the threshold
+  if (coverage) {
+    float lo = max(2.0 * threshold - 1.0, 0.0);
+    float hi = min(2.0 * threshold, 1.0);
+    float alpha = hi > lo ? smoothstep(lo, hi, color.a) : float(color.a > threshold);
+    outColor = vec4(alpha);
+    return;
+  }
+
   if (color.a <= threshold)
     discard;
   outColor = vec4(1, 1, 1, 1);
@@ -19,6 +19,66 @@ uniform float opacity;
 uniform vec3 multColor;
 uniform vec3 screenColor;
 uniform float emissionStrength;
+uniform bool premultiply;
+uniform vec3 globalTint;
+uniform float globalGamma;
+
+uniform bool maskCoverage;
+uniform sampler2D coverageMask;
+
+// Values of blendMode, same as the SHADER_BLEND_ constants of the renderer
+const int SHADER_BLEND_FIXED_FUNCTION = 0;
+const int SHADER_BLEND_OVERLAY = 1;
+const int SHADER_BLEND_HARD_LIGHT = 2;
+// lower layer modes, in composites
+const int SHADER_BLEND_CLIP_TO_LOWER = 3;
+const int SHADER_BLEND_SLICE_FROM_LOWER = 4;
+const int SHADER_BLEND_DARKEN = 5;
+const int SHADER_BLEND_LIGHTEN = 6;
+uniform int blendMode;
+uniform sampler2D backdrop;
+// What is below the composite being drawn
+uniform sampler2D compositeLower;
+
+// Hard light of premultiplied `top` on `bottom`, times the alpha of both
+vec3 hardLight(vec4 top, vec4 bottom) {
+  vec3 multiplied = 2.0 * top.rgb * bottom.rgb;
+  vec3 screened = vec3(top.a * bottom.a) -
+                  2.0 * (vec3(top.a) - top.rgb) * (vec3(bottom.a) - bottom.rgb);
+  return mix(multiplied, screened, step(0.5 * top.a, top.rgb));
+}
+
+// Premultiplied `src` blended with the backdrop, to be drawn with ONE, ONE_MINUS_SRC_ALPHA blending
+vec4 blendBackdrop(vec4 src) {
+  vec4 dst = texelFetch(backdrop, ivec2(gl_FragCoord.xy), 0);
+  vec3 blended;
+  if (blendMode == SHADER_BLEND_OVERLAY) {
+    // overlay is hard light with src and dst swapped
+    blended = hardLight(dst, src);
+  } else if (blendMode == SHADER_BLEND_HARD_LIGHT) {
+    blended = hardLight(src, dst);
+  } else if (blendMode == SHADER_BLEND_DARKEN) {
+    blended = min(src.rgb * dst.a, dst.rgb * src.a);
+  } else {
+    // SHADER_BLEND_LIGHTEN
+    blended = max(src.rgb * dst.a, dst.rgb * src.a);
+  }
+  return vec4(src.rgb * (1.0 - dst.a) + blended, src.a);
+}
+
+// Premultiplied `src` clipped to or sliced from what is below, as fixed function blending does it outside of
+// composites, where the alpha of what is below is that of the composite content drawn so far over what is below
+// the composite. The result replaces the composite content.
+vec4 blendLower(vec4 src) {
+  vec4 dst = texelFetch(backdrop, ivec2(gl_FragCoord.xy), 0);
+  float below = texelFetch(compositeLower, ivec2(gl_FragCoord.xy), 0).a;
+  float lowerAlpha = dst.a + below * (1.0 - dst.a);
+  if (blendMode == SHADER_BLEND_CLIP_TO_LOWER) {
+    return src * lowerAlpha + dst * (1.0 - src.a);
+  } else {
+    return max(src * (1.0 - lowerAlpha) - dst * (1.0 - src.a), 0.0);
+  }
+}
 
 void main() {
   // Sample texture
@@ -28,9 +88,26 @@ void main() {
   vec3 screenOut = vec3(1.0) - ((vec3(1.0) - (texColor.xyz)) *
                                 (vec3(1.0) - (screenColor * texColor.a)));
 
-  // Multiply color math + opacity application.
-  outAlbedo =
-      vec4(screenOut.xyz, texColor.a) * vec4(multColor.xyz, 1) * opacity;
+  // Multiply color math, global adjustments + opacity application.
+  vec3 color = pow(screenOut * multColor * globalTint, vec3(1.0 / globalGamma));
+  outAlbedo = vec4(color, texColor.a) * opacity;
+
+  // Straight alpha textures into premultiplied output
+  if (premultiply) {
+    outAlbedo.rgb *= texColor.a;
+  }
+
+  // Alpha coverage masking
+  if (maskCoverage) {
+    outAlbedo *= texelFetch(coverageMask, ivec2(gl_FragCoord.xy), 0).a;
+  }
+
+  // Blend modes fixed function blending cannot do
+  if (blendMode == SHADER_BLEND_CLIP_TO_LOWER || blendMode == SHADER_BLEND_SLICE_FROM_LOWER) {
+    outAlbedo = blendLower(outAlbedo);
+  } else if (blendMode != SHADER_BLEND_FIXED_FUNCTION) {
+    outAlbedo = blendBackdrop(outAlbedo);
+  }
 
   // Emissive
   outEmissive =
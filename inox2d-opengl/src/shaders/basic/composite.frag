@@ -18,6 +18,46 @@ uniform sampler2D bumpmap;
 uniform float opacity;
 uniform vec3 multColor;
 uniform vec3 screenColor;
+uniform vec3 globalTint;
+uniform float globalGamma;
+
+uniform bool maskCoverage;
+uniform sampler2D coverageMask;
+
+// Values of blendMode, same as the SHADER_BLEND_ constants of the renderer
+const int SHADER_BLEND_FIXED_FUNCTION = 0;
+const int SHADER_BLEND_OVERLAY = 1;
+const int SHADER_BLEND_HARD_LIGHT = 2;
+const int SHADER_BLEND_DARKEN = 5;
+const int SHADER_BLEND_LIGHTEN = 6;
+uniform int blendMode;
+uniform sampler2D backdrop;
+
+// Hard light of premultiplied `top` on `bottom`, times the alpha of both
+vec3 hardLight(vec4 top, vec4 bottom) {
+  vec3 multiplied = 2.0 * top.rgb * bottom.rgb;
+  vec3 screened = vec3(top.a * bottom.a) -
+                  2.0 * (vec3(top.a) - top.rgb) * (vec3(bottom.a) - bottom.rgb);
+  return mix(multiplied, screened, step(0.5 * top.a, top.rgb));
+}
+
+// Premultiplied `src` blended with the backdrop, to be drawn with ONE, ONE_MINUS_SRC_ALPHA blending
+vec4 blendBackdrop(vec4 src) {
+  vec4 dst = texelFetch(backdrop, ivec2(gl_FragCoord.xy), 0);
+  vec3 blended;
+  if (blendMode == SHADER_BLEND_OVERLAY) {
+    // overlay is hard light with src and dst swapped
+    blended = hardLight(dst, src);
+  } else if (blendMode == SHADER_BLEND_HARD_LIGHT) {
+    blended = hardLight(src, dst);
+  } else if (blendMode == SHADER_BLEND_DARKEN) {
+    blended = min(src.rgb * dst.a, dst.rgb * src.a);
+  } else {
+    // SHADER_BLEND_LIGHTEN
+    blended = max(src.rgb * dst.a, dst.rgb * src.a);
+  }
+  return vec4(src.rgb * (1.0 - dst.a) + blended, src.a);
+}
 
 void main() {
   // Sample texture
@@ -27,9 +67,19 @@ void main() {
   vec3 screenOut = vec3(1.0) - ((vec3(1.0) - (texColor.xyz)) *
                                 (vec3(1.0) - (screenColor * texColor.a)));
 
-  // Multiply color math + opacity application.
-  outAlbedo =
-      vec4(screenOut.xyz, texColor.a) * vec4(multColor.xyz, 1) * opacity;
+  // Multiply color math, global adjustments + opacity application.
+  vec3 color = pow(screenOut * multColor * globalTint, vec3(1.0 / globalGamma));
+  outAlbedo = vec4(color, texColor.a) * opacity;
+
+  // Alpha coverage masking
+  if (maskCoverage) {
+    outAlbedo *= texelFetch(coverageMask, ivec2(gl_FragCoord.xy), 0).a;
+  }
+
+  // Blend modes fixed function blending cannot do
+  if (blendMode != SHADER_BLEND_FIXED_FUNCTION) {
+    outAlbedo = blendBackdrop(outAlbedo);
+  }
 
   // Emissive
   outEmissive = texture(emissive, texUVs) * outAlbedo.a;
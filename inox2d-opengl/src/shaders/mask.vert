@@ -6,11 +6,9 @@
 */
 #version 330
 uniform mat4 mvp;
-uniform vec2 offset;
 layout(location = 0) in vec2 verts;
-
-out vec2 texUVs;
+layout(location = 2) in vec2 deform;
 
 void main() {
-  gl_Position = mvp * vec4(verts.x-offset.x, verts.y-offset.y, 0, 1);
+  gl_Position = mvp * vec4(verts + deform, 0, 1);
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use glow::HasContext;
 
-use inox2d::texture::ShallowTexture;
+use inox2d::texture::{ShallowTexture, TextureWrapping};
 
 #[derive(thiserror::Error, Debug)]
 #[error("Could not create texture: {0}")]
@@ -26,12 +26,7 @@ impl Texture {
 			gl.bind_texture(glow::TEXTURE_2D, Some(tex));
 			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
 			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
-			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_BORDER as i32);
-			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_BORDER as i32);
-
-			// Texture parameters for f32 slices are not supported on WASM yet.
-			#[cfg(not(target_arch = "wasm32"))]
-			gl.tex_parameter_f32_slice(glow::TEXTURE_2D, glow::TEXTURE_BORDER_COLOR, &[0.0; 4]);
+			set_wrapping(gl, TextureWrapping::default());
 
 			gl.tex_image_2d(
 				glow::TEXTURE_2D,
@@ -66,6 +61,14 @@ impl Texture {
 		}
 	}
 
+	pub fn set_wrapping(&self, gl: &glow::Context, wrapping: TextureWrapping) {
+		unsafe {
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.tex));
+			set_wrapping(gl, wrapping);
+			gl.bind_texture(glow::TEXTURE_2D, None);
+		}
+	}
+
 	pub fn unbind(&self, gl: &glow::Context) {
 		unsafe { gl.bind_texture(glow::TEXTURE_2D, None) };
 	}
@@ -81,6 +84,118 @@ impl Texture {
 	pub fn bpp(&self) -> u32 {
 		self.bpp
 	}
+
+	/// GPU memory taken by the texture, which has no mipmaps.
+	pub fn memory_bytes(&self) -> usize {
+		texture_bytes(self.width, self.height, self.bpp)
+	}
+}
+
+/// Bytes of a texture without mipmaps.
+pub(crate) fn texture_bytes(width: u32, height: u32, bpp: u32) -> usize {
+	width as usize * height as usize * bpp as usize / 8
+}
+
+/// OpenGL wrap mode of a `TextureWrapping`.
+fn gl_wrap_mode(wrapping: TextureWrapping) -> u32 {
+	match wrapping {
+		TextureWrapping::ClampToBorder => glow::CLAMP_TO_BORDER,
+		TextureWrapping::Repeat => glow::REPEAT,
+		TextureWrapping::MirroredRepeat => glow::MIRRORED_REPEAT,
+	}
+}
+
+/// Sets the wrapping of the texture bound to `TEXTURE_2D`.
+///
+/// # Safety
+///
+/// A texture must be bound to `TEXTURE_2D`
+unsafe fn set_wrapping(gl: &glow::Context, wrapping: TextureWrapping) {
+	let wrap_mode = gl_wrap_mode(wrapping) as i32;
+	gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap_mode);
+	gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap_mode);
+
+	// Texture parameters for f32 slices are not supported on WASM yet.
+	#[cfg(not(target_arch = "wasm32"))]
+	if wrapping == TextureWrapping::ClampToBorder {
+		gl.tex_parameter_f32_slice(glow::TEXTURE_2D, glow::TEXTURE_BORDER_COLOR, &[0.0; 4]);
+	}
+}
+
+/// When model textures are uploaded to the GPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureUpload {
+	/// All textures when creating the renderer.
+	#[default]
+	Eager,
+	/// Each texture when first bound, so textures of parts never drawn are never uploaded.
+	/// Faster to load, at the cost of a hitch on first draws.
+	Lazy,
+}
+
+enum TextureSlot<S, T> {
+	Pending(S),
+	Uploaded(T),
+	Failed,
+}
+
+/// Textures of a model, typically decoded as `ShallowTexture`, uploaded as `T` on first use.
+pub(crate) struct TextureSlots<S, T> {
+	slots: Vec<TextureSlot<S, T>>,
+}
+
+impl<S, T> TextureSlots<S, T> {
+	pub fn new(textures: Vec<S>) -> Self {
+		Self {
+			slots: textures.into_iter().map(TextureSlot::Pending).collect(),
+		}
+	}
+
+	/// Upload all pending textures, failing on the first error.
+	pub fn upload_all<E>(&mut self, mut upload: impl FnMut(&S) -> Result<T, E>) -> Result<(), E> {
+		for i in 0..self.slots.len() {
+			if let TextureSlot::Pending(shalltex) = &self.slots[i] {
+				self.slots[i] = TextureSlot::Uploaded(upload(shalltex)?);
+			}
+		}
+		Ok(())
+	}
+
+	/// Texture `i`, uploaded first if pending. `None` if it does not exist or could not be uploaded.
+	pub fn get_or_upload<E: std::fmt::Display>(
+		&mut self,
+		i: usize,
+		upload: impl FnOnce(&S) -> Result<T, E>,
+	) -> Option<&T> {
+		let slot = self.slots.get_mut(i)?;
+		if let TextureSlot::Pending(shalltex) = slot {
+			tracing::debug!("Uploading shallow texture {:?}", i);
+			*slot = match upload(shalltex) {
+				Ok(uploaded) => TextureSlot::Uploaded(uploaded),
+				Err(e) => {
+					tracing::error!("{}", e);
+					TextureSlot::Failed
+				}
+			};
+		}
+
+		match slot {
+			TextureSlot::Uploaded(uploaded) => Some(uploaded),
+			_ => None,
+		}
+	}
+
+	pub fn is_uploaded(&self, i: usize) -> bool {
+		matches!(self.slots.get(i), Some(TextureSlot::Uploaded(_)))
+	}
+
+	/// All textures uploaded so far.
+	pub fn uploaded(&self) -> impl Iterator<Item = &T> {
+		self.slots.iter().filter_map(|slot| match slot {
+			TextureSlot::Uploaded(uploaded) => Some(uploaded),
+			_ => None,
+		})
+	}
 }
 
 /// Uploads an empty texture.
@@ -107,3 +222,39 @@ pub unsafe fn upload_empty(gl: &glow::Context, tex: glow::Texture, width: u32, h
 	gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
 	gl.bind_texture(glow::TEXTURE_2D, None);
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lazy_upload() {
+		let mut slots = TextureSlots::new(vec![0, 1, 2]);
+		let uploads = std::cell::RefCell::new(Vec::new());
+		let upload = |texture: &u32| {
+			uploads.borrow_mut().push(*texture);
+			Ok::<_, TextureError>(*texture)
+		};
+
+		// only the drawn part's texture is uploaded, once
+		assert_eq!(slots.get_or_upload(1, upload), Some(&1));
+		assert_eq!(slots.get_or_upload(1, upload), Some(&1));
+		assert_eq!(slots.get_or_upload(3, upload), None);
+		assert!(!slots.is_uploaded(0) && slots.is_uploaded(1) && !slots.is_uploaded(2));
+		assert_eq!(*uploads.borrow(), [1]);
+
+		assert_eq!(slots.uploaded().collect::<Vec<_>>(), [&1]);
+
+		slots.upload_all(upload).unwrap();
+		assert!((0..3).all(|i| slots.is_uploaded(i)));
+		assert_eq!(*uploads.borrow(), [1, 0, 2]);
+		assert_eq!(slots.uploaded().collect::<Vec<_>>(), [&0, &1, &2]);
+	}
+
+	#[test]
+	fn wrap_modes() {
+		assert_eq!(gl_wrap_mode(TextureWrapping::default()), glow::CLAMP_TO_BORDER);
+		assert_eq!(gl_wrap_mode(TextureWrapping::Repeat), glow::REPEAT);
+		assert_eq!(gl_wrap_mode(TextureWrapping::MirroredRepeat), glow::MIRRORED_REPEAT);
+	}
+}
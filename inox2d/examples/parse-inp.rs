@@ -3,7 +3,7 @@ use std::io::{BufReader, Read};
 use std::path::PathBuf;
 
 use clap::Parser;
-use inox2d::formats::inp::parse_inp;
+use inox2d::formats::inp::{parse_inp, parse_inx};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -15,8 +15,11 @@ struct Cli {
 fn main() {
 	let cli = Cli::parse();
 
+	let is_inx = cli.inp_path.extension().is_some_and(|ext| ext == "inx");
+	let base_dir = cli.inp_path.parent().map(PathBuf::from).unwrap_or_default();
+
 	let data = {
-		let file = File::open(cli.inp_path).unwrap();
+		let file = File::open(&cli.inp_path).unwrap();
 		let mut file = BufReader::new(file);
 		let mut data = Vec::new();
 		file.read_to_end(&mut data).unwrap();
@@ -26,7 +29,12 @@ fn main() {
 	use std::time::Instant;
 	let now = Instant::now();
 
-	let model = match parse_inp(data.as_slice()) {
+	let parsed = if is_inx {
+		parse_inx(data.as_slice(), &base_dir)
+	} else {
+		parse_inp(data.as_slice())
+	};
+	let model = match parsed {
 		Ok(m) => m,
 		Err(e) => {
 			println!("{e}");
@@ -35,7 +43,7 @@ fn main() {
 	};
 
 	let elapsed = now.elapsed();
-	println!("parse_inp() took: {:.2?}", elapsed);
+	println!("parsing took: {:.2?}", elapsed);
 
 	println!("== Puppet Meta ==\n{}", &model.puppet.meta);
 	// TODO: Implement full node print after ECS
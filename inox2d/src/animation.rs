@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use crate::math::interp::{cubic_weights, interpolate_f32, InterpRange, InterpolateMode};
+use crate::params::{Axis, ParamUuid};
+use crate::puppet::Puppet;
+
+/// Animation of params over a number of frames, as authored in Inochi Creator.
+#[derive(Clone, Debug)]
+pub struct Animation {
+	/// Duration of a frame, in seconds.
+	pub timestep: f32,
+	/// Whether lane values are added to param values instead of replacing them.
+	pub additive: bool,
+	/// Weight of the animation when blended with others.
+	pub weight: f32,
+	/// Number of frames.
+	pub length: u32,
+	/// Frame at which the looped section starts, `None` to loop from the start.
+	pub lead_in: Option<u32>,
+	/// Frame at which the looped section ends, `None` to loop until the end.
+	pub lead_out: Option<u32>,
+	pub lanes: Vec<AnimationLane>,
+}
+
+impl Animation {
+	/// Frames `[start, end)` played over and over by a looping playback.
+	fn loop_range(&self) -> (f32, f32) {
+		let end = self.lead_out.unwrap_or(self.length).min(self.length);
+		let start = self.lead_in.unwrap_or(0).min(end);
+		(start as f32, end as f32)
+	}
+}
+
+/// Keyframes of one axis of a param.
+#[derive(Clone, Debug)]
+pub struct AnimationLane {
+	pub param: ParamUuid,
+	pub axis: Axis,
+	pub interpolation: InterpolateMode,
+	/// Sorted by frame.
+	pub keyframes: Vec<Keyframe>,
+}
+
+impl AnimationLane {
+	/// Value of the lane at `frame`, holding the first and last keyframe values outside of them.
+	/// `None` if there are no keyframes.
+	pub fn sample(&self, frame: f32) -> Option<f32> {
+		let first = self.keyframes.first()?;
+		let last = self.keyframes.last()?;
+		if frame <= first.frame as f32 {
+			return Some(first.value);
+		}
+		if frame >= last.frame as f32 {
+			return Some(last.value);
+		}
+
+		let next = self
+			.keyframes
+			.partition_point(|keyframe| keyframe.frame as f32 <= frame);
+		if self.interpolation == InterpolateMode::Cubic {
+			// the keyframes around the surrounding ones shape the curve, repeating the first and last ones
+			let keyframes = [
+				next.saturating_sub(2),
+				next - 1,
+				next,
+				(next + 1).min(self.keyframes.len() - 1),
+			]
+			.map(|i| &self.keyframes[i]);
+			let weights = cubic_weights(frame, keyframes.map(|keyframe| keyframe.frame as f32));
+			return Some(
+				weights
+					.iter()
+					.zip(keyframes)
+					.map(|(w, keyframe)| w * keyframe.value)
+					.sum(),
+			);
+		}
+
+		let (beg, end) = (&self.keyframes[next - 1], &self.keyframes[next]);
+		Some(interpolate_f32(
+			frame,
+			InterpRange::new(beg.frame as f32, end.frame as f32),
+			InterpRange::new(beg.value, end.value),
+			self.interpolation,
+		))
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+	pub frame: u32,
+	pub value: f32,
+	/// Tension of the curve going through this keyframe, for interpolation modes that use it.
+	pub tension: f32,
+}
+
+/// An animation being played, see `Puppet::play_animation()`.
+#[derive(Clone, Debug)]
+struct Playback {
+	name: String,
+	/// Current frame, fractional between frames.
+	frame: f32,
+	looping: bool,
+}
+
+/// Animations being played on a puppet.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AnimationCtx {
+	/// In the order they were started, which is the order they are applied in.
+	playing: Vec<Playback>,
+}
+
+impl AnimationCtx {
+	/// Keep playing the animations that `animations` still has.
+	pub(crate) fn retain_existing(&mut self, animations: &HashMap<String, Animation>) {
+		self.playing.retain(|playback| animations.contains_key(&playback.name));
+	}
+
+	/// Move playing animations `dt` seconds forward.
+	///
+	/// Animations played once and already shown at their last frame stop, as do animations `animations` no longer has.
+	pub(crate) fn advance(&mut self, animations: &HashMap<String, Animation>, dt: f32) {
+		self.playing.retain_mut(|playback| {
+			let Some(animation) = animations.get(&playback.name) else {
+				return false;
+			};
+			if !playback.looping && playback.frame >= animation.length as f32 {
+				return false;
+			}
+
+			if animation.timestep > 0. {
+				playback.frame += dt / animation.timestep;
+			}
+			let (loop_start, loop_end) = animation.loop_range();
+			if playback.looping && playback.frame >= loop_end && loop_end > loop_start {
+				playback.frame = loop_start + (playback.frame - loop_end) % (loop_end - loop_start);
+			}
+			true
+		});
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlayAnimationError {
+	#[error("No animation named {0}")]
+	NoAnimationNamed(String),
+}
+
+impl Puppet {
+	/// Play animation `name` from its start, over and over: after the first time, only between its lead-in and lead-out.
+	/// Restarts it if it is already playing. See `.apply_animations()`.
+	pub fn play_animation(&mut self, name: &str) -> Result<(), PlayAnimationError> {
+		self.start_playback(name, true)
+	}
+
+	/// Play animation `name` from its start, once. Restarts it if it is already playing.
+	pub fn play_animation_once(&mut self, name: &str) -> Result<(), PlayAnimationError> {
+		self.start_playback(name, false)
+	}
+
+	fn start_playback(&mut self, name: &str, looping: bool) -> Result<(), PlayAnimationError> {
+		if !self.animations.contains_key(name) {
+			return Err(PlayAnimationError::NoAnimationNamed(name.to_owned()));
+		}
+
+		self.stop_animation(name);
+		self.animation_ctx.playing.push(Playback {
+			name: name.to_owned(),
+			frame: 0.,
+			looping,
+		});
+		Ok(())
+	}
+
+	/// Stop animation `name`. Its params are back to their defaults from the next frame on.
+	pub fn stop_animation(&mut self, name: &str) {
+		self.animation_ctx.playing.retain(|playback| playback.name != name);
+	}
+
+	/// Whether animation `name` is playing. An animation played once stops after reaching its last frame.
+	pub fn is_animation_playing(&self, name: &str) -> bool {
+		self.animation_ctx.playing.iter().any(|playback| playback.name == name)
+	}
+
+	/// Set params of playing animations to their values at the current frame of the animations.
+	///
+	/// To be called every frame between `.begin_frame()` and `.end_frame()`, before setting params by hand,
+	/// so that those override or build upon animated values. Animations then move forward by the animation delta
+	/// given to `.end_frame()` or `.end_frame_with()`.
+	///
+	/// Panicks if params are not initialized.
+	pub fn apply_animations(&mut self) {
+		let param_ctx = self
+			.param_ctx
+			.as_mut()
+			.expect("Params of a puppet must be initialized before animating them.");
+
+		// animations removed from `.animations` since they were started are not played anymore
+		self.animation_ctx.retain_existing(&self.animations);
+		for playback in &self.animation_ctx.playing {
+			let Some(animation) = self.animations.get(&playback.name) else {
+				continue;
+			};
+			let frame = playback.frame.min(animation.length as f32);
+
+			for lane in &animation.lanes {
+				let Some(value) = lane.sample(frame) else {
+					continue;
+				};
+				let Some((name, _)) = self.params.iter().find(|(_, param)| param.uuid == lane.param) else {
+					continue;
+				};
+
+				let current = param_ctx.get(name).unwrap();
+				let current = match lane.axis {
+					Axis::X => current.x,
+					Axis::Y => current.y,
+				};
+				let value = if animation.additive {
+					current + value * animation.weight
+				} else {
+					current + (value - current) * animation.weight
+				};
+				param_ctx
+					.set_axis(name, lane.axis, value)
+					.expect("Params of a puppet must be present in its ParamCtx.");
+			}
+		}
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::vec2;
+
+	use crate::formats::fixtures;
+	use crate::math::interp::InterpolateMode;
+	use crate::params::{Axis, ParamUuid};
+	use crate::puppet::Puppet;
+
+	use super::{Animation, AnimationLane, Keyframe, PlayAnimationError};
+
+	fn puppet() -> Puppet {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", Vec::new()),
+			vec![fixtures::param(10, "open", Vec::new())],
+		));
+		let keyframe = |frame, value| Keyframe {
+			frame,
+			value,
+			tension: 0.5,
+		};
+		puppet.animations.insert(
+			"open".to_owned(),
+			Animation {
+				timestep: 0.1,
+				additive: false,
+				weight: 1.0,
+				length: 10,
+				lead_in: None,
+				lead_out: None,
+				lanes: vec![AnimationLane {
+					param: ParamUuid(10),
+					axis: Axis::X,
+					interpolation: InterpolateMode::Linear,
+					keyframes: vec![keyframe(0, 0.0), keyframe(10, 1.0)],
+				}],
+			},
+		);
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet
+	}
+
+	fn open(puppet: &Puppet) -> f32 {
+		puppet.param_ctx.as_ref().unwrap().get("open").unwrap().x
+	}
+
+	/// Start a frame showing animations, after ending the previous one `dt` seconds ago.
+	fn next_frame(puppet: &mut Puppet, dt: f32) {
+		puppet.end_frame(dt);
+		puppet.begin_frame();
+		puppet.apply_animations();
+	}
+
+	#[test]
+	fn playback() {
+		let mut puppet = puppet();
+		assert!(matches!(
+			puppet.play_animation("close"),
+			Err(PlayAnimationError::NoAnimationNamed(_))
+		));
+
+		puppet.play_animation("open").unwrap();
+		puppet.begin_frame();
+		puppet.apply_animations();
+		assert_eq!(open(&puppet), 0.0);
+		next_frame(&mut puppet, 0.5);
+		assert!((open(&puppet) - 0.5).abs() < 1e-5);
+
+		// params set by hand after animations override them
+		puppet.param_ctx.as_mut().unwrap().set("open", vec2(0.2, 0.0)).unwrap();
+		assert_eq!(open(&puppet), 0.2);
+
+		// looping wraps around past the end
+		next_frame(&mut puppet, 0.6);
+		assert!((open(&puppet) - 0.1).abs() < 1e-5);
+
+		// frozen animation time
+		puppet.end_frame_with(0.0, 0.1);
+		puppet.begin_frame();
+		puppet.apply_animations();
+		assert!((open(&puppet) - 0.1).abs() < 1e-5);
+
+		// played once, the last frame is held then the animation stops
+		puppet.play_animation_once("open").unwrap();
+		next_frame(&mut puppet, 1.2);
+		assert_eq!(open(&puppet), 1.0);
+		next_frame(&mut puppet, 0.1);
+		assert!(!puppet.is_animation_playing("open"));
+		assert_eq!(open(&puppet), 0.0);
+	}
+
+	#[test]
+	fn removed_while_playing() {
+		let mut puppet = puppet();
+		puppet.play_animation("open").unwrap();
+		next_frame(&mut puppet, 0.5);
+
+		puppet.animations.remove("open");
+		next_frame(&mut puppet, 0.5);
+		assert!(!puppet.is_animation_playing("open"));
+		assert_eq!(open(&puppet), 0.0);
+	}
+
+	#[test]
+	fn cubic_lane() {
+		let keyframe = |frame, value| Keyframe {
+			frame,
+			value,
+			tension: 0.5,
+		};
+		let mut lane = AnimationLane {
+			param: ParamUuid(10),
+			axis: Axis::X,
+			interpolation: InterpolateMode::Linear,
+			keyframes: vec![keyframe(0, 0.0), keyframe(5, 1.0), keyframe(10, 0.0)],
+		};
+		assert_eq!(lane.sample(2.5), Some(0.5));
+
+		// overshoots the line towards the peak at frame 5
+		lane.interpolation = InterpolateMode::Cubic;
+		assert_eq!(lane.sample(2.5), Some(0.625));
+		assert_eq!(lane.sample(5.0), Some(1.0));
+		assert_eq!(lane.sample(7.5), Some(0.625));
+	}
+}
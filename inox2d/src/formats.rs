@@ -1,42 +1,20 @@
+#[cfg(all(test, feature = "json"))]
+pub(crate) mod fixtures;
+#[cfg(any(feature = "json", feature = "serde_json"))]
 pub mod inp;
+#[cfg(any(feature = "json", feature = "serde_json"))]
 mod json;
+#[cfg(feature = "serde_json")]
+mod json_serde;
+mod obj;
+#[cfg(any(feature = "json", feature = "serde_json"))]
 mod payload;
+#[cfg(any(feature = "json", feature = "serde_json"))]
+mod sanitize;
+#[cfg(feature = "json")]
+mod serialize;
 
-use glam::Vec2;
-
-use std::io::{self, Read};
-use std::slice;
-
+#[cfg(any(feature = "json", feature = "serde_json"))]
 pub use json::JsonError;
-
-#[inline]
-fn read_n<R: Read, const N: usize>(data: &mut R) -> io::Result<[u8; N]> {
-	let mut buf = [0_u8; N];
-	data.read_exact(&mut buf)?;
-	Ok(buf)
-}
-
-#[inline]
-fn read_u8<R: Read>(data: &mut R) -> io::Result<u8> {
-	let buf = read_n::<_, 1>(data)?;
-	Ok(u8::from_ne_bytes(buf))
-}
-
-#[inline]
-fn read_be_u32<R: Read>(data: &mut R) -> io::Result<u32> {
-	let buf = read_n::<_, 4>(data)?;
-	Ok(u32::from_be_bytes(buf))
-}
-
-#[inline]
-fn read_vec<R: Read>(data: &mut R, n: usize) -> io::Result<Vec<u8>> {
-	let mut buf = vec![0_u8; n];
-	data.read_exact(&mut buf)?;
-	Ok(buf)
-}
-
-#[inline]
-fn f32s_as_vec2s(vec: &[f32]) -> &'_ [Vec2] {
-	// SAFETY: the length of the slice never trespasses outside of the array
-	unsafe { slice::from_raw_parts(vec.as_ptr() as *const Vec2, vec.len() / 2) }
-}
+#[cfg(any(feature = "json", feature = "serde_json"))]
+pub use payload::ParseMode;
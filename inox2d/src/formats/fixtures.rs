@@ -0,0 +1,115 @@
+//! Minimal puppet payloads for tests.
+//!
+//! Every builder returns a plain `JsonValue`, so a test can tweak any field before loading it.
+
+use json::{array, object, JsonValue};
+
+use crate::puppet::Puppet;
+
+pub(crate) fn node(uuid: u32, name: &str, children: Vec<JsonValue>) -> JsonValue {
+	object! {
+		uuid: uuid,
+		name: name,
+		"type": "Node",
+		enabled: true,
+		zsort: 0.0,
+		transform: {
+			trans: [0.0, 0.0, 0.0],
+			rot: [0.0, 0.0, 0.0],
+			scale: [1.0, 1.0],
+		},
+		lockToRoot: false,
+		children: children,
+	}
+}
+
+/// A Part with a 2x2 quad mesh centered on its origin.
+pub(crate) fn part(uuid: u32, name: &str) -> JsonValue {
+	let mut part = node(uuid, name, Vec::new());
+	part["type"] = "Part".into();
+	part["blend_mode"] = "Normal".into();
+	part["textures"] = array![0];
+	part["mesh"] = object! {
+		verts: [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0],
+		uvs: [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+		indices: [0, 1, 2, 2, 1, 3],
+		origin: [0.0, 0.0],
+	};
+	part
+}
+
+pub(crate) fn composite(uuid: u32, name: &str, children: Vec<JsonValue>) -> JsonValue {
+	let mut composite = node(uuid, name, children);
+	composite["type"] = "Composite".into();
+	composite["blend_mode"] = "Normal".into();
+	composite
+}
+
+/// A SimplePhysics node driving param `param_uuid` with a spring pendulum.
+pub(crate) fn simple_physics(uuid: u32, name: &str, param_uuid: u32) -> JsonValue {
+	let mut simple_physics = node(uuid, name, Vec::new());
+	simple_physics["type"] = "SimplePhysics".into();
+	simple_physics["param"] = param_uuid.into();
+	simple_physics["model_type"] = "SpringPendulum".into();
+	simple_physics["map_mode"] = "XY".into();
+	simple_physics["gravity"] = 1.0.into();
+	simple_physics["length"] = 100.0.into();
+	simple_physics["frequency"] = 1.0.into();
+	simple_physics["angle_damping"] = 0.5.into();
+	simple_physics["length_damping"] = 0.5.into();
+	simple_physics["output_scale"] = array![1.0, 1.0];
+	simple_physics
+}
+
+/// A one-dimensional param going from 0 to 1, with axis points at both ends.
+pub(crate) fn param(uuid: u32, name: &str, bindings: Vec<JsonValue>) -> JsonValue {
+	object! {
+		uuid: uuid,
+		name: name,
+		is_vec2: false,
+		min: [0.0, 0.0],
+		max: [1.0, 1.0],
+		defaults: [0.0, 0.0],
+		axis_points: [[0.0, 1.0], [0.0]],
+		bindings: bindings,
+	}
+}
+
+/// A binding for a param made by `param()`, given the values at its two axis points.
+pub(crate) fn binding(node: u32, param_name: &str, values: [JsonValue; 2]) -> JsonValue {
+	let [beg, end] = values;
+	let mut binding = object! {
+		node: node,
+		param_name: param_name,
+		interpolate_mode: "Linear",
+		isSet: [[true], [true]],
+	};
+	binding["values"] = JsonValue::Array(vec![array![beg], array![end]]);
+	binding
+}
+
+pub(crate) fn puppet(root: JsonValue, params: Vec<JsonValue>) -> JsonValue {
+	object! {
+		meta: {
+			name: "Test Puppet",
+			version: "1.0-alpha",
+			rigger: null,
+			artist: null,
+			copyright: null,
+			licenseURL: null,
+			contact: null,
+			reference: null,
+			preservePixels: false,
+		},
+		physics: {
+			pixelsPerMeter: 1000.0,
+			gravity: 9.8,
+		},
+		nodes: root,
+		param: params,
+	}
+}
+
+pub(crate) fn load(payload: &JsonValue) -> Puppet {
+	Puppet::new_from_json(payload).expect("Test payload must be valid.")
+}
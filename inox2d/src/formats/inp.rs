@@ -1,34 +1,94 @@
 use std::io::{self, Read};
+use std::path::{Component, Path};
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 use std::sync::Arc;
 
 use image::ImageFormat;
 
-use crate::model::{Model, ModelTexture, VendorData};
+#[cfg(feature = "json")]
+use crate::model::VendorData;
+use crate::model::{Model, ModelTexture};
+use crate::puppet::meta::SpecVersion;
 use crate::puppet::Puppet;
 
-use super::json::JsonError;
-use super::payload::InoxParseError;
-use super::{read_be_u32, read_n, read_u8, read_vec};
+use super::json::{JsonBackend, JsonError, JsonObject};
+use super::payload::{InoxParseError, InoxParseResult, ParseMode};
+
+#[inline]
+fn read_n<R: Read, const N: usize>(data: &mut R) -> io::Result<[u8; N]> {
+	let mut buf = [0_u8; N];
+	data.read_exact(&mut buf)?;
+	Ok(buf)
+}
+
+#[inline]
+fn read_u8<R: Read>(data: &mut R) -> io::Result<u8> {
+	let buf = read_n::<_, 1>(data)?;
+	Ok(u8::from_ne_bytes(buf))
+}
+
+#[inline]
+fn read_be_u32<R: Read>(data: &mut R) -> io::Result<u32> {
+	let buf = read_n::<_, 4>(data)?;
+	Ok(u32::from_be_bytes(buf))
+}
+
+/// Like `read_exact()` into a buffer of length `n`, which only grows as data is read,
+/// so that a corrupt length does not allocate up front.
+#[inline]
+fn read_vec<R: Read>(data: &mut R, n: usize) -> io::Result<Vec<u8>> {
+	let mut buf = Vec::new();
+	data.take(n as u64).read_to_end(&mut buf)?;
+	if buf.len() < n {
+		return Err(io::ErrorKind::UnexpectedEof.into());
+	}
+	Ok(buf)
+}
 
 #[derive(Debug, thiserror::Error)]
 #[error("Could not parse INP file\n  - {0}")]
-pub enum ParseInpError {
+pub enum InpParseError {
 	#[error("magic bytes do not match, the file is not in the INP format")]
-	IncorrectMagic,
+	BadMagic,
+	#[error("the file ends in the middle of a section, it is truncated or a length in it is wrong")]
+	UnexpectedEof,
 	#[error("there is no texture section")]
 	NoTexSect,
 	#[error("BC7 texture encoding is not supported yet")]
 	Bc7NotSupported,
-	#[error("Invalid texture encoding: {0}")]
-	InvalidTexEncoding(u8),
-	Io(#[from] io::Error),
+	#[error("Invalid texture encoding {0}, expected 0 (PNG), 1 (TGA), 2 (BC7) or 3 (WebP)")]
+	UnsupportedTextureType(u8),
+	#[error("texture reference {0:?} leaves the project directory, only relative paths without `..` are allowed")]
+	ExternalTextureOutsideProject(String),
+	Io(io::Error),
 	Utf8(#[from] Utf8Error),
 	FromUtf8(#[from] FromUtf8Error),
+	#[cfg(feature = "json")]
 	JsonParse(#[from] json::Error),
-	InoxParse(#[from] InoxParseError),
-	Json(#[from] JsonError),
+	#[cfg(feature = "serde_json")]
+	SerdeJsonParse(#[from] serde_json::Error),
+	InoxParse(InoxParseError),
+	#[error("the puppet payload is malformed: {0}")]
+	PayloadJson(JsonError),
+}
+
+impl From<io::Error> for InpParseError {
+	fn from(err: io::Error) -> Self {
+		match err.kind() {
+			io::ErrorKind::UnexpectedEof => InpParseError::UnexpectedEof,
+			_ => InpParseError::Io(err),
+		}
+	}
+}
+
+impl From<InoxParseError> for InpParseError {
+	fn from(err: InoxParseError) -> Self {
+		match err {
+			InoxParseError::JsonError(err) => InpParseError::PayloadJson(err),
+			err => InpParseError::InoxParse(err),
+		}
+	}
 }
 
 /// Trans rights!
@@ -36,41 +96,236 @@ const MAGIC: &[u8] = b"TRNSRTS\0";
 /// Texture section header
 const TEX_SECT: &[u8] = b"TEX_SECT";
 /// Optional EXTended Vendor Data section for app provided settings for the puppet
+#[cfg(feature = "json")]
 const EXT_SECT: &[u8] = b"EXT_SECT";
+/// Texture encoding of `.inx` slots holding a UTF-8 path to an image file instead of image data
+const EXTERNAL_TEX_ENCODING: u8 = 255;
+
+/// JSON values the puppet payload is parsed into.
+trait InpPayload: JsonBackend {
+	fn parse_str(payload: &str) -> Result<Self, InpParseError>;
+}
+
+#[cfg(feature = "json")]
+impl InpPayload for json::JsonValue {
+	fn parse_str(payload: &str) -> Result<Self, InpParseError> {
+		Ok(json::parse(payload)?)
+	}
+}
+
+#[cfg(feature = "serde_json")]
+impl InpPayload for serde_json::Value {
+	fn parse_str(payload: &str) -> Result<Self, InpParseError> {
+		Ok(serde_json::from_str(payload)?)
+	}
+}
+
+/// Payload values of `parse_inp()` and `parse_inx()`: the `json` crate's, `serde_json`'s if that is the only one enabled.
+#[cfg(feature = "json")]
+type DefaultPayload = json::JsonValue;
+#[cfg(not(feature = "json"))]
+type DefaultPayload = serde_json::Value;
+
+/// Parse `.inp` files.
+///
+/// The payload is parsed with the `json` crate, or with `serde_json` if only the `serde_json` feature is enabled.
+/// Vendor data is only kept with the `json` feature.
+pub fn parse_inp<R: Read>(data: R) -> Result<Model, InpParseError> {
+	parse::<DefaultPayload, _>(data, None)
+}
+
+/// Same as `parse_inp()`, with the payload parsed by `serde_json`.
+#[cfg(feature = "serde_json")]
+pub fn parse_inp_serde_json<R: Read>(data: R) -> Result<Model, InpParseError> {
+	parse::<serde_json::Value, _>(data, None)
+}
+
+/// Parse `.inx` project files, as saved by Inochi Creator.
+///
+/// Same container as `.inp`, but texture slots may reference image files instead of embedding them.
+/// Relative references are resolved against `base_dir`, usually the directory of the project file,
+/// and may not leave it: absolute references and ones containing `..` are an error.
+/// A referenced file that cannot be read is replaced by a blank texture, with a warning.
+pub fn parse_inx<R: Read>(data: R, base_dir: &Path) -> Result<Model, InpParseError> {
+	parse::<DefaultPayload, _>(data, Some(base_dir))
+}
+
+/// Same as `parse_inx()`, with the payload parsed by `serde_json`.
+#[cfg(feature = "serde_json")]
+pub fn parse_inx_serde_json<R: Read>(data: R, base_dir: &Path) -> Result<Model, InpParseError> {
+	parse::<serde_json::Value, _>(data, Some(base_dir))
+}
+
+/// Project files carry editor state that changes with major versions, unlike exported puppets.
+fn check_editor_version(puppet: &Puppet) -> Result<(), InoxParseError> {
+	let version = &puppet.meta.version;
+	match SpecVersion::parse(version) {
+		Some(parsed) if parsed.major <= SpecVersion::supported().major => Ok(()),
+		_ => Err(InoxParseError::UnsupportedEditorVersion(version.clone())),
+	}
+}
+
+fn read_external_texture(base_dir: &Path, reference: &[u8]) -> Result<ModelTexture, InpParseError> {
+	let reference = std::str::from_utf8(reference)?;
+	let is_inside = Path::new(reference)
+		.components()
+		.all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+	if !is_inside {
+		return Err(InpParseError::ExternalTextureOutsideProject(reference.to_owned()));
+	}
+	let path = base_dir.join(reference);
+
+	let texture = ImageFormat::from_path(&path)
+		.map_err(|e| e.to_string())
+		.and_then(|format| Ok((format, std::fs::read(&path).map_err(|e| e.to_string())?)));
+	match texture {
+		Ok((format, data)) => Ok(ModelTexture {
+			format,
+			data: data.into(),
+		}),
+		Err(e) => {
+			tracing::warn!("Could not load texture {}, using a blank one: {e}", path.display());
+			Ok(blank_texture())
+		}
+	}
+}
+
+/// 1x1 transparent PNG, encoded ahead of time so that parsing does not depend on image encoders.
+#[rustfmt::skip]
+const BLANK_PNG: [u8; 68] = [
+	0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+	0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+	0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x60, 0x00, 0x02, 0x00,
+	0x00, 0x05, 0x00, 0x01, 0xe9, 0xfa, 0xdc, 0xd8, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+	0xae, 0x42, 0x60, 0x82,
+];
+
+/// 1x1 transparent PNG.
+fn blank_texture() -> ModelTexture {
+	ModelTexture {
+		format: ImageFormat::Png,
+		data: Arc::from(&BLANK_PNG[..]),
+	}
+}
+
+/// Write `model` in the `.inp` format, as read by `parse_inp()`.
+///
+/// Textures are written in their format if the INP format supports it, and re-encoded as PNG otherwise,
+/// which fails without the `decode-textures` feature.
+/// See `Puppet::to_json()` for what of the puppet is written.
+#[cfg(feature = "json")]
+pub fn write_inp(model: &Model) -> io::Result<Vec<u8>> {
+	let mut data = MAGIC.to_vec();
+
+	let payload = model.puppet.to_json().dump();
+	write_be_u32(&mut data, payload.len())?;
+	data.extend_from_slice(payload.as_bytes());
+
+	data.extend_from_slice(TEX_SECT);
+	write_be_u32(&mut data, model.textures.len())?;
+	for texture in &model.textures {
+		let (tex_encoding, tex_data) = match texture.format {
+			ImageFormat::Png => (0, texture.data.clone()),
+			ImageFormat::Tga => (1, texture.data.clone()),
+			ImageFormat::WebP => (3, texture.data.clone()),
+			format => (0, reencode_as_png(texture.data.as_ref(), format)?.into()),
+		};
+		write_be_u32(&mut data, tex_data.len())?;
+		data.push(tex_encoding);
+		data.extend_from_slice(&tex_data);
+	}
+
+	if !model.vendors.is_empty() {
+		data.extend_from_slice(EXT_SECT);
+		write_be_u32(&mut data, model.vendors.len())?;
+		for vendor in &model.vendors {
+			write_be_u32(&mut data, vendor.name.len())?;
+			data.extend_from_slice(vendor.name.as_bytes());
+
+			let payload = vendor.payload.dump();
+			write_be_u32(&mut data, payload.len())?;
+			data.extend_from_slice(payload.as_bytes());
+		}
+	}
 
-/// Parse `.inp` and `.inx` files.
-pub fn parse_inp<R: Read>(mut data: R) -> Result<Model, ParseInpError> {
+	Ok(data)
+}
+
+/// Lengths in the INP format are 32-bit.
+#[cfg(feature = "json")]
+fn write_be_u32(data: &mut Vec<u8>, length: usize) -> io::Result<()> {
+	let length = u32::try_from(length).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "section too large"))?;
+	data.extend_from_slice(&length.to_be_bytes());
+	Ok(())
+}
+
+#[cfg(all(feature = "json", feature = "decode-textures"))]
+fn reencode_as_png(data: &[u8], format: ImageFormat) -> io::Result<Vec<u8>> {
+	let to_io_error = |e: image::ImageError| io::Error::new(io::ErrorKind::InvalidData, e);
+
+	let image = image::load_from_memory_with_format(data, format).map_err(to_io_error)?;
+	let mut png = Vec::new();
+	image
+		.write_to(&mut io::Cursor::new(&mut png), ImageFormat::Png)
+		.map_err(to_io_error)?;
+	Ok(png)
+}
+
+#[cfg(all(feature = "json", not(feature = "decode-textures")))]
+fn reencode_as_png(_data: &[u8], format: ImageFormat) -> io::Result<Vec<u8>> {
+	Err(io::Error::new(
+		io::ErrorKind::Unsupported,
+		format!("re-encoding {format:?} textures as PNG requires the decode-textures feature"),
+	))
+}
+
+fn parse<V: InpPayload, R: Read>(mut data: R, inx_base_dir: Option<&Path>) -> Result<Model, InpParseError> {
 	// check magic bytes
 	let magic = read_n::<_, 8>(&mut data)?;
 	if magic != MAGIC {
-		return Err(ParseInpError::IncorrectMagic);
+		return Err(InpParseError::BadMagic);
 	}
 
 	// parse json payload into puppet
 	let length = read_be_u32(&mut data)? as usize;
 	let payload = read_vec(&mut data, length)?;
 	let payload = std::str::from_utf8(&payload)?;
-	let payload = json::parse(payload)?;
-	let puppet = Puppet::new_from_json(&payload)?;
+	let payload = V::parse_str(payload)?;
+	let puppet = Puppet::from_payload(
+		&payload,
+		ParseMode::default(),
+		None::<&fn(&mut Puppet, &str, JsonObject<V>) -> InoxParseResult<()>>,
+	)?;
+	if inx_base_dir.is_some() {
+		check_editor_version(&puppet)?;
+	}
 
 	// check texture section header
-	let tex_sect = read_n::<_, 8>(&mut data).map_err(|_| ParseInpError::NoTexSect)?;
+	let tex_sect = read_n::<_, 8>(&mut data).map_err(|_| InpParseError::NoTexSect)?;
 	if tex_sect != TEX_SECT {
-		return Err(ParseInpError::NoTexSect);
+		return Err(InpParseError::NoTexSect);
 	}
 
 	// retrieve textures
+	// counts are not trusted for allocations, a corrupt one must end in an error instead of running out of memory
 	let tex_count = read_be_u32(&mut data)? as usize;
-	let mut textures = Vec::with_capacity(tex_count);
+	let mut textures = Vec::new();
 	for _ in 0..tex_count {
 		let tex_length = read_be_u32(&mut data)? as usize;
 		let tex_encoding = read_u8(&mut data)?;
 
+		if let (EXTERNAL_TEX_ENCODING, Some(base_dir)) = (tex_encoding, inx_base_dir) {
+			let reference = read_vec(&mut data, tex_length)?;
+			textures.push(read_external_texture(base_dir, &reference)?);
+			continue;
+		}
+
 		let format = match tex_encoding {
 			0 => ImageFormat::Png, // PNG
 			1 => ImageFormat::Tga, // TGA
-			2 => return Err(ParseInpError::Bc7NotSupported),
-			n => return Err(ParseInpError::InvalidTexEncoding(n)),
+			2 => return Err(InpParseError::Bc7NotSupported),
+			3 => ImageFormat::WebP, // WebP
+			n => return Err(InpParseError::UnsupportedTextureType(n)),
 		};
 
 		let data: Arc<[u8]> = read_vec(&mut data, tex_length)?.into();
@@ -78,10 +333,11 @@ pub fn parse_inp<R: Read>(mut data: R) -> Result<Model, ParseInpError> {
 	}
 
 	// read extended section header if present
+	#[cfg(feature = "json")]
 	let vendors = match read_n::<_, 8>(&mut data) {
 		Ok(ext_sect) if ext_sect == EXT_SECT => {
 			let ext_count = read_be_u32(&mut data)? as usize;
-			let mut vendors = Vec::with_capacity(ext_count);
+			let mut vendors = Vec::new();
 			for _ in 0..ext_count {
 				let length = read_be_u32(&mut data)? as usize;
 				let name = read_vec(&mut data, length)?;
@@ -102,6 +358,240 @@ pub fn parse_inp<R: Read>(mut data: R) -> Result<Model, ParseInpError> {
 	Ok(Model {
 		puppet,
 		textures,
+		#[cfg(feature = "json")]
 		vendors,
 	})
 }
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use std::path::Path;
+
+	use json::JsonValue;
+
+	use crate::formats::fixtures;
+
+	use super::*;
+
+	fn container(payload: &JsonValue, textures: &[(u8, &[u8])]) -> Vec<u8> {
+		let payload = payload.dump();
+		let mut data = MAGIC.to_vec();
+		data.extend((payload.len() as u32).to_be_bytes());
+		data.extend(payload.as_bytes());
+		data.extend(TEX_SECT);
+		data.extend((textures.len() as u32).to_be_bytes());
+		for (encoding, texture) in textures {
+			data.extend((texture.len() as u32).to_be_bytes());
+			data.push(*encoding);
+			data.extend(*texture);
+		}
+		data
+	}
+
+	fn payload() -> JsonValue {
+		fixtures::puppet(fixtures::node(0, "root", vec![fixtures::part(1, "part")]), Vec::new())
+	}
+
+	#[test]
+	fn inx_external_textures() {
+		let embedded = blank_texture();
+		let data = container(
+			&payload(),
+			&[(0, &embedded.data), (EXTERNAL_TEX_ENCODING, b"missing/albedo.png")],
+		);
+
+		let model = parse_inx(data.as_slice(), Path::new("/nonexistent")).unwrap();
+		assert_eq!(model.textures.len(), 2);
+		assert_eq!(model.textures[0].data, embedded.data);
+		assert_eq!(model.textures[1].format, ImageFormat::Png);
+		assert_eq!(model.textures[1].data, blank_texture().data);
+
+		// external references are not part of the puppet format
+		assert!(matches!(
+			parse_inp(data.as_slice()),
+			Err(InpParseError::UnsupportedTextureType(EXTERNAL_TEX_ENCODING))
+		));
+	}
+
+	#[test]
+	fn inx_external_textures_stay_in_project() {
+		for reference in ["/etc/albedo.png", "../albedo.png", "textures/../../albedo.png"] {
+			let data = container(&payload(), &[(EXTERNAL_TEX_ENCODING, reference.as_bytes())]);
+			assert!(matches!(
+				parse_inx(data.as_slice(), Path::new("/nonexistent")),
+				Err(InpParseError::ExternalTextureOutsideProject(r)) if r == reference
+			));
+		}
+		// WebP is embedded in project files too
+		let data = container(&payload(), &[(3, b"")]);
+		let model = parse_inx(data.as_slice(), Path::new("/nonexistent")).unwrap();
+		assert_eq!(model.textures[0].format, ImageFormat::WebP);
+	}
+
+	#[test]
+	fn inx_unsupported_editor_version() {
+		let mut payload = payload();
+		payload["meta"]["version"] = "2.0".into();
+		let data = container(&payload, &[]);
+
+		assert!(matches!(
+			parse_inx(data.as_slice(), Path::new(".")),
+			Err(InpParseError::InoxParse(InoxParseError::UnsupportedEditorVersion(version))) if version == "2.0"
+		));
+		assert!(parse_inp(data.as_slice()).is_ok());
+	}
+
+	#[test]
+	fn inp_round_trip() {
+		let mut part = fixtures::part(2, "part");
+		part["masks"] = json::array![{ source: 3, mode: "DodgeMask" }];
+		part["weldedLinks"] = json::array![{ target: 3, indices: [-1, 0, -1, -1], weight: 0.5 }];
+		part["restDeform"] = json::array![0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+		part["groupId"] = 4.into();
+		let mut composite = fixtures::composite(1, "composite", vec![part, fixtures::part(3, "mask")]);
+		composite["blend_mode"] = "Multiply".into();
+
+		let deform = json::array![[0.0, 1.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let zero = json::array![[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let mut payload = fixtures::puppet(
+			fixtures::node(0, "root", vec![composite, fixtures::simple_physics(5, "physics", 11)]),
+			vec![
+				fixtures::param(10, "deform", vec![fixtures::binding(2, "deform", [zero, deform])]),
+				fixtures::param(
+					11,
+					"move",
+					vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 10.0.into()])],
+				),
+			],
+		);
+		payload["animations"] = json::object! {
+			wave: {
+				timestep: 0.1,
+				length: 10,
+				leadIn: 2,
+				leadOut: -1,
+				lanes: [{ uuid: 11, target: 0, interpolation: "Nearest", keyframes: [{ frame: 5, value: 0.5 }] }],
+			},
+		};
+		let model = Model {
+			puppet: fixtures::load(&payload),
+			textures: vec![blank_texture()],
+			vendors: vec![VendorData {
+				name: "vendor".to_owned(),
+				payload: json::object! { setting: true },
+			}],
+		};
+
+		let written = write_inp(&model).unwrap();
+		let parsed = parse_inp(written.as_slice()).unwrap();
+		assert_eq!(parsed.content_hash(), model.content_hash());
+		assert_eq!(write_inp(&parsed).unwrap(), written);
+	}
+
+	#[cfg(feature = "serde_json")]
+	#[test]
+	fn same_model_from_both_backends() {
+		let mut data = write_inp(&Model {
+			puppet: fixtures::load(&payload()),
+			textures: vec![blank_texture()],
+			vendors: vec![VendorData {
+				name: "vendor".to_owned(),
+				payload: json::object! { setting: true },
+			}],
+		})
+		.unwrap();
+
+		let from_json = parse_inp(data.as_slice()).unwrap();
+		let from_serde_json = parse_inp_serde_json(data.as_slice()).unwrap();
+		assert_eq!(from_json.content_hash(), from_serde_json.content_hash());
+		assert_eq!(from_serde_json.vendors.len(), 1);
+
+		// errors of each parser are reported as such
+		data[12] = b'!';
+		assert!(matches!(parse_inp(data.as_slice()), Err(InpParseError::JsonParse(_))));
+		assert!(matches!(
+			parse_inp_serde_json(data.as_slice()),
+			Err(InpParseError::SerdeJsonParse(_))
+		));
+	}
+
+	#[cfg(feature = "decode-textures")]
+	#[test]
+	fn blank_texture_decodes() {
+		let decoded = crate::texture::try_decode_model_textures([blank_texture()].iter()).unwrap();
+		assert_eq!((decoded[0].width(), decoded[0].height()), (1, 1));
+		assert_eq!(decoded[0].pixels(), [0, 0, 0, 0]);
+	}
+
+	#[cfg(feature = "decode-textures")]
+	#[test]
+	fn webp_texture() {
+		let mut webp = Vec::new();
+		image::RgbaImage::from_pixel(2, 1, image::Rgba([255, 0, 0, 255]))
+			.write_to(&mut io::Cursor::new(&mut webp), ImageFormat::WebP)
+			.unwrap();
+		let data = container(&payload(), &[(3, &webp)]);
+
+		let model = parse_inp(data.as_slice()).unwrap();
+		assert_eq!(model.textures[0].format, ImageFormat::WebP);
+		let decoded = crate::texture::try_decode_model_textures(model.textures.iter()).unwrap();
+		assert_eq!((decoded[0].width(), decoded[0].height()), (2, 1));
+		assert_eq!(&decoded[0].pixels()[..4], [255, 0, 0, 255]);
+		let rewritten = parse_inp(write_inp(&model).unwrap().as_slice()).unwrap();
+		assert_eq!(rewritten.textures[0].format, ImageFormat::WebP);
+		assert_eq!(rewritten.textures[0].data, model.textures[0].data);
+
+		let data = container(&payload(), &[(4, b"")]);
+		assert!(matches!(
+			parse_inp(data.as_slice()),
+			Err(InpParseError::UnsupportedTextureType(4))
+		));
+	}
+
+	#[test]
+	fn truncated_and_corrupt_files() {
+		let data = write_inp(&Model {
+			puppet: fixtures::load(&payload()),
+			textures: vec![blank_texture()],
+			vendors: vec![VendorData {
+				name: "vendor".to_owned(),
+				payload: json::object! { setting: true },
+			}],
+		})
+		.unwrap();
+
+		// all cuts within the sections are errors, cuts between them leave out optional ones
+		for len in 0..data.len() {
+			match parse_inp(&data[..len]) {
+				Ok(model) => assert!(model.vendors.is_empty(), "{len}"),
+				Err(InpParseError::UnexpectedEof | InpParseError::NoTexSect) => (),
+				Err(e) => panic!("{len}: {e}"),
+			}
+		}
+		assert!(matches!(parse_inp(&data[..20]), Err(InpParseError::UnexpectedEof)));
+
+		assert!(matches!(parse_inp(&b"TRNSRTS!"[..]), Err(InpParseError::BadMagic)));
+
+		// lengths and counts far beyond the data
+		let mut huge_payload = MAGIC.to_vec();
+		huge_payload.extend(u32::MAX.to_be_bytes());
+		assert!(matches!(
+			parse_inp(huge_payload.as_slice()),
+			Err(InpParseError::UnexpectedEof)
+		));
+		let mut huge_count = container(&payload(), &[]);
+		let count_at = huge_count.len() - 4;
+		huge_count[count_at..].copy_from_slice(&u32::MAX.to_be_bytes());
+		assert!(matches!(
+			parse_inp(huge_count.as_slice()),
+			Err(InpParseError::UnexpectedEof)
+		));
+
+		let mut malformed = payload();
+		malformed["nodes"]["uuid"] = "zero".into();
+		assert!(matches!(
+			parse_inp(container(&malformed, &[]).as_slice()),
+			Err(InpParseError::PayloadJson(_))
+		));
+	}
+}
@@ -3,20 +3,86 @@
 //! > I might turn this into my own JSON crate at this point... ¬¬
 
 use glam::{Vec2, Vec3};
-use json::JsonValue;
 
-pub(super) trait SerialExtend {
-	fn as_object(&self) -> Option<&json::object::Object>;
+/// A parsed JSON value, as walked by the payload deserializer.
+///
+/// Implemented for the values of each JSON crate puppets can be loaded from.
+pub trait JsonBackend: Sized {
+	fn is_null(&self) -> bool;
+	fn is_number(&self) -> bool;
+	fn is_object(&self) -> bool;
+	fn as_bool(&self) -> Option<bool>;
+	fn as_str(&self) -> Option<&str>;
+	fn as_f64(&self) -> Option<f64>;
+	fn as_f32(&self) -> Option<f32> {
+		self.as_f64().map(|val| val as f32)
+	}
+	/// `None` for negative numbers and numbers with a fractional part.
+	fn as_u64(&self) -> Option<u64>;
+	/// `None` for numbers with a fractional part.
+	fn as_i64(&self) -> Option<i64>;
+	fn as_list(&self) -> Option<&[Self]>;
+	/// Value at `key` if this is an object that has it.
+	fn get(&self, key: &str) -> Option<&Self>;
+	/// Key-value pairs of this object, none if this is not an object.
+	fn entries(&self) -> impl Iterator<Item = (&str, &Self)>;
 }
 
-impl SerialExtend for json::JsonValue {
-	fn as_object(&self) -> Option<&json::object::Object> {
-		if let json::JsonValue::Object(transform) = self {
-			Some(transform)
-		} else {
-			None
+#[cfg(feature = "json")]
+impl JsonBackend for json::JsonValue {
+	fn is_null(&self) -> bool {
+		json::JsonValue::is_null(self)
+	}
+
+	fn is_number(&self) -> bool {
+		json::JsonValue::is_number(self)
+	}
+
+	fn is_object(&self) -> bool {
+		json::JsonValue::is_object(self)
+	}
+
+	fn as_bool(&self) -> Option<bool> {
+		json::JsonValue::as_bool(self)
+	}
+
+	fn as_str(&self) -> Option<&str> {
+		json::JsonValue::as_str(self)
+	}
+
+	fn as_f64(&self) -> Option<f64> {
+		json::JsonValue::as_f64(self)
+	}
+
+	fn as_f32(&self) -> Option<f32> {
+		json::JsonValue::as_f32(self)
+	}
+
+	fn as_u64(&self) -> Option<u64> {
+		json::JsonValue::as_u64(self)
+	}
+
+	fn as_i64(&self) -> Option<i64> {
+		json::JsonValue::as_i64(self)
+	}
+
+	fn as_list(&self) -> Option<&[Self]> {
+		match self {
+			json::JsonValue::Array(arr) => Some(arr),
+			_ => None,
 		}
 	}
+
+	fn get(&self, key: &str) -> Option<&Self> {
+		match self {
+			json::JsonValue::Object(obj) => obj.get(key),
+			_ => None,
+		}
+	}
+
+	fn entries(&self) -> impl Iterator<Item = (&str, &Self)> {
+		json::JsonValue::entries(self)
+	}
 }
 
 pub type JsonResult<T> = Result<T, JsonError>;
@@ -56,33 +122,50 @@ impl JsonError {
 	}
 }
 
-#[derive(Copy, Clone)]
-pub struct JsonObject<'a>(pub &'a json::object::Object);
+/// A JSON object, wrapping a value checked to be one.
+pub struct JsonObject<'a, V>(&'a V);
+
+impl<V> Clone for JsonObject<'_, V> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<V> Copy for JsonObject<'_, V> {}
 
 #[allow(unused)]
-impl<'a> JsonObject<'a> {
-	fn get(self, key: &'a str) -> JsonResult<&json::JsonValue> {
+impl<'a, V: JsonBackend> JsonObject<'a, V> {
+	/// `None` if `value` is not an object.
+	pub fn new(value: &'a V) -> Option<Self> {
+		value.is_object().then_some(Self(value))
+	}
+
+	pub fn as_value(self) -> &'a V {
+		self.0
+	}
+
+	fn get(self, key: &str) -> JsonResult<&'a V> {
 		match self.0.get(key) {
 			Some(value) => Ok(value),
 			None => Err(JsonError::KeyDoesNotExist(key.to_owned())),
 		}
 	}
 
-	pub fn get_object(self, key: &'a str) -> JsonResult<JsonObject> {
-		match self.get(key)?.as_object() {
-			Some(obj) => Ok(JsonObject(obj)),
+	pub fn get_object(self, key: &str) -> JsonResult<JsonObject<'a, V>> {
+		match JsonObject::new(self.get(key)?) {
+			Some(obj) => Ok(obj),
 			None => Err(JsonError::ValueIsNotObject(key.to_owned())),
 		}
 	}
 
-	pub fn get_list(self, key: &'a str) -> JsonResult<&[JsonValue]> {
-		match self.get(key)? {
-			json::JsonValue::Array(arr) => Ok(arr),
-			_ => Err(JsonError::ValueIsNotList(key.to_owned())),
+	pub fn get_list(self, key: &str) -> JsonResult<&'a [V]> {
+		match self.get(key)?.as_list() {
+			Some(arr) => Ok(arr),
+			None => Err(JsonError::ValueIsNotList(key.to_owned())),
 		}
 	}
 
-	pub fn get_nullable_str(self, key: &'a str) -> JsonResult<Option<&str>> {
+	pub fn get_nullable_str(self, key: &str) -> JsonResult<Option<&'a str>> {
 		let val = self.get(key)?;
 		if val.is_null() {
 			return Ok(None);
@@ -93,86 +176,75 @@ impl<'a> JsonObject<'a> {
 		}
 	}
 
-	pub fn get_str(self, key: &'a str) -> JsonResult<&str> {
+	pub fn get_str(self, key: &str) -> JsonResult<&'a str> {
 		match self.get(key)?.as_str() {
 			Some(val) => Ok(val),
 			None => Err(JsonError::ValueIsNotString(key.to_owned())),
 		}
 	}
 
-	fn get_number(self, key: &'a str) -> JsonResult<json::number::Number> {
-		match self.get(key)?.as_number() {
-			Some(val) => Ok(val),
-			None => Err(JsonError::ValueIsNotNumber(key.to_owned())),
+	fn get_number(self, key: &str) -> JsonResult<&'a V> {
+		match self.get(key)? {
+			val if val.is_number() => Ok(val),
+			_ => Err(JsonError::ValueIsNotNumber(key.to_owned())),
 		}
 	}
 
+	fn get_integer<T: TryFrom<u64> + TryFrom<i64>>(self, key: &str) -> JsonResult<T> {
+		let val = self.get_number(key)?;
+		let int = match val.as_u64() {
+			Some(val) => T::try_from(val).ok(),
+			None => val.as_i64().and_then(|val| T::try_from(val).ok()),
+		};
+		int.ok_or_else(|| JsonError::ParseIntError(key.to_owned()))
+	}
+
 	pub fn get_f64(&self, key: &str) -> JsonResult<f64> {
-		Ok(self.get_number(key)?.into())
+		Ok(self.get_number(key)?.as_f64().unwrap_or(f64::NAN))
 	}
 
 	pub fn get_f32(&self, key: &str) -> JsonResult<f32> {
-		Ok(self.get_number(key)?.into())
+		Ok(self.get_number(key)?.as_f32().unwrap_or(f32::NAN))
 	}
 
 	pub fn get_u64(&self, key: &str) -> JsonResult<u64> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_u32(&self, key: &str) -> JsonResult<u32> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_u16(&self, key: &str) -> JsonResult<u16> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_u8(&self, key: &str) -> JsonResult<u8> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_usize(&self, key: &str) -> JsonResult<usize> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_i64(&self, key: &str) -> JsonResult<i64> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_i32(&self, key: &str) -> JsonResult<i32> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_i16(&self, key: &str) -> JsonResult<i16> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_i8(&self, key: &str) -> JsonResult<i8> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_isize(&self, key: &str) -> JsonResult<isize> {
-		self.get_number(key)?
-			.try_into()
-			.map_err(|_| JsonError::ParseIntError(key.to_owned()))
+		self.get_integer(key)
 	}
 
 	pub fn get_bool(&self, key: &str) -> JsonResult<bool> {
@@ -181,7 +253,6 @@ impl<'a> JsonObject<'a> {
 			None => Err(JsonError::ValueIsNotBool(key.to_owned())),
 		}
 	}
-
 	pub fn get_vec2(&self, key: &str) -> JsonResult<Vec2> {
 		let list = self.get_list(key)?;
 		if list.len() != 2 {
@@ -191,8 +262,8 @@ impl<'a> JsonObject<'a> {
 			});
 		}
 
-		let x = match list[0].as_number() {
-			Some(val) => val.into(),
+		let x = match list[0].as_f32() {
+			Some(val) => val,
 			None => {
 				return Err(JsonError::ParseVec2Error {
 					key: key.to_owned(),
@@ -201,8 +272,8 @@ impl<'a> JsonObject<'a> {
 			}
 		};
 
-		let y = match list[1].as_number() {
-			Some(val) => val.into(),
+		let y = match list[1].as_f32() {
+			Some(val) => val,
 			None => {
 				return Err(JsonError::ParseVec2Error {
 					key: key.to_owned(),
@@ -222,8 +293,8 @@ impl<'a> JsonObject<'a> {
 			});
 		}
 
-		let x = match list[0].as_number() {
-			Some(val) => val.into(),
+		let x = match list[0].as_f32() {
+			Some(val) => val,
 			None => {
 				return Err(JsonError::ParseVec3Error {
 					key: key.to_owned(),
@@ -232,8 +303,8 @@ impl<'a> JsonObject<'a> {
 			}
 		};
 
-		let y = match list[1].as_number() {
-			Some(val) => val.into(),
+		let y = match list[1].as_f32() {
+			Some(val) => val,
 			None => {
 				return Err(JsonError::ParseVec3Error {
 					key: key.to_owned(),
@@ -242,8 +313,8 @@ impl<'a> JsonObject<'a> {
 			}
 		};
 
-		let z = match list[2].as_number() {
-			Some(val) => val.into(),
+		let z = match list[2].as_f32() {
+			Some(val) => val,
 			None => {
 				return Err(JsonError::ParseVec3Error {
 					key: key.to_owned(),
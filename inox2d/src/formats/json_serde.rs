@@ -0,0 +1,104 @@
+//! Loading puppets from `serde_json` values, for embedders that already parse JSON with `serde_json`.
+//!
+//! The payload deserializer walks these values directly, so this does not need the `json` feature.
+
+use crate::puppet::Puppet;
+
+use super::json::{JsonBackend, JsonObject};
+use super::payload::{InoxParseResult, ParseMode};
+
+impl JsonBackend for serde_json::Value {
+	fn is_null(&self) -> bool {
+		serde_json::Value::is_null(self)
+	}
+
+	fn is_number(&self) -> bool {
+		serde_json::Value::is_number(self)
+	}
+
+	fn is_object(&self) -> bool {
+		serde_json::Value::is_object(self)
+	}
+
+	fn as_bool(&self) -> Option<bool> {
+		serde_json::Value::as_bool(self)
+	}
+
+	fn as_str(&self) -> Option<&str> {
+		serde_json::Value::as_str(self)
+	}
+
+	fn as_f64(&self) -> Option<f64> {
+		serde_json::Value::as_f64(self)
+	}
+
+	fn as_u64(&self) -> Option<u64> {
+		serde_json::Value::as_u64(self)
+	}
+
+	fn as_i64(&self) -> Option<i64> {
+		serde_json::Value::as_i64(self)
+	}
+
+	fn as_list(&self) -> Option<&[Self]> {
+		self.as_array().map(Vec::as_slice)
+	}
+
+	fn get(&self, key: &str) -> Option<&Self> {
+		serde_json::Value::get(self, key)
+	}
+
+	fn entries(&self) -> impl Iterator<Item = (&str, &Self)> {
+		self.as_object()
+			.into_iter()
+			.flatten()
+			.map(|(key, val)| (key.as_str(), val))
+	}
+}
+
+impl Puppet {
+	/// Same as `Puppet::new_from_json()`, for a payload parsed with `serde_json`.
+	pub fn new_from_serde_json(payload: &serde_json::Value) -> InoxParseResult<Self> {
+		Self::new_from_serde_json_with_mode(payload, ParseMode::default())
+	}
+
+	pub fn new_from_serde_json_with_mode(payload: &serde_json::Value, mode: ParseMode) -> InoxParseResult<Self> {
+		Self::from_payload(
+			payload,
+			mode,
+			None::<&fn(&mut Self, &str, JsonObject<serde_json::Value>) -> InoxParseResult<()>>,
+		)
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use crate::formats::fixtures;
+	use crate::model::Model;
+	use crate::puppet::Puppet;
+
+	fn model(puppet: Puppet) -> Model {
+		Model {
+			puppet,
+			textures: Vec::new(),
+			vendors: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn same_puppet_from_both_backends() {
+		let payload = fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![fixtures::param(
+				10,
+				"move",
+				vec![fixtures::binding(1, "transform.t.x", [0.0.into(), (-10.5).into()])],
+			)],
+		);
+		let serde_payload: serde_json::Value = serde_json::from_str(&payload.dump()).unwrap();
+
+		let from_json = Puppet::new_from_json(&payload).unwrap();
+		let from_serde_json = Puppet::new_from_serde_json(&serde_payload).unwrap();
+		assert_eq!(model(from_json).content_hash(), model(from_serde_json).content_hash());
+	}
+}
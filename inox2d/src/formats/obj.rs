@@ -0,0 +1,94 @@
+use std::fmt::Write;
+use std::iter;
+
+use glam::Vec2;
+
+use crate::node::components::{Mesh, TexturedMesh};
+use crate::node::InoxNodeUuid;
+use crate::puppet::Puppet;
+use crate::render::TexturedMeshRenderCtx;
+
+impl Puppet {
+	/// Export the current deformed mesh of a part as Wavefront OBJ text.
+	///
+	/// Vertices are written in the part's local space with `z = 0`, UVs and coordinates as-is.
+	/// Deforms are only accounted for if rendering is initialized.
+	///
+	/// `None` if the node does not exist or is not a part.
+	pub fn export_part_obj(&self, id: InoxNodeUuid) -> Option<String> {
+		let node = self.nodes.get_node(id)?;
+		self.node_comps.get::<TexturedMesh>(id)?;
+		let mesh = self.node_comps.get::<Mesh>(id)?;
+
+		let deforms: Box<dyn Iterator<Item = &Vec2>> = match (
+			self.render_ctx.as_ref(),
+			self.node_comps.get::<TexturedMeshRenderCtx>(id),
+		) {
+			(Some(render_ctx), Some(mesh_ctx)) => {
+				let vert_offset = mesh_ctx.vert_offset as usize;
+				Box::new(render_ctx.vertex_buffers.deforms[vert_offset..(vert_offset + mesh_ctx.vert_len)].iter())
+			}
+			_ => Box::new(iter::repeat(&Vec2::ZERO)),
+		};
+
+		// writing to a String never fails
+		let mut obj = String::new();
+		writeln!(obj, "o {}", node.name).unwrap();
+		for (vertex, deform) in mesh.vertices.iter().zip(deforms) {
+			let vertex = *vertex + *deform;
+			writeln!(obj, "v {} {} 0", vertex.x, vertex.y).unwrap();
+		}
+		for uv in &mesh.uvs {
+			writeln!(obj, "vt {} {}", uv.x, uv.y).unwrap();
+		}
+		// OBJ indices are 1-based
+		for triangle in mesh.indices.chunks_exact(3) {
+			let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+			writeln!(obj, "f {a}/{a} {b}/{b} {c}/{c}").unwrap();
+		}
+
+		Some(obj)
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::vec2;
+
+	use crate::formats::fixtures;
+	use crate::node::InoxNodeUuid;
+	use crate::render::TexturedMeshRenderCtx;
+
+	#[test]
+	fn export_deformed_part() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+
+		let part = InoxNodeUuid(1);
+		let vert_offset = puppet
+			.node_comps
+			.get::<TexturedMeshRenderCtx>(part)
+			.unwrap()
+			.vert_offset as usize;
+		puppet.render_ctx.as_mut().unwrap().vertex_buffers.deforms[vert_offset] = vec2(0.5, 0.25);
+
+		let obj = puppet.export_part_obj(part).unwrap();
+		let vertices = obj
+			.lines()
+			.filter_map(|line| line.strip_prefix("v "))
+			.map(|v| v.split(' ').map(|f| f.parse::<f32>().unwrap()).collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+
+		assert_eq!(vertices.len(), 4);
+		assert_eq!(vertices[0], [-0.5, -0.75, 0.0]);
+		assert_eq!(vertices[3], [1.0, 1.0, 0.0]);
+		assert_eq!(obj.lines().filter(|line| line.starts_with("vt ")).count(), 4);
+		assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 2);
+
+		assert!(puppet.export_part_obj(InoxNodeUuid(0)).is_none());
+	}
+}
@@ -1,20 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::slice;
 
-use glam::{vec2, vec3, Vec2};
-use json::JsonValue;
+use glam::{vec2, Vec2};
 
+use crate::animation::{Animation, AnimationLane, Keyframe};
 use crate::math::interp::InterpolateMode;
 use crate::math::matrix::{Matrix2d, Matrix2dFromSliceVecsError};
 use crate::math::transform::TransformOffset;
 use crate::node::components::*;
-use crate::node::{InoxNode, InoxNodeUuid};
-use crate::params::{AxisPoints, Binding, BindingValues, Param, ParamUuid};
+use crate::node::{InoxNode, InoxNodeUuid, NodeGroup};
+use crate::params::{Axis, AxisPoints, Binding, BindingValues, Param, ParamUuid};
 use crate::physics::PuppetPhysics;
 use crate::puppet::{meta::*, Puppet};
 use crate::texture::TextureId;
 
-use super::f32s_as_vec2s;
-use super::json::{JsonError, JsonObject, SerialExtend};
+use super::json::{JsonBackend, JsonError, JsonObject};
 
 pub type InoxParseResult<T> = Result<T, InoxParseError>;
 
@@ -46,6 +46,22 @@ pub enum InoxParseError {
 	OddNumberOfFloatsInList(usize),
 	#[error("Expected 2 floats in list, got {0}")]
 	Not2FloatsInList(usize),
+	#[error("Non-finite float in {0}")]
+	NonFiniteFloat(String),
+	#[error("Project made with unsupported editor version {0:?}")]
+	UnsupportedEditorVersion(String),
+	#[error("Rest deform has {got} vertices, its mesh has {expected}")]
+	RestDeformVertexCount { expected: usize, got: usize },
+}
+
+/// How to deal with recoverable problems in a payload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+	/// Patch problems up with a warning.
+	#[default]
+	Lenient,
+	/// Fail on any problem.
+	Strict,
 }
 
 // json structure helpers
@@ -63,16 +79,16 @@ fn vals<T>(key: &str, res: InoxParseResult<T>) -> InoxParseResult<T> {
 	res.map_err(|e| e.nested(key))
 }
 
-fn as_nested_list(index: usize, val: &json::JsonValue) -> InoxParseResult<&[json::JsonValue]> {
-	match val {
-		json::JsonValue::Array(arr) => Ok(arr),
-		_ => Err(InoxParseError::JsonError(JsonError::ValueIsNotList(index.to_string()))),
+fn as_nested_list<V: JsonBackend>(index: usize, val: &V) -> InoxParseResult<&[V]> {
+	match val.as_list() {
+		Some(arr) => Ok(arr),
+		None => Err(InoxParseError::JsonError(JsonError::ValueIsNotList(index.to_string()))),
 	}
 }
 
-fn as_object<'file>(msg: &str, val: &'file JsonValue) -> InoxParseResult<JsonObject<'file>> {
-	if let Some(obj) = val.as_object() {
-		Ok(JsonObject(obj))
+fn as_object<'file, V: JsonBackend>(msg: &str, val: &'file V) -> InoxParseResult<JsonObject<'file, V>> {
+	if let Some(obj) = JsonObject::new(val) {
+		Ok(obj)
 	} else {
 		Err(InoxParseError::JsonError(JsonError::ValueIsNotObject(msg.to_owned())))
 	}
@@ -80,22 +96,54 @@ fn as_object<'file>(msg: &str, val: &'file JsonValue) -> InoxParseResult<JsonObj
 
 // node deserialization
 
-struct ParsedNode<'file> {
+struct ParsedNode<'file, V> {
 	node: InoxNode,
 	ty: &'file str,
-	data: JsonObject<'file>,
-	children: &'file [JsonValue],
+	data: JsonObject<'file, V>,
+	children: &'file [V],
+}
+
+/// Highest uuid not taken by any node in the tree of node JSON `root`.
+fn free_node_uuid<V: JsonBackend>(root: &V) -> InoxNodeUuid {
+	fn collect<V: JsonBackend>(node: &V, uuids: &mut HashSet<u32>) {
+		if let Some(uuid) = node
+			.get("uuid")
+			.and_then(V::as_u64)
+			.and_then(|uuid| u32::try_from(uuid).ok())
+		{
+			uuids.insert(uuid);
+		}
+		let children = node.get("children").and_then(V::as_list).unwrap_or(&[]);
+		children.iter().for_each(|child| collect(child, uuids));
+	}
+
+	let mut uuids = HashSet::new();
+	collect(root, &mut uuids);
+	InoxNodeUuid((0..=u32::MAX).rev().find(|uuid| !uuids.contains(uuid)).unwrap())
 }
 
-fn deserialize_node(obj: JsonObject) -> InoxParseResult<ParsedNode> {
+fn deserialize_node<V: JsonBackend>(obj: JsonObject<'_, V>, mode: ParseMode) -> InoxParseResult<ParsedNode<'_, V>> {
+	let uuid = InoxNodeUuid(obj.get_u32("uuid")?);
+	let trans_offset = match obj.get_object("transform") {
+		Err(JsonError::KeyDoesNotExist(_)) if mode == ParseMode::Lenient => {
+			tracing::warn!("Node {} has no transform, using identity.", uuid.0);
+			TransformOffset::default()
+		}
+		transform => vals("transform", deserialize_transform(transform?))?,
+	};
+
 	Ok(ParsedNode {
 		node: InoxNode {
-			uuid: InoxNodeUuid(obj.get_u32("uuid")?),
+			uuid,
 			name: obj.get_str("name")?.to_owned(),
 			enabled: obj.get_bool("enabled")?,
 			zsort: obj.get_f32("zsort")?,
-			trans_offset: vals("transform", deserialize_transform(obj.get_object("transform")?))?,
+			trans_offset,
 			lock_to_root: obj.get_bool("lockToRoot")?,
+			group: obj.get_u32("groupId").ok().map(|id| NodeGroup {
+				id,
+				color: obj.get_vec3("groupColor").ok(),
+			}),
 		},
 		ty: obj.get_str("type")?,
 		data: obj,
@@ -105,35 +153,35 @@ fn deserialize_node(obj: JsonObject) -> InoxParseResult<ParsedNode> {
 
 // components deserialization
 
-fn deserialize_textured_mesh(obj: JsonObject) -> InoxParseResult<TexturedMesh> {
+/// Texture index at `index` of a `textures` list, `None` if there is no number there.
+fn texture_index<V: JsonBackend>(textures: &[V], index: usize) -> InoxParseResult<Option<usize>> {
+	match textures.get(index).filter(|val| val.is_number()) {
+		Some(val) => match val.as_u64().and_then(|val| usize::try_from(val).ok()) {
+			Some(val) => Ok(Some(val)),
+			None => Err(InoxParseError::JsonError(
+				JsonError::ParseIntError(index.to_string()).nested("textures"),
+			)),
+		},
+		None => Ok(None),
+	}
+}
+
+fn deserialize_textured_mesh<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<TexturedMesh> {
 	let (tex_albedo, tex_emissive, tex_bumpmap) = {
 		let textures = obj.get_list("textures")?;
 
-		let tex_albedo = match textures.first().ok_or(InoxParseError::NoAlbedoTexture)?.as_number() {
-			Some(val) => val
-				.try_into()
-				.map(TextureId)
-				.map_err(|_| InoxParseError::JsonError(JsonError::ParseIntError("0".to_owned()).nested("textures")))?,
-			None => return Err(InoxParseError::NoAlbedoTexture),
-		};
-
-		let tex_emissive = match textures.get(1).and_then(JsonValue::as_number) {
-			Some(val) => (val.try_into())
-				// Map u32::MAX to nothing
-				.map(|val| if val == u32::MAX as usize { 0 } else { val })
-				.map(TextureId)
-				.map_err(|_| InoxParseError::JsonError(JsonError::ParseIntError("1".to_owned()).nested("textures")))?,
-			None => TextureId(0),
-		};
-
-		let tex_bumpmap = match textures.get(2).and_then(JsonValue::as_number) {
-			Some(val) => (val.try_into())
-				// Map u32::MAX to nothing
-				.map(|val| if val == u32::MAX as usize { 0 } else { val })
-				.map(TextureId)
-				.map_err(|_| InoxParseError::JsonError(JsonError::ParseIntError("2".to_owned()).nested("textures")))?,
-			None => TextureId(0),
+		let tex_albedo = texture_index(textures, 0)?
+			.map(TextureId)
+			.ok_or(InoxParseError::NoAlbedoTexture)?;
+		// Map u32::MAX to nothing
+		let optional = |index| {
+			texture_index(textures, index).map(|val| match val {
+				Some(val) if val != u32::MAX as usize => TextureId(val),
+				_ => TextureId(0),
+			})
 		};
+		let tex_emissive = optional(1)?;
+		let tex_bumpmap = optional(2)?;
 
 		(tex_albedo, tex_emissive, tex_bumpmap)
 	};
@@ -142,10 +190,17 @@ fn deserialize_textured_mesh(obj: JsonObject) -> InoxParseResult<TexturedMesh> {
 		tex_albedo,
 		tex_emissive,
 		tex_bumpmap,
+		emission_strength: obj.get_f32("emissionStrength").unwrap_or(1.0),
 	})
 }
 
-fn deserialize_simple_physics(obj: JsonObject) -> InoxParseResult<SimplePhysics> {
+fn deserialize_composite<V: JsonBackend>(obj: JsonObject<V>) -> Composite {
+	Composite {
+		propagate_meshgroup: obj.get_bool("propagate_meshgroup").unwrap_or_default(),
+	}
+}
+
+fn deserialize_simple_physics<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<SimplePhysics> {
 	Ok(SimplePhysics {
 		param: ParamUuid(obj.get_u32("param")?),
 
@@ -174,7 +229,8 @@ fn deserialize_simple_physics(obj: JsonObject) -> InoxParseResult<SimplePhysics>
 	})
 }
 
-fn deserialize_drawable(obj: JsonObject) -> InoxParseResult<Drawable> {
+fn deserialize_drawable<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<Drawable> {
+	let defaults = Blending::default();
 	Ok(Drawable {
 		blending: Blending {
 			mode: match obj.get_str("blend_mode")? {
@@ -185,11 +241,15 @@ fn deserialize_drawable(obj: JsonObject) -> InoxParseResult<Drawable> {
 				"Screen" => BlendMode::Screen,
 				"ClipToLower" => BlendMode::ClipToLower,
 				"SliceFromLower" => BlendMode::SliceFromLower,
+				"Overlay" => BlendMode::Overlay,
+				"Darken" => BlendMode::Darken,
+				"Lighten" => BlendMode::Lighten,
+				"HardLight" => BlendMode::HardLight,
 				_ => BlendMode::default(),
 			},
-			tint: obj.get_vec3("tint").unwrap_or(vec3(1.0, 1.0, 1.0)),
-			screen_tint: obj.get_vec3("screenTint").unwrap_or(vec3(0.0, 0.0, 0.0)),
-			opacity: obj.get_f32("opacity").unwrap_or(1.0),
+			tint: obj.get_vec3("tint").unwrap_or(defaults.tint),
+			screen_tint: obj.get_vec3("screenTint").unwrap_or(defaults.screen_tint),
+			opacity: obj.get_f32("opacity").unwrap_or(defaults.opacity),
 		},
 		masks: {
 			if let Ok(masks) = obj.get_list("masks") {
@@ -211,20 +271,39 @@ fn deserialize_drawable(obj: JsonObject) -> InoxParseResult<Drawable> {
 	})
 }
 
-fn deserialize_mesh(obj: JsonObject) -> InoxParseResult<Mesh> {
+fn deserialize_mesh<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<Mesh> {
 	Ok(Mesh {
 		vertices: deserialize_vec2s_flat(obj.get_list("verts")?)?,
 		uvs: deserialize_vec2s_flat(obj.get_list("uvs")?)?,
 		indices: obj
 			.get_list("indices")?
 			.iter()
-			.map_while(JsonValue::as_u16)
+			.map_while(|index| index.as_u64().and_then(|index| u16::try_from(index).ok()))
 			.collect::<Vec<_>>(),
 		origin: obj.get_vec2("origin").unwrap_or_default(),
 	})
 }
 
-fn deserialize_mask(obj: JsonObject) -> InoxParseResult<Mask> {
+fn deserialize_vertex_welds<V: JsonBackend>(vals: &[V]) -> InoxParseResult<VertexWelds> {
+	let mut links = Vec::with_capacity(vals.len());
+	for (i, link) in vals.iter().enumerate() {
+		let link = as_object("link", link).map_err(|e| e.nested(&i.to_string()))?;
+		links.push(WeldedLink {
+			target: InoxNodeUuid(link.get_u32("target")?),
+			// unwelded vertices are marked with -1
+			indices: link
+				.get_list("indices")?
+				.iter()
+				.map(|index| index.as_i64().and_then(|index| u16::try_from(index).ok()))
+				.collect(),
+			weight: link.get_f32("weight")?,
+		});
+	}
+
+	Ok(VertexWelds { links })
+}
+
+fn deserialize_mask<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<Mask> {
 	Ok(Mask {
 		source: InoxNodeUuid(obj.get_u32("source")?),
 		mode: match obj.get_str("mode")? {
@@ -235,7 +314,7 @@ fn deserialize_mask(obj: JsonObject) -> InoxParseResult<Mask> {
 	})
 }
 
-fn deserialize_transform(obj: JsonObject) -> InoxParseResult<TransformOffset> {
+fn deserialize_transform<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<TransformOffset> {
 	Ok(TransformOffset {
 		translation: obj.get_vec3("trans")?,
 		rotation: obj.get_vec3("rot")?,
@@ -244,12 +323,18 @@ fn deserialize_transform(obj: JsonObject) -> InoxParseResult<TransformOffset> {
 	})
 }
 
-fn deserialize_f32s(val: &[json::JsonValue]) -> Vec<f32> {
-	val.iter().filter_map(JsonValue::as_f32).collect::<Vec<_>>()
+fn deserialize_f32s<V: JsonBackend>(val: &[V]) -> Vec<f32> {
+	val.iter().filter_map(V::as_f32).collect::<Vec<_>>()
+}
+
+#[inline]
+fn f32s_as_vec2s(vec: &[f32]) -> &'_ [Vec2] {
+	// SAFETY: the length of the slice never trespasses outside of the array
+	unsafe { slice::from_raw_parts(vec.as_ptr() as *const Vec2, vec.len() / 2) }
 }
 
-fn deserialize_vec2s_flat(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Vec2>> {
-	if vals.len() % 2 != 0 {
+fn deserialize_vec2s_flat<V: JsonBackend>(vals: &[V]) -> InoxParseResult<Vec<Vec2>> {
+	if !vals.len().is_multiple_of(2) {
 		return Err(InoxParseError::OddNumberOfFloatsInList(vals.len()));
 	}
 
@@ -260,7 +345,7 @@ fn deserialize_vec2s_flat(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Vec2>
 	Ok(vertices)
 }
 
-fn deserialize_vec2(vals: &[json::JsonValue]) -> InoxParseResult<Vec2> {
+fn deserialize_vec2<V: JsonBackend>(vals: &[V]) -> InoxParseResult<Vec2> {
 	if vals.len() != 2 {
 		return Err(InoxParseError::Not2FloatsInList(vals.len()));
 	}
@@ -270,7 +355,7 @@ fn deserialize_vec2(vals: &[json::JsonValue]) -> InoxParseResult<Vec2> {
 	Ok(vec2(x, y))
 }
 
-fn deserialize_vec2s(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Vec2>> {
+fn deserialize_vec2s<V: JsonBackend>(vals: &[V]) -> InoxParseResult<Vec<Vec2>> {
 	let mut vec2s = Vec::with_capacity(vals.len());
 	for (i, vals) in vals.iter().enumerate() {
 		vec2s.push(deserialize_vec2(as_nested_list(i, vals)?)?);
@@ -280,22 +365,64 @@ fn deserialize_vec2s(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Vec2>> {
 
 // Puppet deserialization
 
+#[cfg(feature = "json")]
 impl Puppet {
 	pub fn new_from_json(payload: &json::JsonValue) -> InoxParseResult<Self> {
-		Self::new_from_json_with_custom(payload, None::<&fn(&mut Self, &str, JsonObject) -> InoxParseResult<()>>)
+		Self::new_from_json_with_mode(payload, ParseMode::default())
+	}
+
+	pub fn new_from_json_with_mode(payload: &json::JsonValue, mode: ParseMode) -> InoxParseResult<Self> {
+		Self::new_from_json_with_custom_and_mode(
+			payload,
+			mode,
+			None::<&fn(&mut Self, &str, JsonObject<json::JsonValue>) -> InoxParseResult<()>>,
+		)
 	}
 
 	pub fn new_from_json_with_custom(
 		payload: &json::JsonValue,
-		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject) -> InoxParseResult<()>>,
+		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject<json::JsonValue>) -> InoxParseResult<()>>,
+	) -> InoxParseResult<Self> {
+		Self::new_from_json_with_custom_and_mode(payload, ParseMode::default(), load_node_data_custom)
+	}
+
+	pub fn new_from_json_with_custom_and_mode(
+		payload: &json::JsonValue,
+		mode: ParseMode,
+		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject<json::JsonValue>) -> InoxParseResult<()>>,
+	) -> InoxParseResult<Self> {
+		Self::from_payload(payload, mode, load_node_data_custom)
+	}
+}
+
+impl Puppet {
+	/// Puppet of a payload parsed by any JSON backend.
+	pub(crate) fn from_payload<V: JsonBackend>(
+		payload: &V,
+		mode: ParseMode,
+		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject<V>) -> InoxParseResult<()>>,
 	) -> InoxParseResult<Self> {
 		let obj = as_object("(puppet)", payload)?;
 
 		let meta = vals("meta", deserialize_puppet_meta(obj.get_object("meta")?))?;
-		let physics = vals("physics", deserialize_puppet_physics(obj.get_object("physics")?))?;
+		if let Some(warning) = meta.spec_version_warning() {
+			tracing::warn!("{warning}");
+		}
+		let physics = match obj.get_object("physics") {
+			// physics-free puppets have no use for the section
+			Err(JsonError::KeyDoesNotExist(_)) => PuppetPhysics::default(),
+			physics => match vals("physics", deserialize_puppet_physics(physics?)) {
+				Err(e) if mode == ParseMode::Lenient => {
+					tracing::warn!("Invalid puppet physics, using defaults: {e}");
+					PuppetPhysics::default()
+				}
+				physics => physics?,
+			},
+		};
 		let parameters = deserialize_params(obj.get_list("param")?)?;
 
-		let root = vals("nodes", deserialize_node(obj.get_object("nodes")?))?;
+		let nodes = obj.get_object("nodes")?;
+		let root = vals("nodes", deserialize_node(nodes, mode))?;
 		let ParsedNode {
 			node,
 			ty,
@@ -304,32 +431,92 @@ impl Puppet {
 		} = root;
 		let root_id = node.uuid;
 
-		let mut puppet = Self::new(meta, physics, node, parameters);
+		let mut puppet = if ty == "Node" {
+			Self::new(meta, physics, node, parameters)
+		} else {
+			// transforms and rendering expect a plain root, so put any other kind of root under a synthetic one
+			let synthetic_root = InoxNode {
+				uuid: free_node_uuid(nodes.as_value()),
+				name: "Root".to_owned(),
+				enabled: true,
+				zsort: 0.0,
+				trans_offset: TransformOffset::default(),
+				lock_to_root: false,
+				group: None,
+			};
+			tracing::warn!(
+				"Root node {} is a {ty}, putting it under a synthetic root Node {}.",
+				root_id.0,
+				synthetic_root.uuid.0
+			);
+			let synthetic_root_id = synthetic_root.uuid;
+			let mut puppet = Self::new(meta, physics, synthetic_root, parameters);
+			puppet.nodes.add(synthetic_root_id, root_id, node);
+			puppet
+		};
+
+		if let Some(animations) = payload.get("animations") {
+			puppet.animations = deserialize_animations(animations);
+		}
 
-		puppet.load_node_data(root_id, ty, data, load_node_data_custom)?;
-		puppet.load_children_rec(root_id, children, load_node_data_custom)?;
+		puppet.load_node_data(root_id, ty, data, mode, load_node_data_custom)?;
+		puppet.load_children_rec(root_id, children, mode, load_node_data_custom)?;
+
+		puppet.sanitize_floats(mode)?;
 
 		Ok(puppet)
 	}
 
-	fn load_node_data(
+	fn load_node_data<V: JsonBackend>(
 		&mut self,
 		id: InoxNodeUuid,
 		ty: &str,
-		data: JsonObject,
-		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject) -> InoxParseResult<()>>,
+		data: JsonObject<V>,
+		mode: ParseMode,
+		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject<V>) -> InoxParseResult<()>>,
 	) -> InoxParseResult<()> {
 		match ty {
 			"Node" => (),
 			"Part" => {
+				let mesh = match data.get_object("mesh") {
+					Err(JsonError::KeyDoesNotExist(_)) if mode == ParseMode::Lenient => {
+						tracing::warn!("Part {} has no mesh, loading it as a plain Node.", id.0);
+						return Ok(());
+					}
+					mesh => vals("mesh", deserialize_mesh(mesh?))?,
+				};
+				let vertex_count = mesh.vertices.len();
 				self.node_comps.add(id, deserialize_drawable(data)?);
 				self.node_comps.add(id, deserialize_textured_mesh(data)?);
-				self.node_comps
-					.add(id, vals("mesh", deserialize_mesh(data.get_object("mesh")?))?)
+				self.node_comps.add(id, mesh);
+				if let Ok(links) = data.get_list("weldedLinks") {
+					self.node_comps
+						.add(id, vals("weldedLinks", deserialize_vertex_welds(links))?);
+				}
+				if let Ok(deforms) = data.get_list("restDeform") {
+					let deforms = vals("restDeform", deserialize_vec2s_flat(deforms))?;
+					if deforms.len() == vertex_count {
+						self.node_comps.add(id, RestDeform { deforms });
+					} else {
+						let err = InoxParseError::RestDeformVertexCount {
+							expected: vertex_count,
+							got: deforms.len(),
+						};
+						if mode == ParseMode::Strict {
+							return Err(err);
+						}
+						tracing::warn!("Dropping rest deform of Part {}: {err}", id.0);
+					}
+				}
+			}
+			"Mask" => {
+				let mesh = vals("mesh", deserialize_mesh(data.get_object("mesh")?))?;
+				self.node_comps.add(id, deserialize_drawable(data)?);
+				self.node_comps.add(id, mesh);
 			}
 			"Composite" => {
 				self.node_comps.add(id, deserialize_drawable(data)?);
-				self.node_comps.add(id, Composite {});
+				self.node_comps.add(id, deserialize_composite(data));
 			}
 			"SimplePhysics" => {
 				self.node_comps.add(id, deserialize_simple_physics(data)?);
@@ -344,17 +531,18 @@ impl Puppet {
 		Ok(())
 	}
 
-	fn load_children_rec(
+	fn load_children_rec<V: JsonBackend>(
 		&mut self,
 		id: InoxNodeUuid,
-		children: &[JsonValue],
-		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject) -> InoxParseResult<()>>,
+		children: &[V],
+		mode: ParseMode,
+		load_node_data_custom: Option<&impl Fn(&mut Self, &str, JsonObject<V>) -> InoxParseResult<()>>,
 	) -> InoxParseResult<()> {
 		for (i, child) in children.iter().enumerate() {
 			let msg = &format!("children[{}]", i);
 
 			let child = as_object("child", child).map_err(|e| e.nested(msg))?;
-			let child_node = deserialize_node(child).map_err(|e| e.nested(msg))?;
+			let child_node = deserialize_node(child, mode).map_err(|e| e.nested(msg))?;
 			let ParsedNode {
 				node,
 				ty,
@@ -364,10 +552,10 @@ impl Puppet {
 			let child_id = node.uuid;
 
 			self.nodes.add(id, child_id, node);
-			self.load_node_data(child_id, ty, data, load_node_data_custom)
+			self.load_node_data(child_id, ty, data, mode, load_node_data_custom)
 				.map_err(|e| e.nested(msg))?;
 			if !children.is_empty() {
-				self.load_children_rec(child_id, children, load_node_data_custom)
+				self.load_children_rec(child_id, children, mode, load_node_data_custom)
 					.map_err(|e| e.nested(msg))?;
 			}
 		}
@@ -376,18 +564,18 @@ impl Puppet {
 	}
 }
 
-fn deserialize_params(vals: &[json::JsonValue]) -> InoxParseResult<HashMap<String, Param>> {
+fn deserialize_params<V: JsonBackend>(vals: &[V]) -> InoxParseResult<HashMap<String, Param>> {
 	let mut params = HashMap::new();
 
-	for param in vals {
-		let pair = deserialize_param(as_object("param", param)?)?;
+	for (order, param) in vals.iter().enumerate() {
+		let pair = deserialize_param(as_object("param", param)?, order)?;
 		params.insert(pair.0, pair.1);
 	}
 
 	Ok(params)
 }
 
-fn deserialize_param(obj: JsonObject) -> InoxParseResult<(String, Param)> {
+fn deserialize_param<V: JsonBackend>(obj: JsonObject<V>, order: usize) -> InoxParseResult<(String, Param)> {
 	let name = obj.get_str("name")?.to_owned();
 	Ok((
 		name.clone(),
@@ -400,11 +588,12 @@ fn deserialize_param(obj: JsonObject) -> InoxParseResult<(String, Param)> {
 			defaults: obj.get_vec2("defaults")?,
 			axis_points: deserialize_axis_points(obj.get_list("axis_points")?)?,
 			bindings: deserialize_bindings(obj.get_list("bindings")?)?,
+			order,
 		},
 	))
 }
 
-fn deserialize_bindings(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Binding>> {
+fn deserialize_bindings<V: JsonBackend>(vals: &[V]) -> InoxParseResult<Vec<Binding>> {
 	let mut bindings = Vec::new();
 	for val in vals {
 		let Ok(binding_object) = as_object("binding", val) else {
@@ -421,26 +610,31 @@ fn deserialize_bindings(vals: &[json::JsonValue]) -> InoxParseResult<Vec<Binding
 	Ok(bindings)
 }
 
-fn deserialize_binding(obj: JsonObject) -> InoxParseResult<Binding> {
+fn deserialize_binding<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<Binding> {
 	let is_set = obj
 		.get_list("isSet")?
 		.iter()
-		.map(|bools| bools.members().map_while(JsonValue::as_bool).collect())
+		.map(|bools| bools.as_list().unwrap_or(&[]).iter().map_while(V::as_bool).collect())
 		.collect::<Vec<Vec<_>>>();
 
 	Ok(Binding {
 		node: InoxNodeUuid(obj.get_u32("node")?),
 		is_set: Matrix2d::from_slice_vecs(&is_set, true)?,
-		interpolate_mode: match obj.get_str("interpolate_mode")? {
-			"Linear" => InterpolateMode::Linear,
-			"Nearest" => InterpolateMode::Nearest,
-			a => return Err(InoxParseError::UnknownInterpolateMode(a.to_owned())),
-		},
+		interpolate_mode: deserialize_interpolate_mode(obj.get_str("interpolate_mode")?)?,
 		values: deserialize_binding_values(obj.get_str("param_name")?, obj.get_list("values")?)?,
 	})
 }
 
-fn deserialize_binding_values(param_name: &str, values: &[JsonValue]) -> InoxParseResult<BindingValues> {
+fn deserialize_interpolate_mode(mode: &str) -> InoxParseResult<InterpolateMode> {
+	match mode {
+		"Linear" => Ok(InterpolateMode::Linear),
+		"Nearest" => Ok(InterpolateMode::Nearest),
+		"Cubic" => Ok(InterpolateMode::Cubic),
+		unknown => Err(InoxParseError::UnknownInterpolateMode(unknown.to_owned())),
+	}
+}
+
+fn deserialize_binding_values<V: JsonBackend>(param_name: &str, values: &[V]) -> InoxParseResult<BindingValues> {
 	Ok(match param_name {
 		"zSort" => BindingValues::ZSort(deserialize_inner_binding_values(values)?),
 		"transform.t.x" => BindingValues::TransformTX(deserialize_inner_binding_values(values)?),
@@ -463,13 +657,12 @@ fn deserialize_binding_values(param_name: &str, values: &[JsonValue]) -> InoxPar
 
 			BindingValues::Deform(Matrix2d::from_slice_vecs(&parsed, true)?)
 		}
-		// TODO
-		"opacity" => BindingValues::Opacity,
+		"opacity" => BindingValues::Opacity(deserialize_inner_binding_values(values)?),
 		param_name => return Err(InoxParseError::UnknownParamName(param_name.to_owned())),
 	})
 }
 
-fn deserialize_inner_binding_values(values: &[JsonValue]) -> Result<Matrix2d<f32>, Matrix2dFromSliceVecsError> {
+fn deserialize_inner_binding_values<V: JsonBackend>(values: &[V]) -> Result<Matrix2d<f32>, Matrix2dFromSliceVecsError> {
 	let values = values
 		.iter()
 		.enumerate()
@@ -479,20 +672,93 @@ fn deserialize_inner_binding_values(values: &[JsonValue]) -> Result<Matrix2d<f32
 	Matrix2d::from_slice_vecs(&values, true)
 }
 
-fn deserialize_axis_points(vals: &[json::JsonValue]) -> InoxParseResult<AxisPoints> {
+fn deserialize_axis_points<V: JsonBackend>(vals: &[V]) -> InoxParseResult<AxisPoints> {
 	let x = deserialize_f32s(as_nested_list(0, &vals[0])?);
 	let y = deserialize_f32s(as_nested_list(1, &vals[1])?);
 	Ok(AxisPoints { x, y })
 }
 
-fn deserialize_puppet_physics(obj: JsonObject) -> InoxParseResult<PuppetPhysics> {
+/// Animations that cannot be parsed are skipped with a warning.
+fn deserialize_animations<V: JsonBackend>(val: &V) -> HashMap<String, Animation> {
+	let mut animations = HashMap::new();
+	if val.is_null() {
+		return animations;
+	}
+	if !val.is_object() {
+		tracing::warn!("Animations are not a JSON object, ignoring them.");
+		return animations;
+	}
+
+	for (name, animation) in val.entries() {
+		match as_object(name, animation).and_then(|obj| vals(name, deserialize_animation(obj))) {
+			Ok(animation) => {
+				animations.insert(name.to_owned(), animation);
+			}
+			Err(e) => tracing::warn!("Invalid animation {name:?}, skipping it: {e}"),
+		}
+	}
+
+	animations
+}
+
+fn deserialize_animation<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<Animation> {
+	// negative lead frames mean there are none
+	let lead = |key: &str| obj.get_i64(key).ok().and_then(|frame| u32::try_from(frame).ok());
+
+	let lanes = obj.get_list("lanes")?;
+	let mut parsed_lanes = Vec::with_capacity(lanes.len());
+	for (i, lane) in lanes.iter().enumerate() {
+		let lane = as_object("lane", lane).and_then(deserialize_animation_lane);
+		parsed_lanes.push(vals("lanes", lane.map_err(|e| e.nested(&i.to_string())))?);
+	}
+
+	Ok(Animation {
+		timestep: obj.get_f32("timestep")?,
+		additive: obj.get_bool("additive").unwrap_or_default(),
+		weight: obj.get_f32("animationWeight").unwrap_or(1.0),
+		length: obj.get_u32("length")?,
+		lead_in: lead("leadIn"),
+		lead_out: lead("leadOut"),
+		lanes: parsed_lanes,
+	})
+}
+
+fn deserialize_animation_lane<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<AnimationLane> {
+	let mut keyframes = Vec::new();
+	for keyframe in obj.get_list("keyframes")? {
+		let keyframe = as_object("keyframe", keyframe)?;
+		keyframes.push(Keyframe {
+			frame: keyframe.get_u32("frame")?,
+			value: keyframe.get_f32("value")?,
+			tension: keyframe.get_f32("tension").unwrap_or(0.5),
+		});
+	}
+	keyframes.sort_by_key(|keyframe| keyframe.frame);
+
+	Ok(AnimationLane {
+		param: ParamUuid(obj.get_u32("uuid")?),
+		axis: if obj.get_u32("target")? == 0 { Axis::X } else { Axis::Y },
+		interpolation: deserialize_interpolate_mode(obj.get_str("interpolation")?)?,
+		keyframes,
+	})
+}
+
+/// Absent values are the defaults of `PuppetPhysics`.
+fn deserialize_puppet_physics<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<PuppetPhysics> {
+	let defaults = PuppetPhysics::default();
+	let or_default = |key: &str, default: f32| match obj.get_f32(key) {
+		Err(JsonError::KeyDoesNotExist(_)) => Ok(default),
+		value => value,
+	};
+
 	Ok(PuppetPhysics {
-		pixels_per_meter: obj.get_f32("pixelsPerMeter")?,
-		gravity: obj.get_f32("gravity")?,
+		pixels_per_meter: or_default("pixelsPerMeter", defaults.pixels_per_meter)?,
+		gravity: or_default("gravity", defaults.gravity)?,
+		..defaults
 	})
 }
 
-fn deserialize_puppet_meta(obj: JsonObject) -> InoxParseResult<PuppetMeta> {
+fn deserialize_puppet_meta<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<PuppetMeta> {
 	Ok(PuppetMeta {
 		name: obj.get_nullable_str("name")?.map(str::to_owned),
 		version: obj.get_str("version")?.to_owned(),
@@ -511,7 +777,7 @@ fn deserialize_puppet_meta(obj: JsonObject) -> InoxParseResult<PuppetMeta> {
 	})
 }
 
-fn deserialize_puppet_usage_rights(obj: JsonObject) -> InoxParseResult<PuppetUsageRights> {
+fn deserialize_puppet_usage_rights<V: JsonBackend>(obj: JsonObject<V>) -> InoxParseResult<PuppetUsageRights> {
 	Ok(PuppetUsageRights {
 		allowed_users: match obj.get_str("allowed_users")? {
 			"OnlyAuthor" => PuppetAllowedUsers::OnlyAuthor,
@@ -537,3 +803,238 @@ fn deserialize_puppet_usage_rights(obj: JsonObject) -> InoxParseResult<PuppetUsa
 		require_attribution: obj.get_bool("require_attribution")?,
 	})
 }
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use std::cell::RefCell;
+
+	use glam::{vec3, Vec3};
+
+	use super::{InoxParseError, InoxParseResult, ParseMode};
+	use crate::formats::fixtures;
+	use crate::formats::json::JsonObject;
+	use crate::math::transform::TransformOffset;
+	use crate::node::{
+		components::{Blending, Composite, Drawable, Mesh, RestDeform, TexturedMesh},
+		InoxNodeUuid, NodeGroup,
+	};
+	use crate::params::{Axis, ParamUuid};
+	use crate::physics::PuppetPhysics;
+	use crate::puppet::Puppet;
+
+	#[test]
+	fn composite_propagate_meshgroup() {
+		let mut propagating = fixtures::composite(1, "propagating", vec![fixtures::part(2, "child")]);
+		propagating["propagate_meshgroup"] = true.into();
+		let plain = fixtures::composite(3, "plain", Vec::new());
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![propagating, plain]),
+			Vec::new(),
+		));
+
+		let propagate = |id| {
+			puppet
+				.node_comps
+				.get::<Composite>(InoxNodeUuid(id))
+				.unwrap()
+				.propagate_meshgroup
+		};
+		assert!(propagate(1));
+		assert!(!propagate(3));
+	}
+
+	#[test]
+	fn emission_strength() {
+		let mut glowing = fixtures::part(1, "glowing");
+		glowing["emissionStrength"] = 2.5.into();
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![glowing, fixtures::part(2, "plain")]),
+			Vec::new(),
+		));
+
+		let emission_strength = |id| {
+			puppet
+				.node_comps
+				.get::<TexturedMesh>(InoxNodeUuid(id))
+				.unwrap()
+				.emission_strength
+		};
+		assert_eq!(emission_strength(1), 2.5);
+		assert_eq!(emission_strength(2), 1.0);
+	}
+
+	#[test]
+	fn missing_transform_and_mesh() {
+		let mut transformless = fixtures::node(1, "transformless", Vec::new());
+		transformless.remove("transform");
+		let mut meshless = fixtures::part(2, "meshless");
+		meshless.remove("mesh");
+		let payload = fixtures::puppet(fixtures::node(0, "root", vec![transformless, meshless]), Vec::new());
+
+		let puppet = Puppet::new_from_json_with_mode(&payload, ParseMode::Lenient).unwrap();
+		let transformless = puppet.nodes.get_node(InoxNodeUuid(1)).unwrap();
+		assert_eq!(
+			transformless.trans_offset.to_matrix(),
+			TransformOffset::default().to_matrix()
+		);
+		assert!(puppet.nodes.get_node(InoxNodeUuid(2)).is_some());
+		assert!(puppet.node_comps.get::<Drawable>(InoxNodeUuid(2)).is_none());
+		assert!(puppet.node_comps.get::<Mesh>(InoxNodeUuid(2)).is_none());
+
+		assert!(Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).is_err());
+	}
+
+	#[test]
+	fn rest_deform_vertex_count() {
+		let mut part = fixtures::part(1, "part");
+		part["restDeform"] = json::array![0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+		let payload = fixtures::puppet(fixtures::node(0, "root", vec![part]), Vec::new());
+
+		let puppet = Puppet::new_from_json_with_mode(&payload, ParseMode::Lenient).unwrap();
+		assert!(puppet.node_comps.get::<Mesh>(InoxNodeUuid(1)).is_some());
+		assert!(puppet.node_comps.get::<RestDeform>(InoxNodeUuid(1)).is_none());
+
+		assert!(matches!(
+			Puppet::new_from_json_with_mode(&payload, ParseMode::Strict),
+			Err(InoxParseError::RestDeformVertexCount { expected: 4, got: 3 })
+		));
+	}
+
+	#[test]
+	fn drawable_defaults() {
+		let mut tinted = fixtures::part(2, "tinted");
+		tinted["tint"] = json::array![1.0, 0.5, 0.0];
+		tinted["screenTint"] = json::array![0.0, 0.0, 0.5];
+		tinted["opacity"] = 0.5.into();
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "plain"), tinted]),
+			Vec::new(),
+		));
+
+		let blending = |id| &puppet.node_comps.get::<Drawable>(InoxNodeUuid(id)).unwrap().blending;
+		let defaults = Blending::default();
+		assert_eq!(defaults.tint, Vec3::ONE);
+		assert_eq!(
+			(blending(1).tint, blending(1).screen_tint, blending(1).opacity),
+			(defaults.tint, defaults.screen_tint, defaults.opacity)
+		);
+		assert_eq!(
+			(blending(2).tint, blending(2).screen_tint, blending(2).opacity),
+			(vec3(1.0, 0.5, 0.0), vec3(0.0, 0.0, 0.5), 0.5)
+		);
+	}
+
+	#[test]
+	fn node_group() {
+		let mut grouped = fixtures::node(1, "grouped", Vec::new());
+		grouped["groupId"] = 7.into();
+		grouped["groupColor"] = json::array![1.0, 0.0, 0.5];
+		let mut uncolored = fixtures::node(2, "uncolored", Vec::new());
+		uncolored["groupId"] = 8.into();
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![grouped, uncolored]),
+			Vec::new(),
+		));
+
+		assert_eq!(
+			puppet.node_group(InoxNodeUuid(1)),
+			Some(NodeGroup {
+				id: 7,
+				color: Some(vec3(1.0, 0.0, 0.5))
+			})
+		);
+		assert_eq!(
+			puppet.node_group(InoxNodeUuid(2)),
+			Some(NodeGroup { id: 8, color: None })
+		);
+		assert_eq!(puppet.node_group(InoxNodeUuid(0)), None);
+	}
+
+	#[test]
+	fn optional_physics() {
+		let mut payload = fixtures::puppet(fixtures::node(0, "root", Vec::new()), Vec::new());
+		payload.remove("physics");
+		let puppet = Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).unwrap();
+		assert_eq!(
+			puppet.physics.pixels_per_meter,
+			PuppetPhysics::default().pixels_per_meter
+		);
+		assert_eq!(puppet.physics.gravity, PuppetPhysics::default().gravity);
+
+		payload["physics"] = json::object! { gravity: 5.0 };
+		let puppet = Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).unwrap();
+		assert_eq!(
+			puppet.physics.pixels_per_meter,
+			PuppetPhysics::default().pixels_per_meter
+		);
+		assert_eq!(puppet.physics.gravity, 5.0);
+
+		// malformed physics only fail strict parsing
+		payload["physics"]["gravity"] = "down".into();
+		assert!(Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).is_err());
+		let puppet = Puppet::new_from_json(&payload).unwrap();
+		assert_eq!(puppet.physics.gravity, PuppetPhysics::default().gravity);
+	}
+
+	#[test]
+	fn animations() {
+		let mut payload = fixtures::puppet(fixtures::node(0, "root", Vec::new()), Vec::new());
+		payload["animations"] = json::object! {
+			blink: {
+				timestep: 0.05,
+				additive: false,
+				animationWeight: 1.0,
+				length: 20,
+				leadIn: -1,
+				leadOut: 15,
+				lanes: [{
+					uuid: 10,
+					target: 1,
+					interpolation: "Linear",
+					keyframes: [
+						{ frame: 10, value: 0.0, tension: 0.5 },
+						{ frame: 0, value: 1.0, tension: 0.5 },
+					],
+				}],
+			},
+			broken: { timestep: 0.05, lanes: "none" },
+		};
+		let puppet = Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).unwrap();
+
+		assert_eq!(puppet.animations.len(), 1);
+		let blink = &puppet.animations["blink"];
+		assert_eq!((blink.length, blink.lead_in, blink.lead_out), (20, None, Some(15)));
+		let lane = &blink.lanes[0];
+		assert_eq!((lane.param, lane.axis), (ParamUuid(10), Axis::Y));
+		assert_eq!(
+			lane.keyframes.iter().map(|keyframe| keyframe.frame).collect::<Vec<_>>(),
+			[0, 10]
+		);
+
+		// a malformed block does not fail the puppet
+		payload["animations"] = "none".into();
+		assert!(Puppet::new_from_json_with_mode(&payload, ParseMode::Strict)
+			.unwrap()
+			.animations
+			.is_empty());
+	}
+
+	#[test]
+	fn custom_node_data() {
+		let mut camera = fixtures::node(2, "camera", Vec::new());
+		camera["type"] = "Camera".into();
+		camera["fov"] = 60.into();
+		let payload = fixtures::puppet(fixtures::node(1, "root", vec![camera]), Vec::new());
+
+		let loaded = RefCell::new(Vec::new());
+		let load_custom = |_: &mut Puppet, ty: &str, data: JsonObject<json::JsonValue>| -> InoxParseResult<()> {
+			loaded.borrow_mut().push((ty.to_owned(), data.get_u32("fov")?));
+			Ok(())
+		};
+		Puppet::new_from_json_with_custom(&payload, Some(&load_custom)).unwrap();
+		assert_eq!(loaded.take(), [("Camera".to_owned(), 60)]);
+
+		Puppet::new_from_json_with_custom_and_mode(&payload, ParseMode::Strict, Some(&load_custom)).unwrap();
+		assert_eq!(loaded.take(), [("Camera".to_owned(), 60)]);
+	}
+}
@@ -0,0 +1,124 @@
+use glam::Vec2;
+
+use crate::node::components::{Mesh, RestDeform};
+use crate::params::BindingValues;
+use crate::puppet::Puppet;
+
+use super::payload::{InoxParseError, InoxParseResult, ParseMode};
+
+/// Zero non-finite floats with a warning, or fail in strict mode. `what` describes where the floats come from.
+fn sanitize<'a>(
+	floats: impl IntoIterator<Item = &'a mut f32>,
+	what: impl Fn() -> String,
+	mode: ParseMode,
+) -> InoxParseResult<()> {
+	let mut non_finite_count = 0;
+	for float in floats.into_iter().filter(|float| !float.is_finite()) {
+		if mode == ParseMode::Strict {
+			return Err(InoxParseError::NonFiniteFloat(what()));
+		}
+		*float = 0.0;
+		non_finite_count += 1;
+	}
+
+	if non_finite_count > 0 {
+		tracing::warn!("Zeroed {non_finite_count} non-finite float(s) in {}", what());
+	}
+	Ok(())
+}
+
+fn vec2_floats<'a>(vec2s: impl IntoIterator<Item = &'a mut Vec2>) -> impl Iterator<Item = &'a mut f32> {
+	vec2s.into_iter().flat_map(|Vec2 { x, y }| [x, y])
+}
+
+impl Puppet {
+	/// Deal with non-finite floats that would otherwise reach the renderer: mesh data, rest deforms, param bounds
+	/// and binding values.
+	pub(super) fn sanitize_floats(&mut self, mode: ParseMode) -> InoxParseResult<()> {
+		for node in self.nodes.iter() {
+			if let Some(mesh) = self.node_comps.get_mut::<Mesh>(node.uuid) {
+				let Mesh {
+					vertices, uvs, origin, ..
+				} = mesh;
+				let what = || format!("mesh of node {:?}", node.name);
+				sanitize(vec2_floats(vertices.iter_mut().chain(uvs).chain([origin])), what, mode)?;
+			}
+			if let Some(rest) = self.node_comps.get_mut::<RestDeform>(node.uuid) {
+				let what = || format!("rest deform of node {:?}", node.name);
+				sanitize(vec2_floats(&mut rest.deforms), what, mode)?;
+			}
+		}
+
+		for (name, param) in &mut self.params {
+			let what = || format!("bounds of param {name:?}");
+			let bounds = [&mut param.min, &mut param.max, &mut param.defaults];
+			sanitize(vec2_floats(bounds), what, mode)?;
+
+			let what = || format!("axis points of param {name:?}");
+			sanitize(
+				param.axis_points.x.iter_mut().chain(&mut param.axis_points.y),
+				what,
+				mode,
+			)?;
+
+			for binding in &mut param.bindings {
+				let what = || format!("binding values of param {name:?}");
+				match &mut binding.values {
+					BindingValues::ZSort(values)
+					| BindingValues::TransformTX(values)
+					| BindingValues::TransformTY(values)
+					| BindingValues::TransformSX(values)
+					| BindingValues::TransformSY(values)
+					| BindingValues::TransformRX(values)
+					| BindingValues::TransformRY(values)
+					| BindingValues::TransformRZ(values)
+					| BindingValues::Opacity(values) => sanitize(values.as_mut_slice(), what, mode)?,
+					BindingValues::Deform(values) => {
+						sanitize(vec2_floats(values.as_mut_slice().iter_mut().flatten()), what, mode)?
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::vec2;
+
+	use crate::formats::{fixtures, ParseMode};
+	use crate::node::{
+		components::{Mesh, RestDeform},
+		InoxNodeUuid,
+	};
+	use crate::puppet::Puppet;
+
+	#[test]
+	fn nan_vertex() {
+		let mut part = fixtures::part(1, "part");
+		part["mesh"]["verts"][2] = f32::NAN.into();
+		let payload = fixtures::puppet(fixtures::node(0, "root", vec![part]), Vec::new());
+
+		let puppet = fixtures::load(&payload);
+		let mesh = puppet.node_comps.get::<Mesh>(InoxNodeUuid(1)).unwrap();
+		assert_eq!(mesh.vertices[1], vec2(0.0, -1.0));
+
+		assert!(Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).is_err());
+	}
+
+	#[test]
+	fn nan_rest_deform() {
+		let mut part = fixtures::part(1, "part");
+		part["restDeform"] = json::array![0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+		part["restDeform"][0] = f32::NAN.into();
+		let payload = fixtures::puppet(fixtures::node(0, "root", vec![part]), Vec::new());
+
+		let puppet = fixtures::load(&payload);
+		let rest = puppet.node_comps.get::<RestDeform>(InoxNodeUuid(1)).unwrap();
+		assert_eq!(rest.deforms[0], vec2(0.0, 1.0));
+
+		assert!(Puppet::new_from_json_with_mode(&payload, ParseMode::Strict).is_err());
+	}
+}
@@ -0,0 +1,395 @@
+use glam::{Vec2, Vec3};
+use json::{object, JsonValue};
+
+use crate::animation::{Animation, AnimationLane};
+use crate::math::interp::InterpolateMode;
+use crate::math::matrix::Matrix2d;
+use crate::math::transform::TransformOffset;
+use crate::node::components::{
+	BlendMode, Composite, Drawable, MaskMode, Mesh, PhysicsModel, PhysicsParamMapMode, RestDeform, RestOpacity,
+	SimplePhysics, TexturedMesh, VertexWelds,
+};
+use crate::node::{InoxNode, InoxNodeUuid};
+use crate::params::{Axis, Binding, BindingValues, Param};
+use crate::physics::PuppetPhysics;
+use crate::puppet::meta::{
+	PuppetAllowedModification, PuppetAllowedRedistribution, PuppetAllowedUsers, PuppetMeta, PuppetUsageRights,
+};
+use crate::puppet::Puppet;
+
+// json structure helpers
+
+fn serialize_vec2(vec: Vec2) -> JsonValue {
+	vec.to_array().as_slice().into()
+}
+
+fn serialize_vec3(vec: Vec3) -> JsonValue {
+	vec.to_array().as_slice().into()
+}
+
+fn serialize_vec2s_flat(vecs: &[Vec2]) -> JsonValue {
+	vecs.iter().flat_map(|vec| vec.to_array()).collect::<Vec<_>>().into()
+}
+
+fn serialize_vec2s(vecs: &[Vec2]) -> JsonValue {
+	JsonValue::Array(vecs.iter().copied().map(serialize_vec2).collect())
+}
+
+/// Lines as they were passed to `Matrix2d::from_slice_vecs()`.
+fn serialize_matrix<T>(matrix: &Matrix2d<T>, serialize: impl Fn(&T) -> JsonValue) -> JsonValue {
+	let lines = matrix.as_slice().chunks(matrix.width().max(1)).take(matrix.height());
+	JsonValue::Array(
+		lines
+			.map(|line| JsonValue::Array(line.iter().map(&serialize).collect()))
+			.collect(),
+	)
+}
+
+// node serialization
+
+fn serialize_node(puppet: &Puppet, node: &InoxNode) -> JsonValue {
+	let mut obj = object! {
+		uuid: node.uuid.0,
+		name: node.name.as_str(),
+		"type": "Node",
+		enabled: node.enabled,
+		zsort: node.zsort,
+		transform: serialize_transform(&node.trans_offset),
+		lockToRoot: node.lock_to_root,
+	};
+	if let Some(group) = node.group {
+		obj["groupId"] = group.id.into();
+		if let Some(color) = group.color {
+			obj["groupColor"] = serialize_vec3(color);
+		}
+	}
+
+	serialize_node_data(puppet, node.uuid, &mut obj);
+
+	obj["children"] = JsonValue::Array(
+		puppet
+			.nodes
+			.get_children(node.uuid)
+			.map(|child| serialize_node(puppet, child))
+			.collect(),
+	);
+	obj
+}
+
+/// Node type and component data, the reverse of `Puppet::load_node_data()`.
+fn serialize_node_data(puppet: &Puppet, id: InoxNodeUuid, obj: &mut JsonValue) {
+	let comps = &puppet.node_comps;
+
+	if let (Some(drawable), Some(textured_mesh), Some(mesh)) = (
+		comps.get::<Drawable>(id),
+		comps.get::<TexturedMesh>(id),
+		comps.get::<Mesh>(id),
+	) {
+		obj["type"] = "Part".into();
+		serialize_drawable(drawable, obj);
+		serialize_textured_mesh(textured_mesh, obj);
+		obj["mesh"] = serialize_mesh(mesh);
+		if let Some(welds) = comps.get::<VertexWelds>(id) {
+			obj["weldedLinks"] = serialize_vertex_welds(welds);
+		}
+		if let Some(rest) = comps.get::<RestDeform>(id) {
+			obj["restDeform"] = serialize_vec2s_flat(&rest.deforms);
+		}
+	} else if let (Some(drawable), Some(mesh)) = (comps.get::<Drawable>(id), comps.get::<Mesh>(id)) {
+		obj["type"] = "Mask".into();
+		serialize_drawable(drawable, obj);
+		obj["mesh"] = serialize_mesh(mesh);
+	} else if let (Some(drawable), Some(composite)) = (comps.get::<Drawable>(id), comps.get::<Composite>(id)) {
+		obj["type"] = "Composite".into();
+		serialize_drawable(drawable, obj);
+		obj["propagate_meshgroup"] = composite.propagate_meshgroup.into();
+	} else if let Some(simple_physics) = comps.get::<SimplePhysics>(id) {
+		obj["type"] = "SimplePhysics".into();
+		serialize_simple_physics(simple_physics, obj);
+	}
+
+	// the opacity of drawables is changed by params once transforms are initialized
+	if let Some(RestOpacity(opacity)) = comps.get::<RestOpacity>(id) {
+		obj["opacity"] = (*opacity).into();
+	}
+}
+
+// components serialization
+
+fn serialize_textured_mesh(textured_mesh: &TexturedMesh, obj: &mut JsonValue) {
+	obj["textures"] = json::array![
+		textured_mesh.tex_albedo.raw(),
+		textured_mesh.tex_emissive.raw(),
+		textured_mesh.tex_bumpmap.raw(),
+	];
+	obj["emissionStrength"] = textured_mesh.emission_strength.into();
+}
+
+fn serialize_simple_physics(simple_physics: &SimplePhysics, obj: &mut JsonValue) {
+	obj["param"] = simple_physics.param.0.into();
+	obj["model_type"] = match simple_physics.model_type {
+		PhysicsModel::RigidPendulum => "Pendulum",
+		PhysicsModel::SpringPendulum => "SpringPendulum",
+	}
+	.into();
+	obj["map_mode"] = match simple_physics.map_mode {
+		PhysicsParamMapMode::AngleLength => "AngleLength",
+		PhysicsParamMapMode::XY => "XY",
+		PhysicsParamMapMode::YX => "YX",
+	}
+	.into();
+
+	let props = &simple_physics.props;
+	obj["gravity"] = props.gravity.into();
+	obj["length"] = props.length.into();
+	obj["frequency"] = props.frequency.into();
+	obj["angle_damping"] = props.angle_damping.into();
+	obj["length_damping"] = props.length_damping.into();
+	obj["output_scale"] = serialize_vec2(props.output_scale);
+
+	obj["local_only"] = simple_physics.local_only.into();
+}
+
+fn serialize_drawable(drawable: &Drawable, obj: &mut JsonValue) {
+	let blending = &drawable.blending;
+	obj["blend_mode"] = match blending.mode {
+		BlendMode::Normal => "Normal",
+		BlendMode::Multiply => "Multiply",
+		BlendMode::ColorDodge => "ColorDodge",
+		BlendMode::LinearDodge => "LinearDodge",
+		BlendMode::Screen => "Screen",
+		BlendMode::ClipToLower => "ClipToLower",
+		BlendMode::SliceFromLower => "SliceFromLower",
+		BlendMode::Overlay => "Overlay",
+		BlendMode::Darken => "Darken",
+		BlendMode::Lighten => "Lighten",
+		BlendMode::HardLight => "HardLight",
+	}
+	.into();
+	obj["tint"] = serialize_vec3(blending.tint);
+	obj["screenTint"] = serialize_vec3(blending.screen_tint);
+	obj["opacity"] = blending.opacity.into();
+
+	if let Some(masks) = &drawable.masks {
+		obj["mask_threshold"] = masks.threshold.into();
+		obj["masks"] = JsonValue::Array(
+			masks
+				.masks
+				.iter()
+				.map(|mask| {
+					object! {
+						source: mask.source.0,
+						mode: match mask.mode {
+							MaskMode::Mask => "Mask",
+							MaskMode::Dodge => "DodgeMask",
+						},
+					}
+				})
+				.collect(),
+		);
+	}
+}
+
+fn serialize_mesh(mesh: &Mesh) -> JsonValue {
+	object! {
+		verts: serialize_vec2s_flat(&mesh.vertices),
+		uvs: serialize_vec2s_flat(&mesh.uvs),
+		indices: mesh.indices.as_slice(),
+		origin: serialize_vec2(mesh.origin),
+	}
+}
+
+fn serialize_vertex_welds(welds: &VertexWelds) -> JsonValue {
+	JsonValue::Array(
+		welds
+			.links
+			.iter()
+			.map(|link| {
+				object! {
+					target: link.target.0,
+					// unwelded vertices are marked with -1
+					indices: link
+						.indices
+						.iter()
+						.map(|index| index.map_or(-1, i32::from))
+						.collect::<Vec<_>>(),
+					weight: link.weight,
+				}
+			})
+			.collect(),
+	)
+}
+
+fn serialize_transform(transform: &TransformOffset) -> JsonValue {
+	object! {
+		trans: serialize_vec3(transform.translation),
+		rot: serialize_vec3(transform.rotation),
+		scale: serialize_vec2(transform.scale),
+		pixel_snap: transform.pixel_snap,
+	}
+}
+
+// params serialization
+
+fn serialize_param(param: &Param) -> JsonValue {
+	object! {
+		uuid: param.uuid.0,
+		name: param.name.as_str(),
+		is_vec2: param.is_vec2,
+		min: serialize_vec2(param.min),
+		max: serialize_vec2(param.max),
+		defaults: serialize_vec2(param.defaults),
+		axis_points: [param.axis_points.x.as_slice(), param.axis_points.y.as_slice()],
+		bindings: param.bindings.iter().map(serialize_binding).collect::<Vec<_>>(),
+	}
+}
+
+fn serialize_binding(binding: &Binding) -> JsonValue {
+	let scalars = |values: &Matrix2d<f32>| serialize_matrix(values, |value| (*value).into());
+	let (param_name, values) = match &binding.values {
+		BindingValues::ZSort(values) => ("zSort", scalars(values)),
+		BindingValues::TransformTX(values) => ("transform.t.x", scalars(values)),
+		BindingValues::TransformTY(values) => ("transform.t.y", scalars(values)),
+		BindingValues::TransformSX(values) => ("transform.s.x", scalars(values)),
+		BindingValues::TransformSY(values) => ("transform.s.y", scalars(values)),
+		BindingValues::TransformRX(values) => ("transform.r.x", scalars(values)),
+		BindingValues::TransformRY(values) => ("transform.r.y", scalars(values)),
+		BindingValues::TransformRZ(values) => ("transform.r.z", scalars(values)),
+		BindingValues::Deform(values) => ("deform", serialize_matrix(values, |deform| serialize_vec2s(deform))),
+		BindingValues::Opacity(values) => ("opacity", scalars(values)),
+	};
+
+	object! {
+		node: binding.node.0,
+		param_name: param_name,
+		values: values,
+		isSet: serialize_matrix(&binding.is_set, |is_set| (*is_set).into()),
+		interpolate_mode: serialize_interpolate_mode(binding.interpolate_mode),
+	}
+}
+
+fn serialize_interpolate_mode(mode: InterpolateMode) -> &'static str {
+	match mode {
+		InterpolateMode::Nearest => "Nearest",
+		InterpolateMode::Linear => "Linear",
+		InterpolateMode::Cubic => "Cubic",
+	}
+}
+
+// animations serialization
+
+fn serialize_animation(animation: &Animation) -> JsonValue {
+	// absent lead frames are -1
+	let lead = |frame: Option<u32>| frame.map_or(-1, i64::from);
+	object! {
+		timestep: animation.timestep,
+		additive: animation.additive,
+		animationWeight: animation.weight,
+		length: animation.length,
+		leadIn: lead(animation.lead_in),
+		leadOut: lead(animation.lead_out),
+		lanes: animation.lanes.iter().map(serialize_animation_lane).collect::<Vec<_>>(),
+	}
+}
+
+fn serialize_animation_lane(lane: &AnimationLane) -> JsonValue {
+	object! {
+		uuid: lane.param.0,
+		target: match lane.axis {
+			Axis::X => 0,
+			Axis::Y => 1,
+		},
+		interpolation: serialize_interpolate_mode(lane.interpolation),
+		keyframes: lane
+			.keyframes
+			.iter()
+			.map(|keyframe| {
+				object! {
+					frame: keyframe.frame,
+					value: keyframe.value,
+					tension: keyframe.tension,
+				}
+			})
+			.collect::<Vec<_>>(),
+	}
+}
+
+// Puppet serialization
+
+fn serialize_puppet_physics(physics: &PuppetPhysics) -> JsonValue {
+	object! {
+		pixelsPerMeter: physics.pixels_per_meter,
+		gravity: physics.gravity,
+	}
+}
+
+fn serialize_puppet_meta(meta: &PuppetMeta) -> JsonValue {
+	let mut obj = object! {
+		name: meta.name.as_deref(),
+		version: meta.version.as_str(),
+		rigger: meta.rigger.as_deref(),
+		artist: meta.artist.as_deref(),
+		copyright: meta.copyright.as_deref(),
+		licenseURL: meta.license_url.as_deref(),
+		contact: meta.contact.as_deref(),
+		reference: meta.reference.as_deref(),
+		preservePixels: meta.preserve_pixels,
+	};
+	if let Some(rights) = &meta.rights {
+		obj["rights"] = serialize_puppet_usage_rights(rights);
+	}
+	if let Some(thumbnail_id) = meta.thumbnail_id {
+		obj["thumbnailId"] = thumbnail_id.into();
+	}
+	obj
+}
+
+fn serialize_puppet_usage_rights(rights: &PuppetUsageRights) -> JsonValue {
+	object! {
+		allowed_users: match rights.allowed_users {
+			PuppetAllowedUsers::OnlyAuthor => "OnlyAuthor",
+			PuppetAllowedUsers::OnlyLicensee => "OnlyLicensee",
+			PuppetAllowedUsers::Everyone => "Everyone",
+		},
+		allow_violence: rights.allow_violence,
+		allow_sexual: rights.allow_sexual,
+		allow_commercial: rights.allow_commercial,
+		allow_redistribution: match rights.allow_redistribution {
+			PuppetAllowedRedistribution::Prohibited => "Prohibited",
+			PuppetAllowedRedistribution::ViralLicense => "ViralLicense",
+			PuppetAllowedRedistribution::CopyleftLicense => "CopyleftLicense",
+		},
+		allow_modification: match rights.allow_modification {
+			PuppetAllowedModification::Prohibited => "Prohibited",
+			PuppetAllowedModification::AllowPersonal => "AllowPersonal",
+			PuppetAllowedModification::AllowRedistribute => "AllowRedistribute",
+		},
+		require_attribution: rights.require_attribution,
+	}
+}
+
+impl Puppet {
+	/// Payload describing this puppet, as read by `Puppet::new_from_json()`.
+	///
+	/// Data of custom node types is not kept when parsing, so such nodes are written as plain Nodes.
+	/// Runtime state, e.g. current param values, is not included.
+	pub fn to_json(&self) -> JsonValue {
+		let params = self.params_sorted();
+
+		let root = self.nodes.get_node(self.nodes.root_node_id).unwrap();
+		let mut obj = object! {
+			meta: serialize_puppet_meta(&self.meta),
+			physics: serialize_puppet_physics(&self.physics),
+			nodes: serialize_node(self, root),
+			param: params.into_iter().map(serialize_param).collect::<Vec<_>>(),
+		};
+		if !self.animations.is_empty() {
+			let mut animations = JsonValue::new_object();
+			for (name, animation) in &self.animations {
+				animations[name.as_str()] = serialize_animation(animation);
+			}
+			obj["animations"] = animations;
+		}
+		obj
+	}
+}
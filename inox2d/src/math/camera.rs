@@ -1,10 +1,14 @@
-use glam::{Mat4, Vec2};
+use glam::{vec2, vec3, Mat4, Vec2};
 
 #[derive(Clone)]
 pub struct Camera {
 	pub position: Vec2,
 	pub rotation: f32,
 	pub scale: Vec2,
+	/// Mirror the view horizontally around the viewport center, e.g. for mirror mode, leaving `scale` and zoom alone.
+	pub flip_x: bool,
+	/// Mirror the view vertically around the viewport center.
+	pub flip_y: bool,
 }
 
 impl Default for Camera {
@@ -13,6 +17,8 @@ impl Default for Camera {
 			position: Vec2::ZERO,
 			rotation: 0.0,
 			scale: Vec2::ONE,
+			flip_x: false,
+			flip_y: false,
 		}
 	}
 }
@@ -31,6 +37,24 @@ impl Camera {
 		self.real_size(viewport) / 2.0
 	}
 
+	/// Center the camera on the box from `min` to `max` in puppet space, e.g. from `Puppet::bounding_box()`,
+	/// and scale it uniformly so that the box fits `viewport` with `padding` pixels left on each side.
+	///
+	/// The current rotation is kept and accounted for.
+	pub fn fit_bounds(&mut self, min: Vec2, max: Vec2, viewport: Vec2, padding: f32) {
+		self.position = -(min + max) / 2.0;
+
+		// extent of the box as seen through the rotated camera
+		let half_size = (max - min) / 2.0;
+		let rotation = Vec2::from_angle(self.rotation);
+		let extent =
+			2.0 * (rotation.rotate(half_size).abs()).max(rotation.rotate(vec2(half_size.x, -half_size.y)).abs());
+
+		let available = (viewport - 2.0 * padding).max(Vec2::ONE);
+		let scale = available / extent.max(Vec2::splat(f32::EPSILON));
+		self.scale = Vec2::splat(scale.min_element());
+	}
+
 	/// Gets the resulting matrix from the camera and viewport
 	pub fn matrix(&self, viewport: Vec2) -> Mat4 {
 		let real_size = self.real_size(viewport);
@@ -39,10 +63,111 @@ impl Camera {
 		let origin = real_size / 2.0;
 		let pos = self.position.extend(-(u16::MAX as f32 / 2.0));
 
+		// mirrored around the center of clip space, which is the viewport center
+		let flip = vec3(
+			if self.flip_x { -1.0 } else { 1.0 },
+			if self.flip_y { -1.0 } else { 1.0 },
+			1.0,
+		);
+
 		// Return camera ortho matrix
-		Mat4::orthographic_lh(0.0, real_size.x, real_size.y, 0.0, 0.0, u16::MAX as f32)
+		Mat4::from_scale(flip)
+			* Mat4::orthographic_lh(0.0, real_size.x, real_size.y, 0.0, 0.0, u16::MAX as f32)
 			* Mat4::from_translation(origin.extend(0.0))
 			* Mat4::from_rotation_z(self.rotation)
 			* Mat4::from_translation(pos)
 	}
+
+	/// Position in pixels from the top-left corner of `viewport` where point `world` of puppet space is drawn.
+	pub fn world_to_screen(&self, world: Vec2, viewport: Vec2) -> Vec2 {
+		let clip = self.matrix(viewport).project_point3(world.extend(0.0));
+		vec2(clip.x + 1.0, 1.0 - clip.y) / 2.0 * viewport
+	}
+
+	/// Point of puppet space drawn at `screen`, in pixels from the top-left corner of `viewport`.
+	/// Inverse of `.world_to_screen()`.
+	pub fn screen_to_world(&self, screen: Vec2, viewport: Vec2) -> Vec2 {
+		let clip = vec2(2.0 * screen.x / viewport.x - 1.0, 1.0 - 2.0 * screen.y / viewport.y);
+		self.matrix(viewport)
+			.inverse()
+			.project_point3(clip.extend(0.0))
+			.truncate()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fit_bounds() {
+		let viewport = vec2(800.0, 600.0);
+		let (min, max) = (vec2(-50.0, 100.0), vec2(150.0, 2100.0));
+
+		for rotation in [0.0, 0.3, -std::f32::consts::FRAC_PI_2] {
+			let mut camera = Camera {
+				rotation,
+				..Camera::default()
+			};
+			camera.fit_bounds(min, max, viewport, 20.0);
+			assert_eq!(camera.scale.x, camera.scale.y);
+
+			let corners = [min, vec2(max.x, min.y), vec2(min.x, max.y), max]
+				.map(|corner| camera.world_to_screen(corner, viewport));
+			let screen_min = corners.iter().fold(Vec2::MAX, |a, b| a.min(*b));
+			let screen_max = corners.iter().fold(Vec2::MIN, |a, b| a.max(*b));
+
+			// centered, inside the padding, and touching it on one axis
+			assert!(((screen_min + screen_max) / 2.0 - viewport / 2.0).length() < 1e-2);
+			assert!(screen_min.cmpge(Vec2::splat(20.0 - 1e-2)).all());
+			assert!((screen_min.min_element() - 20.0).abs() < 1e-2);
+		}
+	}
+
+	#[test]
+	fn screen_world_round_trip() {
+		let viewport = vec2(800.0, 600.0);
+		let mut camera = Camera::default();
+		assert_eq!(camera.world_to_screen(Vec2::ZERO, viewport), viewport / 2.0);
+		// y goes down on screen, like in puppet space
+		assert_eq!(camera.world_to_screen(vec2(100.0, 50.0), viewport), vec2(500.0, 350.0));
+
+		camera.position = vec2(12.0, -34.0);
+		camera.rotation = 0.5;
+		camera.scale = Vec2::splat(0.15);
+		for world in [Vec2::ZERO, vec2(-1234.5, 678.9), vec2(3000.0, 42.0)] {
+			let screen = camera.world_to_screen(world, viewport);
+			assert!(camera.screen_to_world(screen, viewport).abs_diff_eq(world, 1e-2));
+		}
+		for screen in [Vec2::ZERO, viewport, vec2(123.0, 456.0)] {
+			let world = camera.screen_to_world(screen, viewport);
+			assert!(camera.world_to_screen(world, viewport).abs_diff_eq(screen, 1e-3));
+		}
+	}
+
+	#[test]
+	fn flip() {
+		let viewport = vec2(800.0, 600.0);
+		let mut camera = Camera {
+			position: vec2(12.0, -34.0),
+			scale: Vec2::splat(0.5),
+			..Camera::default()
+		};
+		let world = vec2(100.0, 50.0);
+		let screen = camera.world_to_screen(world, viewport);
+
+		camera.flip_x = true;
+		assert!(camera
+			.world_to_screen(world, viewport)
+			.abs_diff_eq(vec2(viewport.x - screen.x, screen.y), 1e-3));
+		camera.flip_y = true;
+		assert!(camera
+			.world_to_screen(world, viewport)
+			.abs_diff_eq(viewport - screen, 1e-3));
+
+		// what is under the cursor is still found
+		assert!(camera
+			.screen_to_world(viewport - screen, viewport)
+			.abs_diff_eq(world, 1e-2));
+	}
 }
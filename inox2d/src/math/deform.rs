@@ -8,14 +8,27 @@ pub(crate) enum Deform {
 }
 
 /// Element-wise add direct deforms up and write result.
-pub(crate) fn linear_combine<'deforms>(direct_deforms: impl Iterator<Item = &'deforms Vec<Vec2>>, result: &mut [Vec2]) {
-	result.iter_mut().for_each(|deform| *deform = Vec2::ZERO);
-
-	for direct_deform in direct_deforms {
-		if direct_deform.len() != result.len() {
+pub(crate) fn linear_combine<'deforms>(
+	mut direct_deforms: impl Iterator<Item = &'deforms Vec<Vec2>>,
+	result: &mut [Vec2],
+) {
+	let len = result.len();
+	let check_len = |direct_deform: &[Vec2]| {
+		if direct_deform.len() != len {
 			panic!("Trying to combine direct deformations with wrong dimensions.");
 		}
+	};
 
+	// the first deform is copied rather than added to zeros, which saves a pass over the result
+	let Some(first) = direct_deforms.next() else {
+		result.fill(Vec2::ZERO);
+		return;
+	};
+	check_len(first);
+	result.copy_from_slice(first);
+
+	for direct_deform in direct_deforms {
+		check_len(direct_deform);
 		result
 			.iter_mut()
 			.zip(direct_deform.iter())
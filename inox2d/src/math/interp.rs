@@ -6,6 +6,8 @@ pub enum InterpolateMode {
 	Nearest,
 	/// Linear interpolation
 	Linear,
+	/// Catmull-Rom spline through neighboring points
+	Cubic,
 	// there's more but I'm not adding them for now.
 }
 
@@ -40,16 +42,9 @@ impl InterpRange<Vec2> {
 	}
 }
 
+/// `t` outside of `range_in` snaps to the closest end.
 #[inline]
 fn interpolate_nearest(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
-	debug_assert!(
-		range_in.beg <= t && t <= range_in.end,
-		"{} <= {} <= {}",
-		range_in.beg,
-		t,
-		range_in.end
-	);
-
 	if (range_in.end - t) < (t - range_in.beg) {
 		range_out.end
 	} else {
@@ -57,24 +52,51 @@ fn interpolate_nearest(t: f32, range_in: InterpRange<f32>, range_out: InterpRang
 	}
 }
 
+/// `t` outside of `range_in` extrapolates linearly.
 #[inline]
 fn interpolate_linear(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
-	debug_assert!(
-		range_in.beg <= t && t <= range_in.end,
-		"{} is out of input range [{}, {}]",
-		t,
-		range_in.beg,
-		range_in.end,
-	);
+	// A single axis point (e.g. the Y axis of a non-vec2 param) gives an empty range: nothing to interpolate
+	if range_in.beg == range_in.end {
+		return range_out.beg;
+	}
 
 	(t - range_in.beg) * (range_out.end - range_out.beg) / (range_in.end - range_in.beg) + range_out.beg
 }
 
+/// Weights of the values at four consecutive points `[before, beg, end, after]` for cubic interpolation of `t`
+/// between `beg` and `end`, tangents at `beg` and `end` being Catmull-Rom finite differences.
+///
+/// At the ends of an axis, repeat `beg` as `before` or `end` as `after`: the tangent becomes one-sided.
+pub fn cubic_weights(t: f32, points: [f32; 4]) -> [f32; 4] {
+	let [before, beg, end, after] = points;
+	let h = end - beg;
+	// A single axis point gives an empty range: nothing to interpolate
+	if h == 0.0 {
+		return [0.0, 1.0, 0.0, 0.0];
+	}
+
+	// cubic Hermite basis
+	let s = (t - beg) / h;
+	let (s2, s3) = (s * s, s * s * s);
+	let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+	let h10 = s3 - 2.0 * s2 + s;
+	let h01 = -2.0 * s3 + 3.0 * s2;
+	let h11 = s3 - s2;
+
+	// weights of the tangents (v_end - v_before) / (end - before) and (v_after - v_beg) / (after - beg)
+	let tangent_beg = h10 * h / (end - before);
+	let tangent_end = h11 * h / (after - beg);
+
+	[-tangent_beg, h00 - tangent_end, h01 + tangent_beg, tangent_end]
+}
+
+/// Without the neighbors `Cubic` needs, both tangents are one-sided and the curve is the line between the two points:
+/// see `bi_cubic_interpolate_f32()` for cubic interpolation with neighbors.
 #[inline]
 pub fn interpolate_f32(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>, mode: InterpolateMode) -> f32 {
 	match mode {
 		InterpolateMode::Nearest => interpolate_nearest(t, range_in, range_out),
-		InterpolateMode::Linear => interpolate_linear(t, range_in, range_out),
+		InterpolateMode::Linear | InterpolateMode::Cubic => interpolate_linear(t, range_in, range_out),
 	}
 }
 
@@ -162,6 +184,50 @@ pub fn bi_interpolate_f32s_additive(
 	}
 }
 
+/// Linear interpolation factor of `t` in `range_in`, as in `interpolate_linear()`.
+#[inline]
+fn linear_factor(t: f32, range_in: InterpRange<f32>) -> f32 {
+	if range_in.beg == range_in.end {
+		0.0
+	} else {
+		(t - range_in.beg) / (range_in.end - range_in.beg)
+	}
+}
+
+/// Bi-cubic interpolation of the 4x4 values around a cell, `value(i, j)` being the one at the `i`th of the points
+/// weighted by `weights_x` and the `j`th of the points weighted by `weights_y`, see `cubic_weights()`.
+pub fn bi_cubic_interpolate_f32(weights_x: [f32; 4], weights_y: [f32; 4], value: impl Fn(usize, usize) -> f32) -> f32 {
+	let mut sum = 0.0;
+	for (j, wy) in weights_y.into_iter().enumerate() {
+		for (i, wx) in weights_x.into_iter().enumerate() {
+			sum += value(i, j) * wx * wy;
+		}
+	}
+	sum
+}
+
+/// Same as `bi_cubic_interpolate_f32()` for every element.
+pub fn bi_cubic_interpolate_vec2s_additive<'a>(
+	weights_x: [f32; 4],
+	weights_y: [f32; 4],
+	values: impl Fn(usize, usize) -> &'a [Vec2],
+	out: &mut [Vec2],
+) {
+	for (j, wy) in weights_y.into_iter().enumerate() {
+		for (i, wx) in weights_x.into_iter().enumerate() {
+			let w = wx * wy;
+			if w == 0.0 {
+				continue;
+			}
+			for (o, &v) in out.iter_mut().zip(values(i, j)) {
+				*o += v * w;
+			}
+		}
+	}
+}
+
+/// Same as calling `bi_interpolate_vec2()` for every element, but hot for high-poly deforms:
+/// interpolation factors are computed once, leaving a branchless multiply-add per element.
 pub fn bi_interpolate_vec2s_additive(
 	t: Vec2,
 	range_in: InterpRange<Vec2>,
@@ -170,17 +236,48 @@ pub fn bi_interpolate_vec2s_additive(
 	mode: InterpolateMode,
 	out: &mut [Vec2],
 ) {
-	for (((&otb, &ote), (&obb, &obe)), o) in (out_top.beg.iter().zip(out_top.end))
-		.zip(out_bottom.beg.iter().zip(out_bottom.end))
-		.zip(out)
-	{
-		*o += bi_interpolate_vec2(
-			t,
-			range_in,
-			InterpRange::new(otb, ote),
-			InterpRange::new(obb, obe),
-			mode,
-		)
+	match mode {
+		InterpolateMode::Nearest => {
+			// nearest on both axes picks one of the four corners as a whole
+			let (top, bottom) = (
+				out_top.to_x_nearest(t.x, range_in),
+				out_bottom.to_x_nearest(t.x, range_in),
+			);
+			let nearest = if (range_in.end.y - t.y) < (t.y - range_in.beg.y) {
+				bottom
+			} else {
+				top
+			};
+
+			for (o, &n) in out.iter_mut().zip(nearest) {
+				*o += n;
+			}
+		}
+		InterpolateMode::Linear | InterpolateMode::Cubic => {
+			let wx = Vec2::splat(linear_factor(t.x, range_in.to_x()));
+			let wy = Vec2::splat(linear_factor(t.y, range_in.to_y()));
+
+			for (((&otb, &ote), (&obb, &obe)), o) in (out_top.beg.iter().zip(out_top.end))
+				.zip(out_bottom.beg.iter().zip(out_bottom.end))
+				.zip(out)
+			{
+				let beg = otb + (ote - otb) * wx;
+				let end = obb + (obe - obb) * wx;
+				*o += beg + (end - beg) * wy;
+			}
+		}
+	}
+}
+
+impl<'a> InterpRange<&'a [Vec2]> {
+	/// The end of this range nearest to `t` on the X axis of `range_in`.
+	#[inline]
+	fn to_x_nearest(self, t: f32, range_in: InterpRange<Vec2>) -> &'a [Vec2] {
+		if (range_in.end.x - t) < (t - range_in.beg.x) {
+			self.end
+		} else {
+			self.beg
+		}
 	}
 }
 
@@ -206,5 +303,93 @@ mod tests {
 			interpolate_linear(0.0, InterpRange::new(-0.5, 0.0), InterpRange::new(-5.0, 5.0)),
 			5.0
 		);
+		assert_eq!(
+			interpolate_linear(1.5, InterpRange::new(0.0, 1.0), InterpRange::new(-5.0, 5.0)),
+			10.0
+		);
+		assert_eq!(
+			interpolate_linear(0.0, InterpRange::new(0.0, 0.0), InterpRange::new(-5.0, 5.0)),
+			-5.0
+		);
+	}
+
+	#[test]
+	fn bulk_vec2s_match_per_element() {
+		let range_in = InterpRange::new(Vec2::new(-1.0, 0.0), Vec2::new(2.0, 0.5));
+		let corner = |seed: f32| {
+			(0..37)
+				.map(|i| Vec2::new(seed * i as f32, 1.0 / (seed + i as f32)))
+				.collect::<Vec<_>>()
+		};
+		let (tb, te, bb, be) = (corner(0.3), corner(-1.7), corner(2.9), corner(0.01));
+		let out_top = InterpRange::new(tb.as_slice(), te.as_slice());
+		let out_bottom = InterpRange::new(bb.as_slice(), be.as_slice());
+
+		for mode in [
+			InterpolateMode::Nearest,
+			InterpolateMode::Linear,
+			InterpolateMode::Cubic,
+		] {
+			for t in [
+				Vec2::new(-1.0, 0.0),
+				Vec2::new(0.4, 0.1),
+				Vec2::new(1.3, 0.45),
+				Vec2::new(2.0, 0.5),
+			] {
+				let mut bulk = vec![Vec2::ONE; tb.len()];
+				bi_interpolate_vec2s_additive(t, range_in, out_top, out_bottom, mode, &mut bulk);
+
+				for (i, b) in bulk.iter().enumerate() {
+					let expected = Vec2::ONE
+						+ bi_interpolate_vec2(
+							t,
+							range_in,
+							InterpRange::new(tb[i], te[i]),
+							InterpRange::new(bb[i], be[i]),
+							mode,
+						);
+					assert!(b.abs_diff_eq(expected, 1e-4), "{mode:?} at {t}: {b} != {expected}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn cubic_interpolation() {
+		let cubic = |t: f32, points: [f32; 4], values: [f32; 4]| {
+			let weights = cubic_weights(t, points);
+			weights.iter().zip(values).map(|(w, v)| w * v).sum::<f32>()
+		};
+
+		// Catmull-Rom reproduces quadratics on evenly spaced points
+		for t in [1.0, 1.25, 1.5, 2.0] {
+			let v = cubic(t, [0.0, 1.0, 2.0, 3.0], [0.0, 1.0, 4.0, 9.0]);
+			assert!((v - t * t).abs() < 1e-5, "{v} != {}", t * t);
+		}
+		// uneven points
+		assert!((cubic(2.0, [0.0, 1.0, 3.0, 4.0], [0.0, 1.0, 9.0, 16.0]) - 4.5).abs() < 1e-5);
+		// repeated end point for a missing neighbor
+		assert!((cubic(0.25, [0.0, 0.0, 0.5, 1.0], [0.0, 0.0, 1.0, 4.0]) - 0.375).abs() < 1e-5);
+		assert_eq!(cubic_weights(0.3, [0.0, 1.0, 1.0, 2.0]), [0.0, 1.0, 0.0, 0.0]);
+
+		// without neighbors, a line
+		let weights = cubic_weights(0.25, [0.0, 0.0, 1.0, 1.0]);
+		assert!((cubic(0.25, [0.0, 0.0, 1.0, 1.0], [-5.0, -5.0, 5.0, 5.0]) + 2.5).abs() < 1e-5);
+		assert!(((weights[2] + weights[3]) - 0.25).abs() < 1e-5);
+
+		// bi-cubic of a separable function is the product of cubics
+		let (wx, wy) = (
+			cubic_weights(1.5, [0.0, 1.0, 2.0, 3.0]),
+			cubic_weights(0.5, [0.0, 0.0, 1.0, 2.0]),
+		);
+		let ys = [0.0, 0.0, 1.0, 2.0];
+		let v = bi_cubic_interpolate_f32(wx, wy, |i, j| (i * i) as f32 * ys[j]);
+		assert!((v - 2.25 * 0.5).abs() < 1e-5);
+		let mut out = [Vec2::ONE];
+		let values: Vec<[Vec2; 1]> = (0..16)
+			.map(|k| [Vec2::splat(((k % 4) * (k % 4)) as f32 * ys[k / 4])])
+			.collect();
+		bi_cubic_interpolate_vec2s_additive(wx, wy, |i, j| values[j * 4 + i].as_slice(), &mut out);
+		assert!(out[0].abs_diff_eq(Vec2::splat(1.0 + 2.25 * 0.5), 1e-5));
 	}
 }
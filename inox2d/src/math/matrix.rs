@@ -21,6 +21,19 @@ impl<T> Matrix2d<T> {
 		self.height
 	}
 
+	/// Elements in storage order: row by row, of `width` elements each.
+	pub(crate) fn as_slice(&self) -> &[T] {
+		&self.data
+	}
+
+	pub(crate) fn as_mut_slice(&mut self) -> &mut [T] {
+		&mut self.data
+	}
+
+	pub(crate) fn is_transposed(&self) -> bool {
+		self.transposed
+	}
+
 	pub fn get(&self, ix: usize, iy: usize) -> Option<&T> {
 		let (ix, iy) = if self.transposed { (iy, ix) } else { (ix, iy) };
 		self.data.get(iy * self.width + ix)
@@ -1,7 +1,15 @@
+mod content_hash;
+mod license;
+mod mirror;
+
+#[cfg(feature = "json")]
 use std::fmt;
 use std::sync::Arc;
 
-use crate::puppet::Puppet;
+use crate::physics::PuppetPhysics;
+use crate::puppet::{meta::PuppetMeta, Puppet};
+
+pub use license::LicenseInfo;
 
 #[derive(Clone, Debug)]
 pub struct ModelTexture {
@@ -9,12 +17,14 @@ pub struct ModelTexture {
 	pub data: Arc<[u8]>,
 }
 
+#[cfg(feature = "json")]
 #[derive(Clone, Debug)]
 pub struct VendorData {
 	pub name: String,
 	pub payload: json::JsonValue,
 }
 
+#[cfg(feature = "json")]
 impl fmt::Display for VendorData {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let name = &self.name;
@@ -31,5 +41,31 @@ impl fmt::Display for VendorData {
 pub struct Model {
 	pub puppet: Puppet,
 	pub textures: Vec<ModelTexture>,
+	/// Vendor payloads are `json` crate values, so they are only kept with the `json` feature.
+	#[cfg(feature = "json")]
 	pub vendors: Vec<VendorData>,
 }
+
+impl Model {
+	/// A model with an empty puppet and no textures. See `Puppet::empty()`.
+	pub fn empty() -> Self {
+		Self {
+			puppet: Puppet::empty(PuppetMeta::default(), PuppetPhysics::default()),
+			textures: Vec::new(),
+			#[cfg(feature = "json")]
+			vendors: Vec::new(),
+		}
+	}
+
+	/// Replace this model by `new`, keeping current param values of the puppet. See `Puppet::reload_from()`.
+	///
+	/// Renderers created from the old model must be recreated, as textures and vertex buffers may have changed.
+	pub fn reload_from(&mut self, new: Model) {
+		self.puppet.reload_from(new.puppet);
+		self.textures = new.textures;
+		#[cfg(feature = "json")]
+		{
+			self.vendors = new.vendors;
+		}
+	}
+}
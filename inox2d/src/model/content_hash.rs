@@ -0,0 +1,571 @@
+use std::hash::{Hash, Hasher};
+
+use glam::{Vec2, Vec3};
+
+use crate::animation::Animation;
+
+use crate::math::interp::InterpolateMode;
+use crate::math::matrix::Matrix2d;
+use crate::math::transform::TransformOffset;
+use crate::node::components::{
+	BlendMode, Composite, Drawable, MaskMode, Mesh, PhysicsModel, PhysicsParamMapMode, RestDeform, SimplePhysics,
+	TexturedMesh, VertexWelds,
+};
+use crate::node::NodeGroup;
+use crate::params::{Axis, BindingValues, Param};
+use crate::puppet::meta::{
+	PuppetAllowedModification, PuppetAllowedRedistribution, PuppetAllowedUsers, PuppetMeta, PuppetUsageRights,
+};
+use crate::puppet::Puppet;
+
+use super::Model;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, its algorithm is fixed, so hashes can be persisted.
+///
+/// Integers are written little-endian and `usize` as `u64`, so hashes do not depend on the platform either.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+	fn default() -> Self {
+		Self(0xcbf29ce484222325)
+	}
+}
+
+impl Hasher for Fnv1a {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= *byte as u64;
+			self.0 = self.0.wrapping_mul(0x100000001b3);
+		}
+	}
+
+	fn write_u16(&mut self, i: u16) {
+		self.write(&i.to_le_bytes());
+	}
+
+	fn write_u32(&mut self, i: u32) {
+		self.write(&i.to_le_bytes());
+	}
+
+	fn write_u64(&mut self, i: u64) {
+		self.write(&i.to_le_bytes());
+	}
+
+	fn write_usize(&mut self, i: usize) {
+		self.write_u64(i as u64);
+	}
+}
+
+/// `Hash` with an encoding that does not change with the platform or the compiler:
+/// floats are hashed by their bits, lengths as `u64` and enum variants by explicit `u8` tags.
+trait ContentHash {
+	fn content_hash(&self, state: &mut Fnv1a);
+}
+
+/// Types whose `Hash` on `Fnv1a` is already fixed.
+macro_rules! content_hash_via_hash {
+	($($ty:ty),*) => {
+		$(
+			impl ContentHash for $ty {
+				fn content_hash(&self, state: &mut Fnv1a) {
+					self.hash(state);
+				}
+			}
+		)*
+	};
+}
+
+content_hash_via_hash!(bool, u8, u16, u32);
+
+/// Hashes an enum variant by its tag, given explicitly so that reordering variants does not change hashes.
+fn hash_tag(tag: u8, state: &mut Fnv1a) {
+	tag.hash(state);
+}
+
+fn hash_len(len: usize, state: &mut Fnv1a) {
+	(len as u64).hash(state);
+}
+
+/// UTF-8 bytes after their length, rather than `str`'s `Hash`, which is free to change its terminator.
+impl ContentHash for str {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		hash_len(self.len(), state);
+		state.write(self.as_bytes());
+	}
+}
+
+impl ContentHash for String {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.as_str().content_hash(state);
+	}
+}
+
+impl<T: ContentHash + ?Sized> ContentHash for &T {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		(**self).content_hash(state);
+	}
+}
+
+impl ContentHash for f32 {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.to_bits().hash(state);
+	}
+}
+
+impl ContentHash for Vec2 {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.to_array().content_hash(state);
+	}
+}
+
+impl ContentHash for Vec3 {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.to_array().content_hash(state);
+	}
+}
+
+impl<T: ContentHash> ContentHash for [T] {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		hash_len(self.len(), state);
+		self.iter().for_each(|item| item.content_hash(state));
+	}
+}
+
+impl<T: ContentHash, const N: usize> ContentHash for [T; N] {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.as_slice().content_hash(state);
+	}
+}
+
+impl<T: ContentHash> ContentHash for Option<T> {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		match self {
+			None => hash_tag(0, state),
+			Some(value) => {
+				hash_tag(1, state);
+				value.content_hash(state);
+			}
+		}
+	}
+}
+
+impl<T: ContentHash> ContentHash for Vec<T> {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.as_slice().content_hash(state);
+	}
+}
+
+impl<T: ContentHash> ContentHash for Matrix2d<T> {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		hash_len(self.width(), state);
+		hash_len(self.height(), state);
+		self.is_transposed().hash(state);
+		self.as_slice().content_hash(state);
+	}
+}
+
+impl ContentHash for BlendMode {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			BlendMode::Normal => 0,
+			BlendMode::Multiply => 1,
+			BlendMode::ColorDodge => 2,
+			BlendMode::LinearDodge => 3,
+			BlendMode::Screen => 4,
+			BlendMode::ClipToLower => 5,
+			BlendMode::SliceFromLower => 6,
+			BlendMode::Overlay => 7,
+			BlendMode::Darken => 8,
+			BlendMode::Lighten => 9,
+			BlendMode::HardLight => 10,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for MaskMode {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			MaskMode::Mask => 0,
+			MaskMode::Dodge => 1,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for PhysicsModel {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			PhysicsModel::RigidPendulum => 0,
+			PhysicsModel::SpringPendulum => 1,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for PhysicsParamMapMode {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			PhysicsParamMapMode::AngleLength => 0,
+			PhysicsParamMapMode::XY => 1,
+			PhysicsParamMapMode::YX => 2,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for InterpolateMode {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			InterpolateMode::Nearest => 0,
+			InterpolateMode::Linear => 1,
+			InterpolateMode::Cubic => 2,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for Axis {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			Axis::X => 0,
+			Axis::Y => 1,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for PuppetAllowedUsers {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			PuppetAllowedUsers::OnlyAuthor => 0,
+			PuppetAllowedUsers::OnlyLicensee => 1,
+			PuppetAllowedUsers::Everyone => 2,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for PuppetAllowedRedistribution {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			PuppetAllowedRedistribution::Prohibited => 0,
+			PuppetAllowedRedistribution::ViralLicense => 1,
+			PuppetAllowedRedistribution::CopyleftLicense => 2,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for PuppetAllowedModification {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		let tag = match self {
+			PuppetAllowedModification::Prohibited => 0,
+			PuppetAllowedModification::AllowPersonal => 1,
+			PuppetAllowedModification::AllowRedistribute => 2,
+		};
+		hash_tag(tag, state);
+	}
+}
+
+impl ContentHash for PuppetUsageRights {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.allowed_users.content_hash(state);
+		[self.allow_violence, self.allow_sexual, self.allow_commercial].content_hash(state);
+		self.allow_redistribution.content_hash(state);
+		self.allow_modification.content_hash(state);
+		self.require_attribution.content_hash(state);
+	}
+}
+
+impl ContentHash for PuppetMeta {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.name.content_hash(state);
+		self.version.content_hash(state);
+		self.rigger.content_hash(state);
+		self.artist.content_hash(state);
+		self.rights.content_hash(state);
+		self.copyright.content_hash(state);
+		self.license_url.content_hash(state);
+		self.contact.content_hash(state);
+		self.reference.content_hash(state);
+		self.thumbnail_id.content_hash(state);
+		self.preserve_pixels.content_hash(state);
+	}
+}
+
+impl ContentHash for TransformOffset {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.translation.content_hash(state);
+		self.rotation.content_hash(state);
+		self.scale.content_hash(state);
+		self.pixel_snap.content_hash(state);
+	}
+}
+
+impl ContentHash for NodeGroup {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.id.content_hash(state);
+		self.color.content_hash(state);
+	}
+}
+
+impl ContentHash for Drawable {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.blending.mode.content_hash(state);
+		self.blending.tint.content_hash(state);
+		self.blending.screen_tint.content_hash(state);
+		self.blending.opacity.content_hash(state);
+		if let Some(masks) = &self.masks {
+			masks.threshold.content_hash(state);
+			for mask in &masks.masks {
+				mask.source.0.content_hash(state);
+				mask.mode.content_hash(state);
+			}
+		}
+	}
+}
+
+impl ContentHash for Mesh {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.vertices.content_hash(state);
+		self.uvs.content_hash(state);
+		self.indices.content_hash(state);
+		self.origin.content_hash(state);
+	}
+}
+
+impl ContentHash for SimplePhysics {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.param.0.content_hash(state);
+		self.model_type.content_hash(state);
+		self.map_mode.content_hash(state);
+		self.local_only.content_hash(state);
+		let props = &self.props;
+		[
+			props.gravity,
+			props.length,
+			props.frequency,
+			props.angle_damping,
+			props.length_damping,
+		]
+		.content_hash(state);
+		props.output_scale.content_hash(state);
+	}
+}
+
+impl ContentHash for Param {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.uuid.0.content_hash(state);
+		self.name.content_hash(state);
+		self.is_vec2.content_hash(state);
+		[self.min, self.max, self.defaults].content_hash(state);
+		self.axis_points.x.content_hash(state);
+		self.axis_points.y.content_hash(state);
+
+		for binding in &self.bindings {
+			binding.node.0.content_hash(state);
+			binding.interpolate_mode.content_hash(state);
+			binding.is_set.content_hash(state);
+
+			let tag = match &binding.values {
+				BindingValues::ZSort(_) => 0,
+				BindingValues::TransformTX(_) => 1,
+				BindingValues::TransformTY(_) => 2,
+				BindingValues::TransformSX(_) => 3,
+				BindingValues::TransformSY(_) => 4,
+				BindingValues::TransformRX(_) => 5,
+				BindingValues::TransformRY(_) => 6,
+				BindingValues::TransformRZ(_) => 7,
+				BindingValues::Opacity(_) => 8,
+				BindingValues::Deform(_) => 9,
+			};
+			hash_tag(tag, state);
+			match &binding.values {
+				BindingValues::ZSort(values)
+				| BindingValues::TransformTX(values)
+				| BindingValues::TransformTY(values)
+				| BindingValues::TransformSX(values)
+				| BindingValues::TransformSY(values)
+				| BindingValues::TransformRX(values)
+				| BindingValues::TransformRY(values)
+				| BindingValues::TransformRZ(values)
+				| BindingValues::Opacity(values) => values.content_hash(state),
+				BindingValues::Deform(values) => values.content_hash(state),
+			}
+		}
+	}
+}
+
+impl ContentHash for Animation {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		[self.timestep, self.weight].content_hash(state);
+		self.additive.content_hash(state);
+		self.length.content_hash(state);
+		self.lead_in.content_hash(state);
+		self.lead_out.content_hash(state);
+		hash_len(self.lanes.len(), state);
+		for lane in &self.lanes {
+			lane.param.0.content_hash(state);
+			lane.axis.content_hash(state);
+			lane.interpolation.content_hash(state);
+			hash_len(lane.keyframes.len(), state);
+			for keyframe in &lane.keyframes {
+				keyframe.frame.content_hash(state);
+				[keyframe.value, keyframe.tension].content_hash(state);
+			}
+		}
+	}
+}
+
+impl ContentHash for Puppet {
+	fn content_hash(&self, state: &mut Fnv1a) {
+		self.meta.content_hash(state);
+		[self.physics.pixels_per_meter, self.physics.gravity].content_hash(state);
+
+		// pre-order along with children counts fully describes the tree
+		for node in self.nodes.pre_order_iter() {
+			node.uuid.0.content_hash(state);
+			node.name.content_hash(state);
+			[node.enabled, node.lock_to_root].content_hash(state);
+			node.zsort.content_hash(state);
+			node.trans_offset.content_hash(state);
+			node.group.content_hash(state);
+			hash_len(self.nodes.get_children(node.uuid).count(), state);
+
+			let comps = &self.node_comps;
+			if let Some(drawable) = comps.get::<Drawable>(node.uuid) {
+				drawable.content_hash(state);
+			}
+			if let Some(textured_mesh) = comps.get::<TexturedMesh>(node.uuid) {
+				hash_len(textured_mesh.tex_albedo.raw(), state);
+				hash_len(textured_mesh.tex_emissive.raw(), state);
+				hash_len(textured_mesh.tex_bumpmap.raw(), state);
+				textured_mesh.emission_strength.content_hash(state);
+			}
+			if let Some(mesh) = comps.get::<Mesh>(node.uuid) {
+				mesh.content_hash(state);
+			}
+			if let Some(rest) = comps.get::<RestDeform>(node.uuid) {
+				rest.deforms.content_hash(state);
+			}
+			if let Some(composite) = comps.get::<Composite>(node.uuid) {
+				composite.propagate_meshgroup.content_hash(state);
+			}
+			if let Some(simple_physics) = comps.get::<SimplePhysics>(node.uuid) {
+				simple_physics.content_hash(state);
+			}
+			if let Some(welds) = comps.get::<VertexWelds>(node.uuid) {
+				for link in &welds.links {
+					link.target.0.content_hash(state);
+					link.indices.content_hash(state);
+					link.weight.content_hash(state);
+				}
+			}
+		}
+
+		// params live in a map with no defined order
+		let mut params = self.params.values().collect::<Vec<_>>();
+		params.sort_by_key(|param| param.uuid);
+		for param in params {
+			param.content_hash(state);
+		}
+
+		let mut animations = self.animations.iter().collect::<Vec<_>>();
+		animations.sort_by_key(|(name, _)| *name);
+		for (name, animation) in animations {
+			name.content_hash(state);
+			animation.content_hash(state);
+		}
+	}
+}
+
+impl Model {
+	/// Hash of everything parsed into this model: puppet structure and data, params, textures and vendor data.
+	///
+	/// Stable across runs and platforms, so it can key caches of data derived from a model.
+	/// Runtime state, e.g. current param values, is not included.
+	pub fn content_hash(&self) -> u64 {
+		let mut state = Fnv1a::default();
+
+		self.puppet.content_hash(&mut state);
+		for texture in &self.textures {
+			texture.format.extensions_str().content_hash(&mut state);
+			hash_len(texture.data.len(), &mut state);
+			state.write(&texture.data);
+		}
+		#[cfg(feature = "json")]
+		for vendor in &self.vendors {
+			vendor.name.content_hash(&mut state);
+			vendor.payload.dump().content_hash(&mut state);
+		}
+
+		state.finish()
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use std::hash::Hasher;
+
+	use crate::formats::fixtures;
+	use crate::model::Model;
+
+	use super::{ContentHash, Fnv1a};
+
+	fn model(payload: &json::JsonValue) -> Model {
+		Model {
+			puppet: fixtures::load(payload),
+			textures: Vec::new(),
+			vendors: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn content_hash() {
+		let payload = fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![fixtures::param(
+				10,
+				"move",
+				vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 10.0.into()])],
+			)],
+		);
+		assert_eq!(model(&payload).content_hash(), model(&payload).content_hash());
+
+		let mut moved_vertex = payload.clone();
+		moved_vertex["nodes"]["children"][0]["mesh"]["verts"][0] = 0.5.into();
+		assert_ne!(model(&payload).content_hash(), model(&moved_vertex).content_hash());
+
+		let mut rebound = payload.clone();
+		rebound["param"][0]["bindings"][0]["values"][1][0] = 20.0.into();
+		assert_ne!(model(&payload).content_hash(), model(&rebound).content_hash());
+	}
+
+	#[test]
+	fn content_hash_is_pinned() {
+		// hashes key persisted caches, so the encoding may only change on purpose
+		let payload = fixtures::puppet(fixtures::node(0, "root", vec![fixtures::part(1, "part")]), Vec::new());
+		assert_eq!(model(&payload).content_hash(), 0x86f5_c77c_85a7_2d0d);
+	}
+
+	#[test]
+	fn strings_hash_by_length_and_bytes() {
+		let hash = |strings: &[&str]| {
+			let mut state = Fnv1a::default();
+			strings.iter().for_each(|string| string.content_hash(&mut state));
+			state.finish()
+		};
+		assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+		assert_ne!(hash(&["", "a"]), hash(&["a", ""]));
+
+		let mut state = Fnv1a::default();
+		state.write_u64(2);
+		state.write(b"ab");
+		assert_eq!(hash(&["ab"]), state.finish());
+	}
+}
@@ -0,0 +1,96 @@
+use crate::puppet::meta::PuppetUsageRights;
+#[cfg(feature = "decode-textures")]
+use crate::texture::decode_texture;
+use crate::texture::ShallowTexture;
+
+use super::Model;
+
+/// Everything needed to credit the authors of a model and show its terms of use, e.g. in an attribution dialog.
+pub struct LicenseInfo<'model> {
+	pub rights: Option<&'model PuppetUsageRights>,
+	pub copyright: Option<&'model str>,
+	pub license_url: Option<&'model str>,
+	pub contact: Option<&'model str>,
+	/// Decoded thumbnail, if the model has one and it could be decoded. Always `None` without the `decode-textures` feature.
+	pub thumbnail: Option<ShallowTexture>,
+}
+
+impl Model {
+	/// License related metadata of the puppet, along with its decoded thumbnail.
+	pub fn license_info(&self) -> LicenseInfo<'_> {
+		let meta = &self.puppet.meta;
+
+		#[cfg(feature = "decode-textures")]
+		let thumbnail = meta.thumbnail_id.and_then(|id| {
+			let Some(texture) = self.textures.get(id as usize) else {
+				tracing::warn!("Thumbnail texture {id} does not exist");
+				return None;
+			};
+			decode_texture(texture.clone())
+				.inspect_err(|e| tracing::warn!("Could not decode thumbnail texture {id}: {e}"))
+				.ok()
+		});
+		#[cfg(not(feature = "decode-textures"))]
+		let thumbnail = None;
+
+		LicenseInfo {
+			rights: meta.rights.as_ref(),
+			copyright: meta.copyright.as_deref(),
+			license_url: meta.license_url.as_deref(),
+			contact: meta.contact.as_deref(),
+			thumbnail,
+		}
+	}
+}
+
+#[cfg(all(test, feature = "decode-textures", feature = "json"))]
+mod tests {
+	use std::io::Cursor;
+
+	use image::{ImageBuffer, ImageFormat, Rgba};
+
+	use crate::formats::fixtures;
+	use crate::model::{Model, ModelTexture};
+
+	#[test]
+	fn license_info_matches_meta() {
+		let mut payload = fixtures::puppet(fixtures::node(0, "root", Vec::new()), Vec::new());
+		payload["meta"]["copyright"] = "(c) Someone".into();
+		payload["meta"]["licenseURL"] = "https://example.com/license".into();
+		payload["meta"]["thumbnailId"] = 1.into();
+		payload["meta"]["rights"] = json::object! {
+			allowed_users: "Everyone",
+			allow_violence: false,
+			allow_sexual: false,
+			allow_commercial: true,
+			allow_redistribution: "ViralLicense",
+			allow_modification: "AllowPersonal",
+			require_attribution: true,
+		};
+
+		let mut thumbnail = Vec::new();
+		ImageBuffer::<Rgba<u8>, _>::new(3, 2)
+			.write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Png)
+			.unwrap();
+		let thumbnail = ModelTexture {
+			format: ImageFormat::Png,
+			data: thumbnail.into(),
+		};
+		let model = Model {
+			puppet: fixtures::load(&payload),
+			textures: vec![thumbnail.clone(), thumbnail],
+			vendors: Vec::new(),
+		};
+
+		let meta = &model.puppet.meta;
+		let info = model.license_info();
+		assert!(std::ptr::eq(info.rights.unwrap(), meta.rights.as_ref().unwrap()));
+		assert!(info.rights.unwrap().require_attribution);
+		assert_eq!(info.copyright, meta.copyright.as_deref());
+		assert_eq!(info.copyright, Some("(c) Someone"));
+		assert_eq!(info.license_url, meta.license_url.as_deref());
+		assert_eq!(info.contact, None);
+		let thumbnail = info.thumbnail.unwrap();
+		assert_eq!((thumbnail.width(), thumbnail.height()), (3, 2));
+	}
+}
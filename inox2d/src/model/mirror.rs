@@ -0,0 +1,135 @@
+use crate::node::components::{Mesh, RestDeform};
+use crate::params::BindingValues;
+use crate::render::RenderCtx;
+
+use super::Model;
+
+impl Model {
+	/// Mirror the puppet horizontally, around the X origin of the puppet.
+	///
+	/// Meshes, node transforms and param bindings are all mirrored, so the mirrored puppet animates as the mirror image of the original.
+	/// Textures stay the same, as UVs move along with their mirrored vertices.
+	/// Triangle winding is reversed to keep faces pointing the same way.
+	///
+	/// Physics simulation is not mirrored: pendulums still swing in their original directions.
+	pub fn mirror_x(&mut self) {
+		let puppet = &mut self.puppet;
+
+		let ids = puppet.nodes.iter().map(|node| node.uuid).collect::<Vec<_>>();
+		for id in ids {
+			// mirroring a transform is conjugating it by the reflection:
+			// X translation flips, rotations around Y and Z reverse, rotation around X and scale are unaffected
+			let transform = &mut puppet.nodes.get_node_mut(id).unwrap().trans_offset;
+			transform.translation.x = -transform.translation.x;
+			transform.rotation.y = -transform.rotation.y;
+			transform.rotation.z = -transform.rotation.z;
+
+			if let Some(mesh) = puppet.node_comps.get_mut::<Mesh>(id) {
+				mesh.vertices.iter_mut().for_each(|vertex| vertex.x = -vertex.x);
+				mesh.origin.x = -mesh.origin.x;
+				mesh.indices
+					.chunks_exact_mut(3)
+					.for_each(|triangle| triangle.swap(1, 2));
+			}
+			if let Some(rest) = puppet.node_comps.get_mut::<RestDeform>(id) {
+				rest.deforms.iter_mut().for_each(|offset| offset.x = -offset.x);
+			}
+		}
+
+		for param in puppet.params.values_mut() {
+			for binding in &mut param.bindings {
+				match &mut binding.values {
+					BindingValues::TransformTX(values)
+					| BindingValues::TransformRY(values)
+					| BindingValues::TransformRZ(values) => {
+						values.as_mut_slice().iter_mut().for_each(|value| *value = -*value);
+					}
+					BindingValues::Deform(values) => {
+						for deform in values.as_mut_slice() {
+							deform.iter_mut().for_each(|offset| offset.x = -offset.x);
+						}
+					}
+					BindingValues::ZSort(_)
+					| BindingValues::TransformTY(_)
+					| BindingValues::TransformSX(_)
+					| BindingValues::TransformSY(_)
+					| BindingValues::TransformRX(_)
+					| BindingValues::Opacity(_) => (),
+				}
+			}
+		}
+
+		// vertex buffers hold copies of meshes
+		if puppet.render_ctx.is_some() {
+			RenderCtx::uninstall(&puppet.nodes, &mut puppet.node_comps);
+			puppet.render_ctx = Some(RenderCtx::new(puppet).expect("mirrored meshes fit like the original ones"));
+		}
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::{vec2, Vec2};
+
+	use crate::formats::fixtures;
+	use crate::model::Model;
+	use crate::node::InoxNodeUuid;
+	use crate::render::TexturedMeshRenderCtx;
+
+	/// Bounds of the parts of the puppet, with `param` set to `value`.
+	fn bounds(model: &mut Model, value: f32) -> (Vec2, Vec2) {
+		let puppet = &mut model.puppet;
+		puppet.begin_frame();
+		puppet
+			.param_ctx
+			.as_mut()
+			.unwrap()
+			.set("move", vec2(value, 0.0))
+			.unwrap();
+		puppet.end_frame(0.0);
+
+		let vertices = puppet.flat_mesh().vertices;
+		let render_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(InoxNodeUuid(2)).unwrap();
+		let part = &vertices[render_ctx.vert_offset as usize..][..render_ctx.vert_len];
+		part.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), vertex| {
+			(min.min(*vertex), max.max(*vertex))
+		})
+	}
+
+	#[test]
+	fn mirror_x() {
+		let mut parent = fixtures::node(1, "parent", vec![fixtures::part(2, "part")]);
+		parent["transform"]["trans"] = json::array![10.0, 5.0, 0.0];
+		parent["transform"]["rot"] = json::array![0.0, 0.0, 0.3];
+		let payload = fixtures::puppet(
+			fixtures::node(0, "root", vec![parent]),
+			vec![fixtures::param(
+				10,
+				"move",
+				vec![fixtures::binding(2, "transform.t.x", [0.0.into(), 4.0.into()])],
+			)],
+		);
+		let load = |mirrored: bool| {
+			let mut model = Model {
+				puppet: fixtures::load(&payload),
+				textures: Vec::new(),
+				vendors: Vec::new(),
+			};
+			if mirrored {
+				model.mirror_x();
+			}
+			model.puppet.init_transforms();
+			model.puppet.init_rendering();
+			model.puppet.init_params();
+			model
+		};
+		let (mut original, mut mirrored) = (load(false), load(true));
+
+		for value in [0.0, 1.0] {
+			let (min, max) = bounds(&mut original, value);
+			let (mirrored_min, mirrored_max) = bounds(&mut mirrored, value);
+			assert!(mirrored_min.abs_diff_eq(vec2(-max.x, min.y), 1e-4));
+			assert!(mirrored_max.abs_diff_eq(vec2(-min.x, max.y), 1e-4));
+		}
+	}
+}
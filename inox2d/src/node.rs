@@ -1,12 +1,33 @@
 pub mod components;
 pub mod drawables;
 
+use glam::Vec3;
+
 use crate::math::transform::TransformOffset;
 
-#[derive(Clone, Copy, Hash, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(transparent)]
 pub struct InoxNodeUuid(pub(crate) u32);
 
+impl InoxNodeUuid {
+	pub fn new(raw: u32) -> Self {
+		Self(raw)
+	}
+
+	pub fn raw(&self) -> u32 {
+		self.0
+	}
+}
+
+/// Layer group a node is organized in by the editor. Does not affect rendering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeGroup {
+	pub id: u32,
+	/// Color the group is labeled with, if any.
+	pub color: Option<Vec3>,
+}
+
 pub struct InoxNode {
 	pub uuid: InoxNodeUuid,
 	pub name: String,
@@ -14,4 +35,5 @@ pub struct InoxNode {
 	pub zsort: f32,
 	pub trans_offset: TransformOffset,
 	pub lock_to_root: bool,
+	pub group: Option<NodeGroup>,
 }
@@ -22,8 +22,12 @@ use crate::texture::TextureId;
 
 /// If has this as a component, the node should composite all children
 ///
-/// Empty as only a marker, zsorted children list constructed later on demand
-pub struct Composite {}
+/// Zsorted children list constructed later on demand
+pub struct Composite {
+	/// Whether a MeshGroup deform reaching this composite should be propagated to its children.
+	// TODO: Nothing to propagate until Meshgroup is implemented.
+	pub propagate_meshgroup: bool,
+}
 
 /* --- DRAWABLE --- */
 
@@ -34,6 +38,18 @@ pub struct Drawable {
 	pub masks: Option<Masks>,
 }
 
+impl Drawable {
+	/// Number of masks of any mode applied to this drawable.
+	pub fn mask_count(&self) -> usize {
+		self.masks.as_ref().map_or(0, |masks| masks.masks.len())
+	}
+
+	/// Whether any mask, of any mode, applies to this drawable.
+	pub fn has_masks(&self) -> bool {
+		self.mask_count() > 0
+	}
+}
+
 pub struct Blending {
 	pub mode: BlendMode,
 	pub tint: Vec3,
@@ -41,7 +57,20 @@ pub struct Blending {
 	pub opacity: f32,
 }
 
-#[derive(Default, PartialEq, Clone, Copy)]
+/// Blending leaving the look of a drawable as-is: white tint, black screen tint, opaque.
+/// Also what parsing falls back to for absent values.
+impl Default for Blending {
+	fn default() -> Self {
+		Self {
+			mode: BlendMode::default(),
+			tint: Vec3::ONE,
+			screen_tint: Vec3::ZERO,
+			opacity: 1.,
+		}
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub enum BlendMode {
 	/// Normal blending mode.
 	#[default]
@@ -57,16 +86,26 @@ pub enum BlendMode {
 	/// Clip to Lower.
 	/// Special blending mode that clips the drawable
 	/// to a lower rendered area.
+	/// In a composite, the lower area includes what is below the composite.
 	ClipToLower,
 	/// Slice from Lower.
 	/// Special blending mode that slices the drawable
 	/// via a lower rendered area.
 	/// (Basically inverse ClipToLower.)
+	/// In a composite, the lower area includes what is below the composite.
 	SliceFromLower,
+	/// Overlay.
+	Overlay,
+	/// Darken.
+	Darken,
+	/// Lighten.
+	Lighten,
+	/// Hard Light.
+	HardLight,
 }
 
 impl BlendMode {
-	pub const VALUES: [BlendMode; 7] = [
+	pub const VALUES: [BlendMode; 11] = [
 		BlendMode::Normal,
 		BlendMode::Multiply,
 		BlendMode::ColorDodge,
@@ -74,7 +113,19 @@ impl BlendMode {
 		BlendMode::Screen,
 		BlendMode::ClipToLower,
 		BlendMode::SliceFromLower,
+		BlendMode::Overlay,
+		BlendMode::Darken,
+		BlendMode::Lighten,
+		BlendMode::HardLight,
 	];
+
+	/// Whether the mode cannot be done with fixed function blending, and needs to read the color below in a shader instead.
+	pub fn reads_backdrop(self) -> bool {
+		matches!(
+			self,
+			BlendMode::Overlay | BlendMode::HardLight | BlendMode::Darken | BlendMode::Lighten
+		)
+	}
 }
 
 pub struct Masks {
@@ -99,7 +150,7 @@ pub struct Mask {
 	pub mode: MaskMode,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MaskMode {
 	/// The part should be masked by the drawables specified.
 	Mask,
@@ -171,6 +222,8 @@ pub(crate) struct RigidPendulumCtx {
 /// Physical states for simulating a spring pendulum.
 #[derive(Default)]
 pub(crate) struct SpringPendulumCtx {
+	/// Whether the bob has been placed relative to the anchor yet.
+	pub started: bool,
 	pub state: PhysicsState<4, SpringPendulum>,
 }
 
@@ -181,6 +234,8 @@ pub struct TexturedMesh {
 	pub tex_albedo: TextureId,
 	pub tex_emissive: TextureId,
 	pub tex_bumpmap: TextureId,
+	/// Multiplier of the emissive texture.
+	pub emission_strength: f32,
 }
 
 /* --- MESH --- */
@@ -197,6 +252,30 @@ pub struct Mesh {
 	pub origin: Vec2,
 }
 
+/* --- VERTEX WELDS --- */
+
+/// Vertices of a part welded to vertices of other parts, so that seams between them hold while deforming.
+pub struct VertexWelds {
+	pub links: Vec<WeldedLink>,
+}
+
+/// Weld from vertices of the part holding this link to vertices of `target`.
+pub struct WeldedLink {
+	pub target: InoxNodeUuid,
+	/// For each vertex of the holding part, index of the welded vertex in `target`, if any.
+	pub indices: Vec<Option<u16>>,
+	/// How far welded vertices of the holding part are pulled towards their partner, from 0 (not at all) to 1.
+	pub weight: f32,
+}
+
+/* --- REST DEFORM --- */
+
+/// Deform authored as part of the rest pose of a part, which param deforms add up on top of.
+pub struct RestDeform {
+	/// Offset of each vertex of the part's mesh.
+	pub deforms: Vec<Vec2>,
+}
+
 /* --- DEFORM STACK --- */
 
 /// Source of a deform.
@@ -205,6 +284,8 @@ pub struct Mesh {
 pub(crate) enum DeformSource {
 	Param(ParamUuid),
 	Node(InoxNodeUuid),
+	/// Deltas given with `Puppet::apply_deform()`.
+	External,
 }
 
 /// Internal component solving for deforms of a node.
@@ -218,6 +299,8 @@ pub(crate) struct DeformStack {
 	/// map of (src, (enabled, Deform)).
 	/// On reset, only set enabled to false instead of clearing the map, as deforms from same sources tend to come in every frame.
 	pub(crate) stack: std::collections::HashMap<DeformSource, (bool, Deform)>,
+	/// Rest deform always added to the combined deforms, unaffected by reset and deform scale.
+	pub(crate) baseline: Option<Vec<Vec2>>,
 }
 
 /* --- TRANSFORM STORE --- */
@@ -231,6 +314,12 @@ pub struct TransformStore {
 	pub relative: TransformOffset,
 }
 
+/* --- OPACITY --- */
+
+/// Internal component keeping the opacity a drawable is authored with,
+/// as `Blending::opacity` is reset to it every frame before params multiply it.
+pub(crate) struct RestOpacity(pub f32);
+
 /* --- ZSORT --- */
 
 /// Component holding zsort values that may be modified across frames.
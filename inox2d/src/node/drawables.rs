@@ -14,6 +14,7 @@ use crate::puppet::World;
 pub(crate) enum DrawableKind<'comps> {
 	TexturedMesh(TexturedMeshComponents<'comps>),
 	Composite(CompositeComponents<'comps>),
+	PlainMesh(PlainMeshComponents<'comps>),
 }
 
 /// Pack of components for a TexturedMesh. "Part" in Inochi2D terms.
@@ -25,6 +26,14 @@ pub struct TexturedMeshComponents<'comps> {
 	pub mesh: &'comps Mesh,
 }
 
+/// Pack of components for a mesh without textures. "Mask" in Inochi2D terms, only ever drawn as a mask source.
+pub struct PlainMeshComponents<'comps> {
+	// Only the absolute part of `TransformStore` that the renderer backend may need.
+	pub transform: &'comps Mat4,
+	pub drawable: &'comps Drawable,
+	pub mesh: &'comps Mesh,
+}
+
 /// Pack of components for a Composite node.
 pub struct CompositeComponents<'comps> {
 	// Only the absolute part of `TransformStore` that the renderer backend may need.
@@ -39,10 +48,7 @@ impl<'comps> DrawableKind<'comps> {
 	///
 	/// If `check`, will send a warning to `tracing` if component combination non-standard for a supposed-to-be Drawable node.
 	pub(crate) fn new(id: InoxNodeUuid, comps: &'comps World, check: bool) -> Option<Self> {
-		let drawable = match comps.get::<Drawable>(id) {
-			Some(drawable) => drawable,
-			None => return None,
-		};
+		let drawable = comps.get::<Drawable>(id)?;
 		let transform = &comps
 			.get::<TransformStore>(id)
 			.expect("A drawble must have an associated transform.")
@@ -67,15 +73,22 @@ impl<'comps> DrawableKind<'comps> {
 						.expect("A TexturedMesh must have an associated Mesh."),
 				}))
 			}
-			(false, false) => {
-				if check {
-					tracing::warn!(
-						"Node {} as a Drawable has neither TexturedMesh nor Composite, skipping.",
-						id.0
-					);
+			(false, false) => match comps.get::<Mesh>(id) {
+				Some(mesh) => Some(DrawableKind::PlainMesh(PlainMeshComponents {
+					transform,
+					drawable,
+					mesh,
+				})),
+				None => {
+					if check {
+						tracing::warn!(
+							"Node {} as a Drawable has neither TexturedMesh, Composite nor Mesh, skipping.",
+							id.0
+						);
+					}
+					None
 				}
-				None
-			}
+			},
 			(true, false) => Some(DrawableKind::TexturedMesh(TexturedMeshComponents {
 				transform,
 				drawable,
@@ -4,11 +4,14 @@ use glam::{vec2, Vec2};
 
 use crate::math::{
 	deform::Deform,
-	interp::{bi_interpolate_f32, bi_interpolate_vec2s_additive, InterpRange, InterpolateMode},
+	interp::{
+		bi_cubic_interpolate_f32, bi_cubic_interpolate_vec2s_additive, bi_interpolate_f32,
+		bi_interpolate_vec2s_additive, cubic_weights, InterpRange, InterpolateMode,
+	},
 	matrix::Matrix2d,
 };
 use crate::node::{
-	components::{DeformSource, DeformStack, Mesh, TransformStore, ZSort},
+	components::{DeformSource, DeformStack, Drawable, Mesh, TransformStore, ZSort},
 	InoxNodeUuid,
 };
 use crate::puppet::{Puppet, World};
@@ -32,8 +35,8 @@ pub enum BindingValues {
 	TransformRY(Matrix2d<f32>),
 	TransformRZ(Matrix2d<f32>),
 	Deform(Matrix2d<Vec<Vec2>>),
-	// TODO
-	Opacity,
+	/// Multiplies the opacity of a drawable.
+	Opacity(Matrix2d<f32>),
 }
 
 #[derive(Debug, Clone)]
@@ -54,9 +57,58 @@ fn ranges_out(
 	(out_top, out_btm)
 }
 
+/// Indices of the four axis points used to cubic interpolate between `mindex` and `maxdex`, repeating the ends of the axis
+/// in place of missing neighbors, and their cubic weights for `t`.
+fn cubic_axis_weights(axis_points: &[f32], mindex: usize, maxdex: usize, t: f32) -> ([usize; 4], [f32; 4]) {
+	let indices = [
+		mindex.saturating_sub(1),
+		mindex,
+		maxdex,
+		(maxdex + 1).min(axis_points.len() - 1),
+	];
+	(indices, cubic_weights(t, indices.map(|i| axis_points[i])))
+}
+
+/// Indices of the two axis points surrounding `t`. Out of the axis, the first or last two points are picked.
+fn axis_point_indices(axis_points: &[f32], t: f32) -> (usize, usize) {
+	let last_idx = axis_points.len() - 1;
+
+	match axis_points.binary_search_by(|a| a.total_cmp(&t)) {
+		Ok(_) | Err(_) if last_idx == 0 => (last_idx, last_idx),
+		Ok(ind) if ind >= last_idx => (last_idx - 1, last_idx),
+		Ok(ind) => (ind, ind + 1),
+		Err(0) => (0, 1),
+		Err(ind) if ind > last_idx => (last_idx - 1, last_idx),
+		Err(ind) => (ind - 1, ind),
+	}
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ParamUuid(pub u32);
 
+/// How param values out of `[min, max]` are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParamClampMode {
+	/// Clamp the value into range.
+	#[default]
+	Clamp,
+	/// Extend the axis segment closest to the value, continuing its trend.
+	Extrapolate,
+}
+
+/// Which axis points a param value falls between, and how far along, as used to interpolate bindings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugWeights {
+	/// Index of the X axis point before the value. The one after is `x_index + 1`, if any.
+	pub x_index: usize,
+	/// Index of the Y axis point before the value. The one after is `y_index + 1`, if any.
+	pub y_index: usize,
+	/// Blend factor from X axis point `x_index` (0) to the next one (1).
+	pub x_weight: f32,
+	/// Blend factor from Y axis point `y_index` (0) to the next one (1).
+	pub y_weight: f32,
+}
+
 /// Parameter. A simple bounded value that is used to animate nodes through bindings.
 pub struct Param {
 	pub uuid: ParamUuid,
@@ -67,44 +119,58 @@ pub struct Param {
 	pub defaults: Vec2,
 	pub axis_points: AxisPoints,
 	pub bindings: Vec<Binding>,
+	/// Index of the param in the puppet file.
+	pub(crate) order: usize,
 }
 
 impl Param {
+	/// Index of the param in the puppet file, see `Puppet::params_sorted()`.
+	pub fn order(&self) -> usize {
+		self.order
+	}
+
+	/// Axis points and blend factors bindings are interpolated with for value `val`, clamped into range.
+	///
+	/// Meant for inspecting param blending, e.g. when a deform looks wrong.
+	pub fn debug_weights(&self, val: Vec2) -> DebugWeights {
+		let val_normed = (val.clamp(self.min, self.max) - self.min) / (self.max - self.min);
+
+		let weight = |axis_points: &[f32], t: f32| {
+			let (mindex, maxdex) = axis_point_indices(axis_points, t);
+			let (beg, end) = (axis_points[mindex], axis_points[maxdex]);
+			let weight = if end > beg {
+				((t - beg) / (end - beg)).clamp(0.0, 1.0)
+			} else {
+				0.0
+			};
+			(mindex, weight)
+		};
+		let (x_index, x_weight) = weight(&self.axis_points.x, val_normed.x);
+		let (y_index, y_weight) = weight(&self.axis_points.y, val_normed.y);
+
+		DebugWeights {
+			x_index,
+			y_index,
+			x_weight,
+			y_weight,
+		}
+	}
+
 	/// Internal function that modifies puppet components according to one param set.
 	/// Must be only called ONCE per frame to ensure correct behavior.
 	///
 	/// End users may repeatedly apply a same parameter for multiple times in between frames,
 	/// but other facilities should be present to make sure this `apply()` is only called once per parameter.
-	pub(crate) fn apply(&self, val: Vec2, comps: &mut World) {
-		let val = val.clamp(self.min, self.max);
+	pub(crate) fn apply(&self, val: Vec2, clamp_mode: ParamClampMode, comps: &mut World) {
+		let val = match clamp_mode {
+			ParamClampMode::Clamp => val.clamp(self.min, self.max),
+			ParamClampMode::Extrapolate => val,
+		};
 		let val_normed = (val - self.min) / (self.max - self.min);
 
 		// calculate axis point indexes
-		let (x_mindex, x_maxdex) = {
-			let x_temp = self.axis_points.x.binary_search_by(|a| a.total_cmp(&val_normed.x));
-
-			let last_idx = self.axis_points.x.len() - 1;
-
-			match x_temp {
-				Ok(_) | Err(_) if last_idx == 0 => (last_idx, last_idx),
-				Ok(ind) if ind >= last_idx => (last_idx - 1, last_idx),
-				Ok(ind) => (ind, ind + 1),
-				Err(ind) => (ind - 1, ind),
-			}
-		};
-
-		let (y_mindex, y_maxdex) = {
-			let y_temp = self.axis_points.y.binary_search_by(|a| a.total_cmp(&val_normed.y));
-
-			let last_idx = self.axis_points.y.len() - 1;
-
-			match y_temp {
-				Ok(_) | Err(_) if last_idx == 0 => (last_idx, last_idx),
-				Ok(ind) if ind >= last_idx => (last_idx - 1, last_idx),
-				Ok(ind) => (ind, ind + 1),
-				Err(ind) => (ind - 1, ind),
-			}
-		};
+		let (x_mindex, x_maxdex) = axis_point_indices(&self.axis_points.x, val_normed.x);
+		let (y_mindex, y_maxdex) = axis_point_indices(&self.axis_points.y, val_normed.y);
 
 		// Apply offset on each binding
 		for binding in &self.bindings {
@@ -113,76 +179,75 @@ impl Param {
 				vec2(self.axis_points.x[x_maxdex], self.axis_points.y[y_maxdex]),
 			);
 
-			let val_normed = val_normed.clamp(range_in.beg, range_in.end);
+			let val_normed = match clamp_mode {
+				ParamClampMode::Clamp => val_normed.clamp(range_in.beg, range_in.end),
+				ParamClampMode::Extrapolate => val_normed,
+			};
+
+			// cubic interpolation looks at the axis points around the surrounding ones
+			let cubic = (binding.interpolate_mode == InterpolateMode::Cubic).then(|| {
+				(
+					cubic_axis_weights(&self.axis_points.x, x_mindex, x_maxdex, val_normed.x),
+					cubic_axis_weights(&self.axis_points.y, y_mindex, y_maxdex, val_normed.y),
+				)
+			});
+			let interpolate = |matrix: &Matrix2d<f32>| match cubic {
+				Some(((xs, wx), (ys, wy))) => bi_cubic_interpolate_f32(wx, wy, |i, j| matrix[(xs[i], ys[j])]),
+				None => {
+					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+					bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode)
+				}
+			};
 
 			match binding.values {
 				BindingValues::ZSort(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
-					comps.get_mut::<ZSort>(binding.node).unwrap().0 +=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+					comps.get_mut::<ZSort>(binding.node).unwrap().0 += interpolate(matrix);
 				}
 				BindingValues::TransformTX(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
 					comps
 						.get_mut::<TransformStore>(binding.node)
 						.unwrap()
 						.relative
 						.translation
-						.x += bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						.x += interpolate(matrix);
 				}
 				BindingValues::TransformTY(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
 					comps
 						.get_mut::<TransformStore>(binding.node)
 						.unwrap()
 						.relative
 						.translation
-						.y += bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						.y += interpolate(matrix);
 				}
 				BindingValues::TransformSX(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
-					comps.get_mut::<TransformStore>(binding.node).unwrap().relative.scale.x *=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+					comps.get_mut::<TransformStore>(binding.node).unwrap().relative.scale.x *= interpolate(matrix);
 				}
 				BindingValues::TransformSY(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
-					comps.get_mut::<TransformStore>(binding.node).unwrap().relative.scale.y *=
-						bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+					comps.get_mut::<TransformStore>(binding.node).unwrap().relative.scale.y *= interpolate(matrix);
 				}
 				BindingValues::TransformRX(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
 					comps
 						.get_mut::<TransformStore>(binding.node)
 						.unwrap()
 						.relative
 						.rotation
-						.x += bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						.x += interpolate(matrix);
 				}
 				BindingValues::TransformRY(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
 					comps
 						.get_mut::<TransformStore>(binding.node)
 						.unwrap()
 						.relative
 						.rotation
-						.y += bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						.y += interpolate(matrix);
 				}
 				BindingValues::TransformRZ(ref matrix) => {
-					let (out_top, out_bottom) = ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
-
 					comps
 						.get_mut::<TransformStore>(binding.node)
 						.unwrap()
 						.relative
 						.rotation
-						.z += bi_interpolate_f32(val_normed, range_in, out_top, out_bottom, binding.interpolate_mode);
+						.z += interpolate(matrix);
 				}
 				BindingValues::Deform(ref matrix) => {
 					let out_top = InterpRange::new(
@@ -204,14 +269,22 @@ impl Param {
 						let mut direct_deform: Vec<Vec2> = Vec::with_capacity(vert_len);
 						direct_deform.resize(vert_len, Vec2::ZERO);
 
-						bi_interpolate_vec2s_additive(
-							val_normed,
-							range_in,
-							out_top,
-							out_bottom,
-							binding.interpolate_mode,
-							&mut direct_deform,
-						);
+						match cubic {
+							Some(((xs, wx), (ys, wy))) => bi_cubic_interpolate_vec2s_additive(
+								wx,
+								wy,
+								|i, j| matrix[(xs[i], ys[j])].as_slice(),
+								&mut direct_deform,
+							),
+							None => bi_interpolate_vec2s_additive(
+								val_normed,
+								range_in,
+								out_top,
+								out_bottom,
+								binding.interpolate_mode,
+								&mut direct_deform,
+							),
+						}
 
 						direct_deform
 					};
@@ -221,22 +294,67 @@ impl Param {
 						.expect("Nodes being deformed must have a DeformStack component.")
 						.push(DeformSource::Param(self.uuid), Deform::Direct(direct_deform));
 				}
-				// TODO
-				BindingValues::Opacity => {}
+				BindingValues::Opacity(ref matrix) => {
+					if let Some(drawable) = comps.get_mut::<Drawable>(binding.node) {
+						drawable.blending.opacity *= interpolate(matrix);
+					}
+				}
 			}
 		}
 	}
 }
 
+/// Callback notified of the new value of a param, see `Puppet::on_param_changed()`.
+pub type ParamChangedCallback = Box<dyn FnMut(ParamUuid, Vec2) + Send>;
+
 /// Additional struct attached to a puppet for animating through params.
 pub struct ParamCtx {
 	values: HashMap<String, Vec2>,
+	/// How values set out of a param's range are handled.
+	pub clamp_mode: ParamClampMode,
+	/// Values as of the end of the last frame.
+	applied_values: HashMap<String, Vec2>,
+	/// Values as of the last frame, only tracked if there are observers.
+	notified_values: HashMap<String, Vec2>,
+	pub(crate) observers: Vec<ParamChangedCallback>,
 }
 
 impl ParamCtx {
 	pub(crate) fn new(puppet: &Puppet) -> Self {
+		let values: HashMap<String, Vec2> = puppet.params.iter().map(|p| (p.0.to_owned(), p.1.defaults)).collect();
 		Self {
-			values: puppet.params.iter().map(|p| (p.0.to_owned(), p.1.defaults)).collect(),
+			applied_values: values.clone(),
+			notified_values: values.clone(),
+			values,
+			clamp_mode: ParamClampMode::default(),
+			observers: Vec::new(),
+		}
+	}
+
+	/// Remember current values as the ones applied to the puppet, see `Puppet::param_value()`.
+	/// To be called once per frame, after all params are applied.
+	pub(crate) fn record_applied(&mut self) {
+		self.applied_values.clone_from(&self.values);
+	}
+
+	/// Notify observers of every param whose value is different from the one it had when last notified.
+	/// To be called once per frame, after all params are applied.
+	pub(crate) fn notify_changes(&mut self, params: &HashMap<String, Param>) {
+		if self.observers.is_empty() {
+			return;
+		}
+
+		for (name, value) in &self.values {
+			let notified = self.notified_values.entry(name.clone()).or_insert(*value);
+			if notified == value {
+				continue;
+			}
+			*notified = *value;
+
+			let uuid = params.get(name).unwrap().uuid;
+			for observer in &mut self.observers {
+				observer(uuid, *value);
+			}
 		}
 	}
 
@@ -247,6 +365,18 @@ impl ParamCtx {
 		}
 	}
 
+	/// Look up values of param `old_name` by `new_name` from now on.
+	pub(crate) fn rename(&mut self, old_name: &str, new_name: &str) {
+		for values in [&mut self.values, &mut self.applied_values, &mut self.notified_values] {
+			rekey(values, old_name, new_name);
+		}
+	}
+
+	/// Get the value param with name is currently set to.
+	pub fn get(&self, param_name: &str) -> Option<Vec2> {
+		self.values.get(param_name).copied()
+	}
+
 	/// Set param with name to value `val`.
 	pub fn set(&mut self, param_name: &str, val: Vec2) -> Result<(), SetParamError> {
 		if let Some(value) = self.values.get_mut(param_name) {
@@ -257,15 +387,140 @@ impl ParamCtx {
 		}
 	}
 
+	/// Set one axis of param with name to `val`, keeping the other axis at its current value.
+	pub fn set_axis(&mut self, param_name: &str, axis: Axis, val: f32) -> Result<(), SetParamError> {
+		if let Some(value) = self.values.get_mut(param_name) {
+			match axis {
+				Axis::X => value.x = val,
+				Axis::Y => value.y = val,
+			}
+			Ok(())
+		} else {
+			Err(SetParamError::NoParameterNamed(param_name.to_string()))
+		}
+	}
+
 	/// Modify components as specified by all params. Must be called ONCE per frame.
 	pub(crate) fn apply(&self, params: &HashMap<String, Param>, comps: &mut World) {
 		// a correct implementation should not care about the order of `.apply()`
 		for (param_name, val) in self.values.iter() {
-			// TODO: a correct implementation should not fail on param value (0, 0)
-			if *val != Vec2::ZERO {
-				params.get(param_name).unwrap().apply(*val, comps);
-			}
+			params.get(param_name).unwrap().apply(*val, self.clamp_mode, comps);
+		}
+	}
+}
+
+/// Move the value of `old_key` in `map`, if any, to `new_key`.
+pub(crate) fn rekey<V>(map: &mut HashMap<String, V>, old_key: &str, new_key: &str) {
+	if let Some(value) = map.remove(old_key) {
+		map.insert(new_key.to_owned(), value);
+	}
+}
+
+/// An axis of a param.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+	X,
+	Y,
+}
+
+impl Puppet {
+	/// Param with name, if any.
+	pub fn get_param(&self, param_name: &str) -> Option<&Param> {
+		self.params.get(param_name)
+	}
+
+	/// All params, in the order of the puppet file.
+	pub fn params_sorted(&self) -> Vec<&Param> {
+		let mut params = self.params.values().collect::<Vec<_>>();
+		params.sort_by_key(|param| param.order);
+		params
+	}
+
+	/// Mutable access to param with name, e.g. to retune its range or defaults, taking effect from the next frame.
+	/// Its `name` and `uuid` identify it and must be left unchanged, rename it with `.rename_param()` instead.
+	pub fn get_param_mut(&mut self, param_name: &str) -> Option<&mut Param> {
+		self.params.get_mut(param_name)
+	}
+
+	/// Rename param `param_name` to `new_name`. Its value, as well as physics driving it, carry over.
+	pub fn rename_param(&mut self, param_name: &str, new_name: impl Into<String>) -> Result<(), RenameParamError> {
+		let new_name = new_name.into();
+		if new_name == param_name {
+			return match self.params.contains_key(param_name) {
+				true => Ok(()),
+				false => Err(RenameParamError::NoParameterNamed(param_name.to_owned())),
+			};
+		}
+		if self.params.contains_key(&new_name) {
+			return Err(RenameParamError::NameTaken(new_name));
 		}
+		let Some(mut param) = self.params.remove(param_name) else {
+			return Err(RenameParamError::NoParameterNamed(param_name.to_owned()));
+		};
+
+		param.name.clone_from(&new_name);
+		if let Some(param_ctx) = self.param_ctx.as_mut() {
+			param_ctx.rename(param_name, &new_name);
+		}
+		if let Some(physics_ctx) = self.physics_ctx.as_mut() {
+			physics_ctx.rename_param(param.uuid, param_name, &new_name);
+		}
+		self.params.insert(new_name, param);
+		Ok(())
+	}
+
+	/// Value param with name had when the last frame ended, including changes by animations and physics.
+	/// Unlike `ParamCtx::get()`, unaffected by params being reset by `.begin_frame()` or set in the current frame.
+	///
+	/// Panicks if params are not initialized.
+	pub fn param_value(&self, param_name: &str) -> Option<Vec2> {
+		self.param_ctx
+			.as_ref()
+			.expect("Params of a puppet must be initialized before reading their values.")
+			.applied_values
+			.get(param_name)
+			.copied()
+	}
+
+	/// Call `callback` with the new value of every param whose value changed, including by physics,
+	/// once per frame at the end of `.end_frame()`.
+	///
+	/// Panicks if params are not initialized.
+	pub fn on_param_changed(&mut self, callback: ParamChangedCallback) {
+		self.param_ctx
+			.as_mut()
+			.expect("Params of a puppet must be initialized before observing them.")
+			.observers
+			.push(callback);
+	}
+
+	/// Snap the puppet back to its rest pose: params at their defaults, physics at rest, and deforms and transforms
+	/// recomputed, as after initializing a freshly loaded puppet and ending a first frame.
+	/// Observers see the params that change. Playing animations carry on from the next frame.
+	///
+	/// Panicks if params are not initialized.
+	pub fn reset_params(&mut self) {
+		assert!(
+			self.param_ctx.is_some(),
+			"Params of a puppet must be initialized before resetting them."
+		);
+
+		if let Some(physics_ctx) = self.physics_ctx.as_mut() {
+			physics_ctx.reset(&self.nodes, &mut self.node_comps);
+		}
+		self.begin_frame();
+		self.end_frame(0.0);
+	}
+
+	/// Set one axis of a param, e.g. when a tracker only provides one axis of a two-dimensional param.
+	/// The other axis keeps the value it has been set to this frame. See `ParamCtx::set_axis()`.
+	///
+	/// Panicks if params are not initialized.
+	pub fn set_param_axis(&mut self, param_name: &str, axis: Axis, val: f32) -> Result<(), SetParamError> {
+		self.param_ctx
+			.as_mut()
+			.expect("Params of a puppet must be initialized before setting them.")
+			.set_axis(param_name, axis, val)
 	}
 }
 
@@ -275,3 +530,371 @@ pub enum SetParamError {
 	#[error("No parameter named {0}")]
 	NoParameterNamed(String),
 }
+
+/// Possible errors renaming a param.
+#[derive(Debug, thiserror::Error)]
+pub enum RenameParamError {
+	#[error("No parameter named {0}")]
+	NoParameterNamed(String),
+	#[error("A parameter named {0} already exists")]
+	NameTaken(String),
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use std::sync::{Arc, Mutex};
+
+	use glam::vec2;
+
+	use crate::formats::fixtures;
+	use crate::node::{
+		components::{Drawable, TransformStore, ZSort},
+		InoxNodeUuid,
+	};
+	use crate::puppet::Puppet;
+
+	use super::{Axis, ParamClampMode, RenameParamError};
+
+	fn translated_puppet() -> Puppet {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![fixtures::param(
+				10,
+				"move",
+				vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 10.0.into()])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet
+	}
+
+	fn translation_x(puppet: &mut Puppet, val: f32) -> f32 {
+		puppet.begin_frame();
+		puppet.param_ctx.as_mut().unwrap().set("move", vec2(val, 0.0)).unwrap();
+		puppet.end_frame(0.0);
+		let transform = puppet.node_comps.get::<TransformStore>(InoxNodeUuid(1)).unwrap();
+		transform.relative.translation.x
+	}
+
+	#[test]
+	fn clamp_mode() {
+		let mut puppet = translated_puppet();
+
+		assert_eq!(translation_x(&mut puppet, 0.5), 5.0);
+		assert_eq!(translation_x(&mut puppet, 1.5), 10.0);
+
+		puppet.param_ctx.as_mut().unwrap().clamp_mode = ParamClampMode::Extrapolate;
+		assert_eq!(translation_x(&mut puppet, 0.5), 5.0);
+		assert_eq!(translation_x(&mut puppet, 1.5), 15.0);
+		assert_eq!(translation_x(&mut puppet, -0.5), -5.0);
+	}
+
+	#[test]
+	fn set_one_axis() {
+		let mut puppet = translated_puppet();
+		puppet.begin_frame();
+		let param_ctx = puppet.param_ctx.as_mut().unwrap();
+		param_ctx.set("move", vec2(0.25, 0.75)).unwrap();
+
+		puppet.set_param_axis("move", Axis::X, 0.5).unwrap();
+		assert_eq!(puppet.param_ctx.as_ref().unwrap().get("move"), Some(vec2(0.5, 0.75)));
+		puppet.set_param_axis("move", Axis::Y, 0.0).unwrap();
+		assert_eq!(puppet.param_ctx.as_ref().unwrap().get("move"), Some(vec2(0.5, 0.0)));
+		assert!(puppet.set_param_axis("nothing", Axis::X, 0.5).is_err());
+	}
+
+	#[test]
+	fn debug_weights() {
+		let mut param = fixtures::param(10, "move", Vec::new());
+		param["axis_points"][0] = json::array![0.0, 0.5, 1.0];
+		let puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", Vec::new()), vec![param]));
+		let param = &puppet.params["move"];
+
+		let weights = param.debug_weights(vec2(0.25, 0.0));
+		assert_eq!((weights.x_index, weights.x_weight), (0, 0.5));
+		assert_eq!((weights.y_index, weights.y_weight), (0, 0.0));
+
+		let weights = param.debug_weights(vec2(0.5, 0.0));
+		assert_eq!((weights.x_index, weights.x_weight), (1, 0.0));
+		let weights = param.debug_weights(vec2(2.0, 0.0));
+		assert_eq!((weights.x_index, weights.x_weight), (1, 1.0));
+	}
+
+	#[test]
+	fn param_changed_callback() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![
+				fixtures::param(10, "moved", Vec::new()),
+				fixtures::param(11, "still", Vec::new()),
+			],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let changes = Arc::new(Mutex::new(Vec::new()));
+		let observed = changes.clone();
+		puppet.on_param_changed(Box::new(move |uuid, value| {
+			observed.lock().unwrap().push((uuid.0, value))
+		}));
+
+		let mut frame = |moved: f32| {
+			puppet.begin_frame();
+			puppet
+				.param_ctx
+				.as_mut()
+				.unwrap()
+				.set("moved", vec2(moved, 0.0))
+				.unwrap();
+			puppet.end_frame(0.0);
+			std::mem::take(&mut *changes.lock().unwrap())
+		};
+		assert_eq!(frame(0.5), [(10, vec2(0.5, 0.0))]);
+		assert_eq!(frame(0.5), []);
+		assert_eq!(frame(1.0), [(10, vec2(1.0, 0.0))]);
+	}
+
+	#[test]
+	fn param_access() {
+		let mut puppet = translated_puppet();
+		assert!(puppet.get_param("nothing").is_none());
+		assert_eq!(puppet.param_value("move"), Some(vec2(0.0, 0.0)));
+
+		translation_x(&mut puppet, 0.5);
+		assert_eq!(puppet.param_value("move"), Some(vec2(0.5, 0.0)));
+		// not affected by the reset of a new frame
+		puppet.begin_frame();
+		assert_eq!(puppet.param_value("move"), Some(vec2(0.5, 0.0)));
+		assert_eq!(puppet.param_value("nothing"), None);
+
+		puppet.get_param_mut("move").unwrap().max = vec2(0.5, 1.0);
+		assert_eq!(puppet.get_param("move").unwrap().max, vec2(0.5, 1.0));
+		// the range is now 0..0.5
+		assert_eq!(translation_x(&mut puppet, 0.25), 5.0);
+	}
+
+	#[test]
+	fn rename_param() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::part(1, "part"), fixtures::simple_physics(2, "physics", 11)],
+			),
+			vec![
+				fixtures::param(
+					10,
+					"move",
+					vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 10.0.into()])],
+				),
+				fixtures::param(11, "sway", Vec::new()),
+			],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.init_physics();
+		translation_x(&mut puppet, 0.5);
+
+		assert!(matches!(
+			puppet.rename_param("nothing", "slide"),
+			Err(RenameParamError::NoParameterNamed(_))
+		));
+		assert!(matches!(
+			puppet.rename_param("move", "sway"),
+			Err(RenameParamError::NameTaken(_))
+		));
+		puppet.rename_param("move", "slide").unwrap();
+		puppet.rename_param("sway", "swing").unwrap();
+
+		assert!(puppet.get_param("move").is_none());
+		assert_eq!(puppet.get_param("slide").unwrap().name, "slide");
+		assert_eq!(puppet.param_value("slide"), Some(vec2(0.5, 0.0)));
+
+		// set and driven by physics under their new names
+		puppet.begin_frame();
+		puppet
+			.param_ctx
+			.as_mut()
+			.unwrap()
+			.set("slide", vec2(0.25, 0.0))
+			.unwrap();
+		puppet.end_frame(0.1);
+		let transform = puppet.node_comps.get::<TransformStore>(InoxNodeUuid(1)).unwrap();
+		assert_eq!(transform.relative.translation.x, 2.5);
+		assert!(puppet.param_value("swing").is_some());
+	}
+
+	#[test]
+	fn cubic_binding() {
+		let mut binding = fixtures::binding(1, "transform.t.x", [0.0.into(), 0.0.into()]);
+		binding["interpolate_mode"] = "Cubic".into();
+		binding["isSet"] = json::array![[true], [true], [true]];
+		binding["values"] = json::array![[0.0], [10.0], [40.0]];
+		let mut param = fixtures::param(10, "move", vec![binding]);
+		param["axis_points"][0] = json::array![0.0, 0.5, 1.0];
+
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![param],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		// through the axis points, curving in between with the next axis point in sight
+		assert_eq!(translation_x(&mut puppet, 0.5), 10.0);
+		assert_eq!(translation_x(&mut puppet, 1.0), 40.0);
+		assert!((translation_x(&mut puppet, 0.25) - 3.75).abs() < 1e-4);
+		assert!((translation_x(&mut puppet, 0.75) - 23.75).abs() < 1e-4);
+	}
+
+	#[test]
+	fn params_in_file_order() {
+		let names = ["mouth", "eyes", "brows", "angle"];
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", Vec::new()),
+			names
+				.iter()
+				.enumerate()
+				.map(|(i, name)| fixtures::param(10 + i as u32, name, Vec::new()))
+				.collect(),
+		));
+
+		let sorted = puppet.params_sorted();
+		assert_eq!(
+			sorted.iter().map(|param| param.name.as_str()).collect::<Vec<_>>(),
+			names
+		);
+		assert!(std::ptr::eq(sorted[1], puppet.get_param("eyes").unwrap()));
+	}
+
+	#[test]
+	fn opacity_binding() {
+		let mut part = fixtures::part(1, "accessory");
+		part["opacity"] = 0.8.into();
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![part]),
+			vec![fixtures::param(
+				10,
+				"fade",
+				vec![fixtures::binding(1, "opacity", [1.0.into(), 0.0.into()])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let opacity = |puppet: &mut Puppet, val: f32| {
+			puppet.begin_frame();
+			puppet.param_ctx.as_mut().unwrap().set("fade", vec2(val, 0.0)).unwrap();
+			puppet.end_frame(0.0);
+			puppet
+				.node_comps
+				.get::<Drawable>(InoxNodeUuid(1))
+				.unwrap()
+				.blending
+				.opacity
+		};
+		assert_eq!(opacity(&mut puppet, 0.0), 0.8);
+		assert!((opacity(&mut puppet, 0.5) - 0.4).abs() < 1e-6);
+		assert_eq!(opacity(&mut puppet, 1.0), 0.0);
+		// back to the authored opacity, not compounding over frames
+		assert_eq!(opacity(&mut puppet, 0.0), 0.8);
+
+		opacity(&mut puppet, 1.0);
+		assert!(!puppet.is_effectively_visible(InoxNodeUuid(1)));
+
+		// bindings apply at param value 0 too, which fades this one out at rest
+		let mut part = fixtures::part(1, "accessory");
+		part["opacity"] = 0.8.into();
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![part]),
+			vec![fixtures::param(
+				10,
+				"fade",
+				vec![fixtures::binding(1, "opacity", [0.0.into(), 1.0.into()])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		assert_eq!(opacity(&mut puppet, 0.0), 0.0);
+		assert!(!puppet.is_effectively_visible(InoxNodeUuid(1)));
+		assert!((opacity(&mut puppet, 0.5) - 0.4).abs() < 1e-6);
+		assert_eq!(opacity(&mut puppet, 1.0), 0.8);
+		assert_eq!(opacity(&mut puppet, 0.0), 0.0);
+	}
+
+	#[test]
+	fn reset_params() {
+		let load = || {
+			let mut payload = fixtures::puppet(
+				fixtures::node(
+					0,
+					"root",
+					vec![fixtures::part(1, "part"), fixtures::simple_physics(2, "physics", 11)],
+				),
+				vec![
+					fixtures::param(
+						10,
+						"move",
+						vec![
+							fixtures::binding(1, "transform.t.x", [0.0.into(), 10.0.into()]),
+							fixtures::binding(2, "transform.t.x", [0.0.into(), 100.0.into()]),
+						],
+					),
+					fixtures::param(
+						11,
+						"sway",
+						vec![fixtures::binding(1, "zSort", [0.0.into(), 1.0.into()])],
+					),
+				],
+			);
+			payload["param"][0]["defaults"] = json::array![0.25, 0.0];
+			let mut puppet = fixtures::load(&payload);
+			puppet.init_transforms();
+			puppet.init_rendering();
+			puppet.init_params();
+			puppet.init_physics();
+			puppet
+		};
+		let pose = |puppet: &Puppet| {
+			let part = puppet.node_comps.get::<TransformStore>(InoxNodeUuid(1)).unwrap();
+			(
+				["move", "sway"].map(|name| puppet.param_value(name).unwrap()),
+				part.absolute,
+				puppet.node_comps.get::<ZSort>(InoxNodeUuid(1)).unwrap().0,
+			)
+		};
+
+		let mut fresh = load();
+		fresh.begin_frame();
+		fresh.end_frame(0.0);
+
+		let mut puppet = load();
+		for frame in 0..30 {
+			puppet.begin_frame();
+			let param_ctx = puppet.param_ctx.as_mut().unwrap();
+			param_ctx.set("move", vec2((frame % 10) as f32 / 10.0, 0.0)).unwrap();
+			puppet.end_frame(if frame == 0 { 0.0 } else { 1.0 / 60.0 });
+		}
+		assert_ne!(pose(&puppet), pose(&fresh));
+
+		puppet.reset_params();
+		assert_eq!(pose(&puppet), pose(&fresh));
+		assert_eq!(puppet.param_value("move"), Some(vec2(0.25, 0.0)));
+
+		// the swing starts over as well
+		for puppet in [&mut puppet, &mut fresh] {
+			puppet.begin_frame();
+			puppet.param_ctx.as_mut().unwrap().set("move", vec2(1.0, 0.0)).unwrap();
+			puppet.end_frame(1.0 / 60.0);
+		}
+		assert_eq!(pose(&puppet), pose(&fresh));
+	}
+}
@@ -5,16 +5,47 @@ use std::collections::HashMap;
 
 use glam::Vec2;
 
-use crate::node::components::{PhysicsModel, RigidPendulumCtx, SimplePhysics, SpringPendulumCtx, TransformStore};
-use crate::params::ParamUuid;
+use crate::node::components::{
+	PhysicsModel, PhysicsProps, RigidPendulumCtx, SimplePhysics, SpringPendulumCtx, TransformStore,
+};
+use crate::node::InoxNodeUuid;
+use crate::params::{rekey, ParamUuid};
 use crate::puppet::{InoxNodeTree, Puppet, World};
 
+use runge_kutta::IsPhysicsVars;
+
 /// Global physics parameters for the puppet.
 pub struct PuppetPhysics {
 	pub pixels_per_meter: f32,
 	pub gravity: f32,
+	/// Seconds simulated per physics step, whatever the frame rate. Not part of puppet files.
+	///
+	/// Must be positive and finite, the default is used otherwise.
+	pub timestep: f32,
+}
+
+impl PuppetPhysics {
+	/// `.timestep`, if it is a valid duration.
+	fn valid_timestep(&self) -> Option<f32> {
+		(self.timestep > 0. && self.timestep.is_finite()).then_some(self.timestep)
+	}
+}
+
+impl Default for PuppetPhysics {
+	/// Same as a new puppet in Inochi2D Creator.
+	fn default() -> Self {
+		Self {
+			pixels_per_meter: 1000.,
+			gravity: 9.8,
+			timestep: 1. / 60.,
+		}
+	}
 }
 
+/// Most physics steps run in one frame. Time beyond that is dropped, so that a long stall is not followed by
+/// frames ever slower to catch up.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
 type SimplePhysicsProps<'a> = (&'a PuppetPhysics, &'a SimplePhysics);
 
 /// Components implementing this will be able to yield a parameter value every frame based on
@@ -58,31 +89,218 @@ impl<T: SimplePhysicsCtx> SimplePhysicsCtxCommon for T {
 	}
 }
 
+impl Puppet {
+	/// The SimplePhysics node driving a param, if any.
+	pub fn physics_node_for_param(&self, param: ParamUuid) -> Option<InoxNodeUuid> {
+		self.nodes.iter().map(|node| node.uuid).find(|&uuid| {
+			self.node_comps
+				.get::<SimplePhysics>(uuid)
+				.is_some_and(|simple_physics| simple_physics.param == param)
+		})
+	}
+
+	/// Tunable properties of a SimplePhysics node. `None` if the node is not one.
+	pub fn physics_props(&self, node: InoxNodeUuid) -> Option<&PhysicsProps> {
+		Some(&self.node_comps.get::<SimplePhysics>(node)?.props)
+	}
+
+	/// Mutable access to properties of a SimplePhysics node, taking effect from the next simulation step.
+	pub fn physics_props_mut(&mut self, node: InoxNodeUuid) -> Option<&mut PhysicsProps> {
+		Some(&mut self.node_comps.get_mut::<SimplePhysics>(node)?.props)
+	}
+}
+
+/// Simulation state of one pendulum.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PendulumSnapshot {
+	Rigid {
+		/// Position of the bob.
+		bob: Vec2,
+		/// Angle and angular velocity.
+		vars: [f32; 2],
+	},
+	Spring {
+		/// Position and velocity of the bob.
+		vars: [f32; 4],
+	},
+}
+
+/// Simulation state of all SimplePhysics nodes of a puppet. See `Puppet::physics_snapshot()`.
+///
+/// With the `serde` feature, it can be serialized to save the state of a scene.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicsSnapshot {
+	/// Time since first simulation step.
+	pub t: f32,
+	/// Elapsed time not simulated yet, less than a timestep.
+	pub accumulator: f32,
+	/// Param values output by the simulation step before the last one, by param name.
+	pub prev_step_values: HashMap<String, Vec2>,
+	/// Param values output by the last simulation step, by param name.
+	pub step_values: HashMap<String, Vec2>,
+	/// Param values last output to the puppet, held while physics is paused, by param name.
+	pub last_values: HashMap<String, Vec2>,
+	/// State of the pendulum of each SimplePhysics node.
+	pub pendulums: HashMap<InoxNodeUuid, PendulumSnapshot>,
+}
+
+impl Puppet {
+	/// Capture the simulation state of all SimplePhysics nodes, to be restored with `.restore_physics_snapshot()`.
+	///
+	/// Panicks if physics is not initialized.
+	pub fn physics_snapshot(&self) -> PhysicsSnapshot {
+		let physics_ctx = self
+			.physics_ctx
+			.as_ref()
+			.expect("Physics of a puppet must be initialized before taking a snapshot.");
+
+		let mut pendulums = HashMap::new();
+		for node in self.nodes.iter() {
+			if let Some(ctx) = self.node_comps.get::<RigidPendulumCtx>(node.uuid) {
+				let vars = ctx.state.vars.get_f32s();
+				pendulums.insert(node.uuid, PendulumSnapshot::Rigid { bob: ctx.bob, vars });
+			} else if let Some(ctx) = self.node_comps.get::<SpringPendulumCtx>(node.uuid) {
+				let vars = ctx.state.vars.get_f32s();
+				pendulums.insert(node.uuid, PendulumSnapshot::Spring { vars });
+			}
+		}
+
+		PhysicsSnapshot {
+			t: physics_ctx.t,
+			accumulator: physics_ctx.accumulator,
+			prev_step_values: physics_ctx.prev_step_values.clone(),
+			step_values: physics_ctx.step_values.clone(),
+			last_values: physics_ctx.last_values.clone(),
+			pendulums,
+		}
+	}
+
+	/// Restore simulation state captured by `.physics_snapshot()`, so that the simulation continues exactly as it did then.
+	/// Nodes missing from either the snapshot or the puppet, or whose physics model changed, are left as is.
+	///
+	/// Panicks if physics is not initialized.
+	pub fn restore_physics_snapshot(&mut self, snapshot: &PhysicsSnapshot) {
+		let physics_ctx = self
+			.physics_ctx
+			.as_mut()
+			.expect("Physics of a puppet must be initialized before restoring a snapshot.");
+		physics_ctx.t = snapshot.t;
+		physics_ctx.accumulator = snapshot.accumulator;
+		physics_ctx.prev_step_values.clone_from(&snapshot.prev_step_values);
+		physics_ctx.step_values.clone_from(&snapshot.step_values);
+		physics_ctx.last_values.clone_from(&snapshot.last_values);
+
+		for (&node, pendulum) in &snapshot.pendulums {
+			match pendulum {
+				PendulumSnapshot::Rigid { bob, vars } => {
+					if let Some(ctx) = self.node_comps.get_mut::<RigidPendulumCtx>(node) {
+						ctx.bob = *bob;
+						ctx.state.vars.set_f32s(*vars);
+					}
+				}
+				PendulumSnapshot::Spring { vars } => {
+					if let Some(ctx) = self.node_comps.get_mut::<SpringPendulumCtx>(node) {
+						ctx.started = true;
+						ctx.state.vars.set_f32s(*vars);
+					}
+				}
+			}
+		}
+	}
+}
+
 /// Additional struct attached to a puppet for executing all physics nodes.
 pub(crate) struct PhysicsCtx {
 	/// Time since first simulation step.
 	t: f32,
+	/// Elapsed time not simulated yet, less than a timestep.
+	accumulator: f32,
 	param_uuid_to_name: HashMap<ParamUuid, String>,
+	/// Param values output by the simulation step before the last one.
+	prev_step_values: HashMap<String, Vec2>,
+	/// Param values output by the last simulation step.
+	step_values: HashMap<String, Vec2>,
+	/// Param values last output to the puppet, held while physics is paused.
+	last_values: HashMap<String, Vec2>,
+	/// Whether the default timestep stands in for an invalid one since the last warning about it.
+	warned_invalid_timestep: bool,
 }
 
 impl PhysicsCtx {
 	/// MODIFIES puppet. In addition to initializing self, installs physics contexts in the World of components
 	pub fn new(puppet: &mut Puppet) -> Self {
 		for node in puppet.nodes.iter() {
-			if let Some(simple_physics) = puppet.node_comps.get::<SimplePhysics>(node.uuid) {
-				match simple_physics.model_type {
-					PhysicsModel::RigidPendulum => puppet.node_comps.add(node.uuid, RigidPendulumCtx::default()),
-					PhysicsModel::SpringPendulum => puppet.node_comps.add(node.uuid, SpringPendulumCtx::default()),
-				}
-			}
+			Self::install(node.uuid, &mut puppet.node_comps);
 		}
 
 		Self {
 			t: 0.,
+			accumulator: 0.,
 			param_uuid_to_name: puppet.params.iter().map(|p| (p.1.uuid, p.0.to_owned())).collect(),
+			prev_step_values: HashMap::new(),
+			step_values: HashMap::new(),
+			last_values: HashMap::new(),
+			warned_invalid_timestep: false,
+		}
+	}
+
+	/// Start the simulation over, from pendulums at rest.
+	pub(crate) fn reset(&mut self, nodes: &InoxNodeTree, comps: &mut World) {
+		self.t = 0.;
+		self.accumulator = 0.;
+		self.prev_step_values.clear();
+		self.step_values.clear();
+		self.last_values.clear();
+
+		for node in nodes.iter() {
+			comps.remove::<RigidPendulumCtx>(node.uuid);
+			comps.remove::<SpringPendulumCtx>(node.uuid);
+			Self::install(node.uuid, comps);
+		}
+	}
+
+	/// Output values of param `uuid`, named `old_name` until now, under `new_name` from now on.
+	pub(crate) fn rename_param(&mut self, uuid: ParamUuid, old_name: &str, new_name: &str) {
+		self.param_uuid_to_name.insert(uuid, new_name.to_owned());
+		for values in [&mut self.prev_step_values, &mut self.step_values, &mut self.last_values] {
+			rekey(values, old_name, new_name);
+		}
+	}
+
+	/// Timestep of `puppet_physics`, or the default one if it is not valid.
+	/// Warns once when it becomes invalid, rather than every frame it stays so.
+	fn timestep(&mut self, puppet_physics: &PuppetPhysics) -> f32 {
+		match puppet_physics.valid_timestep() {
+			Some(timestep) => {
+				self.warned_invalid_timestep = false;
+				timestep
+			}
+			None => {
+				if !std::mem::replace(&mut self.warned_invalid_timestep, true) {
+					tracing::warn!(
+						"Invalid physics timestep {}, using the default one.",
+						puppet_physics.timestep
+					);
+				}
+				PuppetPhysics::default().timestep
+			}
+		}
+	}
+
+	/// Give a SimplePhysics node the simulation context of its model.
+	pub(crate) fn install(node: InoxNodeUuid, comps: &mut World) {
+		if let Some(simple_physics) = comps.get::<SimplePhysics>(node) {
+			match simple_physics.model_type {
+				PhysicsModel::RigidPendulum => comps.add(node, RigidPendulumCtx::default()),
+				PhysicsModel::SpringPendulum => comps.add(node, SpringPendulumCtx::default()),
+			}
 		}
 	}
 
+	/// Advance the simulation by `dt` seconds of real time, in fixed steps of `puppet_physics.timestep`.
+	/// Output param values are interpolated between the last two steps by the time left over.
 	pub fn step(
 		&mut self,
 		puppet_physics: &PuppetPhysics,
@@ -90,14 +308,45 @@ impl PhysicsCtx {
 		comps: &mut World,
 		dt: f32,
 	) -> HashMap<String, Vec2> {
-		let mut values_to_apply = HashMap::new();
-
+		// paused, or first frame with nothing simulated yet
 		if dt == 0. {
-			return values_to_apply;
+			return self.last_values.clone();
 		} else if dt < 0. {
 			panic!("Time travel has happened.");
 		}
 
+		let timestep = self.timestep(puppet_physics);
+		self.accumulator = (self.accumulator + dt).min(timestep * MAX_STEPS_PER_FRAME as f32);
+		while self.accumulator >= timestep {
+			let values = self.simulate(puppet_physics, nodes, comps, timestep);
+			self.prev_step_values = std::mem::replace(&mut self.step_values, values);
+			self.accumulator -= timestep;
+		}
+
+		let alpha = self.accumulator / timestep;
+		let values_to_apply: HashMap<String, Vec2> = self
+			.step_values
+			.iter()
+			.map(|(name, &value)| {
+				let prev_value = self.prev_step_values.get(name).copied().unwrap_or(value);
+				(name.clone(), prev_value.lerp(value, alpha))
+			})
+			.collect();
+
+		self.last_values.clone_from(&values_to_apply);
+		values_to_apply
+	}
+
+	/// Run one simulation step of `dt` seconds.
+	fn simulate(
+		&mut self,
+		puppet_physics: &PuppetPhysics,
+		nodes: &InoxNodeTree,
+		comps: &mut World,
+		dt: f32,
+	) -> HashMap<String, Vec2> {
+		let mut values_to_apply = HashMap::new();
+
 		for node in nodes.iter() {
 			if let Some(simple_physics) = comps.get::<SimplePhysics>(node.uuid) {
 				// before we use some Rust dark magic so that two components can be mutably borrowed at the same time,
@@ -136,3 +385,183 @@ impl PhysicsCtx {
 		values_to_apply
 	}
 }
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::{vec2, Vec2};
+
+	use super::IsPhysicsVars;
+	use crate::formats::fixtures;
+	use crate::node::components::{SpringPendulumCtx, TransformStore};
+	use crate::params::ParamUuid;
+	use crate::puppet::Puppet;
+
+	fn swinging_puppet() -> Puppet {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::simple_physics(1, "physics", 11)]),
+			vec![
+				fixtures::param(
+					10,
+					"move",
+					vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 100.0.into()])],
+				),
+				fixtures::param(11, "sway", Vec::new()),
+			],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.init_physics();
+		puppet
+	}
+
+	/// Move the physics node back and forth, returning the last physics output.
+	fn swing(puppet: &mut Puppet) -> Vec2 {
+		for frame in 0..30 {
+			puppet.begin_frame();
+			let param_ctx = puppet.param_ctx.as_mut().unwrap();
+			param_ctx.set("move", vec2((frame % 10) as f32 / 10.0, 0.0)).unwrap();
+			puppet.end_frame(if frame == 0 { 0.0 } else { 1.0 / 60.0 });
+		}
+		puppet.param_ctx.as_ref().unwrap().get("sway").unwrap()
+	}
+
+	#[test]
+	fn tune_physics_props() {
+		let mut puppet = swinging_puppet();
+		let node = puppet.physics_node_for_param(ParamUuid(11)).unwrap();
+		assert!(puppet.physics_node_for_param(ParamUuid(10)).is_none());
+		assert_eq!(puppet.physics_props(node).unwrap().frequency, 1.0);
+
+		let mut tuned = swinging_puppet();
+		tuned.physics_props_mut(node).unwrap().frequency = 5.0;
+		assert_eq!(tuned.physics_props(node).unwrap().frequency, 5.0);
+
+		let (output, tuned_output) = (swing(&mut puppet), swing(&mut tuned));
+		assert!(output.is_finite() && tuned_output.is_finite());
+		assert_ne!(output, tuned_output);
+	}
+
+	#[test]
+	fn frozen_physics() {
+		let mut puppet = swinging_puppet();
+		let swung = swing(&mut puppet);
+		let physics_node = puppet.physics_node_for_param(ParamUuid(11)).unwrap();
+		let pendulum_state = |puppet: &Puppet| {
+			let ctx = puppet.node_comps.get::<SpringPendulumCtx>(physics_node).unwrap();
+			ctx.state.vars.get_f32s()
+		};
+		let state = pendulum_state(&puppet);
+
+		puppet.begin_frame();
+		puppet.param_ctx.as_mut().unwrap().set("move", vec2(1.0, 0.0)).unwrap();
+		puppet.end_frame_with(1.0 / 60.0, 0.0);
+
+		// animation applies, physics holds
+		let transform = puppet.node_comps.get::<TransformStore>(physics_node).unwrap();
+		assert_eq!(transform.relative.translation.x, 100.0);
+		assert_eq!(pendulum_state(&puppet), state);
+		assert_eq!(puppet.param_ctx.as_ref().unwrap().get("sway").unwrap(), swung);
+		assert_eq!(puppet.frame_delta().physics, 0.0);
+	}
+
+	#[test]
+	fn restore_physics_snapshot() {
+		/// Sway param values while moving the physics node, starting from the current state.
+		fn trajectory(puppet: &mut Puppet) -> Vec<Vec2> {
+			(0..20)
+				.map(|frame| {
+					puppet.begin_frame();
+					let param_ctx = puppet.param_ctx.as_mut().unwrap();
+					param_ctx.set("move", vec2(frame as f32 / 20.0, 0.0)).unwrap();
+					puppet.end_frame(1.0 / 60.0);
+					puppet.param_ctx.as_ref().unwrap().get("sway").unwrap()
+				})
+				.collect()
+		}
+
+		let mut puppet = swinging_puppet();
+		swing(&mut puppet);
+		let snapshot = puppet.physics_snapshot();
+		let expected = trajectory(&mut puppet);
+
+		// perturb
+		swing(&mut puppet);
+		assert_ne!(puppet.physics_snapshot(), snapshot);
+
+		puppet.restore_physics_snapshot(&snapshot);
+		assert_eq!(puppet.physics_snapshot(), snapshot);
+		assert_eq!(trajectory(&mut puppet), expected);
+	}
+
+	#[cfg(all(feature = "serde", feature = "serde_json"))]
+	#[test]
+	fn serialize_physics_snapshot() {
+		let mut puppet = swinging_puppet();
+		swing(&mut puppet);
+		let snapshot = puppet.physics_snapshot();
+
+		let saved = serde_json::to_string(&snapshot).unwrap();
+		assert_eq!(
+			serde_json::from_str::<super::PhysicsSnapshot>(&saved).unwrap(),
+			snapshot
+		);
+	}
+
+	#[test]
+	fn fixed_timestep() {
+		/// Sway param value after one second at `fps`, the pendulum being released from the side.
+		fn sway_after_a_second(fps: u32) -> Vec2 {
+			let mut puppet = swinging_puppet();
+			let physics_node = puppet.physics_node_for_param(ParamUuid(11)).unwrap();
+			let length = puppet.physics_props(physics_node).unwrap().length;
+			let ctx = puppet.node_comps.get_mut::<SpringPendulumCtx>(physics_node).unwrap();
+			ctx.started = true;
+			ctx.state.vars.bob_pos = vec2(length, 0.0);
+
+			for frame in 0..=fps {
+				puppet.begin_frame();
+				puppet.end_frame(if frame == 0 { 0.0 } else { 1.0 / fps as f32 });
+			}
+			puppet.param_ctx.as_ref().unwrap().get("sway").unwrap()
+		}
+
+		let sway = sway_after_a_second(60);
+		assert_ne!(sway, Vec2::ZERO);
+		assert_eq!(sway_after_a_second(30), sway);
+		assert!(sway_after_a_second(144).abs_diff_eq(sway, 1e-2));
+	}
+
+	#[test]
+	fn stall_catch_up_is_bounded() {
+		let mut puppet = swinging_puppet();
+		swing(&mut puppet);
+		let t = puppet.physics_ctx.as_ref().unwrap().t;
+
+		puppet.begin_frame();
+		puppet.end_frame(5.0);
+
+		let physics_ctx = puppet.physics_ctx.as_ref().unwrap();
+		let timestep = puppet.physics.timestep;
+		assert!(physics_ctx.t - t <= timestep * super::MAX_STEPS_PER_FRAME as f32 + 1e-4);
+		assert!(physics_ctx.accumulator < timestep);
+		assert!(puppet.param_ctx.as_ref().unwrap().get("sway").unwrap().is_finite());
+	}
+
+	#[test]
+	fn invalid_timestep() {
+		let expected = swing(&mut swinging_puppet());
+		for timestep in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+			let mut puppet = swinging_puppet();
+			puppet.physics.timestep = timestep;
+			// does not hang, and simulates as with the default timestep
+			assert_eq!(swing(&mut puppet), expected);
+			assert!(puppet.physics_ctx.as_ref().unwrap().warned_invalid_timestep);
+
+			// warns again once invalid after being valid
+			puppet.physics.timestep = 1. / 30.;
+			swing(&mut puppet);
+			assert!(!puppet.physics_ctx.as_ref().unwrap().warned_invalid_timestep);
+		}
+	}
+}
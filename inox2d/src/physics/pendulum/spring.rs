@@ -36,6 +36,13 @@ impl Pendulum for SpringPendulumCtx {
 	}
 
 	fn tick(&mut self, props: &SimplePhysicsProps, anchor: Vec2, t: f32, dt: f32) -> Vec2 {
+		// Start hanging still at rest below the anchor
+		if !self.started {
+			self.started = true;
+			self.state.vars.bob_pos = anchor + vec2(0., props.1.props.length);
+			self.state.vars.bob_vel = Vec2::ZERO;
+		}
+
 		// Run the spring pendulum simulation
 		self.state.tick(&eval, (props.0, &props.1.props), &anchor, t, dt);
 
@@ -46,6 +53,8 @@ impl Pendulum for SpringPendulumCtx {
 /// Acceleration of bob caused by both
 /// - gravity.
 /// - damped oscillation of the spring-bob system in the radial direction.
+///
+/// Same as the reference Inochi2D implementation, damping included, except for a bob right on the anchor.
 fn eval(
 	state: &mut PhysicsState<4, SpringPendulum>,
 	&(puppet_physics, props): &(&PuppetPhysics, &PhysicsProps),
@@ -58,11 +67,13 @@ fn eval(
 	let spring_ksqrt = props.frequency * 2. * PI;
 	let spring_k = spring_ksqrt.powi(2);
 
+	// Stretched by gravity, the spring hangs at `props.length`
 	let g = props.gravity * puppet_physics.pixels_per_meter * puppet_physics.gravity;
 	let rest_length = props.length - g / spring_k;
 
+	// Straight down if the bob is on the anchor, where the reference divides by zero
 	let off_pos = state.vars.bob_pos - *anchor;
-	let off_pos_norm = off_pos.normalize();
+	let off_pos_norm = off_pos.try_normalize().unwrap_or(Vec2::Y);
 
 	let length_ratio = g / props.length;
 	let crit_damp_angle = 2. * length_ratio.sqrt();
@@ -91,3 +102,54 @@ fn eval(
 
 	state.derivatives.bob_vel = dd_bob;
 }
+
+#[cfg(test)]
+mod tests {
+	use glam::{vec2, Vec2};
+
+	use super::Pendulum;
+	use crate::node::components::{PhysicsModel, PhysicsParamMapMode, PhysicsProps, SimplePhysics, SpringPendulumCtx};
+	use crate::params::ParamUuid;
+	use crate::physics::PuppetPhysics;
+
+	#[test]
+	fn spring_pendulum_settles() {
+		let puppet_physics = PuppetPhysics::default();
+		let simple_physics = SimplePhysics {
+			param: ParamUuid(0),
+			model_type: PhysicsModel::SpringPendulum,
+			map_mode: PhysicsParamMapMode::AngleLength,
+			props: PhysicsProps {
+				length: 100.,
+				..Default::default()
+			},
+			local_only: false,
+		};
+		let props = &(&puppet_physics, &simple_physics);
+
+		let anchor = vec2(50., 20.);
+		let rest = anchor + vec2(0., 100.);
+		let mut ctx = SpringPendulumCtx::default();
+
+		// starts hanging still
+		let bob = ctx.tick(props, anchor, 0., 0.01);
+		assert!(bob.distance(rest) < 1e-2);
+
+		// released from the side
+		ctx.set_bob(anchor + vec2(100., 0.));
+		let mut t = 0.;
+		let mut bob = Vec2::ZERO;
+		for _ in 0..1000 {
+			bob = ctx.tick(props, anchor, t, 0.01);
+			t += 0.01;
+			assert!(bob.is_finite());
+			assert!(bob.distance(anchor) < 300.);
+		}
+		assert!(bob.distance(rest) < 1.);
+
+		// pushed down from right on the anchor, instead of stuck on failed steps
+		ctx.set_bob(anchor);
+		let bob = ctx.tick(props, anchor, t, 0.01);
+		assert!(bob.y > anchor.y, "{bob}");
+	}
+}
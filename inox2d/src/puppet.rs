@@ -1,25 +1,74 @@
 pub mod meta;
 mod transforms;
 mod tree;
+mod validate;
 mod world;
 
 use std::collections::HashMap;
 
-use crate::node::{InoxNode, InoxNodeUuid};
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::animation::{Animation, AnimationCtx};
+use crate::math::camera::Camera;
+use crate::math::deform::Deform;
+use crate::math::transform::TransformOffset;
+use crate::node::components::{BlendMode, DeformSource, DeformStack, Drawable, Mask, TransformStore};
+use crate::node::{InoxNode, InoxNodeUuid, NodeGroup};
 use crate::params::{Param, ParamCtx};
 use crate::physics::{PhysicsCtx, PuppetPhysics};
-use crate::render::RenderCtx;
+use crate::render::{CompositeRenderCtx, RenderCtx, TexturedMeshRenderCtx, VertexBufferOverflow};
 
 use meta::PuppetMeta;
 use transforms::TransformCtx;
 pub use tree::InoxNodeTree;
+pub use validate::PuppetValidationError;
 pub use world::World;
 
+/// Time elapsed in a frame, separately for animation and physics.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameDelta {
+	pub animation: f32,
+	pub physics: f32,
+}
+
+/// Kind of drawable a layer is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerKind {
+	Part,
+	Composite,
+}
+
+/// A drawable as listed in a layers panel, see `Puppet::layer_list()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerInfo {
+	pub uuid: InoxNodeUuid,
+	pub name: String,
+	pub kind: LayerKind,
+	/// Number of ancestors of the node, `0` being the root.
+	pub depth: usize,
+	pub enabled: bool,
+	pub opacity: f32,
+}
+
+/// Whether `point` lies inside triangle `[a, b, c]` or on its edges, by barycentric coordinates.
+/// Never for a degenerate triangle.
+fn triangle_contains([a, b, c]: [Vec2; 3], point: Vec2) -> bool {
+	let (ab, ac, ap) = (b - a, c - a, point - a);
+	let det = ab.perp_dot(ac);
+	if det == 0.0 {
+		return false;
+	}
+
+	let v = ap.perp_dot(ac) / det;
+	let w = ab.perp_dot(ap) / det;
+	v >= 0.0 && w >= 0.0 && v + w <= 1.0
+}
+
 /// Inochi2D puppet.
 pub struct Puppet {
 	pub meta: PuppetMeta,
-	physics: PuppetPhysics,
-	physics_ctx: Option<PhysicsCtx>,
+	pub(crate) physics: PuppetPhysics,
+	pub(crate) physics_ctx: Option<PhysicsCtx>,
 	pub(crate) nodes: InoxNodeTree,
 	pub(crate) node_comps: World,
 	/// Currently only a marker for if transform/zsort components are initialized.
@@ -29,6 +78,13 @@ pub struct Puppet {
 	pub(crate) params: HashMap<String, Param>,
 	/// Context for animating puppet with parameters. See `.init_params()`
 	pub param_ctx: Option<ParamCtx>,
+	/// Animations of params, by name.
+	pub animations: HashMap<String, Animation>,
+	pub(crate) animation_ctx: AnimationCtx,
+	frame_delta: FrameDelta,
+	deform_scale: f32,
+	/// Deltas given with `.apply_deform()` in the current frame, by node.
+	external_deforms: HashMap<InoxNodeUuid, Vec<Vec2>>,
 }
 
 impl Puppet {
@@ -48,10 +104,56 @@ impl Puppet {
 			render_ctx: None,
 			params,
 			param_ctx: None,
+			animations: HashMap::new(),
+			animation_ctx: AnimationCtx::default(),
+			frame_delta: FrameDelta::default(),
+			deform_scale: 1.,
+			external_deforms: HashMap::new(),
+		}
+	}
+
+	/// A puppet with only a root Node, initialized for rendering. Build it up with `.add_node()`.
+	pub fn empty(meta: PuppetMeta, physics: PuppetPhysics) -> Self {
+		let root = InoxNode {
+			uuid: InoxNodeUuid(0),
+			name: "Root".to_owned(),
+			enabled: true,
+			zsort: 0.,
+			trans_offset: TransformOffset::default(),
+			lock_to_root: false,
+			group: None,
+		};
+
+		let mut puppet = Self::new(meta, physics, root, HashMap::new());
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet
+	}
+
+	/// Add `node` as the last child of `parent`, with its components added by `add_comps`.
+	/// Initialized contexts are updated to include the new node.
+	///
+	/// Panicks if `parent` does not exist, if the uuid of `node` is already taken,
+	/// or if rendering is initialized and the mesh of `node` does not fit in vertex buffers.
+	pub fn add_node(&mut self, parent: InoxNodeUuid, node: InoxNode, add_comps: impl FnOnce(&mut World)) {
+		let id = node.uuid;
+		self.nodes.add(parent, id, node);
+		add_comps(&mut self.node_comps);
+
+		if self.transform_ctx.is_some() {
+			TransformCtx::install(id, &mut self.node_comps);
+		}
+		if self.render_ctx.is_some() {
+			// vertex buffers and draw lists are laid out for the whole puppet
+			RenderCtx::uninstall(&self.nodes, &mut self.node_comps);
+			self.render_ctx = Some(RenderCtx::new(self).unwrap_or_else(|e| panic!("{e}")));
+		}
+		if self.physics_ctx.is_some() {
+			PhysicsCtx::install(id, &mut self.node_comps);
 		}
 	}
 
-	/// Create a copy of node transform/zsort for modification. Panicks on second call.
+	/// Create a copy of node transform/zsort/opacity for modification. Panicks on second call.
 	pub fn init_transforms(&mut self) {
 		if self.transform_ctx.is_some() {
 			panic!("Puppet transforms already initialized.")
@@ -64,7 +166,16 @@ impl Puppet {
 	/// Call this on a freshly loaded puppet if rendering is needed. Panicks:
 	/// - if transforms are not initialized.
 	/// - on second call.
+	/// - if meshes do not fit in vertex buffers, see `.try_init_rendering()`.
 	pub fn init_rendering(&mut self) {
+		if let Err(e) = self.try_init_rendering() {
+			panic!("{e}");
+		}
+	}
+
+	/// Same as `.init_rendering()`, failing instead of panicking if meshes of the puppet do not fit in vertex buffers,
+	/// e.g. for puppets loaded from user files.
+	pub fn try_init_rendering(&mut self) -> Result<(), VertexBufferOverflow> {
 		if self.transform_ctx.is_none() {
 			panic!("Puppet rendering depends on initialized puppet transforms.");
 		}
@@ -72,8 +183,9 @@ impl Puppet {
 			panic!("Puppet already initialized for rendering.");
 		}
 
-		let render_ctx = RenderCtx::new(self);
+		let render_ctx = RenderCtx::new(self)?;
 		self.render_ctx = Some(render_ctx);
+		Ok(())
 	}
 
 	/// Call this on a puppet if params are going to be used. Panicks:
@@ -106,6 +218,336 @@ impl Puppet {
 		self.physics_ctx = Some(physics_ctx);
 	}
 
+	/// Replace this puppet by `new`, typically a freshly re-parsed version of the same file, keeping current param values.
+	///
+	/// Contexts initialized on this puppet are initialized on the new one as well, unless `new` already has them.
+	/// Params are matched by name, then by uuid for renamed params. Params with no match start at their defaults.
+	///
+	/// Renderers created for this puppet must be recreated if meshes or textures changed,
+	/// as they keep the vertex buffers and textures of the old one.
+	pub fn reload_from(&mut self, new: Puppet) {
+		let mut old = std::mem::replace(self, new);
+		self.deform_scale = old.deform_scale;
+		self.animation_ctx = std::mem::take(&mut old.animation_ctx);
+		self.animation_ctx.retain_existing(&self.animations);
+
+		if old.transform_ctx.is_some() && self.transform_ctx.is_none() {
+			self.init_transforms();
+		}
+		if old.render_ctx.is_some() && self.render_ctx.is_none() {
+			self.init_rendering();
+		}
+		if old.param_ctx.is_some() && self.param_ctx.is_none() {
+			self.init_params();
+		}
+		if old.physics_ctx.is_some() && self.physics_ctx.is_none() {
+			self.init_physics();
+		}
+
+		let (Some(old_param_ctx), Some(param_ctx)) = (old.param_ctx.as_mut(), self.param_ctx.as_mut()) else {
+			return;
+		};
+		param_ctx.observers = std::mem::take(&mut old_param_ctx.observers);
+		for (name, param) in &self.params {
+			let old_name = if old.params.contains_key(name) {
+				Some(name)
+			} else {
+				old.params.iter().find(|p| p.1.uuid == param.uuid).map(|p| p.0)
+			};
+
+			if let Some(value) = old_name.and_then(|old_name| old_param_ctx.get(old_name)) {
+				param_ctx
+					.set(name, value)
+					.expect("Param names of self must be present in its own ParamCtx.");
+			}
+		}
+		param_ctx.record_applied();
+	}
+
+	/// Whether a node ends up visible: it and all of its ancestors must be enabled, and not fully transparent.
+	///
+	/// `false` for a node that does not exist.
+	pub fn is_effectively_visible(&self, id: InoxNodeUuid) -> bool {
+		let mut ancestors = self.nodes.ancestors(id).peekable();
+		ancestors.peek().is_some()
+			&& ancestors.all(|node| {
+				node.enabled
+					&& self
+						.node_comps
+						.get::<Drawable>(node.uuid)
+						.is_none_or(|drawable| drawable.blending.opacity > 0.0)
+			})
+	}
+
+	/// All drawables in the order they are drawn as of the last `.end_frame()`, children of a composite right after it.
+	///
+	/// Empty if rendering is not initialized.
+	pub fn layer_list(&self) -> Vec<LayerInfo> {
+		let Some(render_ctx) = &self.render_ctx else {
+			return Vec::new();
+		};
+
+		let layer = |id: InoxNodeUuid| {
+			let node = self.nodes.get_node(id)?;
+			let drawable = self.node_comps.get::<Drawable>(id)?;
+			let kind = match self.node_comps.get::<CompositeRenderCtx>(id) {
+				Some(_) => LayerKind::Composite,
+				None => LayerKind::Part,
+			};
+			Some(LayerInfo {
+				uuid: id,
+				name: node.name.clone(),
+				kind,
+				depth: self.nodes.ancestors(id).count() - 1,
+				enabled: node.enabled,
+				opacity: drawable.blending.opacity,
+			})
+		};
+
+		self.draw_order(render_ctx).filter_map(layer).collect()
+	}
+
+	/// Position of a part in the order all parts are drawn in as of the last `.end_frame()`, counting from `0`.
+	///
+	/// `None` if the node is not a part or rendering is not initialized.
+	pub fn part_draw_index(&self, id: InoxNodeUuid) -> Option<usize> {
+		self.draw_order(self.render_ctx.as_ref()?)
+			.filter(|drawable| self.node_comps.get::<CompositeRenderCtx>(*drawable).is_none())
+			.position(|part| part == id)
+	}
+
+	/// Topmost visible part whose deformed mesh covers point `world` of puppet space, as of the last `.end_frame()`,
+	/// e.g. to select the part under the cursor with `Camera::screen_to_world()`.
+	///
+	/// Parts that are not effectively visible, see `.is_effectively_visible()`, are skipped.
+	/// `None` if no part is hit or rendering is not initialized.
+	pub fn pick(&self, world: Vec2) -> Option<InoxNodeUuid> {
+		let render_ctx = self.render_ctx.as_ref()?;
+		let buffers = &render_ctx.vertex_buffers;
+
+		let parts = self.draw_order(render_ctx).collect::<Vec<_>>();
+		parts.into_iter().rev().find(|id| {
+			let Some(mesh_ctx) = self.node_comps.get::<TexturedMeshRenderCtx>(*id) else {
+				return false;
+			};
+			let Some(transform) = self.node_comps.get::<TransformStore>(*id) else {
+				return false;
+			};
+			if !self.is_effectively_visible(*id) {
+				return false;
+			}
+
+			let position = |index: u16| {
+				let index = index as usize;
+				let vertex = buffers.verts[index] + buffers.deforms[index];
+				transform.absolute.transform_point3(vertex.extend(0.0)).truncate()
+			};
+			let start = mesh_ctx.index_offset as usize;
+			buffers.indices[start..start + mesh_ctx.index_len]
+				.chunks_exact(3)
+				.any(|triangle| {
+					triangle_contains(
+						[position(triangle[0]), position(triangle[1]), position(triangle[2])],
+						world,
+					)
+				})
+		})
+	}
+
+	/// All drawables in draw order, children of a composite right after it.
+	fn draw_order<'a>(&'a self, render_ctx: &'a RenderCtx) -> impl Iterator<Item = InoxNodeUuid> + 'a {
+		render_ctx.root_drawables_zsorted().iter().flat_map(|id| {
+			let children = self
+				.node_comps
+				.get::<CompositeRenderCtx>(*id)
+				.map_or(&[][..], |composite| &composite.zsorted_children_list);
+			std::iter::once(*id).chain(children.iter().copied())
+		})
+	}
+
+	/// Editor layer group of a node. `None` if the node does not exist or is in no group.
+	pub fn node_group(&self, id: InoxNodeUuid) -> Option<NodeGroup> {
+		self.nodes.get_node(id)?.group
+	}
+
+	/// Every mask in the puppet along with the node it masks, in pre-order of masked nodes.
+	pub fn masks(&self) -> impl Iterator<Item = (InoxNodeUuid, &Mask)> {
+		self.nodes.pre_order_iter().flat_map(|node| {
+			self.node_comps
+				.get::<Drawable>(node.uuid)
+				.and_then(|drawable| drawable.masks.as_ref())
+				.into_iter()
+				.flat_map(move |masks| masks.masks.iter().map(move |mask| (node.uuid, mask)))
+		})
+	}
+
+	/// Whether a node is a drawable with masks of any mode. `false` if the node does not exist or is not a drawable.
+	pub fn has_masks(&self, id: InoxNodeUuid) -> bool {
+		self.node_comps.get::<Drawable>(id).is_some_and(Drawable::has_masks)
+	}
+
+	/// Every drawable in the puppet along with its number of masks, in pre-order.
+	pub fn mask_counts(&self) -> impl Iterator<Item = (InoxNodeUuid, usize)> + '_ {
+		self.nodes.pre_order_iter().filter_map(|node| {
+			let drawable = self.node_comps.get::<Drawable>(node.uuid)?;
+			Some((node.uuid, drawable.mask_count()))
+		})
+	}
+
+	/// Tree of all nodes, e.g. to look nodes up by name.
+	pub fn nodes(&self) -> &InoxNodeTree {
+		&self.nodes
+	}
+
+	/// Names shared by several nodes, sorted, along with these nodes in pre-order.
+	pub fn duplicate_node_names(&self) -> Vec<(String, Vec<InoxNodeUuid>)> {
+		let mut nodes_by_name = HashMap::<&str, Vec<InoxNodeUuid>>::new();
+		for node in self.nodes.pre_order_iter() {
+			nodes_by_name.entry(&node.name).or_default().push(node.uuid);
+		}
+
+		let mut duplicates = nodes_by_name
+			.into_iter()
+			.filter(|(_, uuids)| uuids.len() > 1)
+			.map(|(name, uuids)| (name.to_owned(), uuids))
+			.collect::<Vec<_>>();
+		duplicates.sort_by(|a, b| a.0.cmp(&b.0));
+		duplicates
+	}
+
+	/// Rename a node. `false` if the node does not exist.
+	pub fn rename_node(&mut self, id: InoxNodeUuid, new_name: impl Into<String>) -> bool {
+		match self.nodes.get_node_mut(id) {
+			Some(node) => {
+				node.name = new_name.into();
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Override the blend mode of a drawable, taking effect on the next draw. `false` if the node is not a drawable.
+	pub fn set_node_blend_mode(&mut self, id: InoxNodeUuid, mode: BlendMode) -> bool {
+		match self.node_comps.get_mut::<Drawable>(id) {
+			Some(drawable) => {
+				drawable.blending.mode = mode;
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Offset each vertex of the mesh of part `id` by `deltas` for the current frame, e.g. for deltas computed by face capture.
+	///
+	/// Call it between `.begin_frame()` and `.end_frame()`. Deltas add up with those from params and from other calls
+	/// in the same frame, and are scaled by the deform scale like them.
+	pub fn apply_deform(&mut self, id: InoxNodeUuid, deltas: &[Vec2]) -> Result<(), ApplyDeformError> {
+		let vert_len = self
+			.node_comps
+			.get::<TexturedMeshRenderCtx>(id)
+			.ok_or(ApplyDeformError::NotDeformable(id.0))?
+			.vert_len;
+		if deltas.len() != vert_len {
+			return Err(ApplyDeformError::LengthMismatch {
+				id: id.0,
+				expected: vert_len,
+				got: deltas.len(),
+			});
+		}
+		// parts not deformed by params have none
+		if self.node_comps.get::<DeformStack>(id).is_none() {
+			self.node_comps.add(id, DeformStack::new(vert_len, None));
+		}
+
+		let sum = self
+			.external_deforms
+			.entry(id)
+			.or_insert_with(|| vec![Vec2::ZERO; deltas.len()]);
+		sum.iter_mut().zip(deltas).for_each(|(sum, delta)| *sum += *delta);
+		Ok(())
+	}
+
+	/// Make mask `index` of drawable `id` be drawn with the silhouette of drawable `source`, taking effect on the next draw.
+	pub fn set_mask_source(
+		&mut self,
+		id: InoxNodeUuid,
+		index: usize,
+		source: InoxNodeUuid,
+	) -> Result<(), SetMaskSourceError> {
+		// a drawable masking itself would recurse forever when drawn
+		if source == id || self.node_comps.get::<Drawable>(source).is_none() {
+			return Err(SetMaskSourceError::InvalidSource(source.0));
+		}
+
+		let drawable = self
+			.node_comps
+			.get_mut::<Drawable>(id)
+			.ok_or(SetMaskSourceError::NotDrawable(id.0))?;
+		let count = drawable.mask_count();
+		let mask = drawable
+			.masks
+			.as_mut()
+			.and_then(|masks| masks.masks.get_mut(index))
+			.ok_or(SetMaskSourceError::NoSuchMask { index, count })?;
+		mask.source = source;
+		Ok(())
+	}
+
+	/// Transform of a node relative to its parent, including changes by params in the current frame.
+	///
+	/// The rest pose transform if transforms are not initialized. `None` if the node does not exist.
+	pub fn node_local_matrix(&self, id: InoxNodeUuid) -> Option<Mat4> {
+		match self.node_comps.get::<TransformStore>(id) {
+			Some(transform) => Some(transform.relative.to_matrix()),
+			None => Some(self.nodes.get_node(id)?.trans_offset.to_matrix()),
+		}
+	}
+
+	/// Transform of a node in puppet space, as of the last `.end_frame()`, e.g. to attach external objects to it.
+	///
+	/// `None` if the node does not exist or transforms are not initialized.
+	pub fn node_world_matrix(&self, id: InoxNodeUuid) -> Option<Mat4> {
+		self.node_comps
+			.get::<TransformStore>(id)
+			.map(|transform| transform.absolute)
+	}
+
+	/// Same as `.node_world_matrix()`: absolute transform of a node as of the last `.end_frame()`,
+	/// e.g. to attach a held object to a bone.
+	pub fn node_world_transform(&self, id: InoxNodeUuid) -> Option<Mat4> {
+		self.node_world_matrix(id)
+	}
+
+	/// Origin of a node in puppet space, as of the last `.end_frame()`, see `.node_world_transform()`.
+	pub fn node_world_position(&self, id: InoxNodeUuid) -> Option<Vec2> {
+		self.node_world_transform(id)
+			.map(|world| world.transform_point3(Vec3::ZERO).truncate())
+	}
+
+	/// Model-view-projection matrix a renderer draws a node with through `camera` into `viewport`,
+	/// for overlays that need to line up with the puppet exactly.
+	///
+	/// `None` if the node does not exist or transforms are not initialized.
+	pub fn node_mvp(&self, id: InoxNodeUuid, camera: &Camera, viewport: Vec2) -> Option<Mat4> {
+		self.node_world_matrix(id).map(|world| camera.matrix(viewport) * world)
+	}
+
+	/// Scale all deforms uniformly from the next `.end_frame()` on, e.g. to reduce motion:
+	/// `0` keeps meshes in rest pose, `1`, the default, deforms them fully.
+	pub fn set_deform_scale(&mut self, scale: f32) {
+		self.deform_scale = scale;
+	}
+
+	/// Current scale of all deforms, see `.set_deform_scale()`.
+	pub fn deform_scale(&self) -> f32 {
+		self.deform_scale
+	}
+
+	/// Elapsed times given to the last `.end_frame()`.
+	pub fn frame_delta(&self) -> FrameDelta {
+		self.frame_delta
+	}
+
 	/// Prepare the puppet for a new frame. User may set params afterwards.
 	pub fn begin_frame(&mut self) {
 		if let Some(render_ctx) = self.render_ctx.as_mut() {
@@ -119,12 +561,29 @@ impl Puppet {
 		if let Some(param_ctx) = self.param_ctx.as_mut() {
 			param_ctx.reset(&self.params);
 		}
+
+		self.external_deforms.clear();
 	}
 
 	/// Freeze puppet for one frame. Rendering, if initialized, may follow.
 	///
-	/// Provide elapsed time for physics, if initialized, to run. Provide `0` for the first call.
+	/// Provide elapsed time for playing animations and physics, if initialized, to run. Provide `0` for the first call.
 	pub fn end_frame(&mut self, dt: f32) {
+		self.end_frame_with(dt, dt);
+	}
+
+	/// `.end_frame()` with separate elapsed times for animation and physics, e.g. to freeze physics with a `physics_dt` of `0`
+	/// while still animating, or for slow motion. Physics-driven params hold their values while physics is frozen.
+	///
+	/// Playing animations move `animation_dt` forward, see `.apply_animations()`.
+	pub fn end_frame_with(&mut self, animation_dt: f32, physics_dt: f32) {
+		self.frame_delta = FrameDelta {
+			animation: animation_dt,
+			physics: physics_dt,
+		};
+		self.animation_ctx.advance(&self.animations, animation_dt);
+		let dt = physics_dt;
+
 		if let Some(param_ctx) = self.param_ctx.as_mut() {
 			param_ctx.apply(&self.params, &mut self.node_comps);
 		}
@@ -163,8 +622,443 @@ impl Puppet {
 			transform_ctx.update(&self.nodes, &mut self.node_comps);
 		}
 
+		if let Some(param_ctx) = self.param_ctx.as_mut() {
+			param_ctx.record_applied();
+			param_ctx.notify_changes(&self.params);
+		}
+
+		// pushed last, as physics resets deform stacks
+		for (id, deltas) in self.external_deforms.drain() {
+			if let Some(deform_stack) = self.node_comps.get_mut::<DeformStack>(id) {
+				deform_stack.push(DeformSource::External, Deform::Direct(deltas));
+			}
+		}
+
 		if let Some(render_ctx) = self.render_ctx.as_mut() {
-			render_ctx.update(&self.nodes, &mut self.node_comps);
+			render_ctx.update(&self.nodes, &mut self.node_comps, self.deform_scale);
 		}
 	}
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetMaskSourceError {
+	#[error("Node {0} is not a drawable")]
+	NotDrawable(u32),
+	#[error("Mask index {index} out of range, the drawable has {count} masks")]
+	NoSuchMask { index: usize, count: usize },
+	#[error("Node {0} is not a drawable other than the masked one, it cannot be a mask source")]
+	InvalidSource(u32),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApplyDeformError {
+	#[error("Node {0} is not a part with a mesh, or rendering is not initialized")]
+	NotDeformable(u32),
+	#[error("Node {id} has {expected} vertices, but {got} deltas were given")]
+	LengthMismatch { id: u32, expected: usize, got: usize },
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::vec2;
+
+	use crate::formats::fixtures;
+	use crate::math::camera::Camera;
+	use crate::node::components::{BlendMode, Drawable, MaskMode};
+	use crate::node::drawables::DrawableKind;
+	use crate::node::InoxNodeUuid;
+	use crate::render::TexturedMeshRenderCtx;
+
+	use super::{ApplyDeformError, LayerKind, SetMaskSourceError};
+
+	#[test]
+	fn reload_keeps_surviving_param_values() {
+		let payload = |renamed: &str, renamed_uuid: u32| {
+			fixtures::puppet(
+				fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+				vec![
+					fixtures::param(10, "kept", Vec::new()),
+					fixtures::param(renamed_uuid, renamed, Vec::new()),
+				],
+			)
+		};
+
+		let mut puppet = fixtures::load(&payload("before", 11));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let param_ctx = puppet.param_ctx.as_mut().unwrap();
+		param_ctx.set("kept", vec2(0.7, 0.0)).unwrap();
+		param_ctx.set("before", vec2(0.3, 0.0)).unwrap();
+
+		puppet.reload_from(fixtures::load(&payload("after", 12)));
+
+		let param_ctx = puppet
+			.param_ctx
+			.as_ref()
+			.expect("Contexts must be initialized on reload.");
+		assert_eq!(param_ctx.get("kept"), Some(vec2(0.7, 0.0)));
+		assert_eq!(param_ctx.get("after"), Some(vec2(0.0, 0.0)));
+		assert_eq!(param_ctx.get("before"), None);
+
+		// contexts already initialized on the new puppet are kept
+		let mut new = fixtures::load(&payload("after", 12));
+		new.init_transforms();
+		new.init_rendering();
+		puppet.reload_from(new);
+
+		let param_ctx = puppet.param_ctx.as_ref().unwrap();
+		assert_eq!(param_ctx.get("kept"), Some(vec2(0.7, 0.0)));
+	}
+
+	#[test]
+	fn effective_visibility() {
+		let mut composite = fixtures::composite(1, "composite", vec![fixtures::part(2, "part")]);
+		composite["enabled"] = false.into();
+		let mut transparent = fixtures::part(3, "transparent");
+		transparent["opacity"] = 0.0.into();
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![composite, transparent, fixtures::part(4, "visible")]),
+			Vec::new(),
+		));
+
+		assert!(!puppet.is_effectively_visible(InoxNodeUuid(1)));
+		assert!(!puppet.is_effectively_visible(InoxNodeUuid(2)));
+		assert!(!puppet.is_effectively_visible(InoxNodeUuid(3)));
+		assert!(puppet.is_effectively_visible(InoxNodeUuid(4)));
+		assert!(!puppet.is_effectively_visible(InoxNodeUuid(5)));
+	}
+
+	#[test]
+	fn rename_duplicate_nodes() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![
+					fixtures::part(1, "eye"),
+					fixtures::part(2, "eye"),
+					fixtures::node(3, "face", vec![fixtures::part(4, "eye")]),
+				],
+			),
+			Vec::new(),
+		));
+
+		let eyes = vec![InoxNodeUuid(1), InoxNodeUuid(2), InoxNodeUuid(4)];
+		assert_eq!(puppet.duplicate_node_names(), [("eye".to_owned(), eyes)]);
+
+		assert!(puppet.rename_node(InoxNodeUuid(1), "left eye"));
+		assert!(puppet.rename_node(InoxNodeUuid(2), "right eye"));
+		assert!(!puppet.rename_node(InoxNodeUuid(5), "nose"));
+		assert!(puppet.duplicate_node_names().is_empty());
+	}
+
+	#[test]
+	fn override_blend_mode() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "flash")]),
+			Vec::new(),
+		));
+
+		assert!(puppet.set_node_blend_mode(InoxNodeUuid(1), BlendMode::LinearDodge));
+		assert!(!puppet.set_node_blend_mode(InoxNodeUuid(0), BlendMode::LinearDodge));
+
+		let drawable = puppet.node_comps.get::<Drawable>(InoxNodeUuid(1)).unwrap();
+		assert_eq!(drawable.blending.mode, BlendMode::LinearDodge);
+	}
+
+	#[test]
+	fn world_matrix() {
+		let mut parent = fixtures::node(1, "parent", vec![fixtures::part(2, "child")]);
+		parent["transform"]["trans"] = json::array![10.0, 5.0, 0.0];
+		parent["children"][0]["transform"]["rot"] = json::array![0.0, 0.0, 0.5];
+		let mut puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", vec![parent]), Vec::new()));
+		assert_eq!(puppet.node_world_matrix(InoxNodeUuid(2)), None);
+		assert_eq!(puppet.node_world_position(InoxNodeUuid(2)), None);
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let parent_world = puppet.node_world_matrix(InoxNodeUuid(1)).unwrap();
+		let child_local = puppet.node_local_matrix(InoxNodeUuid(2)).unwrap();
+		assert_eq!(parent_world.w_axis, glam::vec4(10.0, 5.0, 0.0, 1.0));
+		assert_eq!(
+			puppet.node_world_matrix(InoxNodeUuid(2)),
+			Some(parent_world * child_local)
+		);
+		assert_eq!(puppet.node_local_matrix(InoxNodeUuid(3)), None);
+		assert_eq!(
+			puppet.node_world_transform(InoxNodeUuid(2)),
+			puppet.node_world_matrix(InoxNodeUuid(2))
+		);
+		assert_eq!(puppet.node_world_transform(InoxNodeUuid(3)), None);
+		assert_eq!(puppet.node_world_position(InoxNodeUuid(1)), Some(vec2(10.0, 5.0)));
+	}
+
+	#[test]
+	fn list_masks() {
+		let mut masked = fixtures::part(3, "masked");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }, { source: 2, mode: "DodgeMask" }];
+		let mut composite = fixtures::composite(4, "composite", Vec::new());
+		composite["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::part(1, "mask"), fixtures::part(2, "dodge"), masked, composite],
+			),
+			Vec::new(),
+		));
+
+		let masks = puppet
+			.masks()
+			.map(|(masked, mask)| (masked.0, mask.source.0, mask.mode == MaskMode::Dodge))
+			.collect::<Vec<_>>();
+		assert_eq!(masks, [(3, 1, false), (3, 2, true), (4, 1, false)]);
+
+		assert!(puppet.has_masks(InoxNodeUuid(3)) && puppet.has_masks(InoxNodeUuid(4)));
+		assert!(!puppet.has_masks(InoxNodeUuid(1)) && !puppet.has_masks(InoxNodeUuid(0)));
+		let mask_counts = puppet
+			.mask_counts()
+			.map(|(id, count)| (id.0, count))
+			.collect::<Vec<_>>();
+		assert_eq!(mask_counts, [(1, 0), (2, 0), (3, 2), (4, 1)]);
+	}
+
+	#[test]
+	fn mvp_places_node_origin() {
+		let mut part = fixtures::part(1, "part");
+		part["transform"]["trans"] = json::array![10.0, 5.0, 0.0];
+		let mut puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", vec![part]), Vec::new()));
+		let camera = Camera::default();
+		let viewport = glam::vec2(800.0, 600.0);
+		assert_eq!(puppet.node_mvp(InoxNodeUuid(1), &camera, viewport), None);
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let mvp = puppet.node_mvp(InoxNodeUuid(1), &camera, viewport).unwrap();
+		let Some(DrawableKind::TexturedMesh(components)) =
+			DrawableKind::new(InoxNodeUuid(1), &puppet.node_comps, false)
+		else {
+			panic!("part is not drawable");
+		};
+		assert_eq!(mvp, camera.matrix(viewport) * *components.transform);
+
+		// 10 px right of and 5 px below the centered camera, with y pointing down on screen
+		let origin = mvp.project_point3(glam::Vec3::ZERO);
+		assert!(origin.truncate().abs_diff_eq(glam::vec2(1.0 / 40.0, -1.0 / 60.0), 1e-6));
+	}
+
+	#[test]
+	fn set_mask_source_validates() {
+		let mut masked = fixtures::part(2, "masked");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "mask"), masked]),
+			Vec::new(),
+		));
+
+		assert!(matches!(
+			puppet.set_mask_source(InoxNodeUuid(2), 0, InoxNodeUuid(0)),
+			Err(SetMaskSourceError::InvalidSource(0))
+		));
+		assert!(matches!(
+			puppet.set_mask_source(InoxNodeUuid(2), 0, InoxNodeUuid(2)),
+			Err(SetMaskSourceError::InvalidSource(2))
+		));
+		assert!(matches!(
+			puppet.set_mask_source(InoxNodeUuid(0), 0, InoxNodeUuid(1)),
+			Err(SetMaskSourceError::NotDrawable(0))
+		));
+		assert!(matches!(
+			puppet.set_mask_source(InoxNodeUuid(1), 0, InoxNodeUuid(2)),
+			Err(SetMaskSourceError::NoSuchMask { index: 0, count: 0 })
+		));
+	}
+
+	#[test]
+	fn layer_list() {
+		let mut back = fixtures::part(1, "back");
+		back["zsort"] = (-1.0).into();
+		let mut front = fixtures::part(3, "front");
+		front["zsort"] = 1.0.into();
+		front["opacity"] = 0.5.into();
+		let mut hidden = fixtures::part(4, "hidden");
+		hidden["enabled"] = false.into();
+		let composite = fixtures::composite(2, "composite", vec![hidden]);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![back, composite, front]),
+			Vec::new(),
+		));
+		assert!(puppet.layer_list().is_empty());
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let layers = puppet
+			.layer_list()
+			.into_iter()
+			.map(|layer| {
+				(
+					layer.uuid.0,
+					layer.name,
+					layer.kind,
+					layer.depth,
+					layer.enabled,
+					layer.opacity,
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			layers,
+			[
+				(3, "front".to_owned(), LayerKind::Part, 1, true, 0.5),
+				(2, "composite".to_owned(), LayerKind::Composite, 1, true, 1.0),
+				(4, "hidden".to_owned(), LayerKind::Part, 2, false, 1.0),
+				(1, "back".to_owned(), LayerKind::Part, 1, true, 1.0),
+			]
+		);
+	}
+
+	#[test]
+	fn part_draw_index() {
+		let mut back = fixtures::part(1, "back");
+		back["zsort"] = (-1.0).into();
+		let composite = fixtures::composite(2, "composite", vec![fixtures::part(4, "inner")]);
+		let mut front = fixtures::part(3, "front");
+		front["zsort"] = 1.0.into();
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![back, composite, front]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let indices = puppet
+			.layer_list()
+			.iter()
+			.filter_map(|layer| puppet.part_draw_index(layer.uuid))
+			.collect::<Vec<_>>();
+		assert_eq!(indices, [0, 1, 2]);
+		assert_eq!(puppet.part_draw_index(InoxNodeUuid(2)), None);
+		assert_eq!(puppet.part_draw_index(InoxNodeUuid(0)), None);
+	}
+
+	#[test]
+	fn pick() {
+		// both cover (0, 0), "top" is drawn last
+		let mut bottom = fixtures::part(1, "bottom");
+		bottom["zsort"] = 1.0.into();
+		bottom["transform"]["trans"] = json::array![-1.0, 0.0, 0.0];
+		let mut top = fixtures::part(2, "top");
+		top["transform"]["trans"] = json::array![1.0, 0.0, 0.0];
+		let mut composite = fixtures::composite(3, "composite", vec![fixtures::part(4, "inner")]);
+		composite["transform"]["trans"] = json::array![10.0, 0.0, 0.0];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![bottom, top, composite]),
+			Vec::new(),
+		));
+		assert_eq!(puppet.pick(vec2(0.0, 0.0)), None);
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		assert_eq!(puppet.pick(vec2(0.0, 0.0)), Some(InoxNodeUuid(2)));
+		assert_eq!(puppet.pick(vec2(-1.5, 0.5)), Some(InoxNodeUuid(1)));
+		assert_eq!(puppet.pick(vec2(10.5, -0.5)), Some(InoxNodeUuid(4)));
+		assert_eq!(puppet.pick(vec2(5.0, 0.0)), None);
+
+		puppet
+			.node_comps
+			.get_mut::<Drawable>(InoxNodeUuid(2))
+			.unwrap()
+			.blending
+			.opacity = 0.0;
+		assert_eq!(puppet.pick(vec2(0.0, 0.0)), Some(InoxNodeUuid(1)));
+		puppet.nodes.get_node_mut(InoxNodeUuid(1)).unwrap().enabled = false;
+		assert_eq!(puppet.pick(vec2(0.0, 0.0)), None);
+	}
+
+	#[test]
+	fn apply_deform() {
+		let no_deform = json::array![[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let deform = json::array![[0.0, 1.0], [0.0, 1.0], [0.0, 1.0], [0.0, 1.0]];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![
+					fixtures::part(1, "part"),
+					fixtures::simple_physics(2, "physics", 11),
+					fixtures::part(3, "undeformed"),
+				],
+			),
+			vec![
+				fixtures::param(10, "deform", vec![fixtures::binding(1, "deform", [no_deform, deform])]),
+				fixtures::param(11, "sway", Vec::new()),
+			],
+		));
+		let part = InoxNodeUuid(1);
+		assert!(matches!(
+			puppet.apply_deform(part, &[vec2(1.0, 0.0); 4]),
+			Err(ApplyDeformError::NotDeformable(1))
+		));
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.init_physics();
+		let deforms = |puppet: &super::Puppet, id: InoxNodeUuid| {
+			let render_ctx = puppet.render_ctx.as_ref().unwrap();
+			let mesh_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(id).unwrap();
+			let start = mesh_ctx.vert_offset as usize;
+			render_ctx.vertex_buffers.deforms[start..start + mesh_ctx.vert_len].to_vec()
+		};
+
+		puppet.begin_frame();
+		assert!(matches!(
+			puppet.apply_deform(part, &[vec2(1.0, 0.0); 3]),
+			Err(ApplyDeformError::LengthMismatch {
+				id: 1,
+				expected: 4,
+				got: 3
+			})
+		));
+		assert!(matches!(
+			puppet.apply_deform(InoxNodeUuid(0), &[]),
+			Err(ApplyDeformError::NotDeformable(0))
+		));
+		puppet.apply_deform(part, &[vec2(1.0, 0.0); 4]).unwrap();
+		puppet.apply_deform(part, &[vec2(0.5, 0.0); 4]).unwrap();
+		puppet.apply_deform(InoxNodeUuid(3), &[vec2(0.0, 2.0); 4]).unwrap();
+		puppet
+			.param_ctx
+			.as_mut()
+			.unwrap()
+			.set("deform", vec2(0.5, 0.0))
+			.unwrap();
+		puppet.end_frame(0.1);
+		assert_eq!(deforms(&puppet, part), [vec2(1.5, 0.5); 4]);
+		assert_eq!(deforms(&puppet, InoxNodeUuid(3)), [vec2(0.0, 2.0); 4]);
+
+		// only for the frame they were given in
+		puppet.begin_frame();
+		puppet.end_frame(0.1);
+		assert_eq!(deforms(&puppet, part), [vec2(0.0, 0.0); 4]);
+		assert_eq!(deforms(&puppet, InoxNodeUuid(3)), [vec2(0.0, 0.0); 4]);
+	}
+}
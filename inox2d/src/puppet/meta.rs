@@ -1,5 +1,8 @@
+use std::cmp::Ordering;
 use std::fmt;
 
+use crate::INOCHI2D_SPEC_VERSION;
+
 pub struct PuppetMeta {
 	/// Name of the puppet.
 	pub name: Option<String>,
@@ -26,6 +29,25 @@ pub struct PuppetMeta {
 	pub preserve_pixels: bool,
 }
 
+impl Default for PuppetMeta {
+	/// Metadata of a puppet made for the supported spec version, with nothing else filled in.
+	fn default() -> Self {
+		Self {
+			name: None,
+			version: INOCHI2D_SPEC_VERSION.to_owned(),
+			rigger: None,
+			artist: None,
+			rights: None,
+			copyright: None,
+			license_url: None,
+			contact: None,
+			reference: None,
+			thumbnail_id: None,
+			preserve_pixels: false,
+		}
+	}
+}
+
 fn writeln_opt<T: fmt::Display>(f: &mut fmt::Formatter<'_>, field_name: &str, opt: &Option<T>) -> fmt::Result {
 	let field_name = format!("{:<17}", format!("{field_name}:"));
 	if let Some(ref value) = opt {
@@ -82,6 +104,97 @@ impl fmt::Display for PuppetMeta {
 	}
 }
 
+impl PuppetMeta {
+	/// Parsed `.version`. `None` if it is not a valid spec version.
+	pub fn spec_version(&self) -> Option<SpecVersion> {
+		SpecVersion::parse(&self.version)
+	}
+
+	/// Warning to report when loading this puppet, if it was made for a spec version this crate may not fully support.
+	#[cfg(any(feature = "json", feature = "serde_json"))]
+	pub(crate) fn spec_version_warning(&self) -> Option<String> {
+		match self.spec_version() {
+			Some(version) if version > SpecVersion::supported() => Some(format!(
+				"Puppet made for Inochi2D spec {version}, newer than supported {INOCHI2D_SPEC_VERSION}"
+			)),
+			Some(_) => None,
+			None => Some(format!(
+				"Puppet has an invalid Inochi2D spec version {:?}",
+				self.version
+			)),
+		}
+	}
+}
+
+/// Version of the Inochi2D spec, in the `major.minor[.patch][-pre]` form, e.g. `1.0-alpha`.
+///
+/// Ordered as in semver: a pre-release comes before its release.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpecVersion {
+	pub major: u32,
+	pub minor: u32,
+	pub patch: u32,
+	pub pre: Option<String>,
+}
+
+impl SpecVersion {
+	pub fn parse(version: &str) -> Option<Self> {
+		let (numbers, pre) = match version.split_once('-') {
+			Some((numbers, pre)) => (numbers, Some(pre.to_owned())),
+			None => (version, None),
+		};
+
+		let mut numbers = numbers.split('.').map(str::parse::<u32>);
+		let major = numbers.next()?.ok()?;
+		let minor = numbers.next()?.ok()?;
+		let patch = numbers.next().unwrap_or(Ok(0)).ok()?;
+		if numbers.next().is_some() {
+			return None;
+		}
+
+		Some(Self {
+			major,
+			minor,
+			patch,
+			pre,
+		})
+	}
+
+	/// The spec version implemented by this crate, `INOCHI2D_SPEC_VERSION`.
+	pub fn supported() -> Self {
+		Self::parse(INOCHI2D_SPEC_VERSION).expect("INOCHI2D_SPEC_VERSION must be valid.")
+	}
+}
+
+impl Ord for SpecVersion {
+	fn cmp(&self, other: &Self) -> Ordering {
+		(self.major, self.minor, self.patch)
+			.cmp(&(other.major, other.minor, other.patch))
+			.then_with(|| match (&self.pre, &other.pre) {
+				(None, None) => Ordering::Equal,
+				(None, Some(_)) => Ordering::Greater,
+				(Some(_), None) => Ordering::Less,
+				(Some(pre), Some(other_pre)) => pre.cmp(other_pre),
+			})
+	}
+}
+
+impl PartialOrd for SpecVersion {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl fmt::Display for SpecVersion {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+		if let Some(ref pre) = self.pre {
+			write!(f, "-{pre}")?;
+		}
+		Ok(())
+	}
+}
+
 /// Terms of usage of the puppet.
 pub struct PuppetUsageRights {
 	/// Who is allowed to use the puppet?
@@ -210,3 +323,30 @@ impl fmt::Display for PuppetAllowedModification {
 		)
 	}
 }
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use super::SpecVersion;
+	use crate::formats::fixtures;
+
+	#[test]
+	fn spec_version_order() {
+		let v = |version| SpecVersion::parse(version).unwrap();
+		assert!(v("1.0-alpha") < v("1.0"));
+		assert!(v("1.0") < v("1.0.1"));
+		assert!(v("1.0.1") < v("1.2"));
+		assert_eq!(v("1.0"), v("1.0.0"));
+		assert!(SpecVersion::parse("one").is_none());
+	}
+
+	#[test]
+	fn newer_spec_version_still_parses() {
+		let mut payload = fixtures::puppet(fixtures::node(0, "root", Vec::new()), Vec::new());
+		assert!(fixtures::load(&payload).meta.spec_version_warning().is_none());
+
+		payload["meta"]["version"] = "2.0".into();
+		let puppet = fixtures::load(&payload);
+		assert_eq!(puppet.meta.spec_version(), SpecVersion::parse("2.0"));
+		assert!(puppet.meta.spec_version_warning().is_some());
+	}
+}
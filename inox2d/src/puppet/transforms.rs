@@ -1,24 +1,40 @@
-use crate::node::components::{TransformStore, ZSort};
+use crate::node::components::{Drawable, RestOpacity, TransformStore, ZSort};
+
+use crate::node::InoxNodeUuid;
 
 use super::{InoxNodeTree, Puppet, World};
 
 pub(crate) struct TransformCtx {}
 
 impl TransformCtx {
-	/// Give every node a `TransformStore` and a `ZSort` component, if the puppet is going to be rendered/animated
+	/// Give every node a `TransformStore` and a `ZSort` component, and every drawable a `RestOpacity` component,
+	/// if the puppet is going to be rendered/animated
 	pub fn new(puppet: &mut Puppet) -> Self {
 		for node in puppet.nodes.iter() {
-			puppet.node_comps.add(node.uuid, TransformStore::default());
-			puppet.node_comps.add(node.uuid, ZSort::default());
+			Self::install(node.uuid, &mut puppet.node_comps);
 		}
 		TransformCtx {}
 	}
 
-	/// Reset all transform/zsort values to default.
+	/// Add the components of node `id`.
+	pub(crate) fn install(id: InoxNodeUuid, comps: &mut World) {
+		comps.add(id, TransformStore::default());
+		comps.add(id, ZSort::default());
+		if let Some(drawable) = comps.get::<Drawable>(id) {
+			let opacity = drawable.blending.opacity;
+			comps.add(id, RestOpacity(opacity));
+		}
+	}
+
+	/// Reset all transform/zsort/opacity values to default.
 	pub fn reset(&mut self, nodes: &InoxNodeTree, comps: &mut World) {
 		for node in nodes.iter() {
 			comps.get_mut::<TransformStore>(node.uuid).unwrap().relative = node.trans_offset.clone();
 			comps.get_mut::<ZSort>(node.uuid).unwrap().0 = node.zsort;
+			if let Some(RestOpacity(opacity)) = comps.get::<RestOpacity>(node.uuid) {
+				let opacity = *opacity;
+				comps.get_mut::<Drawable>(node.uuid).unwrap().blending.opacity = opacity;
+			}
 		}
 	}
 
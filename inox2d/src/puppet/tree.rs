@@ -55,16 +55,14 @@ impl InoxNodeTree {
 		Some(self.get_internal_node_mut(id)?.get_mut())
 	}
 
-	/// order is not guaranteed. use pre_order_iter() for pre-order traversal
+	/// All nodes in pre-order: depth-first from the root, each node before its children, children in order.
+	///
+	/// The order only depends on the structure of the tree, not on the order nodes were added in.
 	pub fn iter(&self) -> impl Iterator<Item = &InoxNode> {
-		self.arena.iter().map(|n| {
-			if n.is_removed() {
-				panic!("There is a removed node inside the indextree::Arena of the node tree.")
-			}
-			n.get()
-		})
+		self.pre_order_iter()
 	}
 
+	/// Same as `.iter()`.
 	pub fn pre_order_iter(&self) -> impl Iterator<Item = &InoxNode> {
 		let root_id = self.node_ids.get(&self.root_node_id).unwrap();
 		root_id
@@ -72,6 +70,15 @@ impl InoxNodeTree {
 			.map(|id| self.arena.get(id).unwrap().get())
 	}
 
+	/// The node itself, then its parent, up to the root. Empty if the node does not exist.
+	pub fn ancestors(&self, id: InoxNodeUuid) -> impl Iterator<Item = &InoxNode> {
+		self.node_ids
+			.get(&id)
+			.into_iter()
+			.flat_map(|node_id| node_id.ancestors(&self.arena))
+			.map(|id| self.arena.get(id).unwrap().get())
+	}
+
 	/// WARNING: panicks if called on root
 	pub fn get_parent(&self, children: InoxNodeUuid) -> &InoxNode {
 		self.arena
@@ -93,4 +100,114 @@ impl InoxNodeTree {
 			.children(&self.arena)
 			.map(|id| self.arena.get(id).unwrap().get())
 	}
+
+	/// First node in pre-order named exactly `name`. See `.find_nodes_by_name()` as names are not unique.
+	pub fn find_node_by_name(&self, name: &str) -> Option<InoxNodeUuid> {
+		self.iter().find(|node| node.name == name).map(|node| node.uuid)
+	}
+
+	/// All nodes named exactly `name`, in pre-order.
+	pub fn find_nodes_by_name(&self, name: &str) -> Vec<InoxNodeUuid> {
+		self.iter()
+			.filter(|node| node.name == name)
+			.map(|node| node.uuid)
+			.collect()
+	}
+
+	/// Node reached from the root by following children of exactly the names in `path`, separated by `/`,
+	/// e.g. `"Body/Head/Left Eye"`. The root itself is not part of paths.
+	///
+	/// Where siblings share a name, the first one in pre-order with a match for the rest of the path is picked.
+	pub fn find_node_by_path(&self, path: &str) -> Option<InoxNodeUuid> {
+		let names = path.split('/').collect::<Vec<_>>();
+		self.find_descendant_by_path(self.root_node_id, &names)
+	}
+
+	fn find_descendant_by_path(&self, parent: InoxNodeUuid, names: &[&str]) -> Option<InoxNodeUuid> {
+		let Some((name, rest)) = names.split_first() else {
+			return Some(parent);
+		};
+
+		self.get_children(parent)
+			.filter(|child| child.name == *name)
+			.find_map(|child| self.find_descendant_by_path(child.uuid, rest))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::math::transform::TransformOffset;
+	use crate::node::{InoxNode, InoxNodeUuid};
+
+	use super::InoxNodeTree;
+
+	fn node(uuid: u32) -> InoxNode {
+		named_node(uuid, &uuid.to_string())
+	}
+
+	fn named_node(uuid: u32, name: &str) -> InoxNode {
+		InoxNode {
+			uuid: InoxNodeUuid(uuid),
+			name: name.to_owned(),
+			enabled: true,
+			zsort: 0.,
+			trans_offset: TransformOffset::default(),
+			lock_to_root: false,
+			group: None,
+		}
+	}
+
+	#[test]
+	fn iter_pre_order() {
+		// 0
+		// ├── 1
+		// │   ├── 3
+		// │   └── 4
+		// └── 2
+		//     └── 5
+		let mut tree = InoxNodeTree::new_with_root(node(0));
+		tree.add(InoxNodeUuid(0), InoxNodeUuid(1), node(1));
+		tree.add(InoxNodeUuid(0), InoxNodeUuid(2), node(2));
+		tree.add(InoxNodeUuid(2), InoxNodeUuid(5), node(5));
+		tree.add(InoxNodeUuid(1), InoxNodeUuid(3), node(3));
+		tree.add(InoxNodeUuid(1), InoxNodeUuid(4), node(4));
+
+		let order = tree.iter().map(|node| node.uuid.0).collect::<Vec<_>>();
+		assert_eq!(order, [0, 1, 3, 4, 2, 5]);
+	}
+
+	#[test]
+	fn find_by_name() {
+		// Root
+		// ├── Body
+		// │   └── Eye
+		// ├── Head
+		// │   └── Eye
+		// └── Head
+		//     ├── Eye
+		//     └── Mouth
+		let mut tree = InoxNodeTree::new_with_root(named_node(0, "Root"));
+		tree.add(InoxNodeUuid(0), InoxNodeUuid(1), named_node(1, "Body"));
+		tree.add(InoxNodeUuid(1), InoxNodeUuid(2), named_node(2, "Eye"));
+		tree.add(InoxNodeUuid(0), InoxNodeUuid(3), named_node(3, "Head"));
+		tree.add(InoxNodeUuid(3), InoxNodeUuid(4), named_node(4, "Eye"));
+		tree.add(InoxNodeUuid(0), InoxNodeUuid(5), named_node(5, "Head"));
+		tree.add(InoxNodeUuid(5), InoxNodeUuid(6), named_node(6, "Eye"));
+		tree.add(InoxNodeUuid(5), InoxNodeUuid(7), named_node(7, "Mouth"));
+
+		assert_eq!(tree.find_node_by_name("Eye"), Some(InoxNodeUuid(2)));
+		assert_eq!(
+			tree.find_nodes_by_name("Eye"),
+			[InoxNodeUuid(2), InoxNodeUuid(4), InoxNodeUuid(6)]
+		);
+		assert_eq!(tree.find_node_by_name("eye"), None);
+		assert!(tree.find_nodes_by_name("Nose").is_empty());
+
+		assert_eq!(tree.find_node_by_path("Head/Eye"), Some(InoxNodeUuid(4)));
+		// the second Head is the one with a Mouth
+		assert_eq!(tree.find_node_by_path("Head/Mouth"), Some(InoxNodeUuid(7)));
+		assert_eq!(tree.find_node_by_path("Body"), Some(InoxNodeUuid(1)));
+		assert_eq!(tree.find_node_by_path("Root/Body"), None);
+		assert_eq!(tree.find_node_by_path("Body/Mouth"), None);
+	}
 }
@@ -0,0 +1,155 @@
+use crate::node::components::{Composite, Drawable};
+
+use super::Puppet;
+
+/// A problem with the structure of a puppet, see `Puppet::validate()`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PuppetValidationError {
+	#[error("Mask {index} of node {node} has node {mask_source} as source, which does not exist")]
+	MissingMaskSource { node: u32, index: usize, mask_source: u32 },
+	#[error("Mask {index} of node {node} has node {mask_source} as source, which is not a drawable other than the masked one")]
+	InvalidMaskSource { node: u32, index: usize, mask_source: u32 },
+	#[error("Composite {composite} contains composite {nested}, which cannot be nested and is not drawn")]
+	NestedComposite { composite: u32, nested: u32 },
+	#[error("Param {param:?} has a binding to node {node}, which does not exist")]
+	MissingBindingNode { param: String, node: u32 },
+}
+
+impl Puppet {
+	/// Check references between nodes and params that a malformed file may get wrong:
+	/// - mask sources must be existing drawables, other than the masked one,
+	/// - composites must not contain composites,
+	/// - param bindings must target existing nodes.
+	///
+	/// All problems found are returned, nodes in tree order first, then params in file order.
+	pub fn validate(&self) -> Result<(), Vec<PuppetValidationError>> {
+		let mut errors = Vec::new();
+
+		for node in self.nodes.pre_order_iter() {
+			let masks = self
+				.node_comps
+				.get::<Drawable>(node.uuid)
+				.and_then(|drawable| drawable.masks.as_ref());
+			for (index, mask) in masks.iter().flat_map(|masks| masks.masks.iter().enumerate()) {
+				let (node, mask_source) = (node.uuid.0, mask.source.0);
+				if self.nodes.get_node(mask.source).is_none() {
+					errors.push(PuppetValidationError::MissingMaskSource {
+						node,
+						index,
+						mask_source,
+					});
+				} else if mask.source.0 == node || self.node_comps.get::<Drawable>(mask.source).is_none() {
+					errors.push(PuppetValidationError::InvalidMaskSource {
+						node,
+						index,
+						mask_source,
+					});
+				}
+			}
+
+			if self.node_comps.get::<Composite>(node.uuid).is_some() {
+				let nested = self
+					.nodes
+					.get_children(node.uuid)
+					.filter(|child| self.node_comps.get::<Composite>(child.uuid).is_some());
+				errors.extend(nested.map(|nested| PuppetValidationError::NestedComposite {
+					composite: node.uuid.0,
+					nested: nested.uuid.0,
+				}));
+			}
+		}
+
+		for param in self.params_sorted() {
+			let missing = param
+				.bindings
+				.iter()
+				.filter(|binding| self.nodes.get_node(binding.node).is_none());
+			errors.extend(missing.map(|binding| PuppetValidationError::MissingBindingNode {
+				param: param.name.clone(),
+				node: binding.node.0,
+			}));
+		}
+
+		match errors.is_empty() {
+			true => Ok(()),
+			false => Err(errors),
+		}
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use crate::formats::fixtures;
+
+	use super::PuppetValidationError;
+
+	#[test]
+	fn validate() {
+		let mut masked = fixtures::part(3, "masked");
+		masked["masks"] = json::array![
+			{ source: 1, mode: "Mask" },
+			{ source: 99, mode: "Mask" },
+			{ source: 3, mode: "Mask" },
+			{ source: 0, mode: "Mask" },
+		];
+		let nested = fixtures::composite(5, "nested", vec![fixtures::part(6, "inner")]);
+		let composite = fixtures::composite(4, "composite", vec![fixtures::part(7, "part"), nested]);
+		let puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "source"), masked, composite]),
+			vec![
+				fixtures::param(10, "b", vec![fixtures::binding(42, "zSort", [0.0.into(), 1.0.into()])]),
+				fixtures::param(
+					11,
+					"a",
+					vec![
+						fixtures::binding(1, "zSort", [0.0.into(), 1.0.into()]),
+						fixtures::binding(43, "zSort", [0.0.into(), 1.0.into()]),
+					],
+				),
+			],
+		));
+
+		assert_eq!(
+			puppet.validate(),
+			Err(vec![
+				PuppetValidationError::MissingMaskSource {
+					node: 3,
+					index: 1,
+					mask_source: 99
+				},
+				PuppetValidationError::InvalidMaskSource {
+					node: 3,
+					index: 2,
+					mask_source: 3
+				},
+				PuppetValidationError::InvalidMaskSource {
+					node: 3,
+					index: 3,
+					mask_source: 0
+				},
+				PuppetValidationError::NestedComposite {
+					composite: 4,
+					nested: 5
+				},
+				PuppetValidationError::MissingBindingNode {
+					param: "b".to_owned(),
+					node: 42
+				},
+				PuppetValidationError::MissingBindingNode {
+					param: "a".to_owned(),
+					node: 43
+				},
+			])
+		);
+
+		let valid = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![fixtures::param(
+				10,
+				"p",
+				vec![fixtures::binding(1, "zSort", [0.0.into(), 1.0.into()])],
+			)],
+		));
+		assert_eq!(valid.validate(), Ok(()));
+	}
+}
@@ -83,35 +83,39 @@ impl World {
 		column.push(v);
 	}
 
+	/// Remove the component of type T of a node, if any.
+	pub fn remove<T: Component>(&mut self, node: InoxNodeUuid) -> Option<T> {
+		let pair = self.columns.get_mut(&TypeId::of::<T>())?;
+		// SAFETY: AnyVec in pair must be of type T, enforced by hashing
+		let column = unsafe { pair.0.downcast_mut_unchecked::<T>() };
+
+		let index = pair.1.remove(&node)?;
+		let last = column.len() - 1;
+		if index != last {
+			// the last component is about to be swapped into the removed one's place
+			let moved = pair.1.values_mut().find(|i| **i == last).unwrap();
+			*moved = index;
+		}
+		Some(column.swap_remove(index))
+	}
+
 	pub fn get<T: Component>(&self, node: InoxNodeUuid) -> Option<&T> {
-		let pair = match self.columns.get(&TypeId::of::<T>()) {
-			Some(c) => c,
-			None => return None,
-		};
+		let pair = self.columns.get(&TypeId::of::<T>())?;
 		// SAFETY: AnyVec in pair must be of type T, enforced by hashing
 		let column = unsafe { pair.0.downcast_unchecked() };
 
-		let index = match pair.1.get(&node) {
-			Some(i) => *i,
-			None => return None,
-		};
+		let index = *pair.1.get(&node)?;
 		debug_assert!(index < column.len());
 		// SAFETY: what has been inserted into pair.1 should be a valid index
 		Some(unsafe { column.get_unchecked(index) })
 	}
 
 	pub fn get_mut<T: Component>(&mut self, node: InoxNodeUuid) -> Option<&mut T> {
-		let pair = match self.columns.get_mut(&TypeId::of::<T>()) {
-			Some(c) => c,
-			None => return None,
-		};
+		let pair = self.columns.get_mut(&TypeId::of::<T>())?;
 		// SAFETY: AnyVec in pair must be of type T, enforced by hashing
 		let column = unsafe { pair.0.downcast_mut_unchecked() };
 
-		let index = match pair.1.get(&node) {
-			Some(i) => *i,
-			None => return None,
-		};
+		let index = *pair.1.get(&node)?;
 		debug_assert!(index < column.len());
 		// SAFETY: what has been inserted into pair.1 should be a valid index
 		Some(unsafe { column.get_unchecked_mut(index) })
@@ -255,5 +259,25 @@ mod tests {
 				assert_eq!(world.get_unchecked::<CompC>(NODE_2).f, 8.93);
 			}
 		}
+
+		#[test]
+		fn remove() {
+			let mut world = World::new();
+
+			world.add(NODE_0, CompB { i: 0 });
+			world.add(NODE_1, CompB { i: 1 });
+			world.add(NODE_2, CompB { i: 2 });
+
+			assert_eq!(world.remove::<CompB>(NODE_0).unwrap().i, 0);
+			assert!(world.remove::<CompB>(NODE_0).is_none());
+			assert!(world.remove::<CompA>(NODE_1).is_none());
+
+			assert!(world.get::<CompB>(NODE_0).is_none());
+			assert_eq!(world.get::<CompB>(NODE_1).unwrap().i, 1);
+			assert_eq!(world.get::<CompB>(NODE_2).unwrap().i, 2);
+
+			world.add(NODE_0, CompB { i: 3 });
+			assert_eq!(world.get::<CompB>(NODE_0).unwrap().i, 3);
+		}
 	}
 }
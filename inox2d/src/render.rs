@@ -1,24 +1,36 @@
+mod batch;
+mod blend;
+mod cull;
 mod deform_stack;
+mod snapshot;
 mod vertex_buffers;
+mod welds;
 
 use std::collections::HashSet;
 use std::mem::swap;
+use std::ops::Range;
+
+use glam::{Mat4, Vec2};
 
 use crate::node::{
-	components::{DeformStack, Mask, Masks, ZSort},
-	drawables::{CompositeComponents, DrawableKind, TexturedMeshComponents},
+	components::{DeformStack, Mask, Masks, RestDeform, VertexWelds, ZSort},
+	drawables::{CompositeComponents, DrawableKind, PlainMeshComponents, TexturedMeshComponents},
 	InoxNodeUuid,
 };
 use crate::params::BindingValues;
 use crate::puppet::{InoxNodeTree, Puppet, World};
 
-pub use vertex_buffers::VertexBuffers;
+pub use blend::blend_reference;
+pub use snapshot::FlatMesh;
+pub use vertex_buffers::{VertexBufferOverflow, VertexBuffers, MAX_VERTICES};
 
 /// Additional info per node for rendering a TexturedMesh:
 /// - offset and length of array for mesh point coordinates
 /// - offset and length of array for indices of mesh points defining the mesh
 ///
 /// inside `puppet.render_ctx_vertex_buffers`.
+///
+/// Plain meshes, drawn as masks only, have one too.
 pub struct TexturedMeshRenderCtx {
 	pub index_offset: u16,
 	pub vert_offset: u16,
@@ -37,13 +49,28 @@ pub struct RenderCtx {
 	pub vertex_buffers: VertexBuffers,
 	/// All nodes that need respective draw method calls:
 	/// - including standalone parts and composite parents,
-	/// - excluding (TODO: plain mesh masks) and composite children.
+	/// - excluding plain mesh masks and composite children.
 	root_drawables_zsorted: Vec<InoxNodeUuid>,
+	/// Deforms as of the previous `update()`, to find what changed since.
+	prev_deforms: Vec<Vec2>,
+	/// Number of `update()`s so far.
+	deform_generation: u64,
+	/// Per vertex, the generation its deform last changed in.
+	deform_changed_in: Vec<u64>,
 }
 
 impl RenderCtx {
-	/// MODIFIES puppet. In addition to initializing self, installs render contexts in the World of components
-	pub(super) fn new(puppet: &mut Puppet) -> Self {
+	/// MODIFIES puppet. In addition to initializing self, installs render contexts in the World of components.
+	/// Fails if meshes of the puppet do not fit in vertex buffers, leaving no render context installed.
+	pub(super) fn new(puppet: &mut Puppet) -> Result<Self, VertexBufferOverflow> {
+		let render_ctx = Self::build(puppet);
+		if render_ctx.is_err() {
+			Self::uninstall(&puppet.nodes, &mut puppet.node_comps);
+		}
+		render_ctx
+	}
+
+	fn build(puppet: &mut Puppet) -> Result<Self, VertexBufferOverflow> {
 		let nodes = &puppet.nodes;
 		let comps = &mut puppet.node_comps;
 
@@ -55,20 +82,32 @@ impl RenderCtx {
 				}
 			});
 		}
+		// welds write into the deforms of the welded part, rest deforms are a baseline of them
+		for node in nodes.iter() {
+			if comps.get::<VertexWelds>(node.uuid).is_some() || comps.get::<RestDeform>(node.uuid).is_some() {
+				nodes_to_deform.insert(node.uuid);
+			}
+		}
 		// TODO: Further fill the set when Meshgroup is implemented.
 
 		let mut vertex_buffers = VertexBuffers::default();
 
 		let mut root_drawables_count: usize = 0;
+		// subtracted at the end, as children may be counted after their composite
+		let mut composite_children_count: usize = 0;
 		for node in nodes.iter() {
 			let drawable_kind = DrawableKind::new(node.uuid, comps, true);
 			if let Some(drawable_kind) = drawable_kind {
-				root_drawables_count += 1;
+				// plain meshes are only drawn as mask sources
+				if !matches!(drawable_kind, DrawableKind::PlainMesh(_)) {
+					root_drawables_count += 1;
+				}
 
 				match drawable_kind {
-					DrawableKind::TexturedMesh(components) => {
-						let (index_offset, vert_offset) = vertex_buffers.push(components.mesh);
-						let (index_len, vert_len) = (components.mesh.indices.len(), components.mesh.vertices.len());
+					DrawableKind::TexturedMesh(TexturedMeshComponents { mesh, .. })
+					| DrawableKind::PlainMesh(PlainMeshComponents { mesh, .. }) => {
+						let (index_offset, vert_offset) = vertex_buffers.push(mesh)?;
+						let (index_len, vert_len) = (mesh.indices.len(), mesh.vertices.len());
 
 						comps.add(
 							node.uuid,
@@ -82,24 +121,48 @@ impl RenderCtx {
 
 						// TexturedMesh not deformed by any source does not need a DeformStack
 						if nodes_to_deform.contains(&node.uuid) {
-							comps.add(node.uuid, DeformStack::new(vert_len));
+							let baseline = match comps.get::<RestDeform>(node.uuid) {
+								Some(rest) if rest.deforms.len() == vert_len => Some(rest.deforms.clone()),
+								Some(rest) => {
+									tracing::warn!(
+										"Rest deform of part {} has {} offsets for {} vertices, ignoring.",
+										node.uuid.0,
+										rest.deforms.len(),
+										vert_len
+									);
+									None
+								}
+								None => None,
+							};
+							comps.add(node.uuid, DeformStack::new(vert_len, baseline));
 						}
 					}
 					DrawableKind::Composite { .. } => {
-						// exclude non-drawable children
+						let mut drawable_children_count = 0;
+						// exclude non-drawable children, which are still transformed and simulated like any node,
+						// and composites as they cannot be nested
 						let children_list: Vec<InoxNodeUuid> = nodes
 							.get_children(node.uuid)
-							.filter_map(|n| {
-								if DrawableKind::new(n.uuid, comps, false).is_some() {
+							.filter_map(|n| match DrawableKind::new(n.uuid, comps, false) {
+								Some(DrawableKind::TexturedMesh(_)) => {
+									drawable_children_count += 1;
 									Some(n.uuid)
-								} else {
+								}
+								Some(DrawableKind::Composite(_)) => {
+									drawable_children_count += 1;
+									tracing::warn!(
+										"Composite {} inside Composite {} is not allowed, skipping.",
+										n.uuid.0,
+										node.uuid.0
+									);
 									None
 								}
+								Some(DrawableKind::PlainMesh(_)) | None => None,
 							})
 							.collect();
 
 						// composite children are excluded from root_drawables_zsorted
-						root_drawables_count -= children_list.len();
+						composite_children_count += drawable_children_count;
 
 						comps.add(
 							node.uuid,
@@ -113,13 +176,68 @@ impl RenderCtx {
 			}
 		}
 
+		root_drawables_count -= composite_children_count;
+
 		let mut root_drawables_zsorted = Vec::new();
 		// similarly, populate later, before render
 		root_drawables_zsorted.resize(root_drawables_count, InoxNodeUuid(0));
 
-		Self {
+		Ok(Self {
+			prev_deforms: vertex_buffers.deforms.clone(),
+			deform_changed_in: vec![0; vertex_buffers.deforms.len()],
 			vertex_buffers,
 			root_drawables_zsorted,
+			deform_generation: 0,
+		})
+	}
+
+	/// Range of vertices whose deforms changed in the last frame, `None` if no deform changed.
+	///
+	/// Only enough for a renderer drawing after every frame, see `.dirty_deform_range_since()` otherwise.
+	pub fn dirty_deform_range(&self) -> Option<Range<usize>> {
+		self.dirty_deform_range_since(self.deform_generation.saturating_sub(1))
+	}
+
+	/// Generation of the deforms, increasing every frame.
+	pub fn deform_generation(&self) -> u64 {
+		self.deform_generation
+	}
+
+	/// Range of vertices whose deforms changed in frames after the one that made `generation`,
+	/// `None` if no deform changed.
+	///
+	/// A renderer that keeps deforms in a GPU buffer only needs to upload this sub-slice,
+	/// `generation` being the `.deform_generation()` of its last upload, even if frames were not drawn in between.
+	pub fn dirty_deform_range_since(&self, generation: u64) -> Option<Range<usize>> {
+		let changed = |(i, changed_in): (usize, &u64)| (*changed_in > generation).then_some(i);
+		let first = self.deform_changed_in.iter().enumerate().find_map(changed)?;
+		let last = self.deform_changed_in.iter().enumerate().rev().find_map(changed)?;
+		Some(first..last + 1)
+	}
+
+	/// Find deforms that changed since the previous update, and remember the current ones.
+	fn track_dirty_deforms(&mut self) {
+		self.deform_generation += 1;
+		let deforms = &self.vertex_buffers.deforms;
+		for (i, (new, old)) in deforms.iter().zip(&mut self.prev_deforms).enumerate() {
+			if new != old {
+				*old = *new;
+				self.deform_changed_in[i] = self.deform_generation;
+			}
+		}
+	}
+
+	/// Top-level drawables, i.e. all except composite children, in draw order as of the last `update()`.
+	pub(crate) fn root_drawables_zsorted(&self) -> &[InoxNodeUuid] {
+		&self.root_drawables_zsorted
+	}
+
+	/// Remove render contexts installed by `new()` from the World of components.
+	pub(super) fn uninstall(nodes: &InoxNodeTree, comps: &mut World) {
+		for node in nodes.iter() {
+			comps.remove::<TexturedMeshRenderCtx>(node.uuid);
+			comps.remove::<CompositeRenderCtx>(node.uuid);
+			comps.remove::<DeformStack>(node.uuid);
 		}
 	}
 
@@ -133,8 +251,11 @@ impl RenderCtx {
 	}
 
 	/// Update zsort-ordered info and deform buffer content inside self, according to updated puppet.
-	pub(crate) fn update(&mut self, nodes: &InoxNodeTree, comps: &mut World) {
+	/// Deforms are multiplied by `deform_scale`.
+	pub(crate) fn update(&mut self, nodes: &InoxNodeTree, comps: &mut World, deform_scale: f32) {
 		let mut root_drawable_uuid_zsort_vec = Vec::<(InoxNodeUuid, f32)>::new();
+		// ranges of the deform buffer to write the combined deforms of nodes into
+		let mut deform_ranges = Vec::<(InoxNodeUuid, Range<usize>)>::new();
 
 		// root is definitely not a drawable.
 		for node in nodes.iter().skip(1) {
@@ -142,11 +263,12 @@ impl RenderCtx {
 				let parent = nodes.get_parent(node.uuid);
 				let node_zsort = comps.get::<ZSort>(node.uuid).unwrap().0;
 
-				if !matches!(
-					DrawableKind::new(parent.uuid, comps, false),
-					Some(DrawableKind::Composite(_))
-				) {
-					// exclude composite children
+				// exclude composite children and plain meshes
+				if !matches!(drawable_kind, DrawableKind::PlainMesh(_))
+					&& !matches!(
+						DrawableKind::new(parent.uuid, comps, false),
+						Some(DrawableKind::Composite(_))
+					) {
 					root_drawable_uuid_zsort_vec.push((node.uuid, node_zsort));
 				}
 
@@ -165,6 +287,20 @@ impl RenderCtx {
 								.zsorted_children_list,
 						);
 
+						// rebuilt in tree order, which the stable sort keeps for ties,
+						// rather than in the order of the previous frame
+						zsorted_children_list.clear();
+						zsorted_children_list.extend(
+							nodes
+								.get_children(node.uuid)
+								.filter(|n| {
+									matches!(
+										DrawableKind::new(n.uuid, comps, false),
+										Some(DrawableKind::TexturedMesh(_))
+									)
+								})
+								.map(|n| n.uuid),
+						);
 						zsorted_children_list.sort_by(|a, b| {
 							let zsort_a = comps.get::<ZSort>(*a).unwrap();
 							let zsort_b = comps.get::<ZSort>(*b).unwrap();
@@ -179,24 +315,32 @@ impl RenderCtx {
 								.zsorted_children_list,
 						);
 					}
-					// for TexturedMesh, obtain and write deforms into vertex_buffer
-					DrawableKind::TexturedMesh(..) => {
+					// for TexturedMesh and PlainMesh, obtain deforms to write into vertex_buffer
+					DrawableKind::TexturedMesh(..) | DrawableKind::PlainMesh(..) => {
 						// A TexturedMesh not having an associated DeformStack means it will not be deformed at all, skip.
-						if let Some(deform_stack) = comps.get::<DeformStack>(node.uuid) {
+						if comps.get::<DeformStack>(node.uuid).is_some() {
 							let render_ctx = comps.get::<TexturedMeshRenderCtx>(node.uuid).unwrap();
 							let vert_offset = render_ctx.vert_offset as usize;
-							let vert_len = render_ctx.vert_len;
-							deform_stack.combine(
-								nodes,
-								comps,
-								&mut self.vertex_buffers.deforms[vert_offset..(vert_offset + vert_len)],
-							);
+							deform_ranges.push((node.uuid, vert_offset..vert_offset + render_ctx.vert_len));
 						}
 					}
 				}
 			}
 		}
 
+		deform_stack::combine_all(
+			nodes,
+			comps,
+			&deform_ranges,
+			&mut self.vertex_buffers.deforms,
+			deform_scale,
+		);
+
+		// welds need the deforms of both sides, so they come after all deforms are combined
+		welds::apply(nodes, comps, &mut self.vertex_buffers);
+		self.track_dirty_deforms();
+
+		// collected in tree order, which the stable sort keeps for ties
 		root_drawable_uuid_zsort_vec.sort_by(|a, b| a.1.total_cmp(&b.1).reverse());
 		self.root_drawables_zsorted
 			.iter_mut()
@@ -205,6 +349,20 @@ impl RenderCtx {
 	}
 }
 
+/// Default of `InoxRenderer::max_nesting_depth()`.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+	/// A drawable is drawn as the mask source of a drawable, which is itself a mask source, and so on, too many times.
+	/// This includes masks drawn for composites.
+	#[error("Drawable {id} is nested more than {max_depth} levels deep as a mask source, skipping it")]
+	CompositeNestingTooDeep { id: u32, max_depth: usize },
+	/// A drawable is a mask source of itself, directly or through the masks of its mask sources.
+	#[error("Drawable {id} is drawn as a mask source of itself, skipping it")]
+	MaskCycle { id: u32 },
+}
+
 /// Same as the reference Inochi2D implementation, Inox2D also aims for a "bring your own rendering backend" design.
 /// A custom backend shall implement this trait.
 ///
@@ -230,8 +388,18 @@ pub trait InoxRenderer {
 	/// Ref impl: Disable the stencil buffer.
 	fn on_end_mask(&self);
 
+	/// Start drawing node `id` named `name`, including its masks, until the matching `.on_exit_node()`.
+	/// Nodes drawn in between, e.g. mask sources or composite children, are nested. Does nothing by default.
+	///
+	/// Parts drawn at once by `.draw_textured_mesh_batch()` are entered as one node, the first part of the batch,
+	/// named after all parts of the batch.
+	///
+	/// Ref impl: Push a debug group labelled with the node, for graphics debuggers.
+	fn on_enter_node(&self, _id: InoxNodeUuid, _name: &str) {}
+	/// Done drawing the node last entered with `.on_enter_node()`.
+	fn on_exit_node(&self, _id: InoxNodeUuid) {}
+
 	/// Draw TexturedMesh content.
-	// TODO: TexturedMesh without any texture (usually for mesh masks)?
 	fn draw_textured_mesh_content(
 		&self,
 		as_mask: bool,
@@ -240,6 +408,23 @@ pub trait InoxRenderer {
 		id: InoxNodeUuid,
 	);
 
+	/// Draw a mesh without textures as a mask source, set up by `.on_begin_mask()` like textured mask sources.
+	///
+	/// Ref impl: Write the shape of the mesh to the stencil buffer, without sampling any texture.
+	///
+	/// The default implementation skips the mask source, so renderers written before plain meshes existed keep working.
+	fn draw_plain_mesh_mask(
+		&self,
+		_components: &PlainMeshComponents,
+		_render_ctx: &TexturedMeshRenderCtx,
+		id: InoxNodeUuid,
+	) {
+		tracing::debug!(
+			"Skipping plain mesh mask source {}: not supported by this renderer",
+			id.0
+		);
+	}
+
 	/// Begin compositing. Get prepared for rendering children of a Composite.
 	///
 	/// Ref impl: Prepare composite buffers.
@@ -260,75 +445,200 @@ pub trait InoxRenderer {
 		render_ctx: &CompositeRenderCtx,
 		id: InoxNodeUuid,
 	);
+
+	/// Matrix from puppet space to clip space, if known.
+	///
+	/// `draw()` then skips top-level drawables entirely outside of the view.
+	fn view_matrix(&self) -> Option<Mat4> {
+		None
+	}
+
+	/// Whether `draw()` draws consecutive top-level parts sharing all draw state at once,
+	/// with `draw_textured_mesh_batch()`. Off by default.
+	///
+	/// Parts share draw state if none is masked and they have the same textures, blending, emission and transform.
+	fn batch_parts(&self) -> bool {
+		false
+	}
+
+	/// How deep drawables may be nested in the drawables they mask, see `RenderError::CompositeNestingTooDeep`.
+	fn max_nesting_depth(&self) -> usize {
+		DEFAULT_MAX_NESTING_DEPTH
+	}
+
+	/// Draw several consecutive parts at once.
+	/// `components` are those of the first part, `render_ctx` spans the indices and vertices of all `ids`.
+	fn draw_textured_mesh_batch(
+		&self,
+		components: &TexturedMeshComponents,
+		render_ctx: &TexturedMeshRenderCtx,
+		ids: &[InoxNodeUuid],
+	) {
+		self.draw_textured_mesh_content(false, components, render_ctx, ids[0]);
+	}
 }
 
 pub trait InoxRendererExt {
-	/// Draw a Drawable, which is potentially masked.
+	/// Draw a Drawable, which is potentially masked. Nesting errors are logged, see `.try_draw()`.
+	///
+	/// Nodes are entered without names, see `InoxRenderer::on_enter_node()`.
 	fn draw_drawable(&self, as_mask: bool, comps: &World, id: InoxNodeUuid);
 
 	/// Draw one composite. `components` must be referencing `comps`.
+	///
+	/// Nodes are entered without names, see `InoxRenderer::on_enter_node()`.
 	fn draw_composite(&self, as_mask: bool, comps: &World, components: &CompositeComponents, id: InoxNodeUuid);
 
 	/// Iterate over top-level drawables (excluding masks) in zsort order,
 	/// and make draw calls correspondingly.
 	///
-	/// This effectively draws the complete puppet.
+	/// This effectively draws the complete puppet. Errors are logged, see `.try_draw()`.
 	fn draw(&self, puppet: &Puppet);
+
+	/// Same as `.draw()`, returning the first error encountered.
+	///
+	/// A drawable nested too deeply is skipped along with whatever it masks, and all other drawables are still drawn.
+	fn try_draw(&self, puppet: &Puppet) -> Result<(), RenderError>;
 }
 
-impl<T: InoxRenderer> InoxRendererExt for T {
-	fn draw_drawable(&self, as_mask: bool, comps: &World, id: InoxNodeUuid) {
-		let drawable_kind = DrawableKind::new(id, comps, false).expect("Node must be a Drawable.");
-		let masks = match drawable_kind {
-			DrawableKind::TexturedMesh(ref components) => &components.drawable.masks,
-			DrawableKind::Composite(ref components) => &components.drawable.masks,
-		};
+/// Name of node `id` for `InoxRenderer::on_enter_node()`, empty without `nodes` to look it up in.
+fn node_name(nodes: Option<&InoxNodeTree>, id: InoxNodeUuid) -> &str {
+	nodes
+		.and_then(|nodes| nodes.get_node(id))
+		.map_or("", |node| node.name.as_str())
+}
 
-		let mut has_masks = false;
-		if let Some(ref masks) = masks {
-			has_masks = true;
-			self.on_begin_masks(masks);
-			for mask in &masks.masks {
-				self.on_begin_mask(mask);
+/// Draw a Drawable as a mask source of the drawables in `path`, each one a mask source of the previous one.
+fn draw_nested<R: InoxRenderer>(
+	renderer: &R,
+	as_mask: bool,
+	comps: &World,
+	nodes: Option<&InoxNodeTree>,
+	id: InoxNodeUuid,
+	path: &mut Vec<InoxNodeUuid>,
+) -> Result<(), RenderError> {
+	if path.contains(&id) {
+		return Err(RenderError::MaskCycle { id: id.0 });
+	}
+	let max_depth = renderer.max_nesting_depth();
+	if path.len() > max_depth {
+		return Err(RenderError::CompositeNestingTooDeep { id: id.0, max_depth });
+	}
 
-				self.draw_drawable(true, comps, mask.source);
-			}
-			self.on_begin_masked_content();
-		}
+	path.push(id);
+	renderer.on_enter_node(id, node_name(nodes, id));
+	let drawn = draw_node(renderer, as_mask, comps, nodes, id, path);
+	renderer.on_exit_node(id);
+	path.pop();
+	drawn
+}
 
-		match drawable_kind {
-			DrawableKind::TexturedMesh(ref components) => {
-				self.draw_textured_mesh_content(as_mask, components, comps.get(id).unwrap(), id)
+/// Draw the masks, then the content of a Drawable, see `draw_nested()`.
+fn draw_node<R: InoxRenderer>(
+	renderer: &R,
+	as_mask: bool,
+	comps: &World,
+	nodes: Option<&InoxNodeTree>,
+	id: InoxNodeUuid,
+	path: &mut Vec<InoxNodeUuid>,
+) -> Result<(), RenderError> {
+	let drawable_kind = DrawableKind::new(id, comps, false).expect("Node must be a Drawable.");
+	let masks = match drawable_kind {
+		DrawableKind::TexturedMesh(ref components) => &components.drawable.masks,
+		DrawableKind::Composite(ref components) => &components.drawable.masks,
+		DrawableKind::PlainMesh(ref components) => &components.drawable.masks,
+	};
+
+	let mut drawn = Ok(());
+	if let Some(ref masks) = masks {
+		renderer.on_begin_masks(masks);
+		for mask in &masks.masks {
+			renderer.on_begin_mask(mask);
+
+			drawn = draw_nested(renderer, true, comps, nodes, mask.source, path);
+			if drawn.is_err() {
+				break;
 			}
-			DrawableKind::Composite(ref components) => self.draw_composite(as_mask, comps, components, id),
 		}
+		// on error, the renderer still goes through the whole masking sequence, with nothing masked
+		renderer.on_begin_masked_content();
+	}
 
-		if has_masks {
-			self.on_end_mask();
-		}
+	if drawn.is_ok() {
+		draw_content(renderer, as_mask, comps, nodes, drawable_kind, id);
 	}
 
-	fn draw_composite(&self, as_mask: bool, comps: &World, components: &CompositeComponents, id: InoxNodeUuid) {
-		let render_ctx = comps.get::<CompositeRenderCtx>(id).unwrap();
-		if render_ctx.zsorted_children_list.is_empty() {
-			// Optimization: Nothing to be drawn, skip context switching
-			return;
+	if masks.is_some() {
+		renderer.on_end_mask();
+	}
+	drawn
+}
+
+/// Draw the content of a Drawable, after its masks.
+fn draw_content<R: InoxRenderer>(
+	renderer: &R,
+	as_mask: bool,
+	comps: &World,
+	nodes: Option<&InoxNodeTree>,
+	drawable_kind: DrawableKind,
+	id: InoxNodeUuid,
+) {
+	match drawable_kind {
+		DrawableKind::TexturedMesh(ref components) => {
+			renderer.draw_textured_mesh_content(as_mask, components, comps.get(id).unwrap(), id)
+		}
+		DrawableKind::Composite(ref components) => draw_composite(renderer, as_mask, comps, nodes, components, id),
+		// nothing to see outside of masks
+		DrawableKind::PlainMesh(ref components) if as_mask => {
+			renderer.draw_plain_mesh_mask(components, comps.get(id).unwrap(), id)
 		}
+		DrawableKind::PlainMesh(_) => (),
+	}
+}
 
-		self.begin_composite_content(as_mask, components, render_ctx, id);
+/// Draw one composite and its children, see `InoxRendererExt::draw_composite()`.
+fn draw_composite<R: InoxRenderer>(
+	renderer: &R,
+	as_mask: bool,
+	comps: &World,
+	nodes: Option<&InoxNodeTree>,
+	components: &CompositeComponents,
+	id: InoxNodeUuid,
+) {
+	let render_ctx = comps.get::<CompositeRenderCtx>(id).unwrap();
+	if render_ctx.zsorted_children_list.is_empty() {
+		// Optimization: Nothing to be drawn, skip context switching
+		return;
+	}
 
-		for uuid in &render_ctx.zsorted_children_list {
-			let drawable_kind = DrawableKind::new(*uuid, comps, false)
-				.expect("All children in zsorted_children_list should be a Drawable.");
-			match drawable_kind {
-				DrawableKind::TexturedMesh(components) => {
-					self.draw_textured_mesh_content(as_mask, &components, comps.get(*uuid).unwrap(), *uuid)
-				}
-				DrawableKind::Composite { .. } => panic!("Composite inside Composite not allowed."),
+	renderer.begin_composite_content(as_mask, components, render_ctx, id);
+
+	for uuid in &render_ctx.zsorted_children_list {
+		let drawable_kind = DrawableKind::new(*uuid, comps, false)
+			.expect("All children in zsorted_children_list should be a Drawable.");
+		renderer.on_enter_node(*uuid, node_name(nodes, *uuid));
+		match drawable_kind {
+			DrawableKind::TexturedMesh(components) => {
+				renderer.draw_textured_mesh_content(as_mask, &components, comps.get(*uuid).unwrap(), *uuid)
 			}
+			DrawableKind::Composite { .. } => panic!("Composite inside Composite not allowed."),
+			DrawableKind::PlainMesh { .. } => panic!("Plain mesh inside Composite not allowed."),
 		}
+		renderer.on_exit_node(*uuid);
+	}
 
-		self.finish_composite_content(as_mask, components, render_ctx, id);
+	renderer.finish_composite_content(as_mask, components, render_ctx, id);
+}
+
+impl<T: InoxRenderer> InoxRendererExt for T {
+	fn draw_drawable(&self, as_mask: bool, comps: &World, id: InoxNodeUuid) {
+		if let Err(e) = draw_nested(self, as_mask, comps, None, id, &mut Vec::new()) {
+			tracing::error!("{e}");
+		}
+	}
+
+	fn draw_composite(&self, as_mask: bool, comps: &World, components: &CompositeComponents, id: InoxNodeUuid) {
+		draw_composite(self, as_mask, comps, None, components, id);
 	}
 
 	/// Dispatches draw calls for all nodes of `puppet`
@@ -337,18 +647,842 @@ impl<T: InoxRenderer> InoxRendererExt for T {
 	///
 	/// This does not guarantee the display of a puppet on screen due to these possible reasons:
 	/// - Only provided `InoxRenderer` method implementations are called.
-	/// 
+	///
 	/// For example, maybe the caller still need to transfer content from a texture buffer to the screen surface buffer.
 	/// - The provided `InoxRender` implementation is wrong.
 	/// - `puppet` here does not belong to the `model` this `renderer` is initialized with. This will likely result in panics for non-existent node uuids.
 	fn draw(&self, puppet: &Puppet) {
-		for uuid in &puppet
+		if let Err(e) = self.try_draw(puppet) {
+			tracing::error!("{e}");
+		}
+	}
+
+	fn try_draw(&self, puppet: &Puppet) -> Result<(), RenderError> {
+		let view = self.view_matrix();
+		let comps = &puppet.node_comps;
+
+		let drawables = puppet
 			.render_ctx
 			.as_ref()
 			.expect("RenderCtx of puppet must be initialized before calling draw().")
 			.root_drawables_zsorted
-		{
-			self.draw_drawable(false, &puppet.node_comps, *uuid);
+			.iter()
+			.filter(|uuid| view.is_none_or(|view| cull::in_view(puppet, &view, **uuid)));
+
+		let mut result = Ok(());
+		let mut path = Vec::new();
+		let mut draw_top_level = |uuid: InoxNodeUuid| {
+			let drawn = draw_nested(self, false, comps, Some(&puppet.nodes), uuid, &mut path);
+			if result.is_ok() {
+				result = drawn;
+			}
+		};
+
+		if !self.batch_parts() {
+			drawables.for_each(|uuid| draw_top_level(*uuid));
+			return result;
+		}
+
+		let drawables = drawables.copied().collect::<Vec<_>>();
+		for batch in drawables.chunk_by(|prev, next| batch::joins(comps, *prev, *next)) {
+			match batch {
+				[uuid] => draw_top_level(*uuid),
+				_ => {
+					let Some(DrawableKind::TexturedMesh(components)) = DrawableKind::new(batch[0], comps, false) else {
+						unreachable!("Only parts are batched.");
+					};
+					let names = batch.iter().map(|id| node_name(Some(&puppet.nodes), *id));
+					self.on_enter_node(batch[0], &names.collect::<Vec<_>>().join(", "));
+					self.draw_textured_mesh_batch(&components, &batch::merged_render_ctx(comps, batch), batch);
+					self.on_exit_node(batch[0]);
+				}
+			}
+		}
+		result
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use std::cell::RefCell;
+
+	use super::*;
+	use crate::formats::fixtures;
+
+	/// Renderer recording draw calls.
+	struct Recorder {
+		calls: RefCell<Vec<String>>,
+		/// Also record entering and exiting nodes.
+		nodes: bool,
+		/// Also record masking calls.
+		masks: bool,
+		view: Option<Mat4>,
+		batch_parts: bool,
+		max_nesting_depth: usize,
+	}
+
+	impl Default for Recorder {
+		fn default() -> Self {
+			Self {
+				calls: RefCell::default(),
+				nodes: false,
+				masks: false,
+				view: None,
+				batch_parts: false,
+				max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+			}
+		}
+	}
+
+	impl Recorder {
+		fn record_mask_call(&self, call: &str) {
+			if self.masks {
+				self.calls.borrow_mut().push(call.to_owned());
+			}
 		}
 	}
+
+	impl InoxRenderer for Recorder {
+		fn on_begin_masks(&self, _masks: &Masks) {
+			self.record_mask_call("begin masks");
+		}
+		fn on_begin_mask(&self, _mask: &Mask) {
+			self.record_mask_call("begin mask");
+		}
+		fn on_begin_masked_content(&self) {
+			self.record_mask_call("masked content");
+		}
+		fn on_end_mask(&self) {
+			self.record_mask_call("end mask");
+		}
+
+		fn on_enter_node(&self, id: InoxNodeUuid, name: &str) {
+			if self.nodes {
+				self.calls.borrow_mut().push(format!("enter {} {name}", id.0));
+			}
+		}
+
+		fn on_exit_node(&self, id: InoxNodeUuid) {
+			if self.nodes {
+				self.calls.borrow_mut().push(format!("exit {}", id.0));
+			}
+		}
+
+		fn draw_textured_mesh_content(
+			&self,
+			_as_mask: bool,
+			_components: &TexturedMeshComponents,
+			_render_ctx: &TexturedMeshRenderCtx,
+			id: InoxNodeUuid,
+		) {
+			self.calls.borrow_mut().push(format!("part {}", id.0));
+		}
+
+		fn draw_plain_mesh_mask(
+			&self,
+			_components: &PlainMeshComponents,
+			_render_ctx: &TexturedMeshRenderCtx,
+			id: InoxNodeUuid,
+		) {
+			self.calls.borrow_mut().push(format!("plain mask {}", id.0));
+		}
+
+		fn begin_composite_content(
+			&self,
+			_as_mask: bool,
+			_components: &CompositeComponents,
+			_render_ctx: &CompositeRenderCtx,
+			id: InoxNodeUuid,
+		) {
+			self.calls.borrow_mut().push(format!("begin composite {}", id.0));
+		}
+
+		fn finish_composite_content(
+			&self,
+			_as_mask: bool,
+			_components: &CompositeComponents,
+			_render_ctx: &CompositeRenderCtx,
+			id: InoxNodeUuid,
+		) {
+			self.calls.borrow_mut().push(format!("finish composite {}", id.0));
+		}
+
+		fn view_matrix(&self) -> Option<Mat4> {
+			self.view
+		}
+
+		fn batch_parts(&self) -> bool {
+			self.batch_parts
+		}
+
+		fn max_nesting_depth(&self) -> usize {
+			self.max_nesting_depth
+		}
+
+		fn draw_textured_mesh_batch(
+			&self,
+			_components: &TexturedMeshComponents,
+			render_ctx: &TexturedMeshRenderCtx,
+			ids: &[InoxNodeUuid],
+		) {
+			let ids = ids.iter().map(|id| id.0.to_string()).collect::<Vec<_>>();
+			self.calls
+				.borrow_mut()
+				.push(format!("parts {} ({} indices)", ids.join(" "), render_ctx.index_len));
+		}
+	}
+
+	#[test]
+	fn nested_composite_skipped() {
+		let nested = fixtures::composite(2, "nested", vec![fixtures::part(3, "nested part")]);
+		let composite = fixtures::composite(1, "composite", vec![nested, fixtures::part(4, "part")]);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![composite]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder::default();
+		recorder.draw(&puppet);
+		assert_eq!(
+			recorder.calls.into_inner(),
+			["begin composite 1", "part 4", "finish composite 1"]
+		);
+	}
+
+	#[test]
+	fn empty_composite_not_drawn() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::composite(1, "empty", Vec::new()), fixtures::part(2, "part")],
+			),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder::default();
+		recorder.draw(&puppet);
+		assert_eq!(recorder.calls.into_inner(), ["part 2"]);
+	}
+
+	#[test]
+	fn swap_mask_source() {
+		let mut masked = fixtures::part(3, "masked");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::part(1, "circle"), fixtures::part(2, "square"), masked],
+			),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let masked_draws = |puppet: &Puppet| {
+			let recorder = Recorder::default();
+			recorder.draw_drawable(false, &puppet.node_comps, InoxNodeUuid(3));
+			recorder.calls.into_inner()
+		};
+		assert_eq!(masked_draws(&puppet), ["part 1", "part 3"]);
+
+		puppet.set_mask_source(InoxNodeUuid(3), 0, InoxNodeUuid(2)).unwrap();
+		assert_eq!(masked_draws(&puppet), ["part 2", "part 3"]);
+	}
+
+	#[test]
+	fn composite_mask_source() {
+		let mut masked = fixtures::part(3, "masked");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let hair = fixtures::composite(1, "hair", vec![fixtures::part(2, "strand")]);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![hair, masked]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder::default();
+		recorder.draw_drawable(false, &puppet.node_comps, InoxNodeUuid(3));
+		assert_eq!(
+			recorder.calls.into_inner(),
+			["begin composite 1", "part 2", "finish composite 1", "part 3"]
+		);
+	}
+
+	#[test]
+	fn node_hooks() {
+		let mut masked = fixtures::part(3, "masked");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let hair = fixtures::composite(1, "hair", vec![fixtures::part(2, "strand")]);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![hair, masked]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder {
+			nodes: true,
+			..Default::default()
+		};
+		draw_nested(
+			&recorder,
+			false,
+			&puppet.node_comps,
+			Some(&puppet.nodes),
+			InoxNodeUuid(3),
+			&mut Vec::new(),
+		)
+		.unwrap();
+		assert_eq!(
+			recorder.calls.into_inner(),
+			[
+				"enter 3 masked",
+				"enter 1 hair",
+				"begin composite 1",
+				"enter 2 strand",
+				"part 2",
+				"exit 2",
+				"finish composite 1",
+				"exit 1",
+				"part 3",
+				"exit 3",
+			]
+		);
+	}
+
+	#[test]
+	fn plain_mesh_mask() {
+		let mut mask = fixtures::part(1, "eye clip");
+		mask["type"] = "Mask".into();
+		mask.remove("textures");
+		let mut masked = fixtures::part(2, "eye");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![mask, masked]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		// only drawn as the mask of the eye
+		let recorder = Recorder::default();
+		recorder.draw(&puppet);
+		assert_eq!(recorder.calls.into_inner(), ["plain mask 1", "part 2"]);
+		assert!(puppet
+			.node_comps
+			.get::<TexturedMeshRenderCtx>(InoxNodeUuid(1))
+			.is_some());
+	}
+
+	#[test]
+	fn physics_inside_composite() {
+		let composite = fixtures::composite(
+			1,
+			"composite",
+			vec![fixtures::part(2, "part"), fixtures::simple_physics(3, "physics", 11)],
+		);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![composite]),
+			vec![
+				fixtures::param(
+					10,
+					"move",
+					vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 100.0.into()])],
+				),
+				fixtures::param(11, "sway", Vec::new()),
+			],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.init_physics();
+
+		for frame in 0..30 {
+			puppet.begin_frame();
+			let param_ctx = puppet.param_ctx.as_mut().unwrap();
+			param_ctx.set("move", glam::vec2(frame as f32 / 30.0, 0.0)).unwrap();
+			puppet.end_frame(if frame == 0 { 0.0 } else { 1.0 / 60.0 });
+		}
+
+		// moving the composite moves its physics child, which swings
+		let sway = puppet.param_ctx.as_ref().unwrap().get("sway").unwrap();
+		assert!(sway.is_finite() && sway != glam::Vec2::ZERO);
+
+		let recorder = Recorder::default();
+		recorder.draw(&puppet);
+		assert_eq!(
+			recorder.calls.into_inner(),
+			["begin composite 1", "part 2", "finish composite 1"]
+		);
+	}
+
+	#[test]
+	fn build_empty_puppet() {
+		use glam::{vec2, Vec2};
+
+		use crate::math::transform::TransformOffset;
+		use crate::node::components::{Blending, Drawable, Mesh, TexturedMesh};
+		use crate::node::InoxNode;
+		use crate::physics::PuppetPhysics;
+		use crate::puppet::meta::PuppetMeta;
+		use crate::texture::TextureId;
+
+		let mut puppet = Puppet::empty(PuppetMeta::default(), PuppetPhysics::default());
+
+		let part = InoxNode {
+			uuid: InoxNodeUuid(1),
+			name: "part".to_owned(),
+			enabled: true,
+			zsort: 0.,
+			trans_offset: TransformOffset::default(),
+			lock_to_root: false,
+			group: None,
+		};
+		puppet.add_node(InoxNodeUuid(0), part, |comps| {
+			comps.add(
+				InoxNodeUuid(1),
+				Drawable {
+					blending: Blending::default(),
+					masks: None,
+				},
+			);
+			comps.add(
+				InoxNodeUuid(1),
+				TexturedMesh {
+					tex_albedo: TextureId(0),
+					tex_emissive: TextureId(0),
+					tex_bumpmap: TextureId(0),
+					emission_strength: 1.,
+				},
+			);
+			comps.add(
+				InoxNodeUuid(1),
+				Mesh {
+					vertices: vec![vec2(-1., -1.), vec2(1., -1.), vec2(-1., 1.)],
+					uvs: vec![vec2(0., 0.), vec2(1., 0.), vec2(0., 1.)],
+					indices: vec![0, 1, 2],
+					origin: Vec2::ZERO,
+				},
+			);
+		});
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let render_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(InoxNodeUuid(1)).unwrap();
+		assert_eq!((render_ctx.index_len, render_ctx.vert_len), (3, 3));
+		let recorder = Recorder::default();
+		recorder.draw(&puppet);
+		assert_eq!(recorder.calls.into_inner(), ["part 1"]);
+	}
+
+	#[test]
+	fn dirty_deform_range() {
+		let no_deform = json::array![[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let deform = json::array![[0.0, 0.0], [0.0, 0.5], [0.0, 0.5], [0.0, 0.0]];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::part(1, "still"), fixtures::part(2, "deformed")],
+			),
+			vec![fixtures::param(
+				10,
+				"deform",
+				vec![fixtures::binding(2, "deform", [no_deform, deform])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let frame = |puppet: &mut Puppet, value: f32| {
+			puppet.begin_frame();
+			puppet
+				.param_ctx
+				.as_mut()
+				.unwrap()
+				.set("deform", glam::vec2(value, 0.0))
+				.unwrap();
+			puppet.end_frame(0.0);
+
+			let render_ctx = puppet.render_ctx.as_ref().unwrap();
+			let part_offset = puppet
+				.node_comps
+				.get::<TexturedMeshRenderCtx>(InoxNodeUuid(2))
+				.unwrap()
+				.vert_offset as usize;
+			render_ctx
+				.dirty_deform_range()
+				.map(|range| (range.start - part_offset)..(range.end - part_offset))
+		};
+
+		assert_eq!(frame(&mut puppet, 1.0), Some(1..3));
+		// nothing changed, nothing to upload
+		assert_eq!(frame(&mut puppet, 1.0), None);
+		assert_eq!(frame(&mut puppet, 0.0), Some(1..3));
+
+		// two frames, then one draw: what changed in the first frame must still be uploaded
+		let generation = puppet.render_ctx.as_ref().unwrap().deform_generation();
+		frame(&mut puppet, 1.0);
+		assert_eq!(frame(&mut puppet, 1.0), None);
+		let render_ctx = puppet.render_ctx.as_ref().unwrap();
+		let dirty = render_ctx.dirty_deform_range_since(generation).unwrap();
+		assert_eq!(dirty.len(), 2);
+		assert_eq!(
+			render_ctx.dirty_deform_range_since(render_ctx.deform_generation()),
+			None
+		);
+	}
+
+	#[test]
+	fn part_as_root() {
+		let mut root = fixtures::part(1, "root part");
+		root["transform"]["trans"] = json::array![3.0, 0.0, 0.0];
+		root["children"] = json::array![fixtures::part(2, "child")];
+		let mut puppet = fixtures::load(&fixtures::puppet(root, Vec::new()));
+		assert_ne!(puppet.nodes.root_node_id, InoxNodeUuid(1));
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder::default();
+		recorder.draw(&puppet);
+		let mut drawn = recorder.calls.into_inner();
+		drawn.sort();
+		assert_eq!(drawn, ["part 1", "part 2"]);
+
+		let translation = |id| puppet.node_world_matrix(InoxNodeUuid(id)).unwrap().w_axis.x;
+		assert_eq!(translation(1), 3.0);
+		assert_eq!(translation(2), 3.0);
+	}
+
+	#[test]
+	fn cull_off_screen() {
+		let mut off_screen = fixtures::part(2, "off screen");
+		off_screen["transform"]["trans"] = json::array![100.0, 0.0, 0.0];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "on screen"), off_screen]),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		// a view of puppet space from -10 to 10
+		let recorder = Recorder {
+			view: Some(Mat4::from_scale(glam::Vec3::splat(0.1))),
+			..Default::default()
+		};
+		recorder.draw(&puppet);
+		assert_eq!(recorder.calls.into_inner(), ["part 1"]);
+	}
+
+	#[test]
+	fn deform_scale() {
+		let no_deform = json::array![[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let deform = json::array![[0.0, 1.0], [0.0, 0.5], [2.0, 0.0], [0.0, 0.0]];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "deformed")]),
+			vec![fixtures::param(
+				10,
+				"deform",
+				vec![fixtures::binding(1, "deform", [no_deform, deform])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let mut deforms = |scale: f32| {
+			puppet.set_deform_scale(scale);
+			puppet.begin_frame();
+			puppet
+				.param_ctx
+				.as_mut()
+				.unwrap()
+				.set("deform", glam::vec2(1.0, 0.0))
+				.unwrap();
+			puppet.end_frame(0.0);
+
+			let render_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(InoxNodeUuid(1)).unwrap();
+			let vert_offset = render_ctx.vert_offset as usize;
+			puppet.render_ctx.as_ref().unwrap().vertex_buffers.deforms[vert_offset..][..render_ctx.vert_len].to_vec()
+		};
+
+		let full = deforms(1.0);
+		let halved = deforms(0.5);
+		assert_eq!(halved, full.iter().map(|deform| *deform * 0.5).collect::<Vec<_>>());
+		assert!(deforms(0.0).iter().all(|deform| *deform == Vec2::ZERO));
+	}
+
+	#[test]
+	fn rest_deform() {
+		let mut part = fixtures::part(1, "rest deformed");
+		part["restDeform"] = json::array![0.0, 1.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![part]),
+			vec![fixtures::param(10, "unbound", Vec::new())],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let mut deforms = |scale: f32| {
+			puppet.set_deform_scale(scale);
+			puppet.begin_frame();
+			puppet.end_frame(0.0);
+
+			let render_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(InoxNodeUuid(1)).unwrap();
+			let vert_offset = render_ctx.vert_offset as usize;
+			puppet.render_ctx.as_ref().unwrap().vertex_buffers.deforms[vert_offset..][..render_ctx.vert_len].to_vec()
+		};
+
+		let rest = [Vec2::new(0.0, 1.0), Vec2::ZERO, Vec2::new(0.5, 0.0), Vec2::ZERO];
+		assert_eq!(deforms(1.0), rest);
+		// deform scale only applies to param deforms
+		assert_eq!(deforms(0.0), rest);
+	}
+
+	#[test]
+	fn batch_same_state_parts() {
+		let mut masked = fixtures::part(4, "masked");
+		masked["masks"] = json::array![{ source: 1, mode: "Mask" }];
+		let mut tinted = fixtures::part(6, "tinted");
+		tinted["tint"] = json::array![1.0, 0.0, 0.0];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![
+					fixtures::part(1, "a"),
+					fixtures::part(2, "b"),
+					fixtures::part(3, "c"),
+					masked,
+					fixtures::part(5, "d"),
+					tinted,
+				],
+			),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder {
+			batch_parts: true,
+			..Default::default()
+		};
+		recorder.draw(&puppet);
+		assert_eq!(
+			recorder.calls.into_inner(),
+			["parts 1 2 3 (18 indices)", "part 1", "part 4", "part 5", "part 6"]
+		);
+
+		// a batch is entered as a single node
+		let recorder = Recorder {
+			batch_parts: true,
+			nodes: true,
+			..Default::default()
+		};
+		recorder.draw(&puppet);
+		assert_eq!(
+			recorder.calls.into_inner(),
+			[
+				"enter 1 a, b, c",
+				"parts 1 2 3 (18 indices)",
+				"exit 1",
+				"enter 4 masked",
+				"enter 1 a",
+				"part 1",
+				"exit 1",
+				"part 4",
+				"exit 4",
+				"enter 5 d",
+				"part 5",
+				"exit 5",
+				"enter 6 tinted",
+				"part 6",
+				"exit 6",
+			]
+		);
+	}
+
+	#[test]
+	fn mask_nesting_bounded() {
+		// each part is masked by the next one, the last one by the first one
+		let parts = (1..=4)
+			.map(|id| {
+				let mut part = fixtures::part(id, "masked");
+				part["masks"] = json::array![{ source: id % 4 + 1, mode: "Mask" }];
+				part
+			})
+			.collect();
+		let mut puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", parts), Vec::new()));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder {
+			max_nesting_depth: 2,
+			..Default::default()
+		};
+		assert!(matches!(
+			recorder.try_draw(&puppet),
+			Err(RenderError::CompositeNestingTooDeep { max_depth: 2, .. })
+		));
+		// nothing reaches the screen, as every drawable is masked by a too deep chain
+		assert!(recorder.calls.into_inner().is_empty());
+
+		// the loop is found before the default depth is reached
+		assert!(matches!(
+			Recorder::default().try_draw(&puppet),
+			Err(RenderError::MaskCycle { .. })
+		));
+	}
+
+	#[test]
+	fn mask_cycle() {
+		// each part is masked by both other parts, which would take 2^depth draws to bound by depth
+		let parts = (1..=3)
+			.map(|id| {
+				let mut part = fixtures::part(id, "masked");
+				part["masks"] = json::array![
+					{ source: id % 3 + 1, mode: "Mask" },
+					{ source: (id + 1) % 3 + 1, mode: "Mask" },
+				];
+				part
+			})
+			.collect();
+		let mut puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", parts), Vec::new()));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.begin_frame();
+		puppet.end_frame(0.0);
+
+		let recorder = Recorder {
+			masks: true,
+			..Default::default()
+		};
+		assert!(matches!(
+			recorder.try_draw(&puppet),
+			Err(RenderError::MaskCycle { id: 1 })
+		));
+
+		// every masking sequence is completed, and nothing is drawn
+		let calls = recorder.calls.into_inner();
+		assert!(!calls.iter().any(|call| call.starts_with("part")));
+		let mut open = Vec::new();
+		for call in &calls {
+			match call.as_str() {
+				"begin masks" => open.push(false),
+				"begin mask" => assert_eq!(open.last(), Some(&false)),
+				"masked content" => *open.last_mut().unwrap() = true,
+				"end mask" => assert_eq!(open.pop(), Some(true)),
+				_ => unreachable!(),
+			}
+		}
+		assert!(open.is_empty());
+		// each top-level part stops at the first loop, two mask sources deep
+		assert_eq!(calls.iter().filter(|call| *call == "begin masks").count(), 3 * 3);
+	}
+
+	#[test]
+	fn zsort_ties_in_tree_order() {
+		let composite = fixtures::composite(3, "composite", vec![fixtures::part(4, "a"), fixtures::part(5, "b")]);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::part(1, "a"), fixtures::part(2, "b"), composite],
+			),
+			Vec::new(),
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+
+		let draw_order = |puppet: &mut Puppet, zsort_b: f32| {
+			for id in [2, 5] {
+				puppet.nodes.get_node_mut(InoxNodeUuid(id)).unwrap().zsort = zsort_b;
+			}
+			puppet.begin_frame();
+			puppet.end_frame(0.0);
+			puppet.layer_list().iter().map(|layer| layer.uuid.0).collect::<Vec<_>>()
+		};
+
+		let tree_order = draw_order(&mut puppet, 0.0);
+		assert_eq!(tree_order, [1, 2, 3, 4, 5]);
+		assert_eq!(draw_order(&mut puppet, 1.0), [2, 1, 3, 5, 4]);
+		for _ in 0..3 {
+			assert_eq!(draw_order(&mut puppet, 0.0), tree_order);
+		}
+	}
+
+	#[test]
+	fn vertex_buffer_overflow() {
+		// a part with a mesh of `vert_count` vertices, drawing its first triangle `triangle_count` times
+		let part = |uuid, vert_count: usize, triangle_count: usize| {
+			let mut part = fixtures::part(uuid, "part");
+			let coords = vec![0.0; 2 * vert_count];
+			part["mesh"]["verts"] = coords.clone().into();
+			part["mesh"]["uvs"] = coords.into();
+			part["mesh"]["indices"] = [0, 1, 2].repeat(triangle_count).into();
+			part
+		};
+		let try_init_rendering = |parts| {
+			let mut puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", parts), Vec::new()));
+			puppet.init_transforms();
+			let result = puppet.try_init_rendering();
+			// nothing is left half installed
+			if result.is_err() {
+				assert!(puppet.render_ctx.is_none());
+				assert!(puppet
+					.nodes
+					.iter()
+					.all(|node| puppet.node_comps.get::<TexturedMeshRenderCtx>(node.uuid).is_none()));
+			}
+			result
+		};
+
+		// vertex buffers start with a quad of 4 vertices and 6 indices
+		assert_eq!(try_init_rendering(vec![part(1, MAX_VERTICES - 4, 1)]), Ok(()));
+		assert_eq!(
+			try_init_rendering(vec![part(1, 40_000, 1), part(2, 40_000, 1)]),
+			Err(VertexBufferOverflow::Vertices(80_004))
+		);
+		assert_eq!(try_init_rendering(vec![part(1, 3, 21_843), part(2, 3, 1)]), Ok(()));
+		assert_eq!(
+			try_init_rendering(vec![part(1, 3, 21_844), part(2, 3, 1)]),
+			Err(VertexBufferOverflow::Indices(65_538))
+		);
+		assert_eq!(
+			VertexBufferOverflow::Vertices(80_004).to_string(),
+			"puppet has 80004 vertices, more than the limit of 65536 in a single vertex buffer"
+		);
+	}
 }
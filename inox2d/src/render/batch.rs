@@ -0,0 +1,57 @@
+use crate::node::components::{Drawable, TexturedMesh, TransformStore};
+use crate::node::InoxNodeUuid;
+use crate::puppet::World;
+
+use super::TexturedMeshRenderCtx;
+
+/// Whether part `next`, drawn right after part `prev`, can be drawn along with it in one draw call:
+/// neither is masked, they share textures, blending, emission and transform, and their indices are adjacent.
+/// Parts blended with what is below in a shader must see each other, so are never batched.
+pub(super) fn joins(comps: &World, prev: InoxNodeUuid, next: InoxNodeUuid) -> bool {
+	let state = |id: InoxNodeUuid| {
+		Some((
+			comps.get::<TexturedMeshRenderCtx>(id)?,
+			comps.get::<Drawable>(id).filter(|drawable| !drawable.has_masks())?,
+			comps.get::<TexturedMesh>(id)?,
+			comps.get::<TransformStore>(id)?,
+		))
+	};
+	let (
+		Some((prev_ctx, prev_drawable, prev_texture, prev_transform)),
+		Some((next_ctx, next_drawable, next_texture, next_transform)),
+	) = (state(prev), state(next))
+	else {
+		return false;
+	};
+
+	let (prev_blending, next_blending) = (&prev_drawable.blending, &next_drawable.blending);
+	prev_ctx.index_offset as usize + prev_ctx.index_len == next_ctx.index_offset as usize
+		&& (
+			prev_texture.tex_albedo,
+			prev_texture.tex_emissive,
+			prev_texture.tex_bumpmap,
+		) == (
+			next_texture.tex_albedo,
+			next_texture.tex_emissive,
+			next_texture.tex_bumpmap,
+		) && prev_texture.emission_strength == next_texture.emission_strength
+		&& prev_blending.mode == next_blending.mode
+		&& !prev_blending.mode.reads_backdrop()
+		&& prev_blending.tint == next_blending.tint
+		&& prev_blending.screen_tint == next_blending.screen_tint
+		&& prev_blending.opacity == next_blending.opacity
+		&& prev_transform.absolute == next_transform.absolute
+}
+
+/// Render context spanning the indices and vertices of consecutive parts `ids`, which must all join.
+pub(super) fn merged_render_ctx(comps: &World, ids: &[InoxNodeUuid]) -> TexturedMeshRenderCtx {
+	let render_ctxs = ids.iter().map(|id| comps.get::<TexturedMeshRenderCtx>(*id).unwrap());
+	let first = comps.get::<TexturedMeshRenderCtx>(ids[0]).unwrap();
+
+	TexturedMeshRenderCtx {
+		index_offset: first.index_offset,
+		vert_offset: first.vert_offset,
+		index_len: render_ctxs.clone().map(|render_ctx| render_ctx.index_len).sum(),
+		vert_len: render_ctxs.map(|render_ctx| render_ctx.vert_len).sum(),
+	}
+}
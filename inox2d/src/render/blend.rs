@@ -0,0 +1,139 @@
+use glam::{Vec3, Vec4, Vec4Swizzles};
+
+use crate::node::components::BlendMode;
+
+/// CPU reference of how a backend blends a premultiplied `src` color over a premultiplied `dst` color in a unorm buffer.
+///
+/// This mirrors the fixed function blending of the OpenGL renderer and the reference implementation,
+/// and the shader blending of modes that `BlendMode::reads_backdrop()`,
+/// and is there to pin down blend mode semantics, e.g. for validating a backend.
+pub fn blend_reference(mode: BlendMode, src: Vec4, dst: Vec4) -> Vec4 {
+	let one = Vec4::ONE;
+	let src_a = Vec4::splat(src.w);
+	let dst_a = Vec4::splat(dst.w);
+
+	let blended = match mode {
+		BlendMode::Normal => src + dst * (one - src_a),
+		BlendMode::Multiply => src * dst + dst * (one - src_a),
+		BlendMode::ColorDodge => src * dst + dst,
+		BlendMode::LinearDodge => src + dst,
+		BlendMode::Screen => src + dst * (one - src),
+		// only keep src where there is something below
+		BlendMode::ClipToLower => src * dst_a + dst * (one - src_a),
+		// src where there is nothing below, cut out what is below otherwise
+		BlendMode::SliceFromLower => src * (one - dst_a) - dst * (one - src_a),
+		BlendMode::Darken => separable_over(src, dst, (src.xyz() * dst.w).min(dst.xyz() * src.w)),
+		BlendMode::Lighten => separable_over(src, dst, (src.xyz() * dst.w).max(dst.xyz() * src.w)),
+		// hard light with src and dst swapped
+		BlendMode::Overlay => separable_over(src, dst, hard_light(dst, src)),
+		BlendMode::HardLight => separable_over(src, dst, hard_light(src, dst)),
+	};
+
+	blended.clamp(Vec4::ZERO, one)
+}
+
+/// Premultiplied `src` over `dst`, with `blended` where both are present: the color of a separable blend mode,
+/// premultiplied by the alpha of both.
+fn separable_over(src: Vec4, dst: Vec4, blended: Vec3) -> Vec4 {
+	let color = src.xyz() * (1.0 - dst.w) + dst.xyz() * (1.0 - src.w) + blended;
+	color.extend(src.w + dst.w * (1.0 - src.w))
+}
+
+/// Hard light of premultiplied `top` on `bottom`, premultiplied by the alpha of both.
+fn hard_light(top: Vec4, bottom: Vec4) -> Vec3 {
+	Vec3::select(
+		(top.xyz() * 2.0).cmple(Vec3::splat(top.w)),
+		// multiply where the top is dark, screen where it is light
+		top.xyz() * bottom.xyz() * 2.0,
+		Vec3::splat(top.w * bottom.w) - (Vec3::splat(top.w) - top.xyz()) * (Vec3::splat(bottom.w) - bottom.xyz()) * 2.0,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::vec4;
+
+	use super::*;
+
+	const RED: Vec4 = vec4(1.0, 0.0, 0.0, 1.0);
+	const HALF_BLUE: Vec4 = vec4(0.0, 0.0, 0.5, 0.5);
+	const CLEAR: Vec4 = Vec4::ZERO;
+
+	#[test]
+	fn clip_to_lower() {
+		// opaque backdrop: plain src over
+		assert_eq!(
+			blend_reference(BlendMode::ClipToLower, HALF_BLUE, RED),
+			vec4(0.5, 0.0, 0.5, 1.0)
+		);
+		// nothing below: nothing drawn
+		assert_eq!(blend_reference(BlendMode::ClipToLower, RED, CLEAR), CLEAR);
+		// half transparent backdrop: src scaled by its coverage
+		assert_eq!(
+			blend_reference(BlendMode::ClipToLower, RED, HALF_BLUE),
+			vec4(0.5, 0.0, 0.0, 0.5)
+		);
+	}
+
+	#[test]
+	fn slice_from_lower() {
+		// nothing below: src as is
+		assert_eq!(blend_reference(BlendMode::SliceFromLower, HALF_BLUE, CLEAR), HALF_BLUE);
+		// opaque src over an opaque backdrop: cut out
+		assert_eq!(blend_reference(BlendMode::SliceFromLower, RED, RED), CLEAR);
+		// src not fully covering an opaque backdrop: the subtraction clamps to transparent
+		assert_eq!(blend_reference(BlendMode::SliceFromLower, HALF_BLUE, RED), CLEAR);
+		// opaque src over a half transparent backdrop: src scaled by what is missing below
+		assert_eq!(
+			blend_reference(BlendMode::SliceFromLower, RED, HALF_BLUE),
+			vec4(0.5, 0.0, 0.0, 0.5)
+		);
+	}
+
+	#[test]
+	fn darken_lighten() {
+		// half covered: half of the darkest/lightest of both, half of what is below
+		assert_eq!(
+			blend_reference(BlendMode::Darken, HALF_BLUE, RED),
+			vec4(0.5, 0.0, 0.0, 1.0)
+		);
+		assert_eq!(
+			blend_reference(BlendMode::Lighten, HALF_BLUE, RED),
+			vec4(1.0, 0.0, 0.5, 1.0)
+		);
+		for mode in [BlendMode::Darken, BlendMode::Lighten] {
+			// transparent texels leave what is below alone
+			assert_eq!(blend_reference(mode, CLEAR, RED), RED);
+			assert_eq!(blend_reference(mode, CLEAR, HALF_BLUE), HALF_BLUE);
+			// src over where nothing is below
+			assert_eq!(blend_reference(mode, HALF_BLUE, CLEAR), HALF_BLUE);
+		}
+	}
+
+	#[test]
+	fn overlay_hard_light() {
+		const GRAY: Vec4 = vec4(0.5, 0.5, 0.5, 1.0);
+		const DARK: Vec4 = vec4(0.25, 0.25, 0.25, 1.0);
+		const LIGHT: Vec4 = vec4(0.75, 0.75, 0.75, 1.0);
+
+		// the dark dst multiplies in overlay, the light src screens in hard light
+		assert_eq!(
+			blend_reference(BlendMode::Overlay, LIGHT, DARK),
+			vec4(0.375, 0.375, 0.375, 1.0)
+		);
+		assert_eq!(
+			blend_reference(BlendMode::HardLight, LIGHT, DARK),
+			vec4(0.625, 0.625, 0.625, 1.0)
+		);
+		// overlay of a and b is hard light of b and a
+		assert_eq!(
+			blend_reference(BlendMode::Overlay, DARK, LIGHT),
+			blend_reference(BlendMode::HardLight, LIGHT, DARK)
+		);
+		// gray is neutral in hard light
+		assert_eq!(blend_reference(BlendMode::HardLight, GRAY, DARK), DARK);
+		// src over where nothing is below, dst where nothing is drawn
+		assert_eq!(blend_reference(BlendMode::Overlay, HALF_BLUE, CLEAR), HALF_BLUE);
+		assert_eq!(blend_reference(BlendMode::HardLight, CLEAR, RED), RED);
+	}
+}
@@ -0,0 +1,101 @@
+use glam::{Mat4, Vec2};
+
+use crate::node::components::TransformStore;
+use crate::node::InoxNodeUuid;
+use crate::puppet::Puppet;
+
+use super::{CompositeRenderCtx, TexturedMeshRenderCtx};
+
+/// Axis-aligned bounds of a drawable in puppet space, deformed. `None` if it has no vertices.
+fn world_bounds(puppet: &Puppet, id: InoxNodeUuid) -> Option<(Vec2, Vec2)> {
+	let comps = &puppet.node_comps;
+
+	if let Some(render_ctx) = comps.get::<CompositeRenderCtx>(id) {
+		return render_ctx
+			.zsorted_children_list
+			.iter()
+			.filter_map(|child| world_bounds(puppet, *child))
+			.reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)));
+	}
+
+	let render_ctx = comps.get::<TexturedMeshRenderCtx>(id)?;
+	let transform = &comps.get::<TransformStore>(id)?.absolute;
+	let vertex_buffers = &puppet.render_ctx.as_ref()?.vertex_buffers;
+
+	let range = render_ctx.vert_offset as usize..render_ctx.vert_offset as usize + render_ctx.vert_len;
+	vertex_buffers.verts[range.clone()]
+		.iter()
+		.zip(&vertex_buffers.deforms[range])
+		.map(|(vert, deform)| transform.transform_point3((*vert + *deform).extend(0.0)).truncate())
+		.map(|position| (position, position))
+		.reduce(|(min, max), (position, _)| (min.min(position), max.max(position)))
+}
+
+impl Puppet {
+	/// Axis-aligned bounds of all drawables in puppet space, with deforms as of the last `.end_frame()`,
+	/// e.g. to frame the puppet with `Camera::fit_bounds()`.
+	///
+	/// `None` if rendering is not initialized or the puppet has nothing to draw.
+	pub fn bounding_box(&self) -> Option<(Vec2, Vec2)> {
+		self.render_ctx
+			.as_ref()?
+			.root_drawables_zsorted()
+			.iter()
+			.filter_map(|id| world_bounds(self, *id))
+			.reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+	}
+}
+
+/// Whether drawable `id` may be visible through `view`, a matrix from puppet space to clip space.
+///
+/// Drawables whose screen-space bounds lie fully outside of clip space are not.
+pub(super) fn in_view(puppet: &Puppet, view: &Mat4, id: InoxNodeUuid) -> bool {
+	let Some((min, max)) = world_bounds(puppet, id) else {
+		return false;
+	};
+
+	let corners = [min, Vec2::new(max.x, min.y), Vec2::new(min.x, max.y), max];
+	let (screen_min, screen_max) = corners
+		.iter()
+		.map(|corner| view.project_point3(corner.extend(0.0)).truncate())
+		.fold((Vec2::MAX, Vec2::MIN), |(min, max), corner| {
+			(min.min(corner), max.max(corner))
+		});
+
+	screen_max.cmpge(Vec2::NEG_ONE).all() && screen_min.cmple(Vec2::ONE).all()
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::vec2;
+
+	use crate::formats::fixtures;
+
+	#[test]
+	fn bounding_box() {
+		let mut moved = fixtures::part(2, "moved");
+		moved["transform"]["trans"] = json::array![10.0, 0.0, 0.0];
+		moved["transform"]["scale"] = json::array![2.0, 3.0];
+		let composite = fixtures::composite(3, "composite", vec![fixtures::part(4, "inner")]);
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part"), moved, composite]),
+			Vec::new(),
+		));
+		assert_eq!(puppet.bounding_box(), None);
+
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+		puppet.begin_frame();
+		puppet.end_frame(0.016);
+		assert_eq!(puppet.bounding_box(), Some((vec2(-1.0, -3.0), vec2(12.0, 3.0))));
+
+		let mut empty = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::composite(1, "empty", Vec::new())]),
+			Vec::new(),
+		));
+		empty.init_transforms();
+		empty.init_rendering();
+		assert_eq!(empty.bounding_box(), None);
+	}
+}
@@ -1,17 +1,20 @@
 use std::collections::HashMap;
 use std::mem::swap;
+use std::ops::Range;
 
 use glam::Vec2;
 
 use crate::math::deform::{linear_combine, Deform};
 use crate::node::components::{DeformSource, DeformStack};
+use crate::node::InoxNodeUuid;
 use crate::puppet::{InoxNodeTree, World};
 
 impl DeformStack {
-	pub(crate) fn new(deform_len: usize) -> Self {
+	pub(crate) fn new(deform_len: usize, baseline: Option<Vec<Vec2>>) -> Self {
 		Self {
 			deform_len,
 			stack: HashMap::new(),
+			baseline,
 		}
 	}
 
@@ -22,8 +25,8 @@ impl DeformStack {
 		}
 	}
 
-	/// Combine the deformations received so far according to some rules, and write to the result
-	pub(crate) fn combine(&self, _nodes: &InoxNodeTree, _node_comps: &World, result: &mut [Vec2]) {
+	/// Combine the deformations received so far according to some rules, scale them, add the baseline, and write to the result
+	pub(crate) fn combine(&self, _nodes: &InoxNodeTree, _node_comps: &World, result: &mut [Vec2], scale: f32) {
 		if result.len() != self.deform_len {
 			panic!("Required output deform dimensions different from what DeformStack is initialized with.")
 		}
@@ -37,6 +40,16 @@ impl DeformStack {
 			}
 		});
 		linear_combine(direct_deforms, result);
+
+		// scaling and adding the baseline in one pass
+		match &self.baseline {
+			Some(baseline) => result
+				.iter_mut()
+				.zip(baseline.iter())
+				.for_each(|(deform, rest)| *deform = *deform * scale + *rest),
+			None if scale != 1.0 => result.iter_mut().for_each(|deform| *deform *= scale),
+			None => (),
+		}
 	}
 
 	/// Submit a deform from a source for a node.
@@ -59,3 +72,187 @@ impl DeformStack {
 			.or_insert((true, deform));
 	}
 }
+
+/// Below this many deformed vertices, combining deforms on other threads costs more than it saves.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+const PARALLEL_MIN_VERTICES: usize = 8192;
+
+/// Combine the deform stacks of nodes into their `ranges` of the deform buffer `deforms`, which must not overlap.
+///
+/// With the `parallel` feature, large puppets are processed on the rayon thread pool.
+pub(crate) fn combine_all(
+	nodes: &InoxNodeTree,
+	comps: &World,
+	ranges: &[(InoxNodeUuid, Range<usize>)],
+	deforms: &mut [Vec2],
+	scale: f32,
+) {
+	#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+	if ranges.iter().map(|(_, range)| range.len()).sum::<usize>() >= PARALLEL_MIN_VERTICES {
+		combine_parallel(nodes, comps, ranges, deforms, scale);
+		return;
+	}
+
+	for (id, range) in ranges {
+		let deform_stack = comps
+			.get::<DeformStack>(*id)
+			.expect("Nodes in `ranges` must have a DeformStack.");
+		deform_stack.combine(nodes, comps, &mut deforms[range.clone()], scale);
+	}
+}
+
+/// `combine_all()` on the current rayon thread pool, one job per node.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+fn combine_parallel(
+	nodes: &InoxNodeTree,
+	comps: &World,
+	ranges: &[(InoxNodeUuid, Range<usize>)],
+	deforms: &mut [Vec2],
+	scale: f32,
+) {
+	use rayon::prelude::*;
+
+	// split the buffer into the disjoint slices of each node
+	let mut sorted = ranges.to_vec();
+	sorted.sort_by_key(|(_, range)| range.start);
+	let mut jobs = Vec::with_capacity(sorted.len());
+	let (mut rest, mut rest_start) = (deforms, 0);
+	for (id, range) in sorted {
+		let (_, tail) = std::mem::take(&mut rest).split_at_mut(range.start - rest_start);
+		let (slice, tail) = tail.split_at_mut(range.len());
+		jobs.push((id, slice));
+		(rest, rest_start) = (tail, range.end);
+	}
+
+	jobs.into_par_iter().for_each(|(id, slice)| {
+		let deform_stack = comps
+			.get::<DeformStack>(id)
+			.expect("Nodes in `ranges` must have a DeformStack.");
+		deform_stack.combine(nodes, comps, slice, scale);
+	});
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::{vec2, Vec2};
+
+	use crate::formats::fixtures;
+	use crate::math::deform::Deform;
+	use crate::params::ParamUuid;
+
+	use super::*;
+
+	/// `DeformStack::combine()` as three plain passes: sum, scale, then baseline.
+	fn combine_reference(deform_stack: &DeformStack, scale: f32) -> Vec<Vec2> {
+		let mut result = vec![Vec2::ZERO; deform_stack.deform_len];
+		for (enabled, Deform::Direct(deform)) in deform_stack.stack.values() {
+			if *enabled {
+				result
+					.iter_mut()
+					.zip(deform)
+					.for_each(|(sum, addition)| *sum += *addition);
+			}
+		}
+		result.iter_mut().for_each(|deform| *deform *= scale);
+		if let Some(baseline) = &deform_stack.baseline {
+			result
+				.iter_mut()
+				.zip(baseline)
+				.for_each(|(deform, rest)| *deform += *rest);
+		}
+		result
+	}
+
+	#[test]
+	fn combine_matches_reference() {
+		let puppet = fixtures::load(&fixtures::puppet(fixtures::node(0, "root", Vec::new()), Vec::new()));
+		let deform = |seed: f32| {
+			(0..5)
+				.map(|i| vec2(seed * i as f32 + 0.1, seed - i as f32 / 3.0))
+				.collect()
+		};
+		let baseline = Some(deform(0.7));
+
+		for baseline in [None, baseline] {
+			let mut deform_stack = DeformStack::new(5, baseline);
+			for deformed in [Vec::new(), vec![1], vec![1, 2, 3], vec![2, 3]] {
+				deform_stack.reset();
+				for param in &deformed {
+					let source = DeformSource::Param(ParamUuid(*param));
+					deform_stack.push(source, Deform::Direct(deform(*param as f32 * 1.3)));
+				}
+				for scale in [1.0, 0.5, 3.0] {
+					let mut result = vec![Vec2::NAN; 5];
+					deform_stack.combine(&puppet.nodes, &puppet.node_comps, &mut result, scale);
+					let reference = combine_reference(&deform_stack, scale);
+					assert_eq!(result, reference, "{deformed:?} at {scale}");
+				}
+			}
+		}
+	}
+
+	#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+	#[test]
+	fn parallel_matches_serial() {
+		use json::JsonValue;
+
+		use crate::render::TexturedMeshRenderCtx;
+
+		// parts deformed by a param, with one in between that is not
+		let deformed = (1..=9).filter(|id| *id != 5).collect::<Vec<u32>>();
+		let deform = |scale: f32| {
+			let offsets = (0..4).map(|i| json::array![scale * i as f32, -scale]).collect();
+			JsonValue::Array(offsets)
+		};
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", (1..=9).map(|id| fixtures::part(id, "part")).collect()),
+			vec![fixtures::param(
+				10,
+				"deform",
+				deformed
+					.iter()
+					.map(|id| fixtures::binding(*id, "deform", [deform(0.0), deform(*id as f32)]))
+					.collect(),
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		puppet.begin_frame();
+		puppet
+			.param_ctx
+			.as_mut()
+			.unwrap()
+			.set("deform", vec2(0.7, 0.0))
+			.unwrap();
+		puppet.end_frame(0.0);
+
+		let mut ranges = deformed
+			.iter()
+			.map(|id| {
+				let id = InoxNodeUuid(*id);
+				let render_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(id).unwrap();
+				let start = render_ctx.vert_offset as usize;
+				(id, start..start + render_ctx.vert_len)
+			})
+			.collect::<Vec<_>>();
+		// not necessarily in buffer order
+		ranges.reverse();
+
+		let len = puppet.render_ctx.as_ref().unwrap().vertex_buffers.deforms.len();
+		let mut serial = vec![Vec2::NAN; len];
+		for (id, range) in &ranges {
+			let deform_stack = puppet.node_comps.get::<DeformStack>(*id).unwrap();
+			deform_stack.combine(&puppet.nodes, &puppet.node_comps, &mut serial[range.clone()], 0.5);
+		}
+		for threads in [1, 2, 3, 16] {
+			let mut parallel = vec![Vec2::NAN; len];
+			let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+			pool.install(|| combine_parallel(&puppet.nodes, &puppet.node_comps, &ranges, &mut parallel, 0.5));
+			// untouched ranges stay as they were
+			assert_eq!(format!("{parallel:?}"), format!("{serial:?}"));
+		}
+		assert!(serial.iter().any(|deform| *deform != Vec2::ZERO && !deform.is_nan()));
+	}
+}
@@ -0,0 +1,202 @@
+use glam::Vec2;
+
+use crate::node::components::TransformStore;
+use crate::params::SetParamError;
+use crate::puppet::Puppet;
+
+use super::TexturedMeshRenderCtx;
+
+/// Snapshot of all parts of a puppet as currently deformed, flattened into one mesh in world space.
+///
+/// Laid out the same as `RenderCtx::vertex_buffers`, so `TexturedMeshRenderCtx` offsets of parts index into it.
+#[derive(Clone, Debug)]
+pub struct FlatMesh {
+	pub vertices: Vec<Vec2>,
+	pub uvs: Vec<Vec2>,
+	pub indices: Vec<u16>,
+}
+
+impl Puppet {
+	/// Flatten parts, with deforms and transforms applied, into a `FlatMesh`.
+	///
+	/// Panicks if rendering is not initialized.
+	pub fn flat_mesh(&self) -> FlatMesh {
+		let vertex_buffers = &self
+			.render_ctx
+			.as_ref()
+			.expect("Rendering for a puppet must be initialized before taking a snapshot.")
+			.vertex_buffers;
+
+		let (verts, deforms) = (&vertex_buffers.verts, &vertex_buffers.deforms);
+
+		// vertex ranges of parts, in buffer order, so every vertex is read and written once
+		let mut part_ranges = self
+			.nodes
+			.iter()
+			.filter_map(|node| {
+				let render_ctx = self.node_comps.get::<TexturedMeshRenderCtx>(node.uuid)?;
+				let transform = self.node_comps.get::<TransformStore>(node.uuid)?;
+				let vert_offset = render_ctx.vert_offset as usize;
+				Some((vert_offset..vert_offset + render_ctx.vert_len, &transform.absolute))
+			})
+			.collect::<Vec<_>>();
+		part_ranges.sort_by_key(|(range, _)| range.start);
+
+		let mut vertices = Vec::with_capacity(verts.len());
+		for (range, transform) in part_ranges {
+			// vertices not belonging to any part, e.g. the composite quad, are left untransformed
+			let untransformed = vertices.len()..range.start;
+			vertices.extend(untransformed.map(|i| verts[i] + deforms[i]));
+
+			vertices.extend(
+				(verts[range.clone()].iter().zip(&deforms[range]))
+					.map(|(vert, deform)| transform.transform_point3((*vert + *deform).extend(0.0)).truncate()),
+			);
+		}
+		let untransformed = vertices.len()..verts.len();
+		vertices.extend(untransformed.map(|i| verts[i] + deforms[i]));
+
+		FlatMesh {
+			vertices,
+			uvs: vertex_buffers.uvs.clone(),
+			indices: vertex_buffers.indices.clone(),
+		}
+	}
+
+	/// Sample param `param_name` in `steps` evenly spaced values from its min to its max, all other params at their defaults,
+	/// and take a `FlatMesh` of each.
+	///
+	/// This runs one frame per step without advancing physics, leaving the puppet at the last step.
+	/// Panicks if params are not initialized.
+	pub fn bake_param_sweep(&mut self, param_name: &str, steps: usize) -> Result<Vec<FlatMesh>, SetParamError> {
+		let (min, max) = match self.params.get(param_name) {
+			Some(param) => (param.min, param.max),
+			None => return Err(SetParamError::NoParameterNamed(param_name.to_owned())),
+		};
+
+		let mut meshes = Vec::with_capacity(steps);
+		for step in 0..steps {
+			let t = if steps == 1 {
+				0.0
+			} else {
+				step as f32 / (steps - 1) as f32
+			};
+
+			self.begin_frame();
+			self.param_ctx
+				.as_mut()
+				.expect("Params of a puppet must be initialized before baking a sweep.")
+				.set(param_name, min.lerp(max, t))?;
+			self.end_frame(0.0);
+
+			meshes.push(self.flat_mesh());
+		}
+
+		Ok(meshes)
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::{vec2, Vec2};
+	use json::array;
+
+	use crate::formats::fixtures;
+	use crate::node::{components::TransformStore, InoxNodeUuid};
+	use crate::puppet::Puppet;
+	use crate::render::TexturedMeshRenderCtx;
+
+	/// Straightforward version of `Puppet::flat_mesh()` vertices: deforms first, then transforms.
+	fn two_pass_vertices(puppet: &Puppet) -> Vec<Vec2> {
+		let vertex_buffers = &puppet.render_ctx.as_ref().unwrap().vertex_buffers;
+		let mut vertices = (vertex_buffers.verts.iter().zip(&vertex_buffers.deforms))
+			.map(|(vert, deform)| *vert + *deform)
+			.collect::<Vec<_>>();
+
+		for node in puppet.nodes.iter() {
+			let (Some(render_ctx), Some(transform)) = (
+				puppet.node_comps.get::<TexturedMeshRenderCtx>(node.uuid),
+				puppet.node_comps.get::<TransformStore>(node.uuid),
+			) else {
+				continue;
+			};
+
+			let vert_offset = render_ctx.vert_offset as usize;
+			for vertex in &mut vertices[vert_offset..(vert_offset + render_ctx.vert_len)] {
+				*vertex = transform.absolute.transform_point3(vertex.extend(0.0)).truncate();
+			}
+		}
+
+		vertices
+	}
+
+	#[test]
+	fn fused_matches_two_pass() {
+		let mut transformed = fixtures::part(1, "transformed");
+		transformed["transform"]["trans"] = array![3.0, -2.0, 0.0];
+		transformed["transform"]["rot"] = array![0.0, 0.0, 0.5];
+		transformed["transform"]["scale"] = array![2.0, 1.5];
+		let no_deform = array![[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let deform = array![[0.5, 0.0], [0.0, 0.25], [-0.5, 0.0], [0.0, 1.0]];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(
+				0,
+				"root",
+				vec![fixtures::part(2, "plain"), transformed, fixtures::part(3, "plain")],
+			),
+			vec![fixtures::param(
+				10,
+				"deform",
+				vec![fixtures::binding(1, "deform", [no_deform, deform])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		puppet.begin_frame();
+		puppet
+			.param_ctx
+			.as_mut()
+			.unwrap()
+			.set("deform", vec2(0.5, 0.0))
+			.unwrap();
+		puppet.end_frame(0.0);
+
+		let vertices = puppet.flat_mesh().vertices;
+		assert_eq!(vertices, two_pass_vertices(&puppet));
+		let render_ctx = puppet.node_comps.get::<TexturedMeshRenderCtx>(InoxNodeUuid(1)).unwrap();
+		assert_ne!(vertices[render_ctx.vert_offset as usize], vec2(-1.0, -1.0));
+	}
+
+	#[test]
+	fn sweep_translation() {
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![fixtures::part(1, "part")]),
+			vec![fixtures::param(
+				10,
+				"move",
+				vec![fixtures::binding(1, "transform.t.x", [0.0.into(), 10.0.into()])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		let meshes = puppet.bake_param_sweep("move", 5).unwrap();
+		assert_eq!(meshes.len(), 5);
+
+		let vert_offset = puppet
+			.node_comps
+			.get::<TexturedMeshRenderCtx>(InoxNodeUuid(1))
+			.unwrap()
+			.vert_offset as usize;
+		let xs = meshes
+			.iter()
+			.map(|mesh| mesh.vertices[vert_offset].x)
+			.collect::<Vec<_>>();
+		assert_eq!(xs, [-1.0, 1.5, 4.0, 6.5, 9.0]);
+
+		assert!(puppet.bake_param_sweep("missing", 5).is_err());
+	}
+}
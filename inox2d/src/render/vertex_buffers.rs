@@ -2,6 +2,18 @@ use glam::{vec2, Vec2};
 
 use crate::node::components::Mesh;
 
+/// Most vertices vertex buffers can hold, as vertices are indexed with `u16`.
+pub const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+/// Meshes of a puppet not fitting in vertex buffers, where vertex indices and offsets of meshes in the index buffer are `u16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum VertexBufferOverflow {
+	#[error("puppet has {0} vertices, more than the limit of {MAX_VERTICES} in a single vertex buffer")]
+	Vertices(usize),
+	#[error("puppet has a mesh starting at index {0} of the index buffer, past the limit of {max}", max = u16::MAX)]
+	Indices(usize),
+}
+
 pub struct VertexBuffers {
 	pub verts: Vec<Vec2>,
 	pub uvs: Vec<Vec2>,
@@ -48,17 +60,31 @@ impl Default for VertexBuffers {
 
 impl VertexBuffers {
 	/// Adds the mesh's vertices and UVs to the buffers and returns its index and vertex offset.
-	pub fn push(&mut self, mesh: &Mesh) -> (u16, u16) {
-		let index_offset = self.indices.len() as u16;
-		let vert_offset = self.verts.len() as u16;
+	///
+	/// Nothing is added if the mesh does not fit.
+	pub fn push(&mut self, mesh: &Mesh) -> Result<(u16, u16), VertexBufferOverflow> {
+		let vert_count = self.verts.len() + mesh.vertices.len();
+		if vert_count > MAX_VERTICES {
+			return Err(VertexBufferOverflow::Vertices(vert_count));
+		}
+		let vert_offset = u16::try_from(self.verts.len()).map_err(|_| VertexBufferOverflow::Vertices(vert_count))?;
+		let index_offset =
+			u16::try_from(self.indices.len()).map_err(|_| VertexBufferOverflow::Indices(self.indices.len()))?;
+		// only out of range indices of a malformed mesh can overflow here
+		let indices = (mesh.indices.iter())
+			.map(|index| {
+				(index.checked_add(vert_offset)).ok_or(VertexBufferOverflow::Vertices(
+					vert_offset as usize + *index as usize + 1,
+				))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
 
 		self.verts.extend_from_slice(&mesh.vertices);
 		self.uvs.extend_from_slice(&mesh.uvs);
-		self.indices
-			.extend(mesh.indices.iter().map(|index| index + vert_offset));
+		self.indices.extend(indices);
 		self.deforms
 			.resize(self.deforms.len() + mesh.vertices.len(), Vec2::ZERO);
 
-		(index_offset, vert_offset)
+		Ok((index_offset, vert_offset))
 	}
 }
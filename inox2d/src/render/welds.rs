@@ -0,0 +1,124 @@
+use glam::{Mat4, Vec2};
+
+use crate::node::components::{TransformStore, VertexWelds};
+use crate::puppet::{InoxNodeTree, World};
+
+use super::{TexturedMeshRenderCtx, VertexBuffers};
+
+/// World position of vertex `index` of `vertex_buffers`, deformed.
+fn world_position(vertex_buffers: &VertexBuffers, transform: &Mat4, index: usize) -> Vec2 {
+	let local = vertex_buffers.verts[index] + vertex_buffers.deforms[index];
+	transform.transform_point3(local.extend(0.0)).truncate()
+}
+
+/// Pull welded vertices towards their partners, by overwriting deforms of the parts holding the welds.
+///
+/// Positions are taken from the deforms before any weld is applied, so two parts welded to each other both move.
+pub(super) fn apply(nodes: &InoxNodeTree, comps: &World, vertex_buffers: &mut VertexBuffers) {
+	let mut welded_deforms = Vec::<(usize, Vec2)>::new();
+
+	for node in nodes.iter() {
+		let Some(welds) = comps.get::<VertexWelds>(node.uuid) else {
+			continue;
+		};
+		let (Some(render_ctx), Some(transform)) = (
+			comps.get::<TexturedMeshRenderCtx>(node.uuid),
+			comps.get::<TransformStore>(node.uuid),
+		) else {
+			continue;
+		};
+		let inverse = transform.absolute.inverse();
+		if !inverse.is_finite() {
+			continue;
+		}
+
+		for link in &welds.links {
+			let (Some(target_ctx), Some(target_transform)) = (
+				comps.get::<TexturedMeshRenderCtx>(link.target),
+				comps.get::<TransformStore>(link.target),
+			) else {
+				tracing::warn!("Vertex weld target {} is not a part, ignoring", link.target.0);
+				continue;
+			};
+
+			let welded_indices = link.indices.iter().take(render_ctx.vert_len).enumerate();
+			for (i, target_i) in welded_indices.filter_map(|(i, target_i)| Some((i, (*target_i)? as usize))) {
+				if target_i >= target_ctx.vert_len {
+					continue;
+				}
+
+				let index = render_ctx.vert_offset as usize + i;
+				let target_index = target_ctx.vert_offset as usize + target_i;
+
+				let position = world_position(vertex_buffers, &transform.absolute, index);
+				let target_position = world_position(vertex_buffers, &target_transform.absolute, target_index);
+				let welded = inverse
+					.transform_point3(position.lerp(target_position, link.weight).extend(0.0))
+					.truncate();
+
+				welded_deforms.push((index, welded - vertex_buffers.verts[index]));
+			}
+		}
+	}
+
+	for (index, deform) in welded_deforms {
+		vertex_buffers.deforms[index] = deform;
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+	use glam::vec2;
+	use json::array;
+
+	use crate::formats::fixtures;
+	use crate::node::InoxNodeUuid;
+	use crate::render::TexturedMeshRenderCtx;
+
+	#[test]
+	fn deform_moves_welded_vertex() {
+		// part 1's bottom right vertex sits on part 2's bottom left vertex
+		let mut welded = fixtures::part(1, "welded");
+		welded["weldedLinks"] = array![{ target: 2, indices: [-1, 0, -1, -1], weight: 1.0 }];
+		let mut deformed = fixtures::part(2, "deformed");
+		deformed["transform"]["trans"] = array![2.0, 0.0, 0.0];
+
+		let no_deform = array![[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let deform = array![[0.0, 0.5], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+		let mut puppet = fixtures::load(&fixtures::puppet(
+			fixtures::node(0, "root", vec![welded, deformed]),
+			vec![fixtures::param(
+				10,
+				"deform",
+				vec![fixtures::binding(2, "deform", [no_deform, deform])],
+			)],
+		));
+		puppet.init_transforms();
+		puppet.init_rendering();
+		puppet.init_params();
+
+		puppet.begin_frame();
+		puppet
+			.param_ctx
+			.as_mut()
+			.unwrap()
+			.set("deform", vec2(1.0, 0.0))
+			.unwrap();
+		puppet.end_frame(0.0);
+
+		let deforms = |id: u32| {
+			let render_ctx = puppet
+				.node_comps
+				.get::<TexturedMeshRenderCtx>(InoxNodeUuid(id))
+				.unwrap();
+			let vert_offset = render_ctx.vert_offset as usize;
+			&puppet.render_ctx.as_ref().unwrap().vertex_buffers.deforms[vert_offset..vert_offset + render_ctx.vert_len]
+		};
+
+		assert_eq!(deforms(2)[0], vec2(0.0, 0.5));
+		assert_eq!(
+			deforms(1),
+			[vec2(0.0, 0.0), vec2(0.0, 0.5), vec2(0.0, 0.0), vec2(0.0, 0.0)]
+		);
+	}
+}
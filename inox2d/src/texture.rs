@@ -1,25 +1,48 @@
-use std::io;
+mod atlas;
+#[cfg(feature = "decode-textures")]
+mod decode;
 
-use image::{ImageBuffer, ImageError, ImageFormat, Rgba};
-use tracing::error;
+pub use atlas::{pack_textures, AtlasTexture, UvRemap, ATLAS_MAX_SIZE, ATLAS_PADDING};
 
-use crate::model::ModelTexture;
-
-use simple_tga_reader::{read_tga, TgaDecodeError, TgaImage};
+#[cfg(feature = "decode-textures")]
+pub(crate) use decode::decode_texture;
+#[cfg(feature = "decode-textures")]
+pub use decode::{
+	decode_model_textures, decode_model_textures_with_options, try_decode_model_textures,
+	try_decode_model_textures_with_options, DecodeOptions, DecodeTextureError, TextureDecodeError,
+};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TextureId(pub(crate) usize);
 
 impl TextureId {
+	/// Id of the `raw`-th texture of a model.
+	pub fn new(raw: usize) -> Self {
+		Self(raw)
+	}
+
 	pub fn raw(&self) -> usize {
 		self.0
 	}
 }
 
+/// How a texture is sampled outside of its `[0, 1]` UV range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureWrapping {
+	/// Transparent black.
+	#[default]
+	ClampToBorder,
+	/// Tile the texture.
+	Repeat,
+	/// Tile the texture, mirroring every other tile.
+	MirroredRepeat,
+}
+
 pub struct ShallowTexture {
 	pixels: Vec<u8>,
 	width: u32,
 	height: u32,
+	premultiplied: bool,
 }
 
 impl ShallowTexture {
@@ -27,6 +50,19 @@ impl ShallowTexture {
 		&self.pixels
 	}
 
+	/// Whether the colors of pixels are multiplied by their alpha. Decoded textures are straight alpha until `premultiply()`.
+	pub fn premultiplied(&self) -> bool {
+		self.premultiplied
+	}
+
+	/// Multiply the colors of pixels by their alpha, as the blend funcs of renderers expect. Does nothing if already done.
+	pub fn premultiply(&mut self) {
+		if !self.premultiplied {
+			premultiply(&mut self.pixels);
+			self.premultiplied = true;
+		}
+	}
+
 	pub fn width(&self) -> u32 {
 		self.width
 	}
@@ -36,123 +72,36 @@ impl ShallowTexture {
 	}
 }
 
-impl From<TgaImage> for ShallowTexture {
-	fn from(value: TgaImage) -> Self {
-		Self {
-			pixels: value.data,
-			width: value.header.width as u32,
-			height: value.header.height as u32,
-		}
-	}
-}
-
-impl From<ImageBuffer<Rgba<u8>, Vec<u8>>> for ShallowTexture {
-	fn from(value: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Self {
-		Self {
-			pixels: value.to_vec(),
-			width: value.width(),
-			height: value.height(),
+/// Multiply colors of RGBA8 pixels by their alpha, rounding to nearest.
+fn premultiply(pixels: &mut [u8]) {
+	for pixel in pixels.chunks_exact_mut(4) {
+		let alpha = pixel[3] as u16;
+		for color in &mut pixel[..3] {
+			*color = ((*color as u16 * alpha + 127) / 255) as u8;
 		}
 	}
 }
 
-#[derive(Debug, thiserror::Error)]
-enum DecodeTextureError {
-	#[error("Could not decode TGA texture")]
-	TgaDecode(
-		#[from]
-		#[source]
-		TgaDecodeError,
-	),
-
-	#[error("Could not decode texture")]
-	ImageDecode(
-		#[from]
-		#[source]
-		ImageError,
-	),
-}
-
-fn decode_texture(mtex: ModelTexture) -> Result<ShallowTexture, DecodeTextureError> {
-	if mtex.format == ImageFormat::Tga {
-		let tga_texture = read_tga(&mut io::Cursor::new(&mtex.data))?;
-		Ok(ShallowTexture::from(tga_texture))
-	} else {
-		let img_buf = image::load_from_memory_with_format(&mtex.data, mtex.format)?;
-		Ok(ShallowTexture::from(img_buf.into_rgba8()))
-	}
-}
-
-#[cfg(target_arch = "wasm32")]
-pub fn decode_model_textures<'a>(
-	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
-) -> Vec<ShallowTexture> {
-	(model_textures.cloned())
-		.map(decode_texture)
-		.inspect(|res| {
-			if let Err(e) = res {
-				tracing::error!("{}", e);
-			}
-		})
-		.filter_map(Result::ok)
-		.collect::<Vec<_>>()
-}
-
-/// Decodes model textures in parallel, using as many threads as we can use minus one.
-#[cfg(not(target_arch = "wasm32"))]
-pub fn decode_model_textures<'a>(
-	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
-) -> Vec<ShallowTexture> {
-	use std::sync::mpsc;
-
-	// get number of optimal threads from computer
-	let mut num_threads = std::thread::available_parallelism().unwrap().get();
-
-	// remove at least one thread to not torture the computer
-	if num_threads > 1 {
-		num_threads -= 1;
-	}
-
-	// do not use more threads than there are images
-	if num_threads > model_textures.len() {
-		num_threads = model_textures.len();
-	}
-
-	// use channels to get Rs back from thread computation
-	let (tx_all, rx_all) = mpsc::channel();
-
-	let mut pipes = Vec::with_capacity(num_threads);
-	for th in 0..num_threads {
-		// thread-local channel
-		let (tx, rx) = mpsc::channel::<(usize, ModelTexture)>();
-
-		let tx_all = tx_all.clone();
-		std::thread::Builder::new()
-			.name(format!("Image Decoder Thread ({})", th))
-			.spawn(move || {
-				// get textures from the thread-local channel, decode them, and send them to the global channel
-				while let Ok((i, texture)) = rx.recv() {
-					match decode_texture(texture) {
-						Ok(decoded) => tx_all.send((i, decoded)).unwrap(),
-						Err(e) => tracing::error!("{}", e),
-					}
-				}
-			})
-			.unwrap();
-
-		pipes.push(tx);
-	}
-
-	let n_model_textures = model_textures.len();
-
-	// distribute texture decoding on all threads we make available
-	for ((i, texture), tx) in model_textures.enumerate().zip(pipes.iter().cycle()) {
-		// REMINDER: the texture data is behind an arc, so it's not actually being cloned
-		tx.send((i, texture.clone())).unwrap();
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn premultiply_pixels() {
+		let mut texture = ShallowTexture {
+			pixels: vec![255, 128, 0, 128, 200, 100, 50, 255, 255, 255, 255, 0, 255, 255, 255, 1],
+			width: 4,
+			height: 1,
+			premultiplied: false,
+		};
+		texture.premultiply();
+		assert!(texture.premultiplied());
+		assert_eq!(
+			texture.pixels(),
+			[128, 64, 0, 128, 200, 100, 50, 255, 0, 0, 0, 0, 1, 1, 1, 1]
+		);
+
+		texture.premultiply();
+		assert_eq!(&texture.pixels()[..4], [128, 64, 0, 128]);
 	}
-
-	let mut decoded = rx_all.into_iter().take(n_model_textures).collect::<Vec<_>>();
-	decoded.sort_by_key(|&(i, _)| i);
-
-	decoded.into_iter().map(|(_, tex)| tex).collect()
 }
@@ -0,0 +1,252 @@
+use glam::{uvec2, vec2, UVec2, Vec2};
+
+use super::{premultiply, ShallowTexture};
+
+/// Maximum width and height of atlas pages, supported by any GPU. Larger textures get a page of their own.
+pub const ATLAS_MAX_SIZE: u32 = 4096;
+/// Pixels around each texture in an atlas, filled with its edge pixels so that filtering and mipmaps do not bleed
+/// neighbors in.
+pub const ATLAS_PADDING: u32 = 4;
+
+/// Textures packed into a few large pages by `pack_textures()`.
+pub struct AtlasTexture {
+	pages: Vec<ShallowTexture>,
+}
+
+impl AtlasTexture {
+	/// RGBA8 pages of the atlas, premultiplied if any packed texture was.
+	pub fn pages(&self) -> &[ShallowTexture] {
+		&self.pages
+	}
+}
+
+/// Where a texture ended up in an `AtlasTexture`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRemap {
+	/// Index of the page in `AtlasTexture::pages()`.
+	pub page: usize,
+	/// UV of the top-left corner of the texture on the page.
+	pub offset: Vec2,
+	/// Size of the texture in UVs of the page.
+	pub scale: Vec2,
+}
+
+impl UvRemap {
+	/// UV on the atlas page of `uv` on the original texture.
+	///
+	/// UVs outside of `[0, 1]` land on neighbors: textures that repeat or clamp to their border cannot be packed.
+	pub fn apply(&self, uv: Vec2) -> Vec2 {
+		self.offset + uv * self.scale
+	}
+}
+
+/// Pack `textures` into as few atlas pages as fit, so that renderers bind one texture for many parts.
+///
+/// Returns the atlas and where each of `textures` is on it, in the same order.
+pub fn pack_textures(textures: &[ShallowTexture]) -> (AtlasTexture, Vec<UvRemap>) {
+	pack(textures, ATLAS_MAX_SIZE, ATLAS_PADDING)
+}
+
+/// A page being packed, in rows of textures.
+#[derive(Default)]
+struct Shelves {
+	/// Size of the page, covering all textures placed on it.
+	size: UVec2,
+	row_x: u32,
+	row_y: u32,
+	row_height: u32,
+}
+
+impl Shelves {
+	/// Position of a `size` rectangle appended to the current row or a new one, if it stays within `max_size`.
+	fn place(&mut self, size: UVec2, max_size: u32) -> Option<UVec2> {
+		let new_row = self.row_x > 0 && self.row_x + size.x > max_size;
+		let pos = match new_row {
+			true => uvec2(0, self.row_y + self.row_height),
+			false => uvec2(self.row_x, self.row_y),
+		};
+		if pos.x + size.x > max_size || pos.y + size.y > max_size {
+			return None;
+		}
+
+		if new_row {
+			(self.row_y, self.row_height) = (pos.y, 0);
+		}
+		self.row_x = pos.x + size.x;
+		self.row_height = self.row_height.max(size.y);
+		self.size = self.size.max(uvec2(self.row_x, self.row_y + self.row_height));
+		Some(pos)
+	}
+}
+
+fn pack(textures: &[ShallowTexture], max_size: u32, padding: u32) -> (AtlasTexture, Vec<UvRemap>) {
+	let padded = |texture: &ShallowTexture| uvec2(texture.width, texture.height) + 2 * padding;
+
+	// tallest first, so that rows waste little height
+	let mut order = (0..textures.len()).collect::<Vec<_>>();
+	order.sort_by_key(|&i| std::cmp::Reverse(textures[i].height));
+
+	let mut pages = Vec::<Shelves>::new();
+	let mut placements = vec![(0, UVec2::ZERO); textures.len()];
+	for i in order {
+		let size = padded(&textures[i]);
+		let placed = pages
+			.iter_mut()
+			.enumerate()
+			.find_map(|(page, shelves)| Some((page, shelves.place(size, max_size)?)));
+		placements[i] = match placed {
+			Some(placement) => placement,
+			None => {
+				let mut shelves = Shelves::default();
+				// a texture too large for a page gets one its size
+				let pos = shelves.place(size, max_size.max(size.max_element())).unwrap();
+				pages.push(shelves);
+				(pages.len() - 1, pos)
+			}
+		};
+	}
+
+	let premultiplied = textures.iter().any(|texture| texture.premultiplied);
+	let mut atlas_pages = pages
+		.iter()
+		.map(|shelves| ShallowTexture {
+			pixels: vec![0; shelves.size.x as usize * shelves.size.y as usize * 4],
+			width: shelves.size.x,
+			height: shelves.size.y,
+			premultiplied,
+		})
+		.collect::<Vec<_>>();
+
+	let remaps = textures
+		.iter()
+		.zip(&placements)
+		.map(|(texture, &(page, pos))| {
+			let atlas_page = &mut atlas_pages[page];
+			blit_padded(atlas_page, texture, pos, padding);
+
+			let page_size = uvec2(atlas_page.width, atlas_page.height).as_vec2();
+			UvRemap {
+				page,
+				offset: (pos + padding).as_vec2() / page_size,
+				scale: vec2(texture.width as f32, texture.height as f32) / page_size,
+			}
+		})
+		.collect();
+
+	(AtlasTexture { pages: atlas_pages }, remaps)
+}
+
+/// Copy `texture` to `pos` + `padding` on `page`, extending its edge pixels over the padding around it.
+fn blit_padded(page: &mut ShallowTexture, texture: &ShallowTexture, pos: UVec2, padding: u32) {
+	let (width, height) = (texture.width as usize, texture.height as usize);
+	if width == 0 || height == 0 {
+		return;
+	}
+	let padding = padding as usize;
+	let page_width = page.width as usize;
+
+	for y in 0..height + 2 * padding {
+		let src_y = y.saturating_sub(padding).min(height - 1);
+		let src_row = &texture.pixels[src_y * width * 4..(src_y + 1) * width * 4];
+
+		let dst_start = ((pos.y as usize + y) * page_width + pos.x as usize) * 4;
+		let dst_row = &mut page.pixels[dst_start..dst_start + (width + 2 * padding) * 4];
+		let (left, rest) = dst_row.split_at_mut(padding * 4);
+		let (middle, right) = rest.split_at_mut(width * 4);
+		middle.copy_from_slice(src_row);
+		for pixel in left.chunks_exact_mut(4) {
+			pixel.copy_from_slice(&src_row[..4]);
+		}
+		for pixel in right.chunks_exact_mut(4) {
+			pixel.copy_from_slice(&src_row[(width - 1) * 4..]);
+		}
+		if page.premultiplied && !texture.premultiplied {
+			premultiply(dst_row);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// `width`x`height` texture with distinct opaque pixels, starting from `seed`.
+	fn texture(width: u32, height: u32, seed: u8) -> ShallowTexture {
+		let pixels = (0..width * height)
+			.flat_map(|i| [seed.wrapping_add(i as u8), i as u8, seed, 255])
+			.collect();
+		ShallowTexture {
+			pixels,
+			width,
+			height,
+			premultiplied: false,
+		}
+	}
+
+	fn pixel(texture: &ShallowTexture, pos: UVec2) -> &[u8] {
+		let i = (pos.y * texture.width + pos.x) as usize * 4;
+		&texture.pixels[i..i + 4]
+	}
+
+	/// Every texel of `textures`, looked up through their remaps, is found on the atlas.
+	fn assert_packed(textures: &[ShallowTexture], atlas: &AtlasTexture, remaps: &[UvRemap]) {
+		for (texture, remap) in textures.iter().zip(remaps) {
+			let page = &atlas.pages()[remap.page];
+			let page_size = uvec2(page.width(), page.height()).as_vec2();
+			for y in 0..texture.height {
+				for x in 0..texture.width {
+					let uv = (uvec2(x, y).as_vec2() + 0.5) / uvec2(texture.width, texture.height).as_vec2();
+					let atlas_pos = (remap.apply(uv) * page_size).as_uvec2();
+					assert_eq!(pixel(page, atlas_pos), pixel(texture, uvec2(x, y)));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn pack_into_pages() {
+		let textures = [texture(3, 2, 0), texture(1, 1, 50), texture(2, 3, 100)];
+		let (atlas, remaps) = pack(&textures, 16, 2);
+		assert_eq!(atlas.pages().len(), 1);
+		assert_packed(&textures, &atlas, &remaps);
+
+		// padding repeats edge pixels
+		let page = &atlas.pages()[0];
+		let top_left = (remaps[0].offset * uvec2(page.width(), page.height()).as_vec2()).as_uvec2();
+		assert_eq!(pixel(page, top_left - 2), pixel(&textures[0], UVec2::ZERO));
+		assert_eq!(pixel(page, top_left + uvec2(4, 3)), pixel(&textures[0], uvec2(2, 1)));
+
+		// 8x8 padded textures, 4 to a page
+		let textures = (0..5).map(|i| texture(6, 6, i * 40)).collect::<Vec<_>>();
+		let (atlas, remaps) = pack(&textures, 16, 1);
+		assert_eq!(atlas.pages().len(), 2);
+		assert_packed(&textures, &atlas, &remaps);
+
+		// too large for a page, and placed after the taller one
+		let textures = [texture(20, 1, 0), texture(2, 2, 100)];
+		let (atlas, remaps) = pack(&textures, 16, 1);
+		assert_eq!(remaps.iter().map(|remap| remap.page).collect::<Vec<_>>(), [1, 0]);
+		assert_eq!((atlas.pages()[1].width(), atlas.pages()[1].height()), (22, 3));
+		assert_packed(&textures, &atlas, &remaps);
+
+		let (atlas, remaps) = pack_textures(&[]);
+		assert!(atlas.pages().is_empty() && remaps.is_empty());
+	}
+
+	#[test]
+	fn pack_mixed_alpha() {
+		let mut premultiplied = texture(1, 1, 0);
+		premultiplied.pixels = vec![100, 50, 0, 128];
+		premultiplied.premultiplied = true;
+		let mut straight = texture(1, 1, 0);
+		straight.pixels = vec![200, 100, 0, 128];
+
+		let (atlas, remaps) = pack_textures(&[premultiplied, straight]);
+		let page = &atlas.pages()[0];
+		assert!(page.premultiplied());
+		for remap in remaps {
+			let pos = (remap.apply(Vec2::splat(0.5)) * uvec2(page.width(), page.height()).as_vec2()).as_uvec2();
+			assert_eq!(pixel(page, pos), [100, 50, 0, 128]);
+		}
+	}
+}
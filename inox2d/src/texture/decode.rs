@@ -0,0 +1,277 @@
+use std::io;
+
+use image::{ImageBuffer, ImageError, ImageFormat, Rgba};
+use simple_tga_reader::{read_tga, TgaDecodeError, TgaImage};
+
+use crate::model::ModelTexture;
+
+use super::ShallowTexture;
+
+impl From<TgaImage> for ShallowTexture {
+	fn from(value: TgaImage) -> Self {
+		Self {
+			pixels: value.data,
+			width: value.header.width as u32,
+			height: value.header.height as u32,
+			premultiplied: false,
+		}
+	}
+}
+
+impl From<ImageBuffer<Rgba<u8>, Vec<u8>>> for ShallowTexture {
+	fn from(value: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Self {
+		Self {
+			pixels: value.to_vec(),
+			width: value.width(),
+			height: value.height(),
+			premultiplied: false,
+		}
+	}
+}
+
+/// How model textures are decoded, see `decode_model_textures_with_options()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeOptions {
+	/// Multiply colors by alpha after decoding, see `ShallowTexture::premultiply()`.
+	/// Textures are authored straight alpha, but renderers blend premultiplied colors. On by default.
+	pub premultiply: bool,
+}
+
+impl Default for DecodeOptions {
+	fn default() -> Self {
+		Self { premultiply: true }
+	}
+}
+
+/// Why a texture could not be decoded.
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeTextureError {
+	#[error("Could not decode TGA texture")]
+	TgaDecode(
+		#[from]
+		#[source]
+		TgaDecodeError,
+	),
+
+	#[error("Could not decode texture")]
+	ImageDecode(
+		#[from]
+		#[source]
+		ImageError,
+	),
+}
+
+/// A model texture that could not be decoded.
+#[derive(Debug, thiserror::Error)]
+#[error("Could not decode model texture {index}")]
+pub struct TextureDecodeError {
+	/// Index of the texture in the model.
+	pub index: usize,
+	#[source]
+	pub error: DecodeTextureError,
+}
+
+pub(crate) fn decode_texture(mtex: ModelTexture) -> Result<ShallowTexture, DecodeTextureError> {
+	if mtex.format == ImageFormat::Tga {
+		let tga_texture = read_tga(&mut io::Cursor::new(&mtex.data))?;
+		Ok(ShallowTexture::from(tga_texture))
+	} else {
+		let img_buf = image::load_from_memory_with_format(&mtex.data, mtex.format)?;
+		Ok(ShallowTexture::from(img_buf.into_rgba8()))
+	}
+}
+
+/// Decodes model textures with default options, logging and dropping those that fail to decode.
+///
+/// Dropped textures shift the indices of the following ones, see `try_decode_model_textures()` to fail instead.
+pub fn decode_model_textures<'a>(
+	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+) -> Vec<ShallowTexture> {
+	decode_model_textures_with_options(model_textures, DecodeOptions::default())
+}
+
+/// Decodes model textures like `decode_model_textures()`, as specified by `options`.
+pub fn decode_model_textures_with_options<'a>(
+	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+	options: DecodeOptions,
+) -> Vec<ShallowTexture> {
+	decode_all(model_textures, options)
+		.into_iter()
+		.inspect(|res| {
+			if let Err(e) = res {
+				tracing::error!("{}", e);
+			}
+		})
+		.filter_map(Result::ok)
+		.collect()
+}
+
+/// Decodes model textures with default options, failing with the first texture, by index, that fails to decode.
+pub fn try_decode_model_textures<'a>(
+	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+) -> Result<Vec<ShallowTexture>, TextureDecodeError> {
+	try_decode_model_textures_with_options(model_textures, DecodeOptions::default())
+}
+
+/// Decodes model textures like `try_decode_model_textures()`, as specified by `options`.
+pub fn try_decode_model_textures_with_options<'a>(
+	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+	options: DecodeOptions,
+) -> Result<Vec<ShallowTexture>, TextureDecodeError> {
+	decode_all(model_textures, options)
+		.into_iter()
+		.enumerate()
+		.map(|(index, res)| res.map_err(|error| TextureDecodeError { index, error }))
+		.collect()
+}
+
+/// `decode_texture()`, then what `options` ask for, so that all formats end up the same.
+fn decode_texture_with_options(
+	mtex: ModelTexture,
+	options: DecodeOptions,
+) -> Result<ShallowTexture, DecodeTextureError> {
+	let mut texture = decode_texture(mtex)?;
+	if options.premultiply {
+		texture.premultiply();
+	}
+	Ok(texture)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decode_all<'a>(
+	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+	options: DecodeOptions,
+) -> Vec<Result<ShallowTexture, DecodeTextureError>> {
+	model_textures
+		.cloned()
+		.map(|texture| decode_texture_with_options(texture, options))
+		.collect()
+}
+
+/// Decodes model textures in parallel, using as many threads as we can use minus one.
+#[cfg(not(target_arch = "wasm32"))]
+fn decode_all<'a>(
+	model_textures: impl ExactSizeIterator<Item = &'a ModelTexture>,
+	options: DecodeOptions,
+) -> Vec<Result<ShallowTexture, DecodeTextureError>> {
+	use std::sync::mpsc;
+
+	// get number of optimal threads from computer
+	let mut num_threads = std::thread::available_parallelism().unwrap().get();
+
+	// remove at least one thread to not torture the computer
+	if num_threads > 1 {
+		num_threads -= 1;
+	}
+
+	// do not use more threads than there are images
+	if num_threads > model_textures.len() {
+		num_threads = model_textures.len();
+	}
+
+	// use channels to get Rs back from thread computation
+	let (tx_all, rx_all) = mpsc::channel();
+
+	let mut pipes = Vec::with_capacity(num_threads);
+	for th in 0..num_threads {
+		// thread-local channel
+		let (tx, rx) = mpsc::channel::<(usize, ModelTexture)>();
+
+		let tx_all = tx_all.clone();
+		std::thread::Builder::new()
+			.name(format!("Image Decoder Thread ({})", th))
+			.spawn(move || {
+				// get textures from the thread-local channel, decode them, and send results to the global channel
+				while let Ok((i, texture)) = rx.recv() {
+					tx_all.send((i, decode_texture_with_options(texture, options))).unwrap();
+				}
+			})
+			.unwrap();
+
+		pipes.push(tx);
+	}
+
+	let n_model_textures = model_textures.len();
+
+	// distribute texture decoding on all threads we make available
+	for ((i, texture), tx) in model_textures.enumerate().zip(pipes.iter().cycle()) {
+		// REMINDER: the texture data is behind an arc, so it's not actually being cloned
+		tx.send((i, texture.clone())).unwrap();
+	}
+
+	// every texture gets a result, failed or not
+	let mut decoded = rx_all.into_iter().take(n_model_textures).collect::<Vec<_>>();
+	decoded.sort_by_key(|&(i, _)| i);
+
+	decoded.into_iter().map(|(_, tex)| tex).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::sync::Arc;
+
+	use super::*;
+
+	fn png(width: u32, height: u32) -> ModelTexture {
+		let mut data = Vec::new();
+		ImageBuffer::<Rgba<u8>, _>::new(width, height)
+			.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+			.unwrap();
+		ModelTexture {
+			format: ImageFormat::Png,
+			data: data.into(),
+		}
+	}
+
+	#[test]
+	fn corrupt_texture() {
+		let corrupt = ModelTexture {
+			format: ImageFormat::Png,
+			data: Arc::from(&b"not a png"[..]),
+		};
+		let textures = [png(2, 1), corrupt, png(1, 2)];
+
+		let err = try_decode_model_textures(textures.iter()).err().unwrap();
+		assert_eq!(err.index, 1);
+		assert!(matches!(err.error, DecodeTextureError::ImageDecode(_)));
+
+		// the lenient version drops it
+		let decoded = decode_model_textures(textures.iter());
+		assert_eq!(decoded.iter().map(|tex| tex.width()).collect::<Vec<_>>(), [2, 1]);
+
+		let decoded = try_decode_model_textures([png(2, 1), png(1, 2)].iter()).unwrap();
+		assert_eq!(decoded.len(), 2);
+	}
+
+	#[test]
+	fn premultiplied_by_default() {
+		let mut data = Vec::new();
+		ImageBuffer::from_pixel(1, 1, Rgba([200_u8, 100, 0, 128]))
+			.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+			.unwrap();
+		let png = ModelTexture {
+			format: ImageFormat::Png,
+			data: data.into(),
+		};
+		// 1x1 TGA, uncompressed 32-bit BGRA
+		let mut tga = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 32, 8];
+		tga.extend([0, 100, 200, 128]);
+		let tga = ModelTexture {
+			format: ImageFormat::Tga,
+			data: tga.into(),
+		};
+		let textures = [png, tga];
+
+		for texture in try_decode_model_textures(textures.iter()).unwrap() {
+			assert!(texture.premultiplied());
+			assert_eq!(texture.pixels(), [100, 50, 0, 128]);
+		}
+
+		let straight = DecodeOptions { premultiply: false };
+		for texture in try_decode_model_textures_with_options(textures.iter(), straight).unwrap() {
+			assert!(!texture.premultiplied());
+			assert_eq!(texture.pixels(), [200, 100, 0, 128]);
+		}
+	}
+}